@@ -10,6 +10,7 @@ import {ReentrancyGuard} from "@openzeppelin/contracts/utils/ReentrancyGuard.sol
 import {Pausable} from "@openzeppelin/contracts/utils/Pausable.sol";
 import {IERC20} from "@openzeppelin/contracts/token/ERC20/IERC20.sol";
 import {SafeERC20} from "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
+import {MerkleProof} from "@openzeppelin/contracts/utils/cryptography/MerkleProof.sol";
 
 /**
  * @title ServiceManager
@@ -36,9 +37,17 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
 
     /// @notice Deposit payload type hash for EIP-712
     bytes32 public constant DEPOSIT_PAYLOAD_TYPEHASH = keccak256(
-        "DepositPayload(bytes32 txHash,uint256 amount,bytes32 secretHash,bytes32 aztecAddress,uint64 nonce,uint32 blockHeight)"
+        "DepositPayload(uint8 version,bytes32 txHash,uint32 outputIndex,uint256 amount,uint256 fee,bytes32 secretHash,bytes32 aztecAddress,uint64 nonce,uint32 blockHeight,bytes32 blockHash)"
     );
 
+    /// @notice Batch root type hash for EIP-712
+    bytes32 public constant BATCH_ROOT_TYPEHASH = keccak256("BatchRoot(bytes32 root,uint64 nonce,uint32 count)");
+
+    /// @notice Deposit payload format version accepted by verifyAndDispatch
+    /// @dev Version 2 binds the Zcash block hash, so an attestation names one side of a reorg;
+    ///      version 3 binds the note's output index, so deposits sharing a transaction are distinct
+    uint8 public constant PAYLOAD_VERSION = 3;
+
     /// @notice Maximum number of operators
     uint256 public constant MAX_OPERATORS = 100;
 
@@ -96,6 +105,15 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
     /// @notice Mapping of nonce to whether it has been used
     mapping(uint64 => bool) private _usedNonces;
 
+    /// @notice Mapping of keccak256(txHash, outputIndex) to whether the note was dispatched
+    mapping(bytes32 => bool) private _processedDeposits;
+
+    /// @notice Mapping of batch nonce to the root verified under it
+    mapping(uint64 => bytes32) private _batchRoots;
+
+    /// @notice Mapping of batch root to whether it was verified
+    mapping(bytes32 => bool) private _verifiedRoots;
+
     /// @notice Mapping of operator to pending withdrawal info
     mapping(address => PendingWithdrawal) private _pendingWithdrawals;
 
@@ -273,72 +291,59 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
         bytes calldata aggregatedSig,
         address[] calldata signers
     ) external payable nonReentrant whenNotPaused returns (bytes32 messageHash) {
-        // Validate payload
-        if (payload.amount == 0) revert InvalidPayload();
-        if (payload.secretHash == bytes32(0)) revert InvalidPayload();
-        if (payload.aztecAddress == bytes32(0)) revert InvalidPayload();
-        if (msg.value < messageFee) revert InsufficientStake();
+        _validatePayload(payload);
 
         // Check nonce hasn't been used
         if (_usedNonces[payload.nonce]) revert NonceAlreadyUsed();
 
-        // Calculate required signatures based on quorum
-        uint256 requiredSigners = (_activeOperatorCount * quorumThresholdBps + BASIS_POINTS - 1) / BASIS_POINTS;
-        if (requiredSigners == 0) requiredSigners = 1;
-        if (signers.length < requiredSigners) revert InsufficientSignatures();
+        // Each vault note mints once, whatever nonce it is signed with
+        if (_processedDeposits[_depositKey(payload.txHash, payload.outputIndex)]) revert DepositAlreadyProcessed();
 
-        // Verify all signers are registered operators
-        for (uint256 i = 0; i < signers.length; i++) {
-            if (!_operators[signers[i]].isActive) revert OperatorNotRegistered();
-            // Check for duplicates
-            for (uint256 j = i + 1; j < signers.length; j++) {
-                if (signers[i] == signers[j]) revert InvalidSignature();
-            }
-        }
+        // Verify BLS aggregate signature over the EIP-712 typed data hash
+        _verifySigners(_computePayloadHash(payload), aggregatedSig, signers);
 
-        // Compute EIP-712 typed data hash
-        bytes32 payloadHash = _computePayloadHash(payload);
+        // Mark nonce as used; _dispatch marks the note
+        _usedNonces[payload.nonce] = true;
 
-        // Verify BLS aggregate signature
-        bool valid = blsVerifier.verifySignatures(payloadHash, aggregatedSig, signers);
-        if (!valid) revert InvalidSignature();
+        return _dispatch(payload);
+    }
 
-        // Mark nonce as used
-        _usedNonces[payload.nonce] = true;
+    /**
+     * @inheritdoc IServiceManager
+     */
+    function verifyBatch(
+        bytes32 root,
+        uint64 nonce,
+        uint32 count,
+        bytes calldata aggregatedSig,
+        address[] calldata signers
+    ) external nonReentrant whenNotPaused {
+        if (root == bytes32(0) || count == 0) revert InvalidPayload();
+        if (_batchRoots[nonce] != bytes32(0)) revert NonceAlreadyUsed();
 
-        // Compute content hash for L2 message
-        bytes32 contentHash = keccak256(
-            abi.encode(
-                payload.amount,
-                payload.secretHash,
-                payload.aztecAddress,
-                payload.txHash
-            )
-        );
+        _verifySigners(_computeBatchHash(root, nonce, count), aggregatedSig, signers);
 
-        // Dispatch message to Aztec L2
-        messageHash = inbox.sendL1ToL2Message{value: messageFee}(
-            l2BridgeAddress,
-            contentHash,
-            payload.secretHash,
-            block.number + messageDeadlineBlocks
-        );
+        _batchRoots[nonce] = root;
+        _verifiedRoots[root] = true;
 
-        emit DepositVerified(
-            payload.txHash,
-            payload.amount,
-            payload.secretHash,
-            payload.aztecAddress,
-            messageHash
-        );
+        emit BatchVerified(root, nonce, count);
+    }
 
-        // Refund excess ETH
-        if (msg.value > messageFee) {
-            (bool success, ) = payable(msg.sender).call{value: msg.value - messageFee}("");
-            require(success, "Refund failed");
-        }
+    /**
+     * @inheritdoc IServiceManager
+     */
+    function dispatchBatched(
+        DepositPayload calldata payload,
+        bytes32 root,
+        bytes32[] calldata proof
+    ) external payable nonReentrant whenNotPaused returns (bytes32 messageHash) {
+        _validatePayload(payload);
 
-        return messageHash;
+        if (!_verifiedRoots[root]) revert UnknownBatchRoot();
+        if (!MerkleProof.verifyCalldata(proof, root, _depositLeaf(payload))) revert InvalidMerkleProof();
+        if (_processedDeposits[_depositKey(payload.txHash, payload.outputIndex)]) revert DepositAlreadyProcessed();
+
+        return _dispatch(payload);
     }
 
     /**
@@ -391,6 +396,20 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
         return _usedNonces[nonce];
     }
 
+    /**
+     * @inheritdoc IServiceManager
+     */
+    function isDepositProcessed(bytes32 txHash, uint32 outputIndex) external view returns (bool) {
+        return _processedDeposits[_depositKey(txHash, outputIndex)];
+    }
+
+    /**
+     * @inheritdoc IServiceManager
+     */
+    function batchRoot(uint64 nonce) external view returns (bytes32) {
+        return _batchRoots[nonce];
+    }
+
     /**
      * @inheritdoc IServiceManager
      */
@@ -506,12 +525,16 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
         bytes32 structHash = keccak256(
             abi.encode(
                 DEPOSIT_PAYLOAD_TYPEHASH,
+                payload.version,
                 payload.txHash,
+                payload.outputIndex,
                 payload.amount,
+                payload.fee,
                 payload.secretHash,
                 payload.aztecAddress,
                 payload.nonce,
-                payload.blockHeight
+                payload.blockHeight,
+                payload.blockHash
             )
         );
 
@@ -520,6 +543,134 @@ contract ServiceManager is IServiceManager, Ownable2Step, ReentrancyGuard, Pausa
         );
     }
 
+    /**
+     * @notice Compute EIP-712 typed data hash for a batch root
+     * @param root Merkle root over the batched deposits
+     * @param nonce Batch nonce
+     * @param count Number of deposits in the batch
+     * @return The typed data hash
+     */
+    function _computeBatchHash(bytes32 root, uint64 nonce, uint32 count) internal view returns (bytes32) {
+        bytes32 structHash = keccak256(abi.encode(BATCH_ROOT_TYPEHASH, root, nonce, count));
+
+        return keccak256(
+            abi.encodePacked("\x19\x01", DOMAIN_SEPARATOR, structHash)
+        );
+    }
+
+    /**
+     * @notice Merkle leaf of a deposit in a batch: its attested fields, without the nonce
+     * @param payload The deposit payload
+     * @return The leaf hash
+     */
+    function _depositLeaf(DepositPayload calldata payload) internal pure returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                payload.version,
+                payload.txHash,
+                payload.outputIndex,
+                payload.amount,
+                payload.fee,
+                payload.secretHash,
+                payload.aztecAddress,
+                payload.blockHeight,
+                payload.blockHash
+            )
+        );
+    }
+
+    /**
+     * @notice Reject a malformed payload or an underpaid message fee
+     * @param payload The deposit payload
+     */
+    function _validatePayload(DepositPayload calldata payload) internal view {
+        if (payload.version != PAYLOAD_VERSION) revert UnsupportedPayloadVersion(payload.version);
+        if (payload.amount == 0) revert InvalidPayload();
+        if (payload.secretHash == bytes32(0)) revert InvalidPayload();
+        if (payload.aztecAddress == bytes32(0)) revert InvalidPayload();
+        if (payload.blockHash == bytes32(0)) revert InvalidPayload();
+        if (msg.value < messageFee) revert InsufficientStake();
+    }
+
+    /**
+     * @notice Require a quorum of distinct active operators to have signed a hash
+     * @param hash The EIP-712 typed data hash
+     * @param aggregatedSig Aggregated signature over the hash
+     * @param signers Operators who signed, in signature order
+     */
+    function _verifySigners(bytes32 hash, bytes calldata aggregatedSig, address[] calldata signers) internal view {
+        // Calculate required signatures based on quorum
+        uint256 requiredSigners = (_activeOperatorCount * quorumThresholdBps + BASIS_POINTS - 1) / BASIS_POINTS;
+        if (requiredSigners == 0) requiredSigners = 1;
+        if (signers.length < requiredSigners) revert InsufficientSignatures();
+
+        // Verify all signers are registered operators
+        for (uint256 i = 0; i < signers.length; i++) {
+            if (!_operators[signers[i]].isActive) revert OperatorNotRegistered();
+            // Check for duplicates
+            for (uint256 j = i + 1; j < signers.length; j++) {
+                if (signers[i] == signers[j]) revert InvalidSignature();
+            }
+        }
+
+        bool valid = blsVerifier.verifySignatures(hash, aggregatedSig, signers);
+        if (!valid) revert InvalidSignature();
+    }
+
+    /**
+     * @notice Mark a verified deposit's note processed and send its L1->L2 message
+     * @param payload The verified deposit payload
+     * @return messageHash The hash of the dispatched L1->L2 message
+     */
+    function _dispatch(DepositPayload calldata payload) internal returns (bytes32 messageHash) {
+        _processedDeposits[_depositKey(payload.txHash, payload.outputIndex)] = true;
+
+        // Compute content hash for L2 message
+        bytes32 contentHash = keccak256(
+            abi.encode(
+                payload.amount,
+                payload.secretHash,
+                payload.aztecAddress,
+                payload.txHash
+            )
+        );
+
+        // Dispatch message to Aztec L2
+        messageHash = inbox.sendL1ToL2Message{value: messageFee}(
+            l2BridgeAddress,
+            contentHash,
+            payload.secretHash,
+            block.number + messageDeadlineBlocks
+        );
+
+        emit DepositVerified(
+            payload.txHash,
+            payload.amount,
+            payload.secretHash,
+            payload.aztecAddress,
+            messageHash,
+            payload.outputIndex
+        );
+
+        // Refund excess ETH
+        if (msg.value > messageFee) {
+            (bool success, ) = payable(msg.sender).call{value: msg.value - messageFee}("");
+            require(success, "Refund failed");
+        }
+
+        return messageHash;
+    }
+
+    /**
+     * @notice Key of a Zcash vault note in the processed-deposit mapping
+     * @param txHash Zcash transaction hash
+     * @param outputIndex Index of the vault note in the transaction
+     * @return The mapping key
+     */
+    function _depositKey(bytes32 txHash, uint32 outputIndex) internal pure returns (bytes32) {
+        return keccak256(abi.encode(txHash, outputIndex));
+    }
+
     /**
      * @notice Verify a fraud proof (simplified)
      * @dev In production, implement proper fraud proof verification
@@ -11,12 +11,16 @@ interface IServiceManager {
 
     /// @notice Deposit payload from Zcash shielded transaction
     struct DepositPayload {
+        uint8 version;            // Payload format version
         bytes32 txHash;           // Zcash transaction hash
-        uint256 amount;           // Amount in zatoshi (1 ZEC = 10^8 zatoshi)
+        uint32 outputIndex;       // Vault note index in the transaction (Orchard offset by 2^30)
+        uint256 amount;           // Amount minted in zatoshi, after the fee (1 ZEC = 10^8 zatoshi)
+        uint256 fee;              // Bridge fee in zatoshi, kept in the vault
         bytes32 secretHash;       // Hash of the claim secret
         bytes32 aztecAddress;     // Recipient's Aztec address
         uint64 nonce;             // Unique nonce for replay protection
         uint32 blockHeight;       // Zcash block height
+        bytes32 blockHash;        // Hash of the Zcash block at blockHeight
     }
 
     /// @notice Operator information
@@ -44,9 +48,13 @@ interface IServiceManager {
         uint256 amount,
         bytes32 secretHash,
         bytes32 aztecAddress,
-        bytes32 messageHash
+        bytes32 messageHash,
+        uint32 outputIndex
     );
 
+    /// @notice Emitted when operators attest a Merkle root over a batch of deposits
+    event BatchVerified(bytes32 indexed root, uint64 nonce, uint32 count);
+
     /// @notice Emitted when a withdrawal is processed
     event WithdrawalProcessed(
         bytes32 indexed messageHash,
@@ -62,8 +70,12 @@ interface IServiceManager {
     error InvalidSignature();
     error InsufficientSignatures();
     error NonceAlreadyUsed();
+    error DepositAlreadyProcessed();
     error InvalidPayload();
+    error UnsupportedPayloadVersion(uint8 version);
     error UnauthorizedCaller();
+    error UnknownBatchRoot();
+    error InvalidMerkleProof();
 
     // ============ Functions ============
 
@@ -91,6 +103,37 @@ interface IServiceManager {
         address[] calldata signers
     ) external payable returns (bytes32 messageHash);
 
+    /**
+     * @notice Verify operator signatures over a Merkle root of deposit leaves
+     * @dev Leaves are keccak256(abi.encode(version, txHash, outputIndex, amount, fee, secretHash,
+     *      aztecAddress, blockHeight, blockHash)); each deposit is then minted with dispatchBatched
+     * @param root Merkle root over the batched deposits, pairs hashed in sorted order
+     * @param nonce Batch nonce, used once
+     * @param count Number of deposits in the batch
+     * @param aggregatedSig Aggregated BLS signature (or mock ECDSA signatures)
+     * @param signers Array of operator addresses who signed
+     */
+    function verifyBatch(
+        bytes32 root,
+        uint64 nonce,
+        uint32 count,
+        bytes calldata aggregatedSig,
+        address[] calldata signers
+    ) external;
+
+    /**
+     * @notice Dispatch a deposit included in a verified batch
+     * @param payload The deposit payload; its nonce is ignored
+     * @param root Root of the verified batch including the deposit
+     * @param proof Sibling hashes from the deposit's leaf up to the root
+     * @return messageHash The hash of the dispatched L1->L2 message
+     */
+    function dispatchBatched(
+        DepositPayload calldata payload,
+        bytes32 root,
+        bytes32[] calldata proof
+    ) external payable returns (bytes32 messageHash);
+
     /**
      * @notice Slash an operator for misbehavior
      * @param operator Address of the operator to slash
@@ -112,6 +155,21 @@ interface IServiceManager {
      */
     function isNonceUsed(uint64 nonce) external view returns (bool);
 
+    /**
+     * @notice Check if a Zcash vault note has already been dispatched
+     * @param txHash Zcash transaction hash
+     * @param outputIndex Index of the vault note in the transaction
+     * @return Whether the note has been dispatched
+     */
+    function isDepositProcessed(bytes32 txHash, uint32 outputIndex) external view returns (bool);
+
+    /**
+     * @notice Root verified under a batch nonce
+     * @param nonce The batch nonce
+     * @return The verified root, or zero if the nonce is unused
+     */
+    function batchRoot(uint64 nonce) external view returns (bytes32);
+
     /**
      * @notice Get the minimum stake required for operators
      * @return Minimum stake amount
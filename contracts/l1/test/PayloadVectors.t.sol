@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.30;
+
+import {Test} from "forge-std/Test.sol";
+import {ServiceManager} from "../src/ServiceManager.sol";
+import {BLSVerifier} from "../src/BLSVerifier.sol";
+import {Inbox} from "../src/Inbox.sol";
+import {IServiceManager} from "../src/interfaces/IServiceManager.sol";
+import {IBLSVerifier} from "../src/interfaces/IBLSVerifier.sol";
+import {ECDSA} from "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+import {MessageHashUtils} from "@openzeppelin/contracts/utils/cryptography/MessageHashUtils.sol";
+
+/**
+ * @title PayloadVectorsTest
+ * @notice Locks the sentinel's payload encoding to the contract's
+ * @dev Vectors come from `sentinel test-vectors --output test/vectors/payloads.json`
+ */
+contract PayloadVectorsTest is Test {
+    using MessageHashUtils for bytes32;
+
+    string internal json;
+    ServiceManager internal serviceManager;
+    BLSVerifier internal blsVerifier;
+
+    bytes32 public constant L2_BRIDGE_ADDRESS = bytes32(uint256(0x1234));
+
+    /// @dev Anvil dev account #0, the key the vectors are signed with
+    uint256 internal constant VECTOR_KEY = 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80;
+
+    function setUp() public {
+        json = vm.readFile(string.concat(vm.projectRoot(), "/test/vectors/payloads.json"));
+
+        // Deploy at the vectors' verifying contract on their chain
+        vm.chainId(_uint(".chain_id"));
+        blsVerifier = new BLSVerifier();
+        Inbox inbox = new Inbox(L2_BRIDGE_ADDRESS);
+        address verifyingContract = vm.parseJsonAddress(json, ".verifying_contract");
+        deployCodeTo(
+            "ServiceManager.sol:ServiceManager",
+            abi.encode(address(blsVerifier), address(inbox), L2_BRIDGE_ADDRESS, address(0), 1 ether, uint256(6700)),
+            verifyingContract
+        );
+        serviceManager = ServiceManager(payable(verifyingContract));
+    }
+
+    function test_DomainSeparator() public view {
+        assertEq(serviceManager.DOMAIN_SEPARATOR(), vm.parseJsonBytes32(json, ".domain_separator"));
+    }
+
+    function test_PayloadVectors() public view {
+        address signer = vm.parseJsonAddress(json, ".signer");
+        uint256 count = vm.parseJsonStringArray(json, ".vectors[*].name").length;
+        assertGt(count, 0);
+
+        for (uint256 i = 0; i < count; i++) {
+            _checkVector(string.concat(".vectors[", vm.toString(i), "]"), signer);
+        }
+    }
+
+    /// @dev The Rust signatures are accepted by verifyAndDispatch itself
+    function test_VectorsDispatch() public {
+        address signer = vm.parseJsonAddress(json, ".signer");
+        vm.deal(signer, 10 ether);
+        vm.startPrank(signer);
+        blsVerifier.registerBLSKey(IBLSVerifier.G1Point({x: 1, y: 2}));
+        serviceManager.registerOperator{value: 1 ether}(1 ether);
+
+        address[] memory signers = new address[](1);
+        signers[0] = signer;
+        uint256 count = vm.parseJsonStringArray(json, ".vectors[*].name").length;
+
+        for (uint256 i = 0; i < count; i++) {
+            string memory key = string.concat(".vectors[", vm.toString(i), "]");
+            IServiceManager.DepositPayload memory payload = _payload(key);
+            bytes memory signature = vm.parseJsonBytes(json, string.concat(key, ".signature"));
+            uint256 fee = serviceManager.messageFee();
+
+            // Any field the signature does not cover would let a relayer change it
+            payload.fee += 1;
+            vm.expectRevert(IServiceManager.InvalidSignature.selector);
+            serviceManager.verifyAndDispatch{value: fee}(payload, signature, signers);
+            payload.fee -= 1;
+
+            bytes32 messageHash = serviceManager.verifyAndDispatch{value: fee}(payload, signature, signers);
+            assertTrue(messageHash != bytes32(0));
+            assertTrue(serviceManager.isNonceUsed(payload.nonce));
+            assertTrue(serviceManager.isDepositProcessed(payload.txHash, payload.outputIndex));
+        }
+        vm.stopPrank();
+    }
+
+    /// @dev A note re-signed under a fresh nonce still mints only once
+    function test_DepositProcessedOnce() public {
+        address signer = vm.parseJsonAddress(json, ".signer");
+        assertEq(vm.addr(VECTOR_KEY), signer);
+        vm.deal(signer, 10 ether);
+        vm.startPrank(signer);
+        blsVerifier.registerBLSKey(IBLSVerifier.G1Point({x: 1, y: 2}));
+        serviceManager.registerOperator{value: 1 ether}(1 ether);
+
+        address[] memory signers = new address[](1);
+        signers[0] = signer;
+        uint256 fee = serviceManager.messageFee();
+        IServiceManager.DepositPayload memory payload = _payload(".vectors[0]");
+        serviceManager.verifyAndDispatch{value: fee}(payload, _sign(payload), signers);
+
+        payload.nonce += 1;
+        vm.expectRevert(IServiceManager.DepositAlreadyProcessed.selector);
+        serviceManager.verifyAndDispatch{value: fee}(payload, _sign(payload), signers);
+
+        // Another note in the same transaction is a separate deposit
+        payload.outputIndex += 1;
+        serviceManager.verifyAndDispatch{value: fee}(payload, _sign(payload), signers);
+        vm.stopPrank();
+    }
+
+    function _checkVector(string memory key, address signer) internal view {
+        IServiceManager.DepositPayload memory payload = _payload(key);
+        assertEq(payload.amount + payload.fee, _uint(string.concat(key, ".amount")));
+        assertEq(payload.version, serviceManager.PAYLOAD_VERSION());
+
+        // Plain payload hash, for debugging encodings
+        bytes32 payloadHash = keccak256(
+            abi.encode(
+                payload.version,
+                payload.txHash,
+                payload.outputIndex,
+                payload.amount,
+                payload.fee,
+                payload.secretHash,
+                payload.aztecAddress,
+                payload.nonce,
+                payload.blockHeight,
+                payload.blockHash
+            )
+        );
+        assertEq(payloadHash, vm.parseJsonBytes32(json, string.concat(key, ".payload_hash")));
+
+        // What ServiceManager._computePayloadHash verifies and the sentinel signs
+        bytes32 digest = _digest(payload);
+        assertEq(digest, vm.parseJsonBytes32(json, string.concat(key, ".eip712_digest")));
+
+        bytes memory signature = vm.parseJsonBytes(json, string.concat(key, ".signature"));
+        assertEq(ECDSA.recover(digest.toEthSignedMessageHash(), signature), signer);
+    }
+
+    /// @dev EIP-712 digest of a payload under the deployed domain
+    function _digest(IServiceManager.DepositPayload memory payload) internal view returns (bytes32) {
+        bytes32 structHash = keccak256(
+            abi.encode(
+                serviceManager.DEPOSIT_PAYLOAD_TYPEHASH(),
+                payload.version,
+                payload.txHash,
+                payload.outputIndex,
+                payload.amount,
+                payload.fee,
+                payload.secretHash,
+                payload.aztecAddress,
+                payload.nonce,
+                payload.blockHeight,
+                payload.blockHash
+            )
+        );
+        return keccak256(abi.encodePacked("\x19\x01", serviceManager.DOMAIN_SEPARATOR(), structHash));
+    }
+
+    /// @dev Sign a payload as the sentinel does, with the vector key
+    function _sign(IServiceManager.DepositPayload memory payload) internal view returns (bytes memory) {
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(VECTOR_KEY, _digest(payload).toEthSignedMessageHash());
+        return abi.encodePacked(r, s, v);
+    }
+
+    /// @dev The payload as submitted to verifyAndDispatch, with the net amount
+    function _payload(string memory key) internal view returns (IServiceManager.DepositPayload memory payload) {
+        payload.version = uint8(_uint(string.concat(key, ".version")));
+        payload.txHash = vm.parseJsonBytes32(json, string.concat(key, ".tx_hash"));
+        payload.outputIndex = uint32(_uint(string.concat(key, ".output_index")));
+        payload.amount = _uint(string.concat(key, ".net_amount"));
+        payload.fee = _uint(string.concat(key, ".fee"));
+        payload.secretHash = vm.parseJsonBytes32(json, string.concat(key, ".secret_hash"));
+        payload.aztecAddress = vm.parseJsonBytes32(json, string.concat(key, ".aztec_address"));
+        payload.nonce = uint64(_uint(string.concat(key, ".nonce")));
+        payload.blockHeight = uint32(_uint(string.concat(key, ".block_height")));
+        payload.blockHash = vm.parseJsonBytes32(json, string.concat(key, ".block_hash"));
+    }
+
+    function _uint(string memory key) internal view returns (uint256) {
+        return vm.parseUint(vm.parseJsonString(json, key));
+    }
+}
@@ -7,12 +7,15 @@ import {BLSVerifier} from "../src/BLSVerifier.sol";
 import {Inbox} from "../src/Inbox.sol";
 import {IServiceManager} from "../src/interfaces/IServiceManager.sol";
 import {IBLSVerifier} from "../src/interfaces/IBLSVerifier.sol";
+import {MessageHashUtils} from "@openzeppelin/contracts/utils/cryptography/MessageHashUtils.sol";
 
 /**
  * @title ServiceManagerTest
  * @notice Comprehensive tests for the production ServiceManager contract
  */
 contract ServiceManagerTest is Test {
+    using MessageHashUtils for bytes32;
+
     ServiceManager public serviceManager;
     BLSVerifier public blsVerifier;
     Inbox public inbox;
@@ -335,4 +338,157 @@ contract ServiceManagerTest is Test {
         serviceManager.acceptOwnership();
         assertEq(serviceManager.owner(), newOwner);
     }
+
+    // ============ Batch Tests ============
+
+    function test_VerifyBatchAndDispatchBatched() public {
+        (address signer, uint256 key) = _registerSigningOperator();
+        IServiceManager.DepositPayload memory first = _batchPayload(0);
+        IServiceManager.DepositPayload memory second = _batchPayload(1);
+        bytes32 firstLeaf = _leaf(first);
+        bytes32 secondLeaf = _leaf(second);
+        bytes32 root = _hashPair(firstLeaf, secondLeaf);
+
+        serviceManager.verifyBatch(root, 7, 2, _signBatch(key, root, 7, 2), _signers(signer));
+        assertEq(serviceManager.batchRoot(7), root);
+
+        bytes32[] memory proof = new bytes32[](1);
+        proof[0] = secondLeaf;
+        uint256 fee = serviceManager.messageFee();
+        bytes32 messageHash = serviceManager.dispatchBatched{value: fee}(first, root, proof);
+        assertTrue(messageHash != bytes32(0));
+        assertTrue(serviceManager.isDepositProcessed(first.txHash, first.outputIndex));
+        assertFalse(serviceManager.isDepositProcessed(second.txHash, second.outputIndex));
+
+        // Each note mints once
+        vm.expectRevert(IServiceManager.DepositAlreadyProcessed.selector);
+        serviceManager.dispatchBatched{value: fee}(first, root, proof);
+
+        proof[0] = firstLeaf;
+        serviceManager.dispatchBatched{value: fee}(second, root, proof);
+        assertTrue(serviceManager.isDepositProcessed(second.txHash, second.outputIndex));
+    }
+
+    function test_RevertWhen_BatchNonceReused() public {
+        (address signer, uint256 key) = _registerSigningOperator();
+        bytes32 root = _leaf(_batchPayload(0));
+        serviceManager.verifyBatch(root, 1, 1, _signBatch(key, root, 1, 1), _signers(signer));
+
+        bytes32 other = _leaf(_batchPayload(1));
+        bytes memory signature = _signBatch(key, other, 1, 1);
+        address[] memory signers = _signers(signer);
+        vm.expectRevert(IServiceManager.NonceAlreadyUsed.selector);
+        serviceManager.verifyBatch(other, 1, 1, signature, signers);
+    }
+
+    function test_RevertWhen_BatchSignatureInvalid() public {
+        (address signer, uint256 key) = _registerSigningOperator();
+        bytes32 root = _leaf(_batchPayload(0));
+        address[] memory signers = _signers(signer);
+
+        // A signature over another count, or for another deployment, is refused
+        bytes memory otherCount = _signBatch(key, root, 1, 2);
+        vm.expectRevert(IServiceManager.InvalidSignature.selector);
+        serviceManager.verifyBatch(root, 1, 1, otherCount, signers);
+
+        bytes32 otherDomain = keccak256(
+            abi.encodePacked(
+                "\x19\x01",
+                keccak256("another deployment"),
+                keccak256(abi.encode(serviceManager.BATCH_ROOT_TYPEHASH(), root, uint64(1), uint32(1)))
+            )
+        );
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(key, otherDomain.toEthSignedMessageHash());
+        vm.expectRevert(IServiceManager.InvalidSignature.selector);
+        serviceManager.verifyBatch(root, 1, 1, abi.encodePacked(r, s, v), signers);
+    }
+
+    function test_RevertWhen_BatchedDepositUnproven() public {
+        (address signer, uint256 key) = _registerSigningOperator();
+        IServiceManager.DepositPayload memory first = _batchPayload(0);
+        bytes32 root = _hashPair(_leaf(first), _leaf(_batchPayload(1)));
+        uint256 fee = serviceManager.messageFee();
+        bytes32[] memory proof = new bytes32[](1);
+        proof[0] = _leaf(_batchPayload(1));
+
+        vm.expectRevert(IServiceManager.UnknownBatchRoot.selector);
+        serviceManager.dispatchBatched{value: fee}(first, root, proof);
+
+        serviceManager.verifyBatch(root, 1, 2, _signBatch(key, root, 1, 2), _signers(signer));
+
+        // A deposit outside the batch does not prove against its root
+        IServiceManager.DepositPayload memory outside = _batchPayload(2);
+        vm.expectRevert(IServiceManager.InvalidMerkleProof.selector);
+        serviceManager.dispatchBatched{value: fee}(outside, root, proof);
+
+        // Nor does a batched deposit with a changed amount
+        first.amount += 1;
+        vm.expectRevert(IServiceManager.InvalidMerkleProof.selector);
+        serviceManager.dispatchBatched{value: fee}(first, root, proof);
+    }
+
+    /// @dev Register an operator whose signing key the test holds
+    function _registerSigningOperator() internal returns (address signer, uint256 key) {
+        (signer, key) = makeAddrAndKey("batchOperator");
+        vm.deal(signer, 10 ether);
+        vm.startPrank(signer);
+        blsVerifier.registerBLSKey(IBLSVerifier.G1Point({x: 5, y: 6}));
+        serviceManager.registerOperator{value: MINIMUM_STAKE}(MINIMUM_STAKE);
+        vm.stopPrank();
+    }
+
+    /// @dev A distinct valid deposit for each index
+    function _batchPayload(uint32 outputIndex) internal view returns (IServiceManager.DepositPayload memory) {
+        return IServiceManager.DepositPayload({
+            version: serviceManager.PAYLOAD_VERSION(),
+            txHash: keccak256("batch deposit"),
+            outputIndex: outputIndex,
+            amount: 100_000,
+            fee: 0,
+            secretHash: keccak256(abi.encode("secret", outputIndex)),
+            aztecAddress: bytes32(uint256(0xa2)),
+            nonce: 0,
+            blockHeight: 100,
+            blockHash: keccak256("block")
+        });
+    }
+
+    /// @dev Batch leaf of a deposit, as the sentinel computes it
+    function _leaf(IServiceManager.DepositPayload memory payload) internal pure returns (bytes32) {
+        return keccak256(
+            abi.encode(
+                payload.version,
+                payload.txHash,
+                payload.outputIndex,
+                payload.amount,
+                payload.fee,
+                payload.secretHash,
+                payload.aztecAddress,
+                payload.blockHeight,
+                payload.blockHash
+            )
+        );
+    }
+
+    function _hashPair(bytes32 a, bytes32 b) internal pure returns (bytes32) {
+        return a < b ? keccak256(abi.encodePacked(a, b)) : keccak256(abi.encodePacked(b, a));
+    }
+
+    /// @dev Sign a batch root under the deployed domain
+    function _signBatch(uint256 key, bytes32 root, uint64 nonce, uint32 count) internal view returns (bytes memory) {
+        bytes32 digest = keccak256(
+            abi.encodePacked(
+                "\x19\x01",
+                serviceManager.DOMAIN_SEPARATOR(),
+                keccak256(abi.encode(serviceManager.BATCH_ROOT_TYPEHASH(), root, nonce, count))
+            )
+        );
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(key, digest.toEthSignedMessageHash());
+        return abi.encodePacked(r, s, v);
+    }
+
+    function _signers(address signer) internal pure returns (address[] memory signers) {
+        signers = new address[](1);
+        signers[0] = signer;
+    }
 }
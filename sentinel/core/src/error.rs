@@ -0,0 +1,31 @@
+//! Error types for the shared encoding logic
+
+use thiserror::Error;
+
+/// Errors from parsing, encoding or chain lookups
+#[derive(Error, Debug)]
+pub enum CoreError {
+    /// Memo parsing error
+    #[error("Memo parsing error: {0}")]
+    MemoParse(String),
+
+    /// Invalid payload error
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(String),
+
+    /// Chain source error
+    #[error("Chain source error: {0}")]
+    Source(String),
+}
+
+impl From<serde_json::Error> for CoreError {
+    fn from(err: serde_json::Error) -> Self {
+        CoreError::MemoParse(err.to_string())
+    }
+}
+
+impl From<hex::FromHexError> for CoreError {
+    fn from(err: hex::FromHexError) -> Self {
+        CoreError::InvalidPayload(err.to_string())
+    }
+}
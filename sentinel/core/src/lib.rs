@@ -0,0 +1,27 @@
+//! Shared encoding logic of the Zcash-Aztec Bridge
+//!
+//! The sentinel, the aggregator and wallet integrators must agree byte for
+//! byte on how deposits are described and attested. This crate holds that
+//! logic:
+//!
+//! - [`memo`]: the JSON deposit memo (parsing and creation)
+//! - [`payload`]: deposit payloads, attestations and the hashes signed over
+//!   them, matching the ServiceManager's encoding
+//! - [`source`]: the chain queries used to confirm a deposit
+//!
+//! ```
+//! use sentinel_core::memo::MemoParser;
+//!
+//! let memo = MemoParser::create_memo(&[0x12; 32], &[0x34; 32]).unwrap();
+//! let parsed = MemoParser::new().parse(&memo).unwrap().unwrap();
+//! assert_eq!(parsed.secret_hash, [0x34; 32]);
+//! ```
+
+pub mod error;
+pub mod memo;
+pub mod payload;
+pub mod source;
+
+pub use error::CoreError;
+pub use payload::{Attestation, BridgePayload};
+pub use source::ChainSource;
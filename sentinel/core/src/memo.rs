@@ -0,0 +1,339 @@
+//! Memo field parsing for bridge payloads
+//!
+//! Bridge deposits include a JSON payload in the Zcash memo field:
+//! {
+//!     "type": "bridge_deposit",
+//!     "aztec_address": "0x...",
+//!     "secret_hash": "0x...",
+//!     "version": 1
+//! }
+//!
+//! Optional fields: `refund_address`, `amount` (expected note value in
+//! zatoshi, checked against the decrypted note before attesting), `target`
+//! (the L1 deployment to attest on, by name) and `domain` (the bridge
+//! deployment the deposit is meant for, when one sentinel serves several).
+
+use crate::error::CoreError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+/// Parser for bridge memo payloads
+pub struct MemoParser {
+    /// Expected memo version
+    expected_version: u8,
+
+    /// Bridge domain memos must name, if any
+    domain: Option<String>,
+}
+
+/// Raw memo payload structure
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoPayload {
+    /// Message type identifier
+    #[serde(rename = "type")]
+    pub msg_type: String,
+
+    /// Recipient's Aztec address (hex encoded)
+    pub aztec_address: String,
+
+    /// Hash of the claim secret (hex encoded)
+    pub secret_hash: String,
+
+    /// Protocol version
+    pub version: u8,
+
+    /// Zcash address to refund to if the deposit is rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_address: Option<String>,
+
+    /// Amount the depositor intended to send, in zatoshi
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+
+    /// L1 deployment to attest on, by name (the sentinel's default if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// Bridge deployment the deposit is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+/// Parsed bridge payload from memo
+#[derive(Debug, Clone)]
+pub struct ParsedPayload {
+    /// Aztec address as bytes
+    pub aztec_address: [u8; 32],
+
+    /// Secret hash as bytes
+    pub secret_hash: [u8; 32],
+
+    /// Refund address supplied by the depositor
+    pub refund_address: Option<String>,
+
+    /// Expected note value claimed by the memo
+    pub amount: Option<u64>,
+
+    /// L1 target requested by the memo
+    pub target: Option<String>,
+}
+
+/// A JSON memo of any message type
+#[derive(Debug, Clone)]
+pub struct MemoMessage {
+    /// Message type identifier
+    pub msg_type: String,
+
+    /// The whole memo JSON object
+    pub body: Value,
+}
+
+impl MemoParser {
+    /// Create a new memo parser
+    pub fn new() -> Self {
+        Self {
+            expected_version: 1,
+            domain: None,
+        }
+    }
+
+    /// Only accept memos naming bridge `domain`; without one, only memos naming none
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Parse a memo field into a bridge payload
+    pub fn parse(&self, memo: &[u8; 512]) -> Result<Option<ParsedPayload>, CoreError> {
+        let Some(json_str) = memo_text(memo) else {
+            return Ok(None);
+        };
+
+        // Try to parse as JSON
+        let payload: MemoPayload = match serde_json::from_str(json_str) {
+            Ok(p) => p,
+            Err(_) => {
+                debug!("Memo is not valid JSON, skipping");
+                return Ok(None);
+            }
+        };
+
+        // Validate message type
+        if payload.msg_type != "bridge_deposit" {
+            debug!("Memo type is not bridge_deposit: {}", payload.msg_type);
+            return Ok(None);
+        }
+
+        // Validate version
+        if payload.version != self.expected_version {
+            warn!(
+                "Unexpected memo version: {} (expected {})",
+                payload.version, self.expected_version
+            );
+            return Ok(None);
+        }
+
+        // Leave deposits for other bridge deployments to their sentinels
+        if payload.domain != self.domain {
+            debug!("Memo is for bridge domain {:?}, skipping", payload.domain);
+            return Ok(None);
+        }
+
+        // Parse Aztec address
+        let aztec_address = self.parse_hex_address(&payload.aztec_address)?;
+
+        // Parse secret hash
+        let secret_hash = self.parse_hex_address(&payload.secret_hash)?;
+
+        Ok(Some(ParsedPayload {
+            aztec_address,
+            secret_hash,
+            refund_address: payload.refund_address,
+            amount: payload.amount,
+            target: payload.target,
+        }))
+    }
+
+    /// Parse a memo of any message type, for routing to its handler
+    pub fn parse_message(&self, memo: &[u8; 512]) -> Option<MemoMessage> {
+        let body: Value = serde_json::from_str(memo_text(memo)?).ok()?;
+        let msg_type = body.get("type")?.as_str()?.to_string();
+        Some(MemoMessage { msg_type, body })
+    }
+
+    /// Parse a hex-encoded address into bytes
+    fn parse_hex_address(&self, hex_str: &str) -> Result<[u8; 32], CoreError> {
+        let hex_str = hex_str.strip_prefix("0x").unwrap_or(hex_str);
+
+        if hex_str.len() != 64 {
+            return Err(CoreError::InvalidPayload(format!(
+                "Invalid hex length: expected 64, got {}",
+                hex_str.len()
+            )));
+        }
+
+        let bytes = hex::decode(hex_str)?;
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&bytes);
+
+        Ok(result)
+    }
+
+    /// Create a memo payload for a deposit
+    pub fn create_memo(
+        aztec_address: &[u8; 32],
+        secret_hash: &[u8; 32],
+    ) -> Result<[u8; 512], CoreError> {
+        let payload = MemoPayload {
+            msg_type: "bridge_deposit".to_string(),
+            aztec_address: format!("0x{}", hex::encode(aztec_address)),
+            secret_hash: format!("0x{}", hex::encode(secret_hash)),
+            version: 1,
+            refund_address: None,
+            amount: None,
+            target: None,
+            domain: None,
+        };
+
+        let json = serde_json::to_string(&payload)?;
+
+        if json.len() > 512 {
+            return Err(CoreError::InvalidPayload(
+                "Memo payload too large".to_string(),
+            ));
+        }
+
+        let mut memo = [0u8; 512];
+        memo[..json.len()].copy_from_slice(json.as_bytes());
+
+        Ok(memo)
+    }
+}
+
+/// The memo's text up to the null terminator, if it is non-empty UTF-8
+fn memo_text(memo: &[u8; 512]) -> Option<&str> {
+    // Find the end of the JSON (null terminator or end of memo)
+    let json_end = memo.iter().position(|&b| b == 0).unwrap_or(512);
+
+    // Try to parse as UTF-8
+    let json_str = match std::str::from_utf8(&memo[..json_end]) {
+        Ok(s) => s.trim(),
+        Err(_) => {
+            debug!("Memo is not valid UTF-8, skipping");
+            return None;
+        }
+    };
+
+    // Skip empty memos
+    (!json_str.is_empty()).then_some(json_str)
+}
+
+/// Whether a memo carries no data (ZIP 302 "no memo", or all zeros)
+///
+/// Shielding transactions and shielded coinbase usually carry such memos.
+pub fn is_empty_memo(memo: &[u8; 512]) -> bool {
+    matches!(memo[0], 0x00 | 0xF6) && memo[1..].iter().all(|&b| b == 0)
+}
+
+impl Default for MemoParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_memo() {
+        let parser = MemoParser::new();
+
+        let json = r#"{"type":"bridge_deposit","aztec_address":"0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef","secret_hash":"0xfedcba0987654321fedcba0987654321fedcba0987654321fedcba0987654321","version":1}"#;
+
+        let mut memo = [0u8; 512];
+        memo[..json.len()].copy_from_slice(json.as_bytes());
+
+        let result = parser.parse(&memo).unwrap();
+        assert!(result.is_some());
+
+        let payload = result.unwrap();
+        assert_eq!(
+            hex::encode(payload.aztec_address),
+            "1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
+        );
+    }
+
+    #[test]
+    fn test_parse_memo_amount() {
+        let parser = MemoParser::new();
+
+        let json = r#"{"type":"bridge_deposit","aztec_address":"0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef","secret_hash":"0xfedcba0987654321fedcba0987654321fedcba0987654321fedcba0987654321","version":1,"amount":150000000}"#;
+
+        let mut memo = [0u8; 512];
+        memo[..json.len()].copy_from_slice(json.as_bytes());
+
+        let payload = parser.parse(&memo).unwrap().unwrap();
+        assert_eq!(payload.amount, Some(150_000_000));
+    }
+
+    #[test]
+    fn test_parse_invalid_type() {
+        let parser = MemoParser::new();
+
+        let json =
+            r#"{"type":"other","aztec_address":"0x1234","secret_hash":"0x5678","version":1}"#;
+
+        let mut memo = [0u8; 512];
+        memo[..json.len()].copy_from_slice(json.as_bytes());
+
+        let result = parser.parse(&memo).unwrap();
+        assert!(result.is_none());
+
+        let message = parser.parse_message(&memo).unwrap();
+        assert_eq!(message.msg_type, "other");
+        assert_eq!(message.body["secret_hash"], "0x5678");
+    }
+
+    #[test]
+    fn test_parse_memo_domain() {
+        let json = r#"{"type":"bridge_deposit","aztec_address":"0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef","secret_hash":"0xfedcba0987654321fedcba0987654321fedcba0987654321fedcba0987654321","version":1,"domain":"bridge-b"}"#;
+
+        let mut memo = [0u8; 512];
+        memo[..json.len()].copy_from_slice(json.as_bytes());
+
+        assert!(MemoParser::new().parse(&memo).unwrap().is_none());
+        let parser = MemoParser::new().with_domain(Some("bridge-a".to_string()));
+        assert!(parser.parse(&memo).unwrap().is_none());
+        let parser = MemoParser::new().with_domain(Some("bridge-b".to_string()));
+        assert!(parser.parse(&memo).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_empty_memo() {
+        let mut memo = [0u8; 512];
+        assert!(is_empty_memo(&memo));
+        memo[0] = 0xF6;
+        assert!(is_empty_memo(&memo));
+        memo[1] = b'x';
+        assert!(!is_empty_memo(&memo));
+    }
+
+    #[test]
+    fn test_create_memo() {
+        let aztec_address = [0x12u8; 32];
+        let secret_hash = [0x34u8; 32];
+
+        let memo = MemoParser::create_memo(&aztec_address, &secret_hash).unwrap();
+
+        // Parse it back
+        let parser = MemoParser::new();
+        let result = parser.parse(&memo).unwrap();
+        assert!(result.is_some());
+
+        let payload = result.unwrap();
+        assert_eq!(payload.aztec_address, aztec_address);
+        assert_eq!(payload.secret_hash, secret_hash);
+    }
+}
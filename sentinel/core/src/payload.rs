@@ -0,0 +1,325 @@
+//! Deposit payloads, attestations and their hashes
+//!
+//! The hashes are the ABI encodings the ServiceManager recomputes on L1, so
+//! anything producing or checking attestations must use these functions
+//! rather than re-deriving the encoding.
+//!
+//! Both hashes start with the format version, [`PAYLOAD_VERSION`]. Version 2
+//! added the Zcash block hash, so an attestation names the block it saw and
+//! cannot be read as one for the same height on the other side of a reorg.
+//! Version 3 added the index of the vault note within its transaction, so
+//! two deposits in one transaction are told apart.
+//! A change to the attested fields bumps the version, which the
+//! ServiceManager checks before verifying.
+
+use ethers_core::abi::{encode, ParamType, Token};
+use ethers_core::types::{Address, U256};
+use ethers_core::utils::keccak256;
+
+/// Format version of the attested fields and their encoding
+pub const PAYLOAD_VERSION: u8 = 3;
+
+/// Offset of Orchard action indexes in [`BridgePayload::output_index`], so
+/// they never collide with Sapling output indexes of the same transaction
+pub const ORCHARD_OUTPUT_OFFSET: u32 = 1 << 30;
+
+/// Bridge payload extracted from Zcash memo
+#[derive(Debug, Clone)]
+pub struct BridgePayload {
+    /// Zcash transaction hash
+    pub tx_hash: [u8; 32],
+    /// Index of the vault note in the transaction: the Sapling output
+    /// index, or the Orchard action index plus [`ORCHARD_OUTPUT_OFFSET`]
+    pub output_index: u32,
+    /// Amount in zatoshi
+    pub amount: u64,
+    /// Hash of the claim secret
+    pub secret_hash: [u8; 32],
+    /// Recipient's Aztec address
+    pub aztec_address: [u8; 32],
+    /// Block height where deposit was confirmed
+    pub block_height: u32,
+    /// Hash of the block at `block_height` (internal byte order)
+    pub block_hash: [u8; 32],
+    /// Zcash address to refund to if the deposit is rejected
+    pub refund_address: Option<String>,
+    /// Bridge fee in zatoshi, deducted from `amount` before minting
+    pub fee: u64,
+    /// Amount claimed by the memo, if any; must match the note value
+    pub memo_amount: Option<u64>,
+    /// Vault shard the note was paid to
+    pub vault_shard: String,
+    /// L1 target named by the memo, if any
+    pub target: Option<String>,
+}
+
+impl BridgePayload {
+    /// Amount minted on Aztec after the bridge fee
+    pub fn net_amount(&self) -> u64 {
+        self.amount.saturating_sub(self.fee)
+    }
+
+    /// A valid deposit for tests; override fields with struct update syntax
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn sample() -> Self {
+        Self {
+            tx_hash: [1; 32],
+            output_index: 0,
+            amount: 100_000,
+            secret_hash: [2; 32],
+            aztec_address: [3; 32],
+            block_height: 100,
+            block_hash: [0x44; 32],
+            refund_address: None,
+            fee: 0,
+            memo_amount: None,
+            vault_shard: "primary".to_string(),
+            target: None,
+        }
+    }
+}
+
+/// Attestation signed by the operator
+#[derive(Debug, Clone)]
+pub struct Attestation {
+    /// The deposit payload being attested
+    pub payload: BridgePayload,
+    /// Unique nonce for replay protection
+    pub nonce: u64,
+    /// ECDSA signature
+    pub signature: Vec<u8>,
+}
+
+/// EIP-712 type of the ServiceManager's `DepositPayload`
+///
+/// Its fields, in this order, are [`payload_tokens`], the tuple passed to
+/// [`VERIFY_AND_DISPATCH`] and what [`payload_hash`] covers. A field added
+/// here must be added to all of them and to `IServiceManager.DepositPayload`.
+pub const DEPOSIT_PAYLOAD_TYPE: &str = "DepositPayload(uint8 version,bytes32 txHash,uint32 outputIndex,uint256 amount,uint256 fee,bytes32 secretHash,bytes32 aztecAddress,uint64 nonce,uint32 blockHeight,bytes32 blockHash)";
+
+/// EIP-712 type of the root `ServiceManager.verifyBatch` verifies
+pub const BATCH_ROOT_TYPE: &str = "BatchRoot(bytes32 root,uint64 nonce,uint32 count)";
+
+/// Signature of `ServiceManager.verifyBatch`
+pub const VERIFY_BATCH: &str = "verifyBatch(bytes32,uint64,uint32,bytes,address[])";
+
+/// Signature of `ServiceManager.verifyAndDispatch`
+pub const VERIFY_AND_DISPATCH: &str = "verifyAndDispatch((uint8,bytes32,uint32,uint256,uint256,bytes32,bytes32,uint64,uint32,bytes32),bytes,address[])";
+
+/// Signature of `ServiceManager`'s `DepositVerified` event; `txHash` is the
+/// only indexed field
+pub const DEPOSIT_VERIFIED: &str = "DepositVerified(bytes32,uint256,bytes32,bytes32,bytes32,uint32)";
+
+/// The fields of [`DEPOSIT_PAYLOAD_TYPE`] for a payload and nonce
+///
+/// `amount` is the net amount minted; the fee is its own field.
+pub fn payload_tokens(payload: &BridgePayload, nonce: u64) -> Vec<Token> {
+    vec![
+        Token::Uint(U256::from(PAYLOAD_VERSION)),
+        Token::FixedBytes(payload.tx_hash.to_vec()),
+        Token::Uint(U256::from(payload.output_index)),
+        Token::Uint(U256::from(payload.net_amount())),
+        Token::Uint(U256::from(payload.fee)),
+        Token::FixedBytes(payload.secret_hash.to_vec()),
+        Token::FixedBytes(payload.aztec_address.to_vec()),
+        Token::Uint(U256::from(nonce)),
+        Token::Uint(U256::from(payload.block_height)),
+        Token::FixedBytes(payload.block_hash.to_vec()),
+    ]
+}
+
+/// ABI types of [`payload_tokens`], for decoding dispatch calldata
+pub fn payload_param_types() -> Vec<ParamType> {
+    vec![
+        ParamType::Uint(8),
+        ParamType::FixedBytes(32),
+        ParamType::Uint(32),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::FixedBytes(32),
+        ParamType::FixedBytes(32),
+        ParamType::Uint(64),
+        ParamType::Uint(32),
+        ParamType::FixedBytes(32),
+    ]
+}
+
+/// Position of `blockHeight` in [`payload_tokens`]
+pub const BLOCK_HEIGHT_FIELD: usize = 8;
+
+/// Hash of a payload and nonce (matching Solidity encoding)
+///
+/// `keccak256(abi.encode(version, txHash, outputIndex, netAmount, fee,
+/// secretHash, aztecAddress, nonce, blockHeight, blockHash))`
+pub fn payload_hash(payload: &BridgePayload, nonce: u64) -> [u8; 32] {
+    keccak256(encode(&payload_tokens(payload, nonce)))
+}
+
+/// The ServiceManager's EIP-712 domain separator
+pub fn domain_separator(chain_id: u64, verifying_contract: Address) -> [u8; 32] {
+    keccak256(encode(&[
+        Token::FixedBytes(
+            keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)")
+                .to_vec(),
+        ),
+        Token::FixedBytes(keccak256("NullGravityBridge").to_vec()),
+        Token::FixedBytes(keccak256("1").to_vec()),
+        Token::Uint(U256::from(chain_id)),
+        Token::Address(verifying_contract),
+    ]))
+}
+
+/// EIP-712 digest `ServiceManager._computePayloadHash` verifies against
+pub fn eip712_digest(
+    payload: &BridgePayload,
+    nonce: u64,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> [u8; 32] {
+    let mut tokens = vec![Token::FixedBytes(keccak256(DEPOSIT_PAYLOAD_TYPE).to_vec())];
+    tokens.extend(payload_tokens(payload, nonce));
+    let struct_hash = keccak256(encode(&tokens));
+
+    let mut message = vec![0x19, 0x01];
+    message.extend_from_slice(&domain_separator(chain_id, verifying_contract));
+    message.extend_from_slice(&struct_hash);
+    keccak256(message)
+}
+
+/// EIP-712 digest `ServiceManager.verifyBatch` verifies against: the batch
+/// root, nonce and size, bound to one chain and deployment
+pub fn batch_digest(
+    root: [u8; 32],
+    nonce: u64,
+    count: u32,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> [u8; 32] {
+    let struct_hash = keccak256(encode(&[
+        Token::FixedBytes(keccak256(BATCH_ROOT_TYPE).to_vec()),
+        Token::FixedBytes(root.to_vec()),
+        Token::Uint(U256::from(nonce)),
+        Token::Uint(U256::from(count)),
+    ]));
+
+    let mut message = vec![0x19, 0x01];
+    message.extend_from_slice(&domain_separator(chain_id, verifying_contract));
+    message.extend_from_slice(&struct_hash);
+    keccak256(message)
+}
+
+/// Hash of the attested fields of a deposit, without the nonce
+///
+/// Two attestations for one Zcash note with different deposit hashes are
+/// equivocation.
+pub fn deposit_hash(payload: &BridgePayload) -> [u8; 32] {
+    let tokens = vec![
+        Token::Uint(U256::from(PAYLOAD_VERSION)),
+        Token::FixedBytes(payload.tx_hash.to_vec()),
+        Token::Uint(U256::from(payload.output_index)),
+        Token::Uint(U256::from(payload.net_amount())),
+        Token::Uint(U256::from(payload.fee)),
+        Token::FixedBytes(payload.secret_hash.to_vec()),
+        Token::FixedBytes(payload.aztec_address.to_vec()),
+        Token::Uint(U256::from(payload.block_height)),
+        Token::FixedBytes(payload.block_hash.to_vec()),
+    ];
+
+    keccak256(encode(&tokens))
+}
+
+/// Hash of the deposit fields the depositor controls: the note, its amount
+/// and the recipient
+///
+/// Unlike [`deposit_hash`] it leaves out what the operator derives itself,
+/// the fee and the block hash, so only a change here is equivocation.
+pub fn depositor_hash(payload: &BridgePayload) -> [u8; 32] {
+    keccak256(encode(&[
+        Token::FixedBytes(payload.tx_hash.to_vec()),
+        Token::Uint(U256::from(payload.output_index)),
+        Token::Uint(U256::from(payload.amount)),
+        Token::FixedBytes(payload.secret_hash.to_vec()),
+        Token::FixedBytes(payload.aztec_address.to_vec()),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_bind_nonce_and_fee() {
+        let payload = BridgePayload {
+            block_hash: [4; 32],
+            fee: 1_000,
+            ..BridgePayload::sample()
+        };
+        assert_eq!(payload.net_amount(), 99_000);
+        assert_ne!(payload_hash(&payload, 1), payload_hash(&payload, 2));
+
+        let mut refeed = payload.clone();
+        refeed.fee = 2_000;
+        refeed.amount = 101_000;
+        assert_eq!(refeed.net_amount(), payload.net_amount());
+        assert_ne!(deposit_hash(&refeed), deposit_hash(&payload));
+        assert_ne!(depositor_hash(&refeed), depositor_hash(&payload));
+
+        // The same height in a reorged block is a different deposit
+        let reorged = BridgePayload {
+            block_hash: [5; 32],
+            ..payload.clone()
+        };
+        assert_ne!(payload_hash(&reorged, 1), payload_hash(&payload, 1));
+        assert_ne!(deposit_hash(&reorged), deposit_hash(&payload));
+        // Though not one the depositor made, nor is another fee
+        assert_eq!(depositor_hash(&reorged), depositor_hash(&payload));
+        let refee = BridgePayload {
+            fee: 2_000,
+            ..payload.clone()
+        };
+        assert_eq!(depositor_hash(&refee), depositor_hash(&payload));
+
+        // So is a second vault note in the same transaction
+        let second = BridgePayload {
+            output_index: 1,
+            ..payload.clone()
+        };
+        assert_ne!(deposit_hash(&second), deposit_hash(&payload));
+        assert_ne!(depositor_hash(&second), depositor_hash(&payload));
+    }
+
+    #[test]
+    fn test_batch_digest_binds_deployment() {
+        let contract = Address::repeat_byte(1);
+        let digest = batch_digest([7; 32], 1, 2, 1, contract);
+        assert_ne!(digest, batch_digest([7; 32], 1, 3, 1, contract));
+        assert_ne!(digest, batch_digest([7; 32], 1, 2, 5, contract));
+        assert_ne!(digest, batch_digest([7; 32], 1, 2, 1, Address::repeat_byte(2)));
+    }
+
+    #[test]
+    fn test_payload_layouts_agree() {
+        // The EIP-712 type and the verifyAndDispatch tuple list the same
+        // field types, one per payload token
+        let fields = DEPOSIT_PAYLOAD_TYPE
+            .strip_prefix("DepositPayload(")
+            .and_then(|f| f.strip_suffix(')'))
+            .unwrap();
+        let types: Vec<_> = fields.split(',').map(|f| f.split(' ').next().unwrap()).collect();
+        let tuple = VERIFY_AND_DISPATCH
+            .strip_prefix("verifyAndDispatch((")
+            .and_then(|f| f.strip_suffix("),bytes,address[])"))
+            .unwrap();
+        assert_eq!(types, tuple.split(',').collect::<Vec<_>>());
+        let payload = BridgePayload::sample();
+        let tokens = payload_tokens(&payload, 1);
+        assert_eq!(types.len(), tokens.len());
+
+        let decoded = ethers_core::abi::decode(&payload_param_types(), &encode(&tokens)).unwrap();
+        assert_eq!(decoded, tokens);
+        assert_eq!(
+            decoded[BLOCK_HEIGHT_FIELD].clone().into_uint(),
+            Some(U256::from(payload.block_height))
+        );
+    }
+}
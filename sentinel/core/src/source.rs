@@ -0,0 +1,37 @@
+//! Chain data sources
+//!
+//! The few queries needed to check that a deposit is on the Zcash chain,
+//! independent of whether a full node, lightwalletd or an indexer answers
+//! them.
+
+use crate::error::CoreError;
+use async_trait::async_trait;
+
+/// A source of Zcash chain data
+#[async_trait]
+pub trait ChainSource: Send + Sync {
+    /// Source name for logs
+    fn name(&self) -> &str;
+
+    /// Height of the source's best chain tip
+    async fn tip_height(&self) -> Result<u32, CoreError>;
+
+    /// Transaction ids of the best-chain block at `height`, in block order
+    /// and internal byte order
+    async fn block_txids(&self, height: u32) -> Result<Vec<[u8; 32]>, CoreError>;
+
+    /// Whether `tx_hash` is in the block at `height` with `depth` blocks on
+    /// top of it
+    async fn is_confirmed(
+        &self,
+        tx_hash: &[u8; 32],
+        height: u32,
+        depth: u32,
+    ) -> Result<bool, CoreError> {
+        let tip = self.tip_height().await?;
+        if height > tip || tip - height < depth {
+            return Ok(false);
+        }
+        Ok(self.block_txids(height).await?.contains(tx_hash))
+    }
+}
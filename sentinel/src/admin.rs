@@ -0,0 +1,186 @@
+//! Admin API
+//!
+//! Operator-only actions served next to the status API. Every route requires
+//! `Authorization: Bearer <ADMIN_TOKEN>`; the routes are not mounted at all
+//! when no token is configured.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::halt::{HaltState, HaltSwitch};
+use crate::payout::{PayoutPolicyEngine, PendingPayout};
+use crate::refund::RefundProcessor;
+use crate::store::{DepositRecord, DepositStatus, DepositStore};
+use crate::BridgePayload;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// Shared state behind the admin API
+#[derive(Clone)]
+pub struct AdminState {
+    /// Bearer token required on every request
+    pub token: String,
+
+    /// Deposit store
+    pub store: Arc<DepositStore>,
+
+    /// Channel back into the attestation pipeline
+    pub deposit_sender: mpsc::Sender<BridgePayload>,
+
+    /// Attestation halt switch
+    pub halt: Arc<HaltSwitch>,
+
+    /// Payout policy holding releases for co-approval
+    pub payouts: Option<Arc<PayoutPolicyEngine>>,
+
+    /// Refunds handed to the payout queue
+    pub refunds: Arc<RefundProcessor>,
+}
+
+/// Build the admin API router
+pub fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/admin/deposits/:tx_hash/resume", post(resume_deposit))
+        .route("/admin/deposits/:tx_hash/refunded", post(refunded_deposit))
+        .route("/admin/halt/rearm", post(rearm))
+        .route("/admin/payouts/pending", get(pending_payouts))
+        .route("/admin/payouts/:message_hash/approve", post(approve_payout))
+        .with_state(state)
+}
+
+/// Reject requests without the configured bearer token
+pub fn authorize(headers: &HeaderMap, token: &str) -> Result<(), StatusCode> {
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Compare two secrets in time that does not depend on where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `POST /admin/deposits/:tx_hash/resume` — retry a stale deposit, given as
+/// `<txid>:<output_index>` or a txid with a single vault note
+async fn resume_deposit(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(tx_hash): Path<String>,
+) -> Result<Json<DepositRecord>, Response> {
+    authorize(&headers, &state.token).map_err(IntoResponse::into_response)?;
+
+    let key = state
+        .store
+        .resolve(tx_hash.strip_prefix("0x").unwrap_or(&tx_hash))
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?
+        .key();
+    let record = state
+        .store
+        .transition(&key, DepositStatus::Detected, None)
+        .map_err(|e| (StatusCode::CONFLICT, Json(e.report())).into_response())?;
+
+    let payload = record.to_payload().map_err(IntoResponse::into_response)?;
+    state.deposit_sender.send(payload).await.map_err(|_| {
+        SentinelError::Scanner("attestation pipeline is not running".to_string()).into_response()
+    })?;
+
+    info!("Operator resumed stale deposit {}", key);
+    Ok(Json(record))
+}
+
+/// `POST /admin/deposits/:tx_hash/refunded` — record that the payout side
+/// has paid a queued refund, given like `resume`
+async fn refunded_deposit(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(tx_hash): Path<String>,
+) -> Result<Json<DepositRecord>, Response> {
+    authorize(&headers, &state.token).map_err(IntoResponse::into_response)?;
+
+    let key = state
+        .store
+        .resolve(tx_hash.strip_prefix("0x").unwrap_or(&tx_hash))
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?
+        .key();
+    state
+        .refunds
+        .mark_refunded(&key)
+        .map_err(|e| (StatusCode::CONFLICT, Json(e.report())).into_response())?;
+
+    info!("Refund of deposit {} paid out", key);
+    let record = state.store.get(&key).ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+    Ok(Json(record))
+}
+
+/// `POST /admin/halt/rearm` — resume attestation after reviewing a halt
+async fn rearm(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<HaltState>, StatusCode> {
+    authorize(&headers, &state.token)?;
+
+    let previous = state.halt.rearm().ok_or(StatusCode::CONFLICT)?;
+    info!("Operator re-armed attestation");
+    Ok(Json(previous))
+}
+
+/// `GET /admin/payouts/pending` — releases held for co-approval
+async fn pending_payouts(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<PendingPayout>>, StatusCode> {
+    authorize(&headers, &state.token)?;
+
+    let payouts = state.payouts.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(payouts.pending()))
+}
+
+/// `POST /admin/payouts/:message_hash/approve` — co-approve a held release
+///
+/// The approver is identified by their own token in `X-Approver-Token`.
+async fn approve_payout(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(message_hash): Path<String>,
+) -> Result<Json<PendingPayout>, Response> {
+    authorize(&headers, &state.token).map_err(IntoResponse::into_response)?;
+
+    let payouts = state
+        .payouts
+        .as_ref()
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+    let approver = headers
+        .get("x-approver-token")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|token| payouts.approver_for(token))
+        .ok_or_else(|| StatusCode::FORBIDDEN.into_response())?;
+
+    let message_hash = format!(
+        "0x{}",
+        message_hash.strip_prefix("0x").unwrap_or(&message_hash)
+    );
+    let pending = payouts
+        .approve(&message_hash, &approver, now_secs())
+        .await
+        .map_err(IntoResponse::into_response)?
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    info!(
+        "{} approved release for withdrawal {}",
+        approver, message_hash
+    );
+    Ok(Json(pending))
+}
@@ -0,0 +1,215 @@
+//! Local aggregate signature verification
+//!
+//! The deployed verifier accepts an "aggregated" signature that is the
+//! concatenation of one 65-byte ECDSA signature per signer over the EIP-191
+//! hash of the payload's EIP-712 digest (the BLS interface is kept for a
+//! future upgrade).
+//! Before spending gas on `verifyAndDispatch`, the aggregator checks every
+//! partial locally and reports exactly which one would make the call revert.
+
+use crate::error::SentinelError;
+use ethers::types::{Address, Signature};
+use ethers::utils::hash_message;
+use std::fmt;
+
+/// Length of a single ECDSA partial signature (r, s, v)
+pub const PARTIAL_SIGNATURE_LENGTH: usize = 65;
+
+/// Why a single partial signature failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartialFailure {
+    /// Signature bytes could not be parsed
+    Malformed {
+        /// Position of the partial in the aggregate
+        index: usize,
+        /// Expected signer
+        signer: Address,
+    },
+
+    /// Signature recovers to a different address than the claimed signer
+    WrongSigner {
+        /// Position of the partial in the aggregate
+        index: usize,
+        /// Expected signer
+        signer: Address,
+        /// Address the signature actually recovers to
+        recovered: Address,
+    },
+
+    /// Claimed signer has no registered key
+    Unregistered {
+        /// Position of the partial in the aggregate
+        index: usize,
+        /// Signer without a registered key
+        signer: Address,
+    },
+
+    /// Signer appears more than once
+    Duplicate {
+        /// Position of the repeated entry
+        index: usize,
+        /// Duplicated signer
+        signer: Address,
+    },
+}
+
+impl PartialFailure {
+    /// Signer whose partial failed
+    pub fn signer(&self) -> Address {
+        match self {
+            Self::Malformed { signer, .. }
+            | Self::WrongSigner { signer, .. }
+            | Self::Unregistered { signer, .. }
+            | Self::Duplicate { signer, .. } => *signer,
+        }
+    }
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { index, signer } => {
+                write!(f, "partial #{} for {:?} is malformed", index, signer)
+            }
+            Self::WrongSigner {
+                index,
+                signer,
+                recovered,
+            } => write!(
+                f,
+                "partial #{} for {:?} recovers to {:?}",
+                index, signer, recovered
+            ),
+            Self::Unregistered { index, signer } => {
+                write!(f, "partial #{} signer {:?} has no registered key", index, signer)
+            }
+            Self::Duplicate { index, signer } => {
+                write!(f, "partial #{} duplicates signer {:?}", index, signer)
+            }
+        }
+    }
+}
+
+/// Concatenate partial signatures in signer order
+pub fn aggregate(partials: &[Vec<u8>]) -> Vec<u8> {
+    partials.concat()
+}
+
+/// Verify an aggregated signature against the claimed signer set
+///
+/// `registered` is the set of operators with a registered key on the
+/// verifier contract. Returns every failing partial rather than only the
+/// first, so the aggregator can drop them and retry with the rest.
+pub fn verify_aggregate(
+    message_hash: [u8; 32],
+    aggregated: &[u8],
+    signers: &[Address],
+    registered: &[Address],
+) -> Result<(), SentinelError> {
+    if signers.is_empty() {
+        return Err(SentinelError::Signing("Empty signer set".to_string()));
+    }
+
+    if aggregated.len() != signers.len() * PARTIAL_SIGNATURE_LENGTH {
+        return Err(SentinelError::Signing(format!(
+            "Aggregated signature is {} bytes, expected {} for {} signers",
+            aggregated.len(),
+            signers.len() * PARTIAL_SIGNATURE_LENGTH,
+            signers.len()
+        )));
+    }
+
+    let failures = verify_partials(message_hash, aggregated, signers, registered);
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    Err(SentinelError::Signing(
+        failures
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
+/// Check each partial and collect diagnostics for those that fail
+pub fn verify_partials(
+    message_hash: [u8; 32],
+    aggregated: &[u8],
+    signers: &[Address],
+    registered: &[Address],
+) -> Vec<PartialFailure> {
+    let eth_signed_hash = hash_message(message_hash);
+    let mut failures = Vec::new();
+
+    for (index, (chunk, signer)) in aggregated
+        .chunks(PARTIAL_SIGNATURE_LENGTH)
+        .zip(signers)
+        .enumerate()
+    {
+        let signer = *signer;
+
+        if signers[..index].contains(&signer) {
+            failures.push(PartialFailure::Duplicate { index, signer });
+            continue;
+        }
+
+        if !registered.contains(&signer) {
+            failures.push(PartialFailure::Unregistered { index, signer });
+            continue;
+        }
+
+        let recovered = Signature::try_from(chunk)
+            .ok()
+            .and_then(|sig| sig.recover(eth_signed_hash).ok());
+
+        match recovered {
+            Some(recovered) if recovered == signer => {}
+            Some(recovered) => failures.push(PartialFailure::WrongSigner {
+                index,
+                signer,
+                recovered,
+            }),
+            None => failures.push(PartialFailure::Malformed { index, signer }),
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_aggregate_reports_bad_partial() {
+        let a = wallet("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+        let b = wallet("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d");
+        let hash = [0x11u8; 32];
+
+        let sig_a = a.sign_message(hash).await.unwrap().to_vec();
+        let sig_b = b.sign_message(hash).await.unwrap().to_vec();
+        let registered = vec![a.address(), b.address()];
+
+        let good = aggregate(&[sig_a.clone(), sig_b.clone()]);
+        assert!(verify_aggregate(hash, &good, &registered, &registered).is_ok());
+
+        // Swap the order so each partial recovers to the other signer
+        let swapped = aggregate(&[sig_b, sig_a]);
+        let failures = verify_partials(hash, &swapped, &registered, &registered);
+        assert_eq!(failures.len(), 2);
+        assert!(matches!(failures[0], PartialFailure::WrongSigner { index: 0, .. }));
+    }
+
+    #[test]
+    fn test_length_mismatch_rejected() {
+        let signers = vec![Address::repeat_byte(1)];
+        assert!(verify_aggregate([0u8; 32], &[0u8; 64], &signers, &signers).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! Critical alerts by email
+//!
+//! Some conditions need a human quickly: a circuit breaker halting
+//! attestation, the operator being slashed, or the scanner making no progress
+//! for several minutes. Besides the `ALERT:` log line, these are mailed over
+//! SMTP to the configured recipients.
+//!
+//! Subjects come from a template with `{title}`, `{kind}` and `{network}`
+//! placeholders. Each kind of alert is mailed at most once per interval;
+//! repeats within the interval are counted and reported with the next mail,
+//! so a flapping condition cannot flood an inbox.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::scanner::ScanProgress;
+use ethers::prelude::*;
+use ethers::types::{Address, H256};
+use ethers::utils::keccak256;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+
+/// Default seconds between two mails about the same kind of alert
+pub const DEFAULT_ALERT_INTERVAL_SECS: u64 = 900;
+
+/// Default subject template
+pub const DEFAULT_SUBJECT_TEMPLATE: &str = "[sentinel {network}] {title}";
+
+/// Alerts queued before new ones are dropped
+const ALERT_QUEUE: usize = 64;
+
+/// What needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    /// A safety job halted attestation
+    CircuitBreaker,
+    /// The operator's stake was slashed
+    Slashed,
+    /// The scanner has not completed a pass for too long
+    ScannerStalled,
+}
+
+impl AlertKind {
+    /// Name used in templates
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::CircuitBreaker => "circuit_breaker",
+            Self::Slashed => "slashed",
+            Self::ScannerStalled => "scanner_stalled",
+        }
+    }
+}
+
+/// A critical condition
+#[derive(Debug, Clone)]
+pub struct Alert {
+    /// What happened
+    pub kind: AlertKind,
+
+    /// One-line summary
+    pub title: String,
+
+    /// Details for the mail body
+    pub details: String,
+
+    /// Unix timestamp of the alert
+    pub at: u64,
+}
+
+impl Alert {
+    /// Alert of `kind` raised now
+    pub fn new(kind: AlertKind, title: impl Into<String>, details: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            details: details.into(),
+            at: now_secs(),
+        }
+    }
+}
+
+/// Fill the placeholders of `template` for `alert`
+fn render(template: &str, alert: &Alert, network: &str) -> String {
+    template
+        .replace("{title}", &alert.title)
+        .replace("{kind}", alert.kind.as_str())
+        .replace("{network}", network)
+}
+
+/// Per-kind mail budget
+#[derive(Debug)]
+struct Throttle {
+    /// Minimum seconds between mails of one kind
+    interval: u64,
+
+    /// Last mail time and alerts suppressed since, per kind
+    kinds: HashMap<AlertKind, (u64, u64)>,
+}
+
+impl Throttle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval: interval.as_secs(),
+            kinds: HashMap::new(),
+        }
+    }
+
+    /// Whether an alert of `kind` may be mailed at `now`, with the number
+    /// suppressed since the last mail
+    fn admit(&mut self, kind: AlertKind, now: u64) -> Option<u64> {
+        match self.kinds.get_mut(&kind) {
+            Some((last, suppressed)) if now.saturating_sub(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.kinds.insert(kind, (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// SMTP delivery of alerts
+pub struct EmailNotifier {
+    /// SMTP transport
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+
+    /// Sender
+    from: Mailbox,
+
+    /// Recipients
+    to: Vec<Mailbox>,
+
+    /// Subject template
+    subject_template: String,
+
+    /// Zcash network name
+    network: String,
+}
+
+impl EmailNotifier {
+    /// Notifier sending through `smtp_url` (`smtp://` or `smtps://`, with credentials)
+    pub fn new(
+        smtp_url: &str,
+        from: &str,
+        to: &[String],
+        subject_template: String,
+        network: String,
+    ) -> Result<Self, SentinelError> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url)
+            .map_err(|e| SentinelError::Config(format!("invalid SMTP URL: {}", e)))?
+            .build();
+        let mailbox = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| SentinelError::Config(format!("invalid address {}: {}", address, e)))
+        };
+        Ok(Self {
+            transport,
+            from: mailbox(from)?,
+            to: to.iter().map(|a| mailbox(a)).collect::<Result<_, _>>()?,
+            subject_template,
+            network,
+        })
+    }
+
+    /// Mail `alert`, noting how many similar alerts were held back
+    async fn send(&self, alert: &Alert, suppressed: u64) -> Result<(), SentinelError> {
+        let mut body = format!(
+            "{}\n\n{}\n\nKind: {}\nNetwork: {}\nRaised at: {} (Unix time)\n",
+            alert.title,
+            alert.details,
+            alert.kind.as_str(),
+            self.network,
+            alert.at
+        );
+        if suppressed > 0 {
+            body.push_str(&format!(
+                "\n{} similar alert(s) were suppressed since the previous mail.\n",
+                suppressed
+            ));
+        }
+
+        let mut message = Message::builder().from(self.from.clone()).subject(render(
+            &self.subject_template,
+            alert,
+            &self.network,
+        ));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .body(body)
+            .map_err(|e| SentinelError::Config(e.to_string()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(SentinelError::network)?;
+        Ok(())
+    }
+}
+
+/// Cheap handle alerts are raised through
+#[derive(Debug, Clone, Default)]
+pub struct Alerter {
+    /// Queue of the delivery task, if alert mail is configured
+    queue: Option<mpsc::Sender<Alert>>,
+}
+
+impl Alerter {
+    /// Start delivering alerts through `notifier`, at most one per kind per `interval`
+    pub fn spawn(notifier: EmailNotifier, interval: Duration) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Alert>(ALERT_QUEUE);
+        tokio::spawn(async move {
+            let mut throttle = Throttle::new(interval);
+            while let Some(alert) = rx.recv().await {
+                let Some(suppressed) = throttle.admit(alert.kind, alert.at) else {
+                    debug!("Suppressed {} alert mail", alert.kind.as_str());
+                    continue;
+                };
+                if let Err(e) = notifier.send(&alert, suppressed).await {
+                    warn!("Failed to mail {} alert: {}", alert.kind.as_str(), e);
+                }
+            }
+        });
+        Self { queue: Some(tx) }
+    }
+
+    /// Queue `alert` for mailing without waiting
+    pub fn raise(&self, alert: Alert) {
+        if let Some(queue) = &self.queue {
+            if queue.try_send(alert).is_err() {
+                warn!("Alert queue full, dropping alert mail");
+            }
+        }
+    }
+}
+
+/// Alert when the scanner completes no pass for `stall`
+pub async fn watch_scanner(progress: Arc<ScanProgress>, stall: Duration, alerter: Alerter) {
+    let mut stalled = false;
+    loop {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let healthy = progress.is_healthy(stall);
+        if !healthy && !stalled {
+            error!("ALERT: scanner made no progress for over {:?}", stall);
+            alerter.raise(Alert::new(
+                AlertKind::ScannerStalled,
+                "Scanner stalled",
+                format!(
+                    "No scan pass completed for over {:?}; last synced height {}.",
+                    stall,
+                    progress.synced_height()
+                ),
+            ));
+        }
+        stalled = !healthy;
+    }
+}
+
+/// Alert when the ServiceManager slashes `operator`
+pub struct SlashingMonitor {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Operator whose slashing is reported
+    operator: Address,
+
+    /// Alert handle
+    alerter: Alerter,
+
+    /// Next L1 block to query
+    from_block: Option<u64>,
+}
+
+impl SlashingMonitor {
+    /// Create a new monitor
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        operator: Address,
+        alerter: Alerter,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            operator,
+            alerter,
+            from_block: None,
+        }
+    }
+
+    /// Poll forever
+    pub async fn run(mut self, poll_interval: Duration) {
+        loop {
+            if let Err(e) = self.poll().await {
+                warn!("Slashing monitor error: {}", e);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Look for `OperatorSlashed` events since the last poll
+    async fn poll(&mut self) -> Result<(), SentinelError> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        // Only slashings from startup on are news
+        let from_block = *self.from_block.get_or_insert(head);
+        if head < from_block {
+            return Ok(());
+        }
+
+        let filter = Filter::new()
+            .address(self.service_manager_address)
+            .topic0(H256::from(keccak256(
+                b"OperatorSlashed(address,uint256,bytes)",
+            )))
+            .topic1(H256::from(self.operator))
+            .from_block(from_block)
+            .to_block(head);
+        for log in self.provider.get_logs(&filter).await? {
+            let amount = log
+                .data
+                .get(0..32)
+                .map(U256::from_big_endian)
+                .unwrap_or_default();
+            error!(
+                "ALERT: operator {:?} slashed by {} wei",
+                self.operator, amount
+            );
+            self.alerter.raise(Alert::new(
+                AlertKind::Slashed,
+                "Operator slashed",
+                format!(
+                    "Operator {:?} was slashed by {} wei in L1 transaction {:?}.",
+                    self.operator,
+                    amount,
+                    log.transaction_hash.unwrap_or_default()
+                ),
+            ));
+        }
+
+        self.from_block = Some(head + 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_rendered_and_throttled() {
+        let alert = Alert::new(
+            AlertKind::CircuitBreaker,
+            "Attestation halted",
+            "reconciliation",
+        );
+        assert_eq!(
+            render(DEFAULT_SUBJECT_TEMPLATE, &alert, "mainnet"),
+            "[sentinel mainnet] Attestation halted"
+        );
+        assert_eq!(render("{kind}", &alert, "mainnet"), "circuit_breaker");
+
+        let mut throttle = Throttle::new(Duration::from_secs(900));
+        assert_eq!(throttle.admit(AlertKind::CircuitBreaker, 1_000), Some(0));
+        assert_eq!(throttle.admit(AlertKind::CircuitBreaker, 1_100), None);
+        assert_eq!(throttle.admit(AlertKind::CircuitBreaker, 1_200), None);
+        // Other kinds have their own budget
+        assert_eq!(throttle.admit(AlertKind::Slashed, 1_200), Some(0));
+        assert_eq!(throttle.admit(AlertKind::CircuitBreaker, 1_900), Some(2));
+        assert_eq!(throttle.admit(AlertKind::CircuitBreaker, 2_000), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! Anomaly-detection circuit breaker
+//!
+//! Watches the deposit stream over a sliding window and trips the halt switch
+//! on patterns that suggest an exploit or a scanner bug: a surge in deposit
+//! count or value, the same secret hash appearing on several deposits, or a
+//! spike in vault-addressed notes whose memo fails to parse. Once tripped,
+//! attestation stays halted until an operator re-arms it via the admin API.
+//!
+//! Deposits are windowed on block time (height × target spacing) rather than
+//! detection time, so a backfill, rescan or catch-up after downtime replays old
+//! deposits at their original spacing instead of as one burst. Memo failures
+//! carry no height, so that check is suspended while the scanner is behind the
+//! tip.
+
+use crate::clock::now_secs;
+use crate::halt::HaltSwitch;
+use crate::scanner::ScanProgress;
+use crate::BridgePayload;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default sliding window length (1 hour)
+pub const DEFAULT_ANOMALY_WINDOW_SECS: u64 = 60 * 60;
+
+/// Zcash target block spacing in seconds
+const BLOCK_SPACING_SECS: u64 = 75;
+
+/// Blocks behind the tip at which the scanner counts as catching up
+const CATCH_UP_LAG: u32 = 10;
+
+/// Limits within one window; `None` disables a check
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyThresholds {
+    /// Maximum number of deposits
+    pub max_deposits: Option<u64>,
+
+    /// Maximum total deposit value in zatoshi
+    pub max_value: Option<u64>,
+
+    /// Maximum deposits sharing one secret hash
+    pub max_secret_repeats: Option<u64>,
+
+    /// Maximum memo parse failures
+    pub max_memo_failures: Option<u64>,
+}
+
+/// Events seen within the current window
+#[derive(Debug, Default)]
+struct Window {
+    /// Deposits as (block secs, amount, secret hash, (tx hash, output index))
+    deposits: VecDeque<(u64, u64, [u8; 32], ([u8; 32], u32))>,
+
+    /// Latest block time seen on a deposit
+    block_secs: u64,
+
+    /// Memo parse failures as (unix secs, count)
+    memo_failures: VecDeque<(u64, u64)>,
+
+    /// Scanner memo failure total at the last check
+    memo_failures_seen: u64,
+}
+
+/// Trips the halt switch on anomalous deposit patterns
+pub struct AnomalyDetector {
+    /// Window length
+    window: Duration,
+
+    /// Limits within the window
+    thresholds: AnomalyThresholds,
+
+    /// Switch to trip
+    halt: Arc<HaltSwitch>,
+
+    /// Events within the window
+    state: Mutex<Window>,
+}
+
+impl AnomalyDetector {
+    /// Create a new detector
+    pub fn new(window: Duration, thresholds: AnomalyThresholds, halt: Arc<HaltSwitch>) -> Self {
+        Self {
+            window,
+            thresholds,
+            halt,
+            state: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Poll the scanner's memo failure counter forever
+    pub async fn run(self: Arc<Self>, progress: Arc<ScanProgress>, interval: Duration) {
+        loop {
+            let total = progress.memo_failures();
+            if progress.synced_height().saturating_add(CATCH_UP_LAG) < progress.confirmed_tip() {
+                // Failures found while catching up span more than the window
+                self.skip_memo_failures(total);
+            } else {
+                self.observe_memo_failures(total, now_secs());
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Record a detected deposit; re-processing the same deposit is ignored
+    pub fn observe_deposit(&self, payload: &BridgePayload) {
+        let secs = payload.block_height as u64 * BLOCK_SPACING_SECS;
+        let mut state = self.state.lock().unwrap();
+        state.block_secs = state.block_secs.max(secs);
+        let cutoff = state.block_secs.saturating_sub(self.window.as_secs());
+        while state.deposits.front().is_some_and(|(ts, _, _, _)| *ts < cutoff) {
+            state.deposits.pop_front();
+        }
+        if secs < cutoff {
+            return;
+        }
+        let note = (payload.tx_hash, payload.output_index);
+        if state.deposits.iter().any(|(_, _, _, seen)| *seen == note) {
+            return;
+        }
+        // Keep the window ordered by block time for pruning
+        let at = state.deposits.partition_point(|(ts, _, _, _)| *ts <= secs);
+        state
+            .deposits
+            .insert(at, (secs, payload.amount, payload.secret_hash, note));
+
+        if let Some(reason) = self.deposit_anomaly(&state, &payload.secret_hash) {
+            self.trip(&mut state, reason);
+        }
+    }
+
+    /// Advance past the scanner's memo failure total without recording it
+    pub fn skip_memo_failures(&self, total: u64) {
+        self.state.lock().unwrap().memo_failures_seen = total;
+    }
+
+    /// Record the scanner's running memo failure total
+    pub fn observe_memo_failures(&self, total: u64, now: u64) {
+        let mut state = self.state.lock().unwrap();
+        let new = total.saturating_sub(state.memo_failures_seen);
+        state.memo_failures_seen = total;
+        if new > 0 {
+            state.memo_failures.push_back((now, new));
+        }
+        let cutoff = now.saturating_sub(self.window.as_secs());
+        while state.memo_failures.front().is_some_and(|(ts, _)| *ts < cutoff) {
+            state.memo_failures.pop_front();
+        }
+
+        let failures: u64 = state.memo_failures.iter().map(|(_, n)| n).sum();
+        if let Some(max) = self.thresholds.max_memo_failures {
+            if failures > max {
+                let reason = format!("{} memo parse failures within {:?}", failures, self.window);
+                self.trip(&mut state, reason);
+            }
+        }
+    }
+
+    /// First deposit-stream anomaly in the window, if any
+    fn deposit_anomaly(&self, state: &Window, secret_hash: &[u8; 32]) -> Option<String> {
+        let count = state.deposits.len() as u64;
+        if self.thresholds.max_deposits.is_some_and(|max| count > max) {
+            return Some(format!("{} deposits within {:?}", count, self.window));
+        }
+
+        let value: u64 = state.deposits.iter().map(|(_, amount, _, _)| amount).sum();
+        if self.thresholds.max_value.is_some_and(|max| value > max) {
+            return Some(format!("{} zatoshi deposited within {:?}", value, self.window));
+        }
+
+        let mut repeats: HashMap<&[u8; 32], u64> = HashMap::new();
+        for (_, _, hash, _) in &state.deposits {
+            *repeats.entry(hash).or_default() += 1;
+        }
+        let repeats = repeats.get(secret_hash).copied().unwrap_or_default();
+        if self.thresholds.max_secret_repeats.is_some_and(|max| repeats > max) {
+            return Some(format!(
+                "secret hash {} used by {} deposits",
+                hex::encode(secret_hash),
+                repeats
+            ));
+        }
+
+        None
+    }
+
+    /// Halt attestation and start a fresh window for after the re-arm
+    fn trip(&self, state: &mut Window, reason: String) {
+        self.halt.trip("anomaly detector", reason);
+        state.deposits.clear();
+        state.memo_failures.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(tx: u8, secret: u8, block_height: u32) -> BridgePayload {
+        BridgePayload {
+            tx_hash: [tx; 32],
+            amount: 1,
+            secret_hash: [secret; 32],
+            aztec_address: [0xef; 32],
+            block_height,
+            ..BridgePayload::sample()
+        }
+    }
+
+    fn detector(thresholds: AnomalyThresholds) -> (AnomalyDetector, Arc<HaltSwitch>) {
+        let halt = Arc::new(HaltSwitch::new());
+        // Ten blocks
+        let detector = AnomalyDetector::new(Duration::from_secs(750), thresholds, halt.clone());
+        (detector, halt)
+    }
+
+    const NONE: AnomalyThresholds = AnomalyThresholds {
+        max_deposits: None,
+        max_value: None,
+        max_secret_repeats: None,
+        max_memo_failures: None,
+    };
+
+    #[test]
+    fn test_count_surge_within_window() {
+        let (detector, halt) = detector(AnomalyThresholds {
+            max_deposits: Some(2),
+            ..NONE
+        });
+
+        detector.observe_deposit(&payload(1, 1, 100));
+        detector.observe_deposit(&payload(2, 2, 105));
+        // First deposit has left the window
+        detector.observe_deposit(&payload(3, 3, 112));
+        // Re-processing a deposit does not count twice
+        detector.observe_deposit(&payload(3, 3, 112));
+        assert!(!halt.is_halted());
+
+        detector.observe_deposit(&payload(4, 4, 113));
+        assert!(halt.is_halted());
+    }
+
+    #[test]
+    fn test_backfill_replays_at_block_spacing() {
+        let (detector, halt) = detector(AnomalyThresholds {
+            max_deposits: Some(2),
+            ..NONE
+        });
+
+        // A catch-up burst of deposits mined a day apart
+        for (i, height) in (1000..).step_by(1152).take(10).enumerate() {
+            detector.observe_deposit(&payload(i as u8, i as u8, height));
+        }
+        // Older than the window by the time it arrives
+        detector.observe_deposit(&payload(0xaa, 0xaa, 1000));
+        assert!(!halt.is_halted());
+    }
+
+    #[test]
+    fn test_repeated_secret_hash() {
+        let (detector, halt) = detector(AnomalyThresholds {
+            max_secret_repeats: Some(1),
+            ..NONE
+        });
+
+        detector.observe_deposit(&payload(1, 7, 100));
+        detector.observe_deposit(&payload(1, 7, 100));
+        assert!(!halt.is_halted());
+        // A second note in the same transaction is a separate deposit
+        let second_note = BridgePayload {
+            output_index: 1,
+            ..payload(1, 7, 100)
+        };
+        detector.observe_deposit(&second_note);
+        assert!(halt.is_halted());
+    }
+
+    #[test]
+    fn test_memo_failure_spike() {
+        let (detector, halt) = detector(AnomalyThresholds {
+            max_memo_failures: Some(5),
+            ..NONE
+        });
+
+        detector.observe_memo_failures(5, 0);
+        assert!(!halt.is_halted());
+        detector.observe_memo_failures(6, 10);
+        assert!(halt.is_halted());
+    }
+
+    #[test]
+    fn test_memo_failures_skipped_while_catching_up() {
+        let (detector, halt) = detector(AnomalyThresholds {
+            max_memo_failures: Some(5),
+            ..NONE
+        });
+
+        detector.skip_memo_failures(50);
+        detector.observe_memo_failures(52, 0);
+        assert!(!halt.is_halted());
+    }
+}
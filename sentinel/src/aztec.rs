@@ -0,0 +1,100 @@
+//! Aztec node client for recipient validation
+//!
+//! Before attesting, optionally ask an Aztec node whether the memo's
+//! `aztec_address` is a deployed account or contract. Minting to an address
+//! nobody controls strands the user's funds, so unreachable recipients are
+//! rejected and routed to the refund flow instead.
+
+use crate::error::SentinelError;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::debug;
+
+/// JSON-RPC response envelope
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Minimal Aztec node JSON-RPC client
+pub struct AztecClient {
+    /// Node JSON-RPC URL
+    url: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl AztecClient {
+    /// Create a new client
+    pub fn new(url: String, timeout: Duration) -> Result<Self, SentinelError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(SentinelError::network)?;
+
+        Ok(Self { url, client })
+    }
+
+    /// Whether `address` has a contract instance (accounts are contracts on Aztec)
+    pub async fn is_registered(&self, address: &[u8; 32]) -> Result<bool, SentinelError> {
+        let address = format!("0x{}", hex::encode(address));
+        let result = self
+            .request("node_getContractInstance", json!([address]))
+            .await?;
+
+        debug!("Aztec contract instance lookup for {}: {}", address, result);
+        Ok(!result.is_null())
+    }
+
+    /// Whether the L1→L2 message `message_hash` is in the node's L2 message tree
+    pub async fn is_message_synced(&self, message_hash: &[u8; 32]) -> Result<bool, SentinelError> {
+        let message_hash = format!("0x{}", hex::encode(message_hash));
+        let result = self
+            .request("node_isL1ToL2MessageSynced", json!([message_hash]))
+            .await?;
+
+        debug!("Aztec L1→L2 message sync for {}: {}", message_hash, result);
+        Ok(result.as_bool().unwrap_or(false))
+    }
+
+    /// Perform a JSON-RPC request
+    async fn request(&self, method: &str, params: Value) -> Result<Value, SentinelError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(SentinelError::network)?
+            .json()
+            .await
+            .map_err(SentinelError::network)?;
+
+        if let Some(error) = response.error {
+            return Err(SentinelError::Network(format!(
+                "Aztec RPC {} failed ({}): {}",
+                method, error.code, error.message
+            )));
+        }
+
+        Ok(response.result.unwrap_or(Value::Null))
+    }
+}
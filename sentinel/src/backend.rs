@@ -0,0 +1,287 @@
+//! Chain data backends
+//!
+//! The scanner reads blocks from lightwalletd by default (`BACKEND=lightwalletd`).
+//! Operators running zebrad, or still running zcashd, can point it straight
+//! at the node instead with `BACKEND=zebrad` or `BACKEND=zcashd` and
+//! `ZCASH_RPC_URL`: blocks come from `getblock` and transactions from
+//! `getrawtransaction` (zcashd needs `txindex=1` for that), and each block
+//! is cut down to the compact form lightwalletd would have served, so the
+//! rest of the pipeline is unchanged. zcashd credentials are covered in
+//! `zcash_rpc.rs`. Features that need lightwalletd itself (mempool preview,
+//! transparent address watching, spend detection) are not available there.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use crate::zcash_rpc::{RpcAuth, ZcashRpcClient};
+use anyhow::Context;
+use async_trait::async_trait;
+use std::io::{Cursor, Read};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use zcash_client_backend::proto::compact_formats::{
+    CompactBlock, CompactOrchardAction, CompactSaplingOutput, CompactSaplingSpend, CompactTx,
+};
+use zcash_primitives::block::BlockHeader;
+use zcash_primitives::consensus::{BlockHeight, BranchId, MAIN_NETWORK, TEST_NETWORK};
+use zcash_primitives::transaction::Transaction;
+
+/// Longest a node RPC call may take
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bytes of a note ciphertext kept in compact outputs
+const COMPACT_NOTE_SIZE: usize = 52;
+
+/// Where the scanner reads the chain from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Compact blocks from lightwalletd
+    Lightwalletd,
+    /// Full blocks from zebrad's JSON-RPC
+    Zebrad,
+    /// Full blocks from zcashd's JSON-RPC
+    Zcashd,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "lightwalletd" => Ok(Self::Lightwalletd),
+            "zebrad" => Ok(Self::Zebrad),
+            "zcashd" => Ok(Self::Zcashd),
+            _ => anyhow::bail!("BACKEND must be lightwalletd, zebrad or zcashd"),
+        }
+    }
+}
+
+/// A source of blocks and transactions other than lightwalletd
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Source name for logs
+    fn name(&self) -> &str;
+
+    /// Height of the best chain tip
+    async fn tip_height(&self) -> Result<u32, SentinelError>;
+
+    /// Best-chain block at `height`, in compact form
+    async fn compact_block(&self, height: u32) -> Result<CompactBlock, SentinelError>;
+
+    /// Serialized transaction `txid` (internal byte order)
+    async fn raw_transaction(&self, txid: &[u8; 32]) -> Result<Vec<u8>, SentinelError>;
+}
+
+/// Block source for the configured backend; none for lightwalletd
+pub fn block_source(config: &SentinelConfig) -> anyhow::Result<Option<Arc<dyn BlockSource>>> {
+    match config.backend {
+        Backend::Lightwalletd => Ok(None),
+        Backend::Zebrad | Backend::Zcashd => {
+            let url = config
+                .zcash_rpc_url
+                .clone()
+                .context("A full-node BACKEND requires ZCASH_RPC_URL")?;
+            let rpc =
+                ZcashRpcClient::new(url, RPC_TIMEOUT)?.with_auth(RpcAuth::from_config(config));
+            let name = match config.backend {
+                Backend::Zcashd => "zcashd",
+                _ => "zebrad",
+            };
+            Ok(Some(Arc::new(NodeSource::new(name, rpc, &config.network))))
+        }
+    }
+}
+
+/// Blocks from a full node's JSON-RPC (zebrad or zcashd)
+pub struct NodeSource {
+    /// Node kind, for logs
+    name: &'static str,
+
+    /// Node RPC client
+    rpc: ZcashRpcClient,
+
+    /// Network the node is on (mainnet, testnet or regtest)
+    network: String,
+}
+
+impl NodeSource {
+    /// Read blocks of `network` through `rpc` of the `name` node
+    pub fn new(name: &'static str, rpc: ZcashRpcClient, network: &str) -> Self {
+        Self {
+            name,
+            rpc,
+            network: network.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for NodeSource {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn tip_height(&self) -> Result<u32, SentinelError> {
+        self.rpc.block_count().await
+    }
+
+    async fn compact_block(&self, height: u32) -> Result<CompactBlock, SentinelError> {
+        let raw = self.rpc.raw_block(height).await?;
+        compact_block(&self.network, height, &raw)
+    }
+
+    async fn raw_transaction(&self, txid: &[u8; 32]) -> Result<Vec<u8>, SentinelError> {
+        let mut display = *txid;
+        display.reverse();
+        self.rpc.raw_transaction(&hex::encode(display)).await
+    }
+}
+
+/// Read a Bitcoin-style variable-length integer
+fn read_compact_size(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    let width = match first[0] {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => return Ok(u64::from(n)),
+    };
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes[..width])?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Consensus branch of blocks at `height` on `network`
+fn branch_id(network: &str, height: u32) -> BranchId {
+    let height = BlockHeight::from_u32(height);
+    match network {
+        "mainnet" => BranchId::for_height(&MAIN_NETWORK, height),
+        "testnet" => BranchId::for_height(&TEST_NETWORK, height),
+        // Regtest nodes run with every network upgrade active
+        _ => BranchId::Nu5,
+    }
+}
+
+/// Cut the serialized `network` block at `height` down to its compact form
+///
+/// Only transactions with Sapling or Orchard parts are kept, in block
+/// order with their position as index, as lightwalletd does. The block has
+/// no chain metadata.
+pub fn compact_block(
+    network: &str,
+    height: u32,
+    raw: &[u8],
+) -> Result<CompactBlock, SentinelError> {
+    let malformed =
+        |e: std::io::Error| SentinelError::Scanner(format!("malformed block {}: {}", height, e));
+    let branch = branch_id(network, height);
+
+    let mut reader = Cursor::new(raw);
+    let header = BlockHeader::read(&mut reader).map_err(malformed)?;
+    let tx_count = read_compact_size(&mut reader).map_err(malformed)?;
+
+    let mut vtx = Vec::new();
+    for index in 0..tx_count {
+        let tx = Transaction::read(&mut reader, branch).map_err(malformed)?;
+        let mut compact = CompactTx {
+            index,
+            hash: tx.txid().as_ref().to_vec(),
+            ..CompactTx::default()
+        };
+        if let Some(bundle) = tx.sapling_bundle() {
+            compact.spends = bundle
+                .shielded_spends()
+                .iter()
+                .map(|spend| CompactSaplingSpend {
+                    nf: spend.nullifier().0.to_vec(),
+                })
+                .collect();
+            compact.outputs = bundle
+                .shielded_outputs()
+                .iter()
+                .map(|output| CompactSaplingOutput {
+                    cmu: output.cmu().to_bytes().to_vec(),
+                    ephemeral_key: output.ephemeral_key().0.to_vec(),
+                    ciphertext: output.enc_ciphertext()[..COMPACT_NOTE_SIZE].to_vec(),
+                })
+                .collect();
+        }
+        if let Some(bundle) = tx.orchard_bundle() {
+            compact.actions = bundle
+                .actions()
+                .iter()
+                .map(|action| CompactOrchardAction {
+                    nullifier: action.nullifier().to_bytes().to_vec(),
+                    cmx: action.cmx().to_bytes().to_vec(),
+                    ephemeral_key: action.encrypted_note().epk_bytes.to_vec(),
+                    ciphertext: action.encrypted_note().enc_ciphertext[..COMPACT_NOTE_SIZE]
+                        .to_vec(),
+                })
+                .collect();
+        }
+        if !compact.spends.is_empty() || !compact.outputs.is_empty() || !compact.actions.is_empty()
+        {
+            vtx.push(compact);
+        }
+    }
+    if reader.position() != raw.len() as u64 {
+        return Err(SentinelError::Scanner(format!(
+            "trailing bytes after block {}",
+            height
+        )));
+    }
+
+    Ok(CompactBlock {
+        height: u64::from(height),
+        hash: header.hash().0.to_vec(),
+        prev_hash: header.prev_block.0.to_vec(),
+        time: header.time,
+        vtx,
+        ..CompactBlock::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zcash_primitives::block::{BlockHash, BlockHeaderData};
+
+    #[test]
+    fn test_raw_block_made_compact() {
+        let header = BlockHeader::from_data(BlockHeaderData {
+            version: 4,
+            prev_block: BlockHash([1; 32]),
+            merkle_root: [2; 32],
+            final_sapling_root: [3; 32],
+            time: 1_700_000_000,
+            bits: 0x1d00ffff,
+            nonce: [4; 32],
+            solution: vec![5; 1344],
+        })
+        .unwrap();
+        let mut raw = Vec::new();
+        header.write(&mut raw).unwrap();
+        raw.push(0);
+
+        let block = compact_block("mainnet", 2_500_000, &raw).unwrap();
+        assert_eq!(block.height, 2_500_000);
+        assert_eq!(block.hash, header.hash().0);
+        assert_eq!(block.prev_hash, [1; 32]);
+        assert_eq!(block.time, 1_700_000_000);
+        assert!(block.vtx.is_empty());
+
+        // A truncated block, or one with bytes left over, is refused
+        assert!(compact_block("mainnet", 2_500_000, &raw[..raw.len() - 1]).is_err());
+        raw.push(0);
+        assert!(compact_block("mainnet", 2_500_000, &raw).is_err());
+
+        // NU5 activated earlier on mainnet than on testnet
+        assert_eq!(branch_id("mainnet", 1_700_000), BranchId::Nu5);
+        assert_eq!(branch_id("testnet", 1_700_000), BranchId::Canopy);
+
+        assert_eq!("zebrad".parse::<Backend>().unwrap(), Backend::Zebrad);
+        assert_eq!("zcashd".parse::<Backend>().unwrap(), Backend::Zcashd);
+        assert!("zcashd-rpc".parse::<Backend>().is_err());
+    }
+}
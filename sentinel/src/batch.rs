@@ -0,0 +1,824 @@
+//! Merkle-batched attestations
+//!
+//! In batch mode deposits are not attested one by one. They accumulate over an
+//! epoch; at the end of the epoch a Merkle tree is built over their leaf
+//! hashes, the root is signed by a quorum of operators under the contract's
+//! EIP-712 domain and submitted with `verifyBatch`. Each deposit is then
+//! dispatched on L1 with `dispatchBatched`, presenting its payload and an
+//! inclusion proof, which the status API serves per deposit.
+//!
+//! Pairs are hashed in sorted order, so proofs verify with OpenZeppelin's
+//! `MerkleProof.verify`.
+
+use crate::clock::now_secs;
+use crate::consistency::{PeerChecker, SignedDigests};
+use crate::equivocation::EquivocationCheck;
+use crate::error::SentinelError;
+use crate::halt::HaltSwitch;
+use crate::leader::LeaderSchedule;
+use crate::maintenance::MaintenanceSchedule;
+use crate::quorum::{QuorumCalculator, StakeRegistry};
+use crate::ratelimit::SigningRateLimiter;
+use crate::reorg::ReorgLog;
+use crate::signer::{check_amounts, deposit_hash, AttestationSigner};
+use crate::signlog::SignedNote;
+use crate::store::{deposit_key, payload_key, DepositStatus, DepositStore};
+use crate::targets::StageContext;
+use crate::BridgePayload;
+use ethers::types::Address;
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Default epoch length (10 minutes)
+pub const DEFAULT_BATCH_EPOCH_SECS: u64 = 10 * 60;
+
+/// Default maximum deposits per batch
+pub const DEFAULT_BATCH_MAX_SIZE: usize = 256;
+
+/// Leaf hash of a deposit: keccak256 of its ABI-encoded payload fields
+/// without the nonce, as `ServiceManager.dispatchBatched` computes it
+pub fn leaf_hash(payload: &BridgePayload) -> [u8; 32] {
+    deposit_hash(payload)
+}
+
+/// Hash two nodes in sorted order
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(lo);
+    buf[32..].copy_from_slice(hi);
+    keccak256(buf)
+}
+
+/// Binary Merkle tree; an odd node at the end of a layer is promoted as is
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Layers from the leaves up to the root
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Build a tree over `leaves` (must not be empty)
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().map_or(false, |l| l.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|l| l.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sibling path for the leaf at `index`
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Check an inclusion proof against a root
+pub fn verify_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    proof.iter().fold(leaf, |acc, sibling| hash_pair(&acc, sibling)) == root
+}
+
+/// A submitted batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    /// Batch nonce (replay protection for the root signature)
+    pub nonce: u64,
+
+    /// Merkle root (hex)
+    pub root: String,
+
+    /// Store keys of the batched deposits, in leaf order
+    #[serde(alias = "tx_hashes")]
+    pub deposits: Vec<String>,
+
+    /// Leaf hashes (hex), in leaf order
+    pub leaves: Vec<String>,
+
+    /// L1 transaction that submitted the root
+    pub l1_tx_hash: String,
+
+    /// Unix timestamp of submission
+    pub submitted_at: u64,
+}
+
+/// Inclusion proof for one deposit
+#[derive(Debug, Clone, Serialize)]
+pub struct InclusionProof {
+    /// Batch nonce
+    pub batch_nonce: u64,
+
+    /// Merkle root (hex)
+    pub root: String,
+
+    /// Position of the leaf
+    pub index: usize,
+
+    /// Leaf hash (hex)
+    pub leaf: String,
+
+    /// Sibling hashes from the leaf up (hex)
+    pub proof: Vec<String>,
+}
+
+/// A batch put up for signatures, fixed until its nonce is used on L1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    /// Batch nonce
+    pub nonce: u64,
+
+    /// Merkle root (hex)
+    pub root: String,
+
+    /// Store keys of the batched deposits, in leaf order
+    pub deposits: Vec<String>,
+
+    /// Leaf hashes (hex), in leaf order
+    pub leaves: Vec<String>,
+}
+
+impl Proposal {
+    /// Proposal over `payloads`, in the given order
+    fn new(nonce: u64, payloads: &[BridgePayload]) -> Self {
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(leaf_hash).collect();
+        Self {
+            nonce,
+            root: hex::encode(MerkleTree::new(leaves.clone()).root()),
+            deposits: payloads.iter().map(payload_key).collect(),
+            leaves: leaves.iter().map(hex::encode).collect(),
+        }
+    }
+
+    /// Merkle root
+    fn root(&self) -> [u8; 32] {
+        parse_hash(&self.root).unwrap_or_default()
+    }
+}
+
+/// Batching progress, kept across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BatchState {
+    /// Lowest batch nonce not known to be used on L1
+    next_nonce: u64,
+
+    /// Store keys of the deposits waiting for a batch
+    pending: Vec<String>,
+
+    /// Our proposal for `next_nonce`, if we made one
+    proposal: Option<Proposal>,
+
+    /// A peer's proposal for `next_nonce` we signed instead
+    cosigned: Option<Proposal>,
+}
+
+/// Accumulates deposits and attests them in Merkle batches
+///
+/// Operators take turns proposing batches: the leader for a batch nonce
+/// proposes from its pending deposits, the others sign its root once they
+/// hold every deposit in it themselves and it passes the same guards as a
+/// single attestation. An operator signs one root per nonce, its own or a
+/// peer's. Whoever proposed submits the root once the signers reach quorum.
+pub struct BatchAttester {
+    /// Signer for the batch root
+    signer: Arc<AttestationSigner>,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Operator registry, for the quorum check and proposer rotation
+    stake_registry: Arc<StakeRegistry>,
+
+    /// Quorum calculator
+    quorum: QuorumCalculator,
+
+    /// Attestation halt switch
+    halt: Arc<HaltSwitch>,
+
+    /// Signing caps, shared with single attestations
+    rate_limiter: Arc<Mutex<SigningRateLimiter>>,
+
+    /// Never batch a note contradicting an earlier attestation of it
+    equivocation: EquivocationCheck,
+
+    /// Windows in which batching pauses
+    maintenance: Arc<MaintenanceSchedule>,
+
+    /// Zcash blocks reorged out, whose deposits are never attested
+    reorgs: Arc<ReorgLog>,
+
+    /// Peer sentinels, for their proposals and signatures
+    peers: Arc<PeerChecker>,
+
+    /// Our signatures, served to peers
+    signed: Arc<SignedDigests>,
+
+    /// How long each operator has to propose before the next one takes over
+    leader_timeout: Duration,
+
+    /// Maximum deposits per batch
+    max_size: usize,
+
+    /// Batching progress
+    state: Mutex<BatchState>,
+
+    /// When `next_nonce` became the nonce to batch under
+    nonce_since: Mutex<(u64, Instant)>,
+
+    /// Submitted batches
+    batches: Mutex<Vec<Batch>>,
+
+    /// File the submitted batches are persisted to
+    path: PathBuf,
+
+    /// File the batching progress is persisted to
+    state_path: PathBuf,
+}
+
+impl BatchAttester {
+    /// Create a batch attester, loading previously submitted batches from
+    /// `path` and the batching progress from beside it; `start_block` is the
+    /// ServiceManager's deployment block
+    pub fn open(
+        signer: Arc<AttestationSigner>,
+        start_block: u64,
+        stake_registry: Arc<StakeRegistry>,
+        context: &StageContext,
+        max_size: usize,
+        path: PathBuf,
+    ) -> Result<Self, SentinelError> {
+        let mut batches: Vec<Batch> = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| SentinelError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+
+        // Batches written before deposits were per note list bare tx hashes
+        for key in batches.iter_mut().flat_map(|b| b.deposits.iter_mut()) {
+            if !key.contains(':') {
+                *key = deposit_key(key, 0);
+            }
+        }
+
+        let state_path = path.with_extension("state.json");
+        let state = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                SentinelError::Storage(format!("{}: {}", state_path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BatchState {
+                next_nonce: batches.iter().map(|b| b.nonce + 1).max().unwrap_or(0),
+                ..BatchState::default()
+            },
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+
+        let equivocation = EquivocationCheck::new(
+            signer.provider(),
+            signer.service_manager_address(),
+            start_block,
+            context.store.clone(),
+            context.halt.clone(),
+        );
+
+        Ok(Self {
+            signer,
+            store: context.store.clone(),
+            stake_registry,
+            quorum: QuorumCalculator::new(context.quorum_threshold_bps),
+            halt: context.halt.clone(),
+            rate_limiter: context.rate_limiter.clone(),
+            equivocation,
+            maintenance: context.maintenance.clone(),
+            reorgs: context.reorgs.clone(),
+            peers: context.peers.clone(),
+            signed: context.signed.clone(),
+            leader_timeout: context.leader_timeout,
+            max_size,
+            nonce_since: Mutex::new((state.next_nonce, Instant::now())),
+            state: Mutex::new(state),
+            batches: Mutex::new(batches),
+            path,
+            state_path,
+        })
+    }
+
+    /// Queue a checked deposit for the next batch
+    pub fn push(&self, payload: BridgePayload) -> Result<(), SentinelError> {
+        // Same amount checks as a single attestation
+        check_amounts(&payload)?;
+
+        let key = payload_key(&payload);
+        let mut state = self.state.lock().unwrap();
+        if !state.pending.contains(&key) {
+            state.pending.push(key);
+            self.persist_state(&state)?;
+        }
+        Ok(())
+    }
+
+    /// Work towards a batch at the end of every epoch forever
+    pub async fn run(self: Arc<Self>, epoch: Duration) {
+        loop {
+            tokio::time::sleep(epoch).await;
+            match self.flush().await {
+                Ok(Some(batch)) => info!(
+                    "Batch {} submitted: {} deposits under root {}",
+                    batch.nonce,
+                    batch.deposits.len(),
+                    batch.root
+                ),
+                Ok(None) => {}
+                Err(e) => error!("Batch submission failed: {}", e),
+            }
+        }
+    }
+
+    /// Catch up with batches verified on L1, sign a peer's proposal or make
+    /// our own, and submit ours once it has quorum
+    pub async fn flush(&self) -> Result<Option<Batch>, SentinelError> {
+        self.maintenance.wait_out("batching").await;
+        let mut submitted = self.sync().await?;
+
+        if self.halt.is_halted() {
+            warn!("Attestation halted, batch held back");
+            return Ok(submitted);
+        }
+
+        self.cosign().await?;
+        if let Some(batch) = self.propose().await? {
+            submitted = Some(batch);
+        }
+        Ok(submitted)
+    }
+
+    /// The batch we are collecting signatures for, served to peers
+    pub fn proposal(&self) -> Option<Proposal> {
+        self.state.lock().unwrap().proposal.clone()
+    }
+
+    /// Advance past batch nonces used on L1, recording the batches we proposed
+    /// or signed
+    async fn sync(&self) -> Result<Option<Batch>, SentinelError> {
+        let mut recorded = None;
+        loop {
+            let nonce = self.state.lock().unwrap().next_nonce;
+            let root = self.signer.batch_root(nonce).await?;
+            if root == [0u8; 32] {
+                return Ok(recorded);
+            }
+
+            let ours = {
+                let state = self.state.lock().unwrap();
+                [&state.proposal, &state.cosigned]
+                    .into_iter()
+                    .flatten()
+                    .find(|p| p.nonce == nonce && p.root() == root)
+                    .cloned()
+            };
+            match ours {
+                Some(proposal) => recorded = Some(self.record(&proposal, None)?),
+                // Deposits we had in another root stay pending; the contract
+                // mints each note once, however many roots include it
+                None => warn!("Batch {} verified under a root we did not sign", nonce),
+            }
+
+            let mut state = self.state.lock().unwrap();
+            state.next_nonce = nonce + 1;
+            state.proposal = None;
+            state.cosigned = None;
+            self.persist_state(&state)?;
+        }
+    }
+
+    /// Sign a peer's proposal for the current nonce, unless we signed a
+    /// root for it already
+    async fn cosign(&self) -> Result<(), SentinelError> {
+        let (nonce, signed_root) = {
+            let state = self.state.lock().unwrap();
+            let signed = [&state.proposal, &state.cosigned]
+                .into_iter()
+                .flatten()
+                .find(|p| p.nonce == state.next_nonce)
+                .cloned();
+            (state.next_nonce, signed)
+        };
+
+        for proposal in self.peers.proposals().await {
+            if proposal.nonce != nonce {
+                continue;
+            }
+            match &signed_root {
+                // Republish after a restart
+                Some(signed) if *signed == proposal => {}
+                Some(_) => continue,
+                None => {
+                    if let Err(reason) = self.check_proposal(&proposal).await {
+                        warn!("Not signing batch {} ({}): {}", nonce, proposal.root, reason);
+                        continue;
+                    }
+                }
+            }
+
+            let payloads = self.payloads(&proposal.deposits)?;
+            if signed_root.is_none() {
+                self.admit(&payloads)?;
+            }
+            let signature = self.signer.sign_batch(&payloads, proposal.root(), nonce).await?;
+            self.publish(&proposal, &signature)?;
+            info!("Signed batch {} proposed by a peer: {}", nonce, proposal.root);
+
+            let mut state = self.state.lock().unwrap();
+            state.cosigned = Some(proposal);
+            return self.persist_state(&state);
+        }
+        Ok(())
+    }
+
+    /// Why we would not sign a peer's proposal, if anything
+    async fn check_proposal(&self, proposal: &Proposal) -> Result<(), String> {
+        if proposal.deposits.is_empty()
+            || proposal.deposits.len() > self.max_size
+            || proposal.deposits.len() != proposal.leaves.len()
+        {
+            return Err("malformed proposal".to_string());
+        }
+        let pending = self.state.lock().unwrap().pending.clone();
+        let payloads = self.payloads(&proposal.deposits).map_err(|e| e.to_string())?;
+        let entries = proposal.deposits.iter().zip(&proposal.leaves).zip(&payloads);
+        for ((key, leaf), payload) in entries {
+            if !pending.contains(key) {
+                return Err(format!("deposit {} not admitted here", key));
+            }
+            if hex::encode(leaf_hash(payload)) != *leaf {
+                return Err(format!("deposit {} differs from what we observed", key));
+            }
+            self.guard(payload).await?;
+        }
+        if *proposal != Proposal::new(proposal.nonce, &payloads) {
+            return Err("root does not match its leaves".to_string());
+        }
+        Ok(())
+    }
+
+    /// Propose a batch if it is our turn, and submit it once signed by quorum
+    async fn propose(&self) -> Result<Option<Batch>, SentinelError> {
+        let (nonce, proposal, cosigned) = {
+            let state = self.state.lock().unwrap();
+            let proposal = state.proposal.clone().filter(|p| p.nonce == state.next_nonce);
+            let cosigned = state.cosigned.as_ref().is_some_and(|p| p.nonce == state.next_nonce);
+            (state.next_nonce, proposal, cosigned)
+        };
+        if cosigned {
+            return Ok(None);
+        }
+
+        let operators = self.stake_registry.operators().await?;
+        let active: Vec<_> = operators
+            .iter()
+            .filter(|op| op.is_active)
+            .map(|op| op.address)
+            .collect();
+
+        let proposal = match proposal {
+            Some(proposal) => proposal,
+            None => {
+                if !self.is_our_turn(nonce, &active) {
+                    return Ok(None);
+                }
+                let payloads = self.select().await?;
+                if payloads.is_empty() {
+                    return Ok(None);
+                }
+                self.admit(&payloads)?;
+                let proposal = Proposal::new(nonce, &payloads);
+                let mut state = self.state.lock().unwrap();
+                state.proposal = Some(proposal.clone());
+                self.persist_state(&state)?;
+                proposal
+            }
+        };
+
+        // Signing the same root again is idempotent; publish it for peers
+        let payloads = self.payloads(&proposal.deposits)?;
+        let root = proposal.root();
+        let signature = self.signer.sign_batch(&payloads, root, nonce).await?;
+        let digest = self.publish(&proposal, &signature)?;
+
+        // Refuse to submit until the signers carry enough stake
+        let cosigners = self
+            .peers
+            .signatures(&digest, self.signer.address(), &active)
+            .await;
+        let mut signers = vec![self.signer.address()];
+        signers.extend(cosigners.iter().map(|(cosigner, _)| *cosigner));
+        if let Err(e) = self.quorum.ensure(&operators, &signers) {
+            warn!("Batch {} not submitted yet: {}", nonce, e);
+            return Ok(None);
+        }
+
+        let count = proposal.deposits.len() as u32;
+        let l1_tx_hash = self
+            .signer
+            .submit_batch(root, nonce, count, &signature, &cosigners)
+            .await?;
+        let batch = self.record(&proposal, Some(l1_tx_hash))?;
+
+        let mut state = self.state.lock().unwrap();
+        state.next_nonce = nonce + 1;
+        state.proposal = None;
+        self.persist_state(&state)?;
+        Ok(Some(batch))
+    }
+
+    /// Whether our turn to propose under `nonce` has come; each operator in
+    /// the rotation gets `leader_timeout` before the next one takes over
+    fn is_our_turn(&self, nonce: u64, active: &[Address]) -> bool {
+        let since = {
+            let mut since = self.nonce_since.lock().unwrap();
+            if since.0 != nonce {
+                *since = (nonce, Instant::now());
+            }
+            since.1
+        };
+        let seed = keccak256(nonce.to_be_bytes());
+        let schedule = LeaderSchedule::new(seed, active, self.leader_timeout);
+        match schedule.wait_for(self.signer.address()) {
+            Some(turn) => since.elapsed() >= turn,
+            None => {
+                warn!("Operator is not in the active set, not proposing batches");
+                false
+            }
+        }
+    }
+
+    /// Pending deposits for a new batch, in key order, dropping those that
+    /// will never be batched
+    async fn select(&self) -> Result<Vec<BridgePayload>, SentinelError> {
+        let mut pending = self.state.lock().unwrap().pending.clone();
+        pending.sort();
+
+        let mut selected = Vec::new();
+        let mut dropped = Vec::new();
+        for key in pending {
+            if selected.len() == self.max_size {
+                break;
+            }
+            let payload = match self.store.get(&key).map(|record| record.to_payload()) {
+                Some(Ok(payload)) => payload,
+                _ => {
+                    warn!("Batched deposit {} missing from the store, dropped", key);
+                    dropped.push(key);
+                    continue;
+                }
+            };
+            if self.reorgs.is_orphaned(&payload) {
+                warn!("Deposit {} reorged out, not batched", key);
+                dropped.push(key);
+                continue;
+            }
+            match self.equivocation.check(&payload, self.signed_deposit(&payload)).await {
+                Ok(None) => selected.push(payload),
+                Ok(Some(dispatch)) => {
+                    info!("Deposit {} already dispatched on L1, not batched", key);
+                    dispatch.mark_submitted(&self.store)?;
+                    dropped.push(key);
+                }
+                Err(e) => error!("Refusing to batch deposit {}: {}", key, e),
+            }
+        }
+
+        if !dropped.is_empty() {
+            let mut state = self.state.lock().unwrap();
+            state.pending.retain(|key| !dropped.contains(key));
+            self.persist_state(&state)?;
+        }
+        Ok(selected)
+    }
+
+    /// Why `payload` must not be signed into a batch now, if anything
+    async fn guard(&self, payload: &BridgePayload) -> Result<(), String> {
+        if self.reorgs.is_orphaned(payload) {
+            return Err(format!("deposit {} reorged out", payload_key(payload)));
+        }
+        match self.equivocation.check(payload, self.signed_deposit(payload)).await {
+            Ok(None) => Ok(()),
+            Ok(Some(_)) => Err(format!("deposit {} already dispatched", payload_key(payload))),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Count a new batch against the signing caps
+    fn admit(&self, payloads: &[BridgePayload]) -> Result<(), SentinelError> {
+        let amount = payloads.iter().map(BridgePayload::net_amount).sum();
+        self.rate_limiter
+            .lock()
+            .unwrap()
+            .admit(amount, now_secs())
+            .map_err(SentinelError::Signing)
+    }
+
+    /// What we signed for the payload's note, if anything
+    fn signed_deposit(&self, payload: &BridgePayload) -> Option<SignedNote> {
+        self.signer
+            .signed_deposit(&payload.tx_hash, payload.output_index)
+    }
+
+    /// Serve our signature over a proposal's root to peers, returning its
+    /// digest
+    fn publish(&self, proposal: &Proposal, signature: &[u8]) -> Result<[u8; 32], SentinelError> {
+        let digest = self.signer.compute_batch_digest(
+            proposal.root(),
+            proposal.nonce,
+            proposal.deposits.len() as u32,
+        );
+        self.signed.insert(digest, self.signer.address(), signature);
+        Ok(digest)
+    }
+
+    /// Stored payloads of deposit keys
+    fn payloads(&self, keys: &[String]) -> Result<Vec<BridgePayload>, SentinelError> {
+        keys.iter()
+            .map(|key| {
+                self.store
+                    .get(key)
+                    .ok_or_else(|| SentinelError::Storage(format!("deposit {} not found", key)))?
+                    .to_payload()
+            })
+            .collect()
+    }
+
+    /// Record a batch verified on L1, marking its deposits submitted
+    fn record(
+        &self,
+        proposal: &Proposal,
+        l1_tx_hash: Option<String>,
+    ) -> Result<Batch, SentinelError> {
+        let batch = Batch {
+            nonce: proposal.nonce,
+            root: proposal.root.clone(),
+            deposits: proposal.deposits.clone(),
+            leaves: proposal.leaves.clone(),
+            l1_tx_hash: l1_tx_hash.clone().unwrap_or_default(),
+            submitted_at: now_secs(),
+        };
+
+        {
+            let mut batches = self.batches.lock().unwrap();
+            batches.push(batch.clone());
+            self.persist(&batches)?;
+        }
+
+        for key in &batch.deposits {
+            let recorded = self
+                .store
+                .transition(key, DepositStatus::Submitted, None)
+                .and_then(|_| match &l1_tx_hash {
+                    Some(l1_tx_hash) => self
+                        .store
+                        .update(key, |r| r.l1_tx_hash = Some(l1_tx_hash.clone())),
+                    None => Ok(()),
+                });
+            if let Err(e) = recorded {
+                error!("Failed to record batched deposit {}: {}", key, e);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.pending.retain(|key| !batch.deposits.contains(key));
+        self.persist_state(&state)?;
+        Ok(batch)
+    }
+
+    /// Inclusion proof for the batched deposit stored under `key`
+    pub fn proof(&self, key: &str) -> Option<InclusionProof> {
+        let key = key.strip_prefix("0x").unwrap_or(key);
+        let batches = self.batches.lock().unwrap();
+        let batch = batches.iter().find(|b| b.deposits.iter().any(|k| k == key))?;
+        let index = batch.deposits.iter().position(|k| k == key)?;
+
+        let leaves = batch
+            .leaves
+            .iter()
+            .map(|l| parse_hash(l))
+            .collect::<Option<Vec<[u8; 32]>>>()?;
+        let tree = MerkleTree::new(leaves);
+
+        Some(InclusionProof {
+            batch_nonce: batch.nonce,
+            root: batch.root.clone(),
+            index,
+            leaf: batch.leaves[index].clone(),
+            proof: tree.proof(index).iter().map(hex::encode).collect(),
+        })
+    }
+
+    /// Look up a batch by its root
+    pub fn batch(&self, root: &str) -> Option<Batch> {
+        let root = root.strip_prefix("0x").unwrap_or(root);
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|b| b.root == root)
+            .cloned()
+    }
+
+    /// Write the batches atomically to disk
+    fn persist(&self, batches: &[Batch]) -> Result<(), SentinelError> {
+        let bytes = serde_json::to_vec_pretty(batches)?;
+        let tmp = self.path.with_extension("json.tmp");
+
+        std::fs::write(&tmp, bytes).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// Write the batching progress atomically to disk
+    fn persist_state(&self, state: &BatchState) -> Result<(), SentinelError> {
+        let bytes = serde_json::to_vec_pretty(state)?;
+        let tmp = self.state_path.with_extension("json.tmp");
+
+        std::fs::write(&tmp, bytes).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, &self.state_path)
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+/// 32-byte hash from hex
+fn parse_hash(s: &str) -> Option<[u8; 32]> {
+    hex::decode(s).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proofs_verify() {
+        for n in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..n).map(|i| keccak256([i])).collect();
+            let tree = MerkleTree::new(leaves.clone());
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                assert!(verify_proof(*leaf, &tree.proof(i), tree.root()), "n={} i={}", n, i);
+            }
+            assert!(!verify_proof(keccak256([0xff]), &tree.proof(0), tree.root()));
+        }
+    }
+
+    #[test]
+    fn test_single_leaf_is_root() {
+        let leaf = keccak256([1]);
+        let tree = MerkleTree::new(vec![leaf]);
+        assert_eq!(tree.root(), leaf);
+        assert!(tree.proof(0).is_empty());
+    }
+
+    #[test]
+    fn test_proposal_root_commits_to_its_leaves() {
+        let first = BridgePayload::sample();
+        let mut second = BridgePayload::sample();
+        second.output_index = 1;
+
+        let proposal = Proposal::new(7, &[first.clone(), second.clone()]);
+        assert_eq!(proposal.deposits, vec![payload_key(&first), payload_key(&second)]);
+        for (i, leaf) in proposal.leaves.iter().enumerate() {
+            let proof = MerkleTree::new(vec![leaf_hash(&first), leaf_hash(&second)]).proof(i);
+            assert!(verify_proof(parse_hash(leaf).unwrap(), &proof, proposal.root()));
+        }
+
+        // A peer proposing the same deposits under another root is caught
+        let mut forged = proposal.clone();
+        forged.root = hex::encode(keccak256([0]));
+        assert_ne!(forged, Proposal::new(7, &[first, second]));
+    }
+}
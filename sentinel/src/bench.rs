@@ -0,0 +1,199 @@
+//! Scanner benchmark
+//!
+//! `sentinel bench scan` runs the scanner's trial decryption over a synthetic
+//! corpus (valid points and commitments, so every output goes through key
+//! agreement and the KDF like a real miss) or over recorded replay fixtures,
+//! and reports throughput and per-stage timing. Allocation counts need the
+//! `bench-alloc` feature, which installs a counting global allocator.
+
+use crate::config::SentinelConfig;
+use crate::replay::load_blocks;
+use crate::scanner::{compact_output, DecryptScratch, Scanner};
+use anyhow::Result;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use zcash_client_backend::proto::compact_formats::{CompactBlock, CompactSaplingOutput, CompactTx};
+use zcash_primitives::sapling::{value::NoteValue, Note, Rseed};
+use zcash_primitives::zip32::ExtendedSpendingKey;
+
+#[cfg(feature = "bench-alloc")]
+mod alloc {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    pub static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+    pub static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+    /// System allocator that counts allocations
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+}
+
+#[cfg(feature = "bench-alloc")]
+pub use alloc::CountingAllocator;
+
+/// Allocations and bytes allocated so far, if counting is compiled in
+fn alloc_counters() -> Option<(u64, u64)> {
+    #[cfg(feature = "bench-alloc")]
+    {
+        use std::sync::atomic::Ordering;
+        Some((
+            alloc::ALLOCATIONS.load(Ordering::Relaxed),
+            alloc::ALLOCATED_BYTES.load(Ordering::Relaxed),
+        ))
+    }
+    #[cfg(not(feature = "bench-alloc"))]
+    None
+}
+
+/// Options for `sentinel bench scan`
+pub struct ScanBenchArgs {
+    /// Synthetic blocks to generate
+    pub blocks: u32,
+
+    /// Sapling outputs per synthetic block
+    pub outputs_per_block: u32,
+
+    /// Replay fixture directory to use instead of a synthetic corpus
+    pub fixtures: Option<PathBuf>,
+
+    /// Passes over the corpus
+    pub iterations: u32,
+}
+
+/// Entry point for `sentinel bench scan`
+pub fn scan_command(config: &SentinelConfig, args: ScanBenchArgs) -> Result<()> {
+    let (sender, _receiver) = mpsc::channel(1);
+    let scanner = Scanner::new(
+        config.lightwalletd_url.clone(),
+        &config.shards(),
+        config.confirmation_depth,
+        sender,
+    )?;
+
+    let corpus = match &args.fixtures {
+        Some(dir) => load_blocks(&dir.join("blocks"))?,
+        None => synthetic_corpus(args.blocks, args.outputs_per_block),
+    };
+    let outputs: u64 = corpus
+        .iter()
+        .flat_map(|b| &b.vtx)
+        .map(|tx| tx.outputs.len() as u64)
+        .sum::<u64>()
+        * u64::from(args.iterations);
+
+    // Stage 1: parsing compact outputs
+    let parse = time(args.iterations, || {
+        for output in corpus.iter().flat_map(|b| &b.vtx).flat_map(|tx| &tx.outputs) {
+            std::hint::black_box(compact_output(output));
+        }
+    });
+
+    // Whole scan: parsing plus batched trial decryption under every shard
+    // key, reusing buffers across blocks as the decrypt stage does
+    let allocs_before = alloc_counters();
+    let mut hits = 0;
+    let mut scratch = DecryptScratch::default();
+    let mut found = Vec::new();
+    let scan = time(args.iterations, || {
+        for block in &corpus {
+            found.clear();
+            if scanner.find_vault_outputs_with(block, &mut scratch, &mut found).is_ok() {
+                hits += found.len();
+            }
+        }
+    });
+    let allocs_after = alloc_counters();
+
+    let decrypt = scan.saturating_sub(parse);
+    println!(
+        "Corpus:          {} blocks, {} outputs x {} passes",
+        corpus.len(),
+        outputs / u64::from(args.iterations.max(1)),
+        args.iterations
+    );
+    println!("Vault hits:      {}", hits);
+    println!("Throughput:      {:.0} outputs/sec", outputs as f64 / scan.as_secs_f64());
+    println!("Parse:           {:?} ({:?}/output)", parse, per_output(parse, outputs));
+    println!("Trial decrypt:   {:?} ({:?}/output)", decrypt, per_output(decrypt, outputs));
+    match (allocs_before, allocs_after) {
+        (Some((count0, bytes0)), Some((count1, bytes1))) => println!(
+            "Allocations:     {} ({:.1}/output, {} bytes)",
+            count1 - count0,
+            (count1 - count0) as f64 / outputs.max(1) as f64,
+            bytes1 - bytes0
+        ),
+        _ => println!("Allocations:     n/a (build with --features bench-alloc)"),
+    }
+    Ok(())
+}
+
+/// Run `f` `iterations` times and return the total elapsed time
+fn time(iterations: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed()
+}
+
+/// Mean time per output
+fn per_output(total: Duration, outputs: u64) -> Duration {
+    total / u32::try_from(outputs.max(1)).unwrap_or(u32::MAX)
+}
+
+/// Blocks of outputs to unrelated addresses, with valid points and commitments
+fn synthetic_corpus(blocks: u32, outputs_per_block: u32) -> Vec<CompactBlock> {
+    let mut counter: u32 = 0;
+    (0..blocks)
+        .map(|b| {
+            let outputs = (0..outputs_per_block)
+                .map(|_| {
+                    counter += 1;
+                    let seed = [&counter.to_le_bytes()[..], &[0u8; 28]].concat();
+                    let (_, address) = ExtendedSpendingKey::master(&seed).default_address();
+                    let note = Note::from_parts(
+                        address,
+                        NoteValue::from_raw(u64::from(counter)),
+                        Rseed::AfterZip212([counter as u8; 32]),
+                    );
+                    CompactSaplingOutput {
+                        cmu: note.cmu().to_bytes().to_vec(),
+                        // Any address's pk_d is a valid ephemeral key encoding
+                        ephemeral_key: address.to_bytes()[11..].to_vec(),
+                        ciphertext: vec![counter as u8; 52],
+                    }
+                })
+                .collect();
+            CompactBlock {
+                height: 2_000_000 + u64::from(b),
+                hash: [b as u8; 32].to_vec(),
+                vtx: vec![CompactTx {
+                    index: 1,
+                    hash: [b as u8; 32].to_vec(),
+                    outputs,
+                    ..CompactTx::default()
+                }],
+                ..CompactBlock::default()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,222 @@
+//! Regtest end-to-end harness
+//!
+//! One-command smoke test of the whole bridge path against a local
+//! zebrad + lightwalletd + anvil stack (see `docker/docker-compose.yml`):
+//! sends a memo-carrying deposit to the vault from a regtest wallet, mines it
+//! past the confirmation depth, runs the sentinel pipeline, and waits for the
+//! matching `DepositVerified` event on the local ServiceManager.
+//!
+//! The sentinel inherits this process's environment (operator key, vault
+//! keys, ...); the harness only overrides the endpoints and confirmation
+//! depth it controls.
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Filter, H256};
+use ethers::utils::keccak256;
+use sentinel_core::memo::MemoPayload;
+use sentinel_core::payload::DEPOSIT_VERIFIED;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Regtest end-to-end harness
+#[derive(Debug, Parser)]
+#[command(name = "sentinel-e2e", about = "Regtest end-to-end bridge smoke test")]
+struct Args {
+    /// zebrad JSON-RPC URL (used to mine blocks)
+    #[arg(long, env = "ZCASH_RPC_URL", default_value = "http://127.0.0.1:18232")]
+    zcash_rpc: String,
+
+    /// Wallet JSON-RPC URL supporting z_sendmany (zallet or zcashd)
+    #[arg(long, env = "WALLET_RPC_URL", default_value = "http://127.0.0.1:28232")]
+    wallet_rpc: String,
+
+    /// Wallet address funding the deposit
+    #[arg(long, env = "E2E_FUNDING_ADDRESS")]
+    funding_address: String,
+
+    /// lightwalletd gRPC URL
+    #[arg(long, env = "LIGHTWALLETD_URL", default_value = "http://127.0.0.1:9067")]
+    lightwalletd: String,
+
+    /// Anvil RPC URL
+    #[arg(long, env = "L1_RPC_URL", default_value = "http://127.0.0.1:8545")]
+    l1_rpc: String,
+
+    /// Deployed ServiceManager address
+    #[arg(long, env = "SERVICE_MANAGER_ADDRESS")]
+    service_manager: Address,
+
+    /// Vault shielded address
+    #[arg(long, env = "VAULT_ADDRESS")]
+    vault_address: String,
+
+    /// Deposit amount in zatoshi
+    #[arg(long, default_value_t = 100_000_000)]
+    amount: u64,
+
+    /// Confirmations the sentinel waits for
+    #[arg(long, default_value_t = 3)]
+    confirmations: u32,
+
+    /// Sentinel binary (defaults to the one next to this harness)
+    #[arg(long)]
+    sentinel_bin: Option<PathBuf>,
+
+    /// Give up after this many seconds
+    #[arg(long, default_value_t = 300)]
+    timeout_secs: u64,
+}
+
+/// Minimal JSON-RPC 1.0 client for zebrad and the wallet
+struct Rpc {
+    /// Endpoint URL
+    url: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl Rpc {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&json!({"jsonrpc": "1.0", "id": "e2e", "method": method, "params": params}))
+            .send()
+            .await
+            .with_context(|| format!("{} unreachable", self.url))?
+            .json()
+            .await?;
+        if !response["error"].is_null() {
+            bail!("{} failed: {}", method, response["error"]);
+        }
+        Ok(response["result"].clone())
+    }
+}
+
+/// Kills the sentinel when the harness exits, pass or fail
+struct SentinelProcess(Child);
+
+impl Drop for SentinelProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let deadline = Instant::now() + Duration::from_secs(args.timeout_secs);
+
+    let node = Rpc::new(&args.zcash_rpc);
+    let wallet = Rpc::new(&args.wallet_rpc);
+    let provider = Provider::<Http>::try_from(args.l1_rpc.as_str())?;
+
+    // 1. The stack is up
+    let start_height = node.call("getblockcount", json!([])).await?;
+    let l1_start = provider.get_block_number().await.context("anvil unreachable")?;
+    println!("[1/5] zebrad at height {}, anvil at block {}", start_height, l1_start);
+
+    // 2. Send a memo-carrying deposit to the vault
+    let aztec_address: [u8; 32] = keccak256(b"sentinel-e2e recipient");
+    let secret_hash: [u8; 32] = keccak256(format!("sentinel-e2e {}", start_height));
+    let memo = serde_json::to_string(&MemoPayload {
+        msg_type: "bridge_deposit".to_string(),
+        aztec_address: format!("0x{}", hex::encode(aztec_address)),
+        secret_hash: format!("0x{}", hex::encode(secret_hash)),
+        version: 1,
+        refund_address: None,
+        amount: Some(args.amount),
+        target: None,
+        domain: None,
+    })?;
+    let recipients = json!([{
+        "address": args.vault_address,
+        "amount": args.amount as f64 / 100_000_000.0,
+        "memo": hex::encode(memo),
+    }]);
+    let opid = wallet
+        .call("z_sendmany", json!([args.funding_address, recipients, 1]))
+        .await?;
+    let txid = wait_for_operation(&wallet, &opid, deadline).await?;
+    println!("[2/5] deposit sent: {}", txid);
+
+    // 3. Mine it past the confirmation depth
+    node.call("generate", json!([args.confirmations + 1])).await?;
+    println!("[3/5] mined {} blocks", args.confirmations + 1);
+
+    // 4. Run the sentinel pipeline
+    let bin = match args.sentinel_bin {
+        Some(bin) => bin,
+        None => std::env::current_exe()?.with_file_name("sentinel"),
+    };
+    let _sentinel = SentinelProcess(
+        Command::new(&bin)
+            .arg("run")
+            .env("ZCASH_NETWORK", "regtest")
+            .env("LIGHTWALLETD_URL", &args.lightwalletd)
+            .env("L1_RPC_URL", &args.l1_rpc)
+            .env("SERVICE_MANAGER_ADDRESS", format!("{:?}", args.service_manager))
+            .env("VAULT_ADDRESS", &args.vault_address)
+            .env("CONFIRMATION_DEPTH", args.confirmations.to_string())
+            .stdin(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to start {}", bin.display()))?,
+    );
+    println!("[4/5] sentinel started");
+
+    // 5. Wait for the attestation to land on L1
+    let mut display: [u8; 32] = hex::decode(txid.as_str().unwrap_or_default())?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("malformed txid {}", txid))?;
+    let topics = [H256::from(display), {
+        display.reverse();
+        H256::from(display)
+    }];
+    let filter = Filter::new()
+        .address(args.service_manager)
+        .topic0(H256::from(keccak256(DEPOSIT_VERIFIED)))
+        .topic1(topics.to_vec())
+        .from_block(l1_start);
+
+    while Instant::now() < deadline {
+        if let Some(log) = provider.get_logs(&filter).await?.first() {
+            println!(
+                "[5/5] attestation landed in L1 tx {:?}",
+                log.transaction_hash.unwrap_or_default()
+            );
+            println!("PASS");
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+
+    bail!("FAIL: no DepositVerified for {} within {}s", txid, args.timeout_secs)
+}
+
+/// Wait for an async wallet operation and return its txid
+async fn wait_for_operation(wallet: &Rpc, opid: &Value, deadline: Instant) -> Result<Value> {
+    while Instant::now() < deadline {
+        let results = wallet.call("z_getoperationresult", json!([[opid]])).await?;
+        if let Some(result) = results.as_array().and_then(|r| r.first()) {
+            return match result["status"].as_str() {
+                Some("success") => Ok(result["result"]["txid"].clone()),
+                _ => bail!("deposit failed: {}", result["error"]),
+            };
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    bail!("timed out waiting for wallet operation {}", opid)
+}
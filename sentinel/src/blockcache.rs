@@ -0,0 +1,163 @@
+//! On-disk compact block cache
+//!
+//! Confirmed compact blocks fetched from lightwalletd are kept under
+//! `<DATA_DIR>/block_cache/<height>.bin` (prost-encoded, as in replay
+//! fixtures). After a restart, or on `sentinel rescan`, the fetch stage
+//! reads the heights it has there instead of downloading them again, and
+//! only goes to lightwalletd from the first missing one.
+//!
+//! The cache holds at most `BLOCK_CACHE_MB`; past that the lowest heights
+//! are pruned first. Blocks above a reorg's fork point are dropped with it,
+//! so the cache never serves a block from an abandoned chain twice.
+
+use crate::error::SentinelError;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+
+/// Default disk budget of the block cache
+pub const DEFAULT_BLOCK_CACHE_MB: u64 = 512;
+
+/// Compact blocks on disk, keyed by height
+pub struct BlockCache {
+    /// Cache directory
+    dir: PathBuf,
+
+    /// Most bytes kept on disk
+    max_bytes: u64,
+
+    /// Cached heights and their file sizes
+    index: Mutex<BTreeMap<u32, u64>>,
+}
+
+impl BlockCache {
+    /// Open the cache in `dir`, keeping at most `max_bytes`
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self, SentinelError> {
+        let dir = dir.into();
+        let storage = |e: std::io::Error| SentinelError::Storage(e.to_string());
+        std::fs::create_dir_all(&dir).map_err(storage)?;
+
+        let mut index = BTreeMap::new();
+        for entry in std::fs::read_dir(&dir).map_err(storage)? {
+            let entry = entry.map_err(storage)?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+                continue;
+            }
+            let Some(height) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse().ok())
+            else {
+                continue;
+            };
+            index.insert(height, entry.metadata().map_err(storage)?.len());
+        }
+
+        let cache = Self {
+            dir,
+            max_bytes,
+            index: Mutex::new(index),
+        };
+        cache.prune(&mut cache.index.lock().unwrap());
+        Ok(cache)
+    }
+
+    /// Number of cached blocks
+    pub fn len(&self) -> usize {
+        self.index.lock().unwrap().len()
+    }
+
+    /// File holding the block at `height`
+    fn path(&self, height: u32) -> PathBuf {
+        self.dir.join(format!("{}.bin", height))
+    }
+
+    /// The cached block at `height`; unreadable entries are dropped
+    pub fn get(&self, height: u32) -> Option<CompactBlock> {
+        if !self.index.lock().unwrap().contains_key(&height) {
+            return None;
+        }
+        let block = std::fs::read(self.path(height))
+            .ok()
+            .and_then(|bytes| CompactBlock::decode(bytes.as_slice()).ok())
+            .filter(|block| block.height == u64::from(height));
+        if block.is_none() {
+            self.index.lock().unwrap().remove(&height);
+            let _ = std::fs::remove_file(self.path(height));
+        }
+        block
+    }
+
+    /// Cache `block`, pruning the lowest heights past the budget
+    pub fn put(&self, block: &CompactBlock) -> Result<(), SentinelError> {
+        let height = block.height as u32;
+        let bytes = block.encode_to_vec();
+        let tmp = self.dir.join(format!("{}.tmp", height));
+        std::fs::write(&tmp, &bytes)
+            .and_then(|_| std::fs::rename(&tmp, self.path(height)))
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+
+        let mut index = self.index.lock().unwrap();
+        index.insert(height, bytes.len() as u64);
+        self.prune(&mut index);
+        Ok(())
+    }
+
+    /// Drop every block above `height`
+    pub fn truncate(&self, height: u32) {
+        let mut index = self.index.lock().unwrap();
+        for (dropped, _) in index.split_off(&(height + 1)) {
+            let _ = std::fs::remove_file(self.path(dropped));
+        }
+    }
+
+    /// Remove the lowest heights until the cache fits its budget
+    fn prune(&self, index: &mut BTreeMap<u32, u64>) {
+        let mut total: u64 = index.values().sum();
+        while total > self.max_bytes {
+            let Some((height, size)) = index.pop_first() else {
+                break;
+            };
+            let _ = std::fs::remove_file(self.path(height));
+            total -= size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: u64) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: vec![height as u8; 32],
+            ..CompactBlock::default()
+        }
+    }
+
+    #[test]
+    fn test_cache_prunes_and_truncates() {
+        let dir = tempfile::tempdir().unwrap();
+        let size = block(100).encode_to_vec().len() as u64;
+        let cache = BlockCache::open(dir.path(), 3 * size).unwrap();
+        for height in 100..=103 {
+            cache.put(&block(height)).unwrap();
+        }
+
+        // The lowest height went to stay within the budget
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(100).is_none());
+        assert_eq!(cache.get(103).unwrap().hash, vec![103; 32]);
+
+        // A reorg at 101 drops the blocks above it, also across a reopen
+        cache.truncate(101);
+        let cache = BlockCache::open(dir.path(), 3 * size).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(101).is_some());
+        assert!(cache.get(102).is_none());
+    }
+}
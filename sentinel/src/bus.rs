@@ -0,0 +1,389 @@
+//! Event bus publisher
+//!
+//! Publishes deposit lifecycle events to NATS or Kafka so accounting and
+//! notification systems can follow bridge activity without polling the
+//! sentinel. `EVENT_BUS_URL` selects the bus:
+//!
+//! - `nats://[user:pass@]host:4222`: core NATS, subject `<prefix>.<event>`
+//! - `kafka+http://proxy:8082` (or `kafka+https://`): a Kafka REST proxy,
+//!   topic `<prefix>.<event>`, keyed by the Zcash txid
+//!
+//! Payloads are JSON or protobuf (`EVENT_BUS_FORMAT`); the protobuf schema is
+//! [`EventMessage`].
+
+use crate::error::SentinelError;
+use crate::events::{BridgeEvent, EventSink};
+use async_trait::async_trait;
+use prost::Message;
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+/// Default topic and subject prefix
+pub const DEFAULT_EVENT_BUS_PREFIX: &str = "sentinel";
+
+/// Longest a publish may take
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Payload encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Serialization {
+    /// JSON object, as served by the status API
+    Json,
+    /// [`EventMessage`] protobuf
+    Protobuf,
+}
+
+impl FromStr for Serialization {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "protobuf" | "proto" => Ok(Self::Protobuf),
+            _ => anyhow::bail!("EVENT_BUS_FORMAT must be json or protobuf"),
+        }
+    }
+}
+
+/// Protobuf form of a [`BridgeEvent`]
+///
+/// ```proto
+/// message EventMessage {
+///   string kind = 1;
+///   string tx_hash = 2;
+///   uint64 amount = 3;
+///   uint64 fee = 4;
+///   uint32 block_height = 5;
+///   string aztec_address = 6;
+///   optional uint64 nonce = 7;
+///   optional string l1_tx_hash = 8;
+///   uint64 at = 9;
+/// }
+/// ```
+#[derive(Clone, PartialEq, Message)]
+pub struct EventMessage {
+    #[prost(string, tag = "1")]
+    pub kind: String,
+    #[prost(string, tag = "2")]
+    pub tx_hash: String,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(uint64, tag = "4")]
+    pub fee: u64,
+    #[prost(uint32, tag = "5")]
+    pub block_height: u32,
+    #[prost(string, tag = "6")]
+    pub aztec_address: String,
+    #[prost(uint64, optional, tag = "7")]
+    pub nonce: Option<u64>,
+    #[prost(string, optional, tag = "8")]
+    pub l1_tx_hash: Option<String>,
+    #[prost(uint64, tag = "9")]
+    pub at: u64,
+}
+
+impl From<&BridgeEvent> for EventMessage {
+    fn from(event: &BridgeEvent) -> Self {
+        Self {
+            kind: event.kind.as_str().to_string(),
+            tx_hash: event.tx_hash.clone(),
+            amount: event.amount,
+            fee: event.fee,
+            block_height: event.block_height,
+            aztec_address: event.aztec_address.clone(),
+            nonce: event.nonce,
+            l1_tx_hash: event.l1_tx_hash.clone(),
+            at: event.at,
+        }
+    }
+}
+
+impl Serialization {
+    /// Encode an event
+    pub fn encode(self, event: &BridgeEvent) -> Result<Vec<u8>, SentinelError> {
+        match self {
+            Self::Json => {
+                serde_json::to_vec(event).map_err(|e| SentinelError::InvalidPayload(e.to_string()))
+            }
+            Self::Protobuf => Ok(EventMessage::from(event).encode_to_vec()),
+        }
+    }
+}
+
+/// Build the publisher for `url`
+pub fn publisher(
+    url: &str,
+    prefix: &str,
+    serialization: Serialization,
+) -> anyhow::Result<Arc<dyn EventSink>> {
+    if let Some(rest) = url.strip_prefix("nats://") {
+        return Ok(Arc::new(NatsPublisher::new(rest, prefix, serialization)));
+    }
+    if let Some(rest) = url.strip_prefix("kafka+") {
+        if !rest.starts_with("http://") && !rest.starts_with("https://") {
+            anyhow::bail!("Kafka event bus must be kafka+http:// or kafka+https://");
+        }
+        return Ok(Arc::new(KafkaRestPublisher::new(
+            rest,
+            prefix,
+            serialization,
+        )?));
+    }
+    anyhow::bail!("EVENT_BUS_URL must start with nats:// or kafka+http(s)://")
+}
+
+/// Core NATS publisher over a single connection
+pub struct NatsPublisher {
+    /// `host:port`
+    address: String,
+
+    /// `user:pass` for CONNECT, if given in the URL
+    credentials: Option<(String, String)>,
+
+    /// Subject prefix
+    prefix: String,
+
+    /// Payload encoding
+    serialization: Serialization,
+
+    /// Open connection, re-established after a failure
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl NatsPublisher {
+    /// Publisher for `[user:pass@]host:port`
+    pub fn new(authority: &str, prefix: &str, serialization: Serialization) -> Self {
+        let (credentials, address) = match authority.rsplit_once('@') {
+            Some((userinfo, address)) => (
+                userinfo
+                    .split_once(':')
+                    .map(|(user, pass)| (user.to_string(), pass.to_string())),
+                address,
+            ),
+            None => (None, authority),
+        };
+        Self {
+            address: address.trim_end_matches('/').to_string(),
+            credentials,
+            prefix: prefix.to_string(),
+            serialization,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Open a connection and complete the NATS handshake
+    async fn connect(&self) -> Result<BufReader<TcpStream>, SentinelError> {
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .map_err(SentinelError::network)?;
+        let mut stream = BufReader::new(stream);
+
+        // The server speaks first with its INFO line
+        let mut info_line = String::new();
+        stream
+            .read_line(&mut info_line)
+            .await
+            .map_err(SentinelError::network)?;
+        if !info_line.starts_with("INFO") {
+            return Err(SentinelError::Network(format!(
+                "unexpected NATS greeting from {}",
+                self.address
+            )));
+        }
+
+        let mut options = json!({ "verbose": false, "pedantic": false, "name": "sentinel" });
+        if let Some((user, pass)) = &self.credentials {
+            options["user"] = json!(user);
+            options["pass"] = json!(pass);
+        }
+        stream
+            .get_mut()
+            .write_all(format!("CONNECT {}\r\n", options).as_bytes())
+            .await
+            .map_err(SentinelError::network)?;
+        info!("Connected to NATS at {}", self.address);
+        Ok(stream)
+    }
+
+    /// Write one PUB, connecting first if needed
+    async fn send(&self, subject: &str, payload: &[u8]) -> Result<(), SentinelError> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.connect().await?);
+        }
+        let Some(stream) = connection.as_mut() else {
+            return Err(SentinelError::Network("NATS not connected".to_string()));
+        };
+
+        let mut frame = format!("PUB {} {}\r\n", subject, payload.len()).into_bytes();
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(b"\r\n");
+        let result = stream.get_mut().write_all(&frame).await;
+        if let Err(e) = result {
+            // Reconnect on the next event
+            *connection = None;
+            return Err(SentinelError::network(e));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsPublisher {
+    fn name(&self) -> &str {
+        "nats"
+    }
+
+    async fn publish(&self, event: &BridgeEvent) -> Result<(), SentinelError> {
+        let subject = format!("{}.{}", self.prefix, event.kind.as_str());
+        let payload = self.serialization.encode(event)?;
+        tokio::time::timeout(PUBLISH_TIMEOUT, self.send(&subject, &payload))
+            .await
+            .map_err(|_| SentinelError::Network("NATS publish timed out".to_string()))??;
+        debug!("Published {} to NATS", subject);
+        Ok(())
+    }
+}
+
+/// Kafka publisher through a Confluent-compatible REST proxy
+pub struct KafkaRestPublisher {
+    /// Proxy base URL
+    url: String,
+
+    /// Topic prefix
+    prefix: String,
+
+    /// Payload encoding
+    serialization: Serialization,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl KafkaRestPublisher {
+    /// Publisher posting to the REST proxy at `url`
+    pub fn new(url: &str, prefix: &str, serialization: Serialization) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(PUBLISH_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            prefix: prefix.to_string(),
+            serialization,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaRestPublisher {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    async fn publish(&self, event: &BridgeEvent) -> Result<(), SentinelError> {
+        let topic = format!("{}.{}", self.prefix, event.kind.as_str());
+        // JSON records are embedded as is; protobuf ones travel as base64
+        let (content_type, value) = match self.serialization {
+            Serialization::Json => ("application/vnd.kafka.json.v2+json", json!(event)),
+            Serialization::Protobuf => (
+                "application/vnd.kafka.binary.v2+json",
+                json!(base64(&self.serialization.encode(event)?)),
+            ),
+        };
+        let key = match self.serialization {
+            Serialization::Json => json!(event.tx_hash),
+            Serialization::Protobuf => json!(base64(event.tx_hash.as_bytes())),
+        };
+
+        self.client
+            .post(format!("{}/topics/{}", self.url, topic))
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(json!({ "records": [{ "key": key, "value": value }] }).to_string())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(SentinelError::network)?;
+        debug!("Published {} to Kafka", topic);
+        Ok(())
+    }
+}
+
+/// Standard base64 with padding
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_nats_publish_frames_event() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket
+                .write_all(b"INFO {\"server_id\":\"test\"}\r\n")
+                .await
+                .unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            // CONNECT line, then PUB header, payload line
+            while received.windows(2).filter(|w| w == b"\r\n").count() < 3 {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+            String::from_utf8(received).unwrap()
+        });
+
+        let publisher = NatsPublisher::new(
+            &format!("bridge:secret@{}", address),
+            "sentinel",
+            Serialization::Json,
+        );
+        let payload = crate::BridgePayload::sample();
+        let event = BridgeEvent::new(EventKind::DepositDetected, &payload);
+        publisher.publish(&event).await.unwrap();
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("CONNECT {"));
+        assert!(received.contains("\"user\":\"bridge\""));
+        let json = serde_json::to_vec(&event).unwrap();
+        assert!(received.contains(&format!("PUB sentinel.deposit_detected {}\r\n", json.len())));
+
+        let proto = Serialization::Protobuf.encode(&event).unwrap();
+        let decoded = EventMessage::decode(&proto[..]).unwrap();
+        assert_eq!(decoded.tx_hash, event.tx_hash);
+        assert_eq!(decoded.nonce, None);
+        assert_eq!(base64(b"sentinel"), "c2VudGluZWw=");
+    }
+}
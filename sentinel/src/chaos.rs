@@ -0,0 +1,202 @@
+//! Fault injection for resilience testing
+//!
+//! Built only with the `chaos` feature. Failures are injected at the
+//! transport boundaries so retry, failover and queue persistence can be
+//! exercised against a real stack:
+//!
+//! - L1 and Zcash node JSON-RPC traffic is routed through a local proxy that
+//!   answers with HTTP 500s and drops transaction receipts at configured rates
+//! - lightwalletd scan passes are delayed and fail with injected timeouts
+//!
+//! Faults are drawn from a seeded PRNG so a failing scenario can be rerun.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use serde_json::{json, Value};
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::warn;
+
+/// Injected failure rates and delays
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+    /// Fraction of L1 RPC requests answered with HTTP 500
+    pub l1_error_rate: f64,
+
+    /// Fraction of `eth_getTransactionReceipt` results replaced with null
+    pub dropped_receipt_rate: f64,
+
+    /// Fraction of Zcash node RPC requests answered with HTTP 500
+    pub zcash_rpc_error_rate: f64,
+
+    /// Fraction of lightwalletd scan passes failing with a timeout
+    pub grpc_timeout_rate: f64,
+
+    /// Delay added before every scan pass, in milliseconds
+    pub block_delay_ms: u64,
+
+    /// PRNG seed
+    pub seed: u64,
+}
+
+impl ChaosConfig {
+    /// Load `CHAOS_*` settings from the environment
+    pub fn from_env() -> Result<Self> {
+        let rate = |name: &str| -> Result<f64> {
+            let value: f64 = env::var(name)
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .with_context(|| format!("Invalid {}", name))?;
+            anyhow::ensure!((0.0..=1.0).contains(&value), "{} must be between 0 and 1", name);
+            Ok(value)
+        };
+
+        Ok(Self {
+            l1_error_rate: rate("CHAOS_L1_ERROR_RATE")?,
+            dropped_receipt_rate: rate("CHAOS_DROPPED_RECEIPT_RATE")?,
+            zcash_rpc_error_rate: rate("CHAOS_ZCASH_RPC_ERROR_RATE")?,
+            grpc_timeout_rate: rate("CHAOS_GRPC_TIMEOUT_RATE")?,
+            block_delay_ms: env::var("CHAOS_BLOCK_DELAY_MS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("Invalid CHAOS_BLOCK_DELAY_MS")?,
+            seed: env::var("CHAOS_SEED")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .context("Invalid CHAOS_SEED")?,
+        })
+    }
+}
+
+/// Active fault injector
+struct Chaos {
+    /// Rates and delays
+    config: ChaosConfig,
+
+    /// xorshift64 state
+    rng: Mutex<u64>,
+}
+
+impl Chaos {
+    /// Whether to inject a fault with probability `rate`
+    fn roll(&self, rate: f64) -> bool {
+        if rate <= 0.0 {
+            return false;
+        }
+        let mut state = self.rng.lock().unwrap();
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state as f64 / u64::MAX as f64) < rate
+    }
+}
+
+/// Global injector, set once by [`install`]
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+/// Enable fault injection and route RPC endpoints through the chaos proxy
+pub async fn install(mut config: SentinelConfig) -> Result<SentinelConfig> {
+    let chaos = ChaosConfig::from_env()?;
+    warn!("Fault injection enabled: {:?}", chaos);
+
+    config.l1_rpc_url = spawn_proxy(config.l1_rpc_url.clone(), chaos.l1_error_rate).await?;
+    if let Some(url) = config.zcash_rpc_url.take() {
+        config.zcash_rpc_url = Some(spawn_proxy(url, chaos.zcash_rpc_error_rate).await?);
+    }
+
+    let _ = CHAOS.set(Chaos {
+        rng: Mutex::new(chaos.seed.max(1)),
+        config: chaos,
+    });
+    Ok(config)
+}
+
+/// Delay and possibly fail a lightwalletd scan pass
+pub async fn lightwalletd_fault() -> Result<(), SentinelError> {
+    let Some(chaos) = CHAOS.get() else {
+        return Ok(());
+    };
+    if chaos.config.block_delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(chaos.config.block_delay_ms)).await;
+    }
+    if chaos.roll(chaos.config.grpc_timeout_rate) {
+        return Err(SentinelError::Grpc(tonic::Status::deadline_exceeded(
+            "injected lightwalletd timeout",
+        )));
+    }
+    Ok(())
+}
+
+/// Proxy state
+#[derive(Clone)]
+struct ProxyState {
+    /// Upstream JSON-RPC URL
+    upstream: String,
+
+    /// Fraction of requests answered with HTTP 500
+    error_rate: f64,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+/// Start a faulty JSON-RPC proxy in front of `upstream`; returns its URL
+async fn spawn_proxy(upstream: String, error_rate: f64) -> Result<String> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+
+    let app = Router::new().route("/", post(proxy)).with_state(ProxyState {
+        upstream,
+        error_rate,
+        client: reqwest::Client::new(),
+    });
+    let server = axum::Server::from_tcp(listener)?.serve(app.into_make_service());
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("Chaos proxy stopped: {}", e);
+        }
+    });
+
+    Ok(url)
+}
+
+/// Forward one JSON-RPC request, injecting faults
+async fn proxy(State(state): State<ProxyState>, body: Bytes) -> Response {
+    let chaos = CHAOS.get();
+    if chaos.is_some_and(|c| c.roll(state.error_rate)) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "injected fault").into_response();
+    }
+
+    let upstream = match state
+        .client
+        .post(&state.upstream)
+        .header("content-type", "application/json")
+        .body(body.clone())
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
+    let status = StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let bytes = upstream.bytes().await.unwrap_or_default();
+
+    // Receipts that never arrive
+    let request: Value = serde_json::from_slice(&body).unwrap_or_default();
+    if request["method"] == "eth_getTransactionReceipt"
+        && chaos.is_some_and(|c| c.roll(c.config.dropped_receipt_rate))
+    {
+        let dropped = json!({"jsonrpc": "2.0", "id": request["id"], "result": null});
+        return axum::Json(dropped).into_response();
+    }
+
+    (status, bytes.to_vec()).into_response()
+}
@@ -0,0 +1,139 @@
+//! Scan height checkpoint
+//!
+//! The parse stage records the last block it has finished with in
+//! `<DATA_DIR>/scan_checkpoint.json`, so a restarted sentinel resumes just
+//! after it instead of rescanning from genesis. A block only counts once
+//! every deposit in it has been taken off the deposit queue, and the
+//! checkpoint does not move while coinbase deposits wait for maturity in
+//! memory, so a crash never skips a deposit. The block's hash is kept too:
+//! the fetch stage checks the first block after a restart builds on it.
+
+use crate::error::SentinelError;
+use crate::lease::LeaseFence;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Least time between two checkpoint writes
+const WRITE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Contents of the checkpoint file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    /// Last fully scanned height
+    pub height: u32,
+
+    /// Hash of the block at `height` (hex, internal byte order)
+    pub hash: String,
+}
+
+impl ScanCheckpoint {
+    /// Block hash as bytes
+    pub fn hash_bytes(&self) -> Result<[u8; 32], SentinelError> {
+        hex::decode(&self.hash)?
+            .try_into()
+            .map_err(|_| SentinelError::Storage("checkpoint hash is not 32 bytes".to_string()))
+    }
+}
+
+/// On-disk checkpoint of the scanned height
+#[derive(Debug)]
+pub struct CheckpointStore {
+    /// Checkpoint file
+    path: PathBuf,
+
+    /// Checkpoint found when the store was opened
+    loaded: Option<ScanCheckpoint>,
+
+    /// When the checkpoint was last written
+    last_write: Mutex<Option<Instant>>,
+
+    /// Leader lease the checkpoint is only written under, in an HA pair
+    fence: Option<Arc<LeaseFence>>,
+}
+
+impl CheckpointStore {
+    /// Open the checkpoint at `path`; there is none on a first run
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SentinelError> {
+        let path = path.into();
+        let loaded = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let checkpoint: ScanCheckpoint = serde_json::from_slice(&bytes)?;
+                checkpoint.hash_bytes()?;
+                Some(checkpoint)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        Ok(Self {
+            path,
+            loaded,
+            last_write: Mutex::new(None),
+            fence: None,
+        })
+    }
+
+    /// Leave the checkpoint to the leader unless `fence` holds the lease; a
+    /// standby scanning ahead keeps its height in memory
+    pub fn with_fence(mut self, fence: Arc<LeaseFence>) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+
+    /// Checkpoint to resume from
+    pub fn loaded(&self) -> Option<&ScanCheckpoint> {
+        self.loaded.as_ref()
+    }
+
+    /// Record that scanning is complete up to `height`, writing at most
+    /// once per `WRITE_INTERVAL`
+    pub fn record(&self, height: u32, hash: &[u8; 32]) -> Result<(), SentinelError> {
+        if self.fence.as_ref().is_some_and(|fence| fence.check().is_err()) {
+            return Ok(());
+        }
+        let mut last_write = self.last_write.lock().unwrap();
+        if last_write.is_some_and(|at| at.elapsed() < WRITE_INTERVAL) {
+            return Ok(());
+        }
+        self.write(&ScanCheckpoint {
+            height,
+            hash: hex::encode(hash),
+        })?;
+        *last_write = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Replace the checkpoint file in one rename
+    fn write(&self, checkpoint: &ScanCheckpoint) -> Result<(), SentinelError> {
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(checkpoint)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan_checkpoint.json");
+        let store = CheckpointStore::open(&path).unwrap();
+        assert_eq!(store.loaded(), None);
+
+        // Writes closer together than the interval are skipped
+        store.record(2_500_000, &[7; 32]).unwrap();
+        store.record(2_500_001, &[8; 32]).unwrap();
+
+        let reopened = CheckpointStore::open(&path).unwrap();
+        let checkpoint = reopened.loaded().unwrap();
+        assert_eq!(checkpoint.height, 2_500_000);
+        assert_eq!(checkpoint.hash_bytes().unwrap(), [7; 32]);
+
+        std::fs::write(&path, r#"{"height": 1, "hash": "00"}"#).unwrap();
+        assert!(CheckpointStore::open(&path).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Claim secret reveal monitoring
+//!
+//! A deposit is claimed on Aztec by revealing the secret behind its
+//! `secret_hash`, which consumes the L1→L2 message dispatched for it. This
+//! module links each submitted deposit to its message hash (from the
+//! ServiceManager's `DepositVerified` event), watches the inbox for the
+//! message being consumed or cancelled, and flags deposits that stay unclaimed
+//! past a timeout.
+//!
+//! A deposit dispatched on L1 is never refunded from here: a cancelled
+//! message is raised for the operator, who must establish that it was not
+//! consumed before refunding anything. The next L1 block to query is kept in
+//! `<DATA_DIR>/claims.json`, so a restart resumes where the monitor stopped.
+
+use crate::clock::now_secs;
+use crate::dispatches::{DepositVerified, DEPOSIT_VERIFIED};
+use crate::error::SentinelError;
+use crate::store::{DepositStatus, DepositStore};
+use crate::withdrawal::MAX_LOG_RANGE;
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes, H256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Default time a deposit may stay unclaimed before alerting (7 days)
+pub const DEFAULT_CLAIM_TIMEOUT_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Monitor progress, persisted in the data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MonitorState {
+    /// Next L1 block to query
+    from_block: u64,
+}
+
+/// Watches for claims of attested deposits
+pub struct ClaimMonitor {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Inbox contract address (resolved from the ServiceManager)
+    inbox_address: Option<Address>,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Time after submission before an unclaimed deposit expires
+    claim_timeout: Duration,
+
+    /// Next L1 block to query
+    state: MonitorState,
+
+    /// Where `state` is persisted
+    state_path: PathBuf,
+}
+
+impl ClaimMonitor {
+    /// Create a monitor resuming from its state file at `state_path`, or
+    /// from `start_block` (the ServiceManager's deployment block) on a first
+    /// run
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        start_block: u64,
+        store: Arc<DepositStore>,
+        claim_timeout: Duration,
+        state_path: PathBuf,
+    ) -> Result<Self, SentinelError> {
+        let mut state: MonitorState = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                SentinelError::Storage(format!("{}: {}", state_path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => MonitorState::default(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        state.from_block = state.from_block.max(start_block);
+
+        Ok(Self {
+            provider,
+            service_manager_address,
+            inbox_address: None,
+            store,
+            claim_timeout,
+            state,
+            state_path,
+        })
+    }
+
+    /// Poll for claims forever
+    pub async fn run(mut self, poll_interval: Duration) {
+        loop {
+            if let Err(e) = self.poll().await {
+                error!("Claim monitor error: {}", e);
+            }
+            self.expire_unclaimed();
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Process dispatch and inbox events up to the current L1 head
+    pub async fn poll(&mut self) -> Result<(), SentinelError> {
+        let inbox = self.inbox().await?;
+        let head = self.provider.get_block_number().await?.as_u64();
+
+        while self.state.from_block <= head {
+            let from_block = self.state.from_block;
+            let to_block = head.min(from_block + MAX_LOG_RANGE - 1);
+            self.process_range(inbox, from_block, to_block).await?;
+
+            self.state.from_block = to_block + 1;
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Process the events of L1 blocks `from_block..=to_block`
+    async fn process_range(
+        &self,
+        inbox: Address,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<(), SentinelError> {
+        // Link submitted deposits to their L1→L2 message hash
+        let dispatched = self
+            .logs(self.service_manager_address, DEPOSIT_VERIFIED.as_bytes(), from_block, to_block)
+            .await?;
+        for log in dispatched {
+            let dispatch = DepositVerified::decode(&log)?;
+            let key = dispatch.key();
+            if self.store.get(&key).is_some() {
+                self.store
+                    .set_message_hash(&key, &hex::encode(dispatch.message_hash))?;
+            }
+        }
+
+        // Consumed messages mean the secret was revealed and the deposit claimed
+        let consumed = self
+            .logs(inbox, b"MessageConsumed(bytes32)", from_block, to_block)
+            .await?;
+        for log in consumed {
+            if let Some(message_hash) = log.topics.get(1) {
+                self.on_claimed(message_hash)?;
+                if let (Some(record), Some(claim_tx)) = (
+                    self.store.find_by_message_hash(&hex::encode(message_hash)),
+                    log.transaction_hash,
+                ) {
+                    self.store.update(&record.key(), |r| {
+                        r.claim_tx_hash = Some(format!("{:?}", claim_tx))
+                    })?;
+                }
+            }
+        }
+
+        // The zZEC of a cancelled message may still have been minted; leave
+        // any refund to the operator
+        let cancelled = self
+            .logs(inbox, b"MessageCancelled(bytes32)", from_block, to_block)
+            .await?;
+        for log in cancelled {
+            let Some(message_hash) = log.topics.get(1) else {
+                continue;
+            };
+            if let Some(record) = self.store.find_by_message_hash(&hex::encode(message_hash)) {
+                error!(
+                    "ALERT: claim message of deposit {} ({} zatoshi) cancelled; \
+                     check it was not consumed before refunding it",
+                    record.key(),
+                    record.amount
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the state file in one rename
+    fn persist(&self) -> Result<(), SentinelError> {
+        let tmp = self.state_path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.state)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.state_path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// Flag submitted deposits that stayed unclaimed past the timeout
+    pub fn expire_unclaimed(&self) {
+        let now = now_secs();
+
+        for record in self.store.with_status(DepositStatus::Submitted) {
+            if now.saturating_sub(record.updated_at) < self.claim_timeout.as_secs() {
+                continue;
+            }
+
+            warn!(
+                "ALERT: deposit {} ({} zatoshi) unclaimed for over {:?}",
+                record.tx_hash, record.amount, self.claim_timeout
+            );
+            if let Err(e) = self
+                .store
+                .transition(&record.key(), DepositStatus::ClaimExpired, None)
+            {
+                error!("Failed to expire deposit {}: {}", record.tx_hash, e);
+            }
+        }
+    }
+
+    /// Mark the deposit owning `message_hash` claimed
+    fn on_claimed(&self, message_hash: &H256) -> Result<(), SentinelError> {
+        let Some(record) = self.store.find_by_message_hash(&hex::encode(message_hash)) else {
+            return Ok(());
+        };
+
+        if record.status.can_transition_to(DepositStatus::Claimed) {
+            self.store
+                .transition(&record.key(), DepositStatus::Claimed, None)?;
+            info!("Deposit {} is now {:?}", record.tx_hash, DepositStatus::Claimed);
+        }
+        Ok(())
+    }
+
+    /// Fetch logs for `event` emitted by `address` in `from_block..=to_block`
+    async fn logs(
+        &self,
+        address: Address,
+        event: &[u8],
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, SentinelError> {
+        let filter = Filter::new()
+            .address(address)
+            .topic0(H256::from(keccak256(event)))
+            .from_block(from_block)
+            .to_block(to_block);
+
+        Ok(self.provider.get_logs(&filter).await?)
+    }
+
+    /// Resolve the inbox address from the ServiceManager once
+    async fn inbox(&mut self) -> Result<Address, SentinelError> {
+        if let Some(inbox) = self.inbox_address {
+            return Ok(inbox);
+        }
+
+        let call = TransactionRequest::new()
+            .to(self.service_manager_address)
+            .data(Bytes::from(keccak256(b"inbox()")[0..4].to_vec()));
+        let result = self.provider.call(&call.into(), None).await?;
+        if result.len() < 32 {
+            return Err(SentinelError::L1("Malformed inbox() response".to_string()));
+        }
+
+        let inbox = Address::from_slice(&result[12..32]);
+        self.inbox_address = Some(inbox);
+        Ok(inbox)
+    }
+}
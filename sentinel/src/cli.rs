@@ -0,0 +1,320 @@
+//! Command-line interface
+//!
+//! Running `sentinel` without a subcommand starts the watcher; operational
+//! tasks are exposed as subcommands.
+
+use clap::{Parser, Subcommand};
+use ethers::types::Address;
+use std::path::PathBuf;
+
+/// Sentinel AVS command line
+#[derive(Debug, Parser)]
+#[command(name = "sentinel", version, about = "Zcash chain watcher and attestation signer")]
+pub struct Cli {
+    /// Subcommand to run (defaults to `run`)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Top-level subcommands
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the sentinel pipeline
+    Run,
+
+    /// Run the pipeline, scanning again from a height below the checkpoint
+    Rescan {
+        /// First block height to scan again
+        #[arg(long)]
+        from_height: u32,
+    },
+
+    /// Manage AVS operator rewards
+    Rewards {
+        #[command(subcommand)]
+        action: RewardsCommand,
+    },
+
+    /// Vault reserve reporting
+    Reserves {
+        #[command(subcommand)]
+        action: ReservesCommand,
+    },
+
+    /// Show the full cross-chain journey of a deposit
+    Trace {
+        /// Zcash transaction id of the deposit, as `<txid>:<output_index>`
+        /// when the transaction holds several vault notes
+        tx_hash: String,
+
+        /// Print the stored record as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Aggregate stored deposits into per-period statistics
+    Report {
+        /// Period to aggregate over: daily, weekly or monthly
+        #[arg(long, default_value = "monthly")]
+        period: crate::report::Period,
+
+        /// Output format: json or csv
+        #[arg(long, default_value = "json")]
+        format: crate::report::ReportFormat,
+
+        /// Write the report to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Verify the operator key audit trail and list every use of the key
+    KeyAudit {
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Deploy the L1 contracts to a local anvil node and run the pipeline
+    Dev {
+        /// Forge output directory of contracts/l1
+        #[arg(long, default_value = "../contracts/l1/out")]
+        artifacts: PathBuf,
+    },
+
+    /// Generate an operator key into an encrypted keystore
+    Keygen {
+        /// Directory to write the keystore to
+        #[arg(long, default_value = "./keys")]
+        dir: PathBuf,
+
+        /// Keystore file name (defaults to a UUID)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Environment variable holding the keystore password
+        #[arg(long, default_value = "KEYSTORE_PASSWORD")]
+        password_env: String,
+
+        /// EigenLayer AVS directory; also prints the signed registration digest
+        #[arg(long)]
+        avs_directory: Option<Address>,
+
+        /// ServiceManager the operator registers with
+        #[arg(long, env = "SERVICE_MANAGER_ADDRESS")]
+        service_manager: Option<Address>,
+
+        /// L1 RPC used to compute the registration digest
+        #[arg(long, env = "L1_RPC_URL", default_value = "http://localhost:8545")]
+        l1_rpc_url: String,
+
+        /// Seconds the registration signature stays valid
+        #[arg(long, default_value_t = 3600)]
+        expiry_secs: u64,
+    },
+
+    /// Derive the vault viewing keys and address from its mnemonic
+    DeriveVk {
+        /// Environment variable holding the mnemonic
+        #[arg(long, default_value = "VAULT_MNEMONIC")]
+        mnemonic_env: String,
+
+        /// Environment variable holding the BIP-39 passphrase, if any
+        #[arg(long, default_value = "VAULT_MNEMONIC_PASSPHRASE")]
+        passphrase_env: String,
+
+        /// ZIP-32 path (defaults to m/32'/<coin>'/0')
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Zcash network
+        #[arg(long, env = "ZCASH_NETWORK", default_value = "regtest")]
+        network: String,
+    },
+
+    /// Check an attestation signature against a payload and operator address
+    VerifyAttestation {
+        /// Payload JSON file (`-` for stdin), e.g. from `sentinel trace --json`
+        #[arg(long)]
+        payload: Option<PathBuf>,
+
+        /// Zcash transaction hash (hex)
+        #[arg(long)]
+        tx_hash: Option<String>,
+
+        /// Index of the vault note in the transaction (default 0)
+        #[arg(long)]
+        output_index: Option<u32>,
+
+        /// Note value in zatoshi, before the fee
+        #[arg(long)]
+        amount: Option<u64>,
+
+        /// Bridge fee in zatoshi
+        #[arg(long)]
+        fee: Option<u64>,
+
+        /// Claim secret hash (hex)
+        #[arg(long)]
+        secret_hash: Option<String>,
+
+        /// Recipient Aztec address (hex)
+        #[arg(long)]
+        aztec_address: Option<String>,
+
+        /// Zcash block height
+        #[arg(long)]
+        block_height: Option<u32>,
+
+        /// Hash of the Zcash block at the height (hex)
+        #[arg(long)]
+        block_hash: Option<String>,
+
+        /// Attestation nonce
+        #[arg(long)]
+        nonce: Option<u64>,
+
+        /// Signature (hex)
+        #[arg(long)]
+        signature: String,
+
+        /// Operator address expected to have signed
+        #[arg(long)]
+        operator: Address,
+
+        /// Chain id of the EIP-712 domain
+        #[arg(long, default_value_t = crate::vectors::DEFAULT_CHAIN_ID)]
+        chain_id: u64,
+
+        /// ServiceManager address of the EIP-712 domain
+        #[arg(long, env = "SERVICE_MANAGER_ADDRESS", default_value = crate::vectors::DEFAULT_VERIFYING_CONTRACT)]
+        verifying_contract: Address,
+    },
+
+    /// Emit payload hash and signature test vectors for the Foundry suite
+    TestVectors {
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Chain id of the EIP-712 domain
+        #[arg(long, default_value_t = crate::vectors::DEFAULT_CHAIN_ID)]
+        chain_id: u64,
+
+        /// ServiceManager address of the EIP-712 domain
+        #[arg(long, default_value = crate::vectors::DEFAULT_VERIFYING_CONTRACT)]
+        verifying_contract: Address,
+    },
+
+    /// Performance benchmarks
+    Bench {
+        #[command(subcommand)]
+        action: BenchCommand,
+    },
+
+    /// Record blocks and vault transactions from lightwalletd as replay fixtures
+    Record {
+        /// Fixture directory to write
+        fixture_dir: PathBuf,
+
+        /// First block height
+        #[arg(long)]
+        from: u64,
+
+        /// Last block height
+        #[arg(long)]
+        to: u64,
+    },
+
+    /// Replay recorded blocks and transactions through the scanner
+    Replay {
+        /// Fixture directory with `blocks/` and `tx/`
+        fixture_dir: PathBuf,
+
+        /// Print one JSON object per deposit
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run a scripted chain-history scenario against the mock lightwalletd
+    #[cfg(feature = "mock-lightwalletd")]
+    Scenario {
+        /// Scenario JSON file
+        file: PathBuf,
+    },
+
+    /// Shield testnet funds into the vault with a valid bridge memo
+    SendTestDeposit {
+        /// Amount to deposit, in zatoshi
+        #[arg(long)]
+        amount: u64,
+
+        /// Recipient Aztec address (hex)
+        #[arg(long)]
+        aztec_address: String,
+
+        /// Claim secret hash (hex)
+        #[arg(long)]
+        secret_hash: String,
+
+        /// Funded transparent address to spend from
+        #[arg(long)]
+        from: String,
+
+        /// Hex secp256k1 key for the funding address
+        #[arg(long, env = "TEST_DEPOSIT_KEY", hide_env_values = true)]
+        transparent_key: String,
+
+        /// Directory holding sapling-spend.params and sapling-output.params
+        #[arg(long)]
+        params_dir: Option<PathBuf>,
+    },
+}
+
+/// `sentinel rewards` subcommands
+#[derive(Debug, Subcommand)]
+pub enum RewardsCommand {
+    /// Claim accrued rewards from the rewards coordinator
+    Claim {
+        /// Claim even if the accrued amount is below the configured threshold
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// `sentinel reserves` subcommands
+#[derive(Debug, Subcommand)]
+pub enum ReservesCommand {
+    /// Produce a signed proof-of-reserves report
+    Report {
+        /// Zcash height to compute the report at (defaults to everything stored)
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Write the report to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// `sentinel bench` subcommands
+#[derive(Debug, Subcommand)]
+pub enum BenchCommand {
+    /// Measure trial-decryption throughput
+    Scan {
+        /// Synthetic blocks to generate
+        #[arg(long, default_value_t = 100)]
+        blocks: u32,
+
+        /// Sapling outputs per synthetic block
+        #[arg(long, default_value_t = 100)]
+        outputs_per_block: u32,
+
+        /// Replay fixture directory to use instead of a synthetic corpus
+        #[arg(long)]
+        fixtures: Option<PathBuf>,
+
+        /// Passes over the corpus
+        #[arg(long, default_value_t = 1)]
+        iterations: u32,
+    },
+}
@@ -0,0 +1,55 @@
+//! Wall clock
+//!
+//! Deadlines, expiry and rate windows read the time through [`now_secs`].
+//! Normally that is the system clock; under the simulation runtime
+//! (`src/sim.rs`) it follows tokio's paused clock instead, so timers and
+//! timestamps advance together and hours pass in milliseconds.
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    /// Virtual epoch: Unix time at `anchor`, and tokio's instant at that point
+    static VIRTUAL: Cell<Option<(u64, tokio::time::Instant)>> = Cell::new(None);
+}
+
+/// Current Unix time in seconds
+pub fn now_secs() -> u64 {
+    if let Some((start, anchor)) = VIRTUAL.with(Cell::get) {
+        return start + anchor.elapsed().as_secs();
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Year, month and day (UTC) of the day `days` after the Unix epoch
+pub fn civil_date(days: u64) -> (i64, u32, u32) {
+    // Civil-from-days, Howard Hinnant's algorithm
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// Follow tokio's clock from Unix time `start` on this thread
+///
+/// Only meaningful on a current-thread runtime with paused time, where every
+/// task runs on the thread that installed the virtual clock.
+#[cfg(test)]
+pub fn set_virtual(start: u64) {
+    VIRTUAL.with(|v| v.set(Some((start, tokio::time::Instant::now()))));
+}
+
+/// Return this thread to the system clock
+#[cfg(test)]
+pub fn clear_virtual() {
+    VIRTUAL.with(|v| v.set(None));
+}
@@ -0,0 +1,333 @@
+//! Cross-operator deposit consistency check
+//!
+//! Before signing, optionally ask peer sentinels whether they observed the same
+//! deposit with identical fields. A single compromised lightwalletd can feed one
+//! operator fabricated data, but it cannot make independent peers agree. Every
+//! attested field is compared, the block hash and fee included, so agreeing
+//! peers sign the same digest; each sentinel serves its view from its deposit
+//! store once it has fixed the deposit's fee.
+//!
+//! The same channel carries signatures: every sentinel serves the attestation
+//! and batch root signatures it made by EIP-712 digest, and the operator
+//! dispatching a deposit or batch collects its peers' to reach quorum.
+//! Operators sign each note under the same nonce, so their digests agree
+//! whenever their views do.
+
+use crate::aggregate::{verify_partials, PARTIAL_SIGNATURE_LENGTH};
+use crate::batch::Proposal;
+use crate::error::SentinelError;
+use crate::reputation::ReputationTracker;
+use crate::store::deposit_key;
+use crate::BridgePayload;
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Default time peers are given to confirm a deposit, in seconds
+pub const DEFAULT_PEER_CONFIRM_DEADLINE_SECS: u64 = 30;
+
+/// Wait before asking peers about a deposit again, doubled on each round
+const CONFIRM_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait between two rounds of asking peers
+const CONFIRM_MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// Deposit as reported between sentinels (hex encoded fields)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservedDeposit {
+    /// Zcash transaction hash
+    pub tx_hash: String,
+
+    /// Index of the vault note in the transaction
+    #[serde(default)]
+    pub output_index: u32,
+
+    /// Amount in zatoshi
+    pub amount: u64,
+
+    /// Hash of the claim secret
+    pub secret_hash: String,
+
+    /// Recipient's Aztec address
+    pub aztec_address: String,
+
+    /// Block height of the deposit
+    pub block_height: u32,
+
+    /// Hash of the block containing the deposit
+    pub block_hash: String,
+
+    /// Bridge fee in zatoshi
+    pub fee: u64,
+}
+
+impl From<&BridgePayload> for ObservedDeposit {
+    fn from(payload: &BridgePayload) -> Self {
+        Self {
+            tx_hash: hex::encode(payload.tx_hash),
+            output_index: payload.output_index,
+            amount: payload.amount,
+            secret_hash: hex::encode(payload.secret_hash),
+            aztec_address: hex::encode(payload.aztec_address),
+            block_height: payload.block_height,
+            block_hash: hex::encode(payload.block_hash),
+            fee: payload.fee,
+        }
+    }
+}
+
+impl ObservedDeposit {
+    /// Store key of the observed note
+    pub fn key(&self) -> String {
+        deposit_key(&self.tx_hash, self.output_index)
+    }
+}
+
+/// An attestation signature as served to peers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerSignature {
+    /// Signing operator
+    pub signer: Address,
+
+    /// ECDSA signature over the digest, hex
+    pub signature: String,
+}
+
+/// Attestation signatures this sentinel made, served to the peer dispatching
+/// them via the status API
+#[derive(Default)]
+pub struct SignedDigests {
+    /// Signatures keyed by EIP-712 digest
+    signatures: RwLock<HashMap<[u8; 32], PeerSignature>>,
+}
+
+impl SignedDigests {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a signature `signer` made over `digest`
+    pub fn insert(&self, digest: [u8; 32], signer: Address, signature: &[u8]) {
+        self.signatures.write().unwrap().insert(
+            digest,
+            PeerSignature {
+                signer,
+                signature: hex::encode(signature),
+            },
+        );
+    }
+
+    /// Our signature over `digest`, if any
+    pub fn get(&self, digest: &[u8; 32]) -> Option<PeerSignature> {
+        self.signatures.read().unwrap().get(digest).cloned()
+    }
+}
+
+/// Queries peer sentinels for their view of a deposit
+pub struct PeerChecker {
+    /// Base URLs of peer status APIs
+    peers: Vec<String>,
+
+    /// Number of agreeing peers required before signing
+    required: usize,
+
+    /// How long peers are given to confirm a deposit
+    confirm_deadline: Duration,
+
+    /// HTTP client
+    client: reqwest::Client,
+
+    /// Tracker credited with peers' invalid signatures, if any
+    reputation: Option<Arc<ReputationTracker>>,
+}
+
+impl PeerChecker {
+    /// Create a new checker
+    pub fn new(peers: Vec<String>, required: usize, timeout: Duration) -> Result<Self, SentinelError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(SentinelError::network)?;
+
+        Ok(Self {
+            peers,
+            required,
+            confirm_deadline: Duration::from_secs(DEFAULT_PEER_CONFIRM_DEADLINE_SECS),
+            client,
+            reputation: None,
+        })
+    }
+
+    /// Give peers `deadline` to confirm each deposit
+    pub fn with_confirm_deadline(mut self, deadline: Duration) -> Self {
+        self.confirm_deadline = deadline;
+        self
+    }
+
+    /// Count invalid peer signatures against their operators in `reputation`
+    pub fn with_reputation(mut self, reputation: Arc<ReputationTracker>) -> Self {
+        self.reputation = Some(reputation);
+        self
+    }
+
+    /// Whether any peer confirmation is required
+    pub fn is_enabled(&self) -> bool {
+        self.required > 0
+    }
+
+    /// Confirm with peers that they saw the same deposit
+    ///
+    /// Peers are asked again with backoff until enough agree and none reports
+    /// different fields, or until the confirmation deadline passes: a peer
+    /// that has not seen the deposit yet, or lags on its block, is given time
+    /// to catch up. Only a difference still reported at the deadline is a
+    /// hard disagreement; unreachable peers or peers that have not seen the
+    /// deposit by then simply do not count towards the required confirmations.
+    pub async fn confirm(&self, payload: &BridgePayload) -> Result<(), SentinelError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let ours = ObservedDeposit::from(payload);
+        let key = ours.key();
+        let deadline = tokio::time::Instant::now() + self.confirm_deadline;
+        let mut delay = CONFIRM_BACKOFF;
+        let mut agreeing: HashSet<&str> = HashSet::new();
+
+        loop {
+            let mut mismatch = None;
+            for peer in self.peers.iter().filter(|p| !agreeing.contains(p.as_str())) {
+                match self.fetch(peer, &key).await {
+                    Ok(Some(theirs)) if theirs == ours => {
+                        debug!("Peer {} agrees on deposit {}", peer, key);
+                        agreeing.insert(peer);
+                    }
+                    Ok(Some(theirs)) => {
+                        debug!("Peer {} reports {:?} for deposit {}", peer, theirs, key);
+                        mismatch = Some(format!(
+                            "peer {} reports {:?}, we observed {:?}",
+                            peer, theirs, ours
+                        ));
+                    }
+                    Ok(None) => debug!("Peer {} has not observed deposit {}", peer, key),
+                    Err(e) => warn!("Peer {} unreachable: {}", peer, e),
+                }
+            }
+
+            if mismatch.is_none() && agreeing.len() >= self.required {
+                return Ok(());
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(SentinelError::PeerDisagreement(mismatch.unwrap_or_else(|| {
+                    format!(
+                        "only {} of {} required peers confirmed deposit {}",
+                        agreeing.len(),
+                        self.required,
+                        key
+                    )
+                })));
+            }
+            tokio::time::sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(CONFIRM_MAX_BACKOFF);
+        }
+    }
+
+    /// Valid peer signatures over `digest` from `active` operators other
+    /// than `own`, as (signer, signature) pairs
+    ///
+    /// Unreachable peers, peers that have not signed yet and signatures that
+    /// do not recover to their claimed signer are skipped.
+    pub async fn signatures(
+        &self,
+        digest: &[u8; 32],
+        own: Address,
+        active: &[Address],
+    ) -> Vec<(Address, Vec<u8>)> {
+        let mut signatures: Vec<(Address, Vec<u8>)> = Vec::new();
+        for peer in &self.peers {
+            let url = format!("{}/signatures/{}", peer.trim_end_matches('/'), hex::encode(digest));
+            let theirs = match self.get::<PeerSignature>(&url).await {
+                Ok(Some(theirs)) => theirs,
+                Ok(None) => {
+                    debug!("Peer {} has not signed {}", peer, hex::encode(digest));
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Peer {} unreachable: {}", peer, e);
+                    continue;
+                }
+            };
+            if theirs.signer == own || signatures.iter().any(|(known, _)| *known == theirs.signer) {
+                continue;
+            }
+            let valid = hex::decode(&theirs.signature).ok().filter(|signature| {
+                signature.len() == PARTIAL_SIGNATURE_LENGTH
+                    && verify_partials(*digest, signature, &[theirs.signer], active).is_empty()
+            });
+            let Some(signature) = valid else {
+                warn!(
+                    "Ignoring invalid signature from {:?} over {}",
+                    theirs.signer,
+                    hex::encode(digest)
+                );
+                if let Some(reputation) = &self.reputation {
+                    reputation.record_invalid_signature(theirs.signer);
+                }
+                continue;
+            };
+            signatures.push((theirs.signer, signature));
+        }
+        signatures
+    }
+
+    /// Batches peers have proposed and are collecting signatures for
+    pub async fn proposals(&self) -> Vec<Proposal> {
+        let mut proposals = Vec::new();
+        for peer in &self.peers {
+            let url = format!("{}/batches/proposal", peer.trim_end_matches('/'));
+            match self.get(&url).await {
+                Ok(Some(proposal)) => proposals.push(proposal),
+                Ok(None) => {}
+                Err(e) => warn!("Peer {} unreachable: {}", peer, e),
+            }
+        }
+        proposals
+    }
+
+    /// Fetch a peer's view of a deposit
+    async fn fetch(&self, peer: &str, key: &str) -> Result<Option<ObservedDeposit>, SentinelError> {
+        self.get(&format!("{}/deposits/{}", peer.trim_end_matches('/'), key))
+            .await
+    }
+
+    /// GET a JSON resource from a peer; `None` if it has none
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<Option<T>, SentinelError> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(SentinelError::network)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let value = response
+            .error_for_status()
+            .map_err(SentinelError::network)?
+            .json()
+            .await
+            .map_err(SentinelError::network)?;
+
+        Ok(Some(value))
+    }
+}
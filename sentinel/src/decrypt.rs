@@ -0,0 +1,132 @@
+//! Trial-decryption worker pool
+//!
+//! Trial decryption is pure CPU work, so it runs on dedicated OS threads
+//! rather than on the async runtime, which keeps the gRPC and L1 tasks
+//! responsive while a backfill saturates the remaining cores. Each worker
+//! owns its decryption buffers; workers can be pinned to specific cores.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use crate::scanner::{DecryptScratch, ScannedBlock, Scanner};
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+
+/// Worker count when unset: every core but one, which is left to the runtime
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1).max(1))
+}
+
+/// Pool size and placement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptSettings {
+    /// Number of worker threads
+    pub workers: usize,
+
+    /// Cores to pin workers to, assigned round-robin (no pinning if empty)
+    pub cores: Vec<usize>,
+}
+
+impl DecryptSettings {
+    /// Settings from the configuration
+    pub fn from_config(config: &SentinelConfig) -> Self {
+        Self {
+            workers: config.decrypt_workers,
+            cores: config.decrypt_cores.clone(),
+        }
+    }
+}
+
+/// One block to decrypt, with where to send the result
+struct Job {
+    /// Block to trial-decrypt
+    block: CompactBlock,
+
+    /// Receives the vault outputs and spends found
+    reply: oneshot::Sender<Result<ScannedBlock>>,
+}
+
+/// Handle to the running workers; they exit once it is dropped
+pub struct DecryptPool {
+    /// Queue feeding the workers
+    jobs: mpsc::Sender<Job>,
+
+    /// Number of workers
+    workers: usize,
+}
+
+impl DecryptPool {
+    /// Start the workers
+    pub fn start(scanner: Arc<Scanner>, settings: &DecryptSettings) -> Result<Self> {
+        let workers = settings.workers.max(1);
+        let (jobs, queue) = mpsc::channel::<Job>(workers);
+        let queue = Arc::new(Mutex::new(queue));
+
+        for index in 0..workers {
+            let core = (!settings.cores.is_empty())
+                .then(|| settings.cores[index % settings.cores.len()]);
+            let scanner = scanner.clone();
+            let queue = queue.clone();
+            std::thread::Builder::new()
+                .name(format!("decrypt-{}", index))
+                .spawn(move || worker(scanner, queue, core))?;
+        }
+        info!(
+            "Started {} decryption workers{}",
+            workers,
+            if settings.cores.is_empty() { "" } else { " (pinned)" }
+        );
+
+        Ok(Self { jobs, workers })
+    }
+
+    /// Number of workers
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Queue a block, waiting while every worker is busy
+    pub async fn submit(
+        &self,
+        block: CompactBlock,
+    ) -> Result<oneshot::Receiver<Result<ScannedBlock>>, SentinelError> {
+        let (reply, result) = oneshot::channel();
+        self.jobs
+            .send(Job { block, reply })
+            .await
+            .map_err(|_| SentinelError::Scanner("decryption workers stopped".to_string()))?;
+        Ok(result)
+    }
+}
+
+/// Worker loop: decrypt blocks until the pool is dropped
+fn worker(scanner: Arc<Scanner>, queue: Arc<Mutex<mpsc::Receiver<Job>>>, core: Option<usize>) {
+    if let Some(id) = core {
+        if !core_affinity::set_for_current(core_affinity::CoreId { id }) {
+            warn!("Could not pin decryption worker to core {}", id);
+        }
+    }
+
+    let mut scratch = DecryptScratch::default();
+    loop {
+        // Only one idle worker waits on the queue at a time
+        let Some(job) = queue.lock().unwrap().blocking_recv() else {
+            return;
+        };
+        let mut outputs = Vec::new();
+        let result = scanner
+            .find_vault_outputs_with(&job.block, &mut scratch, &mut outputs)
+            .and_then(|()| {
+                Ok(ScannedBlock {
+                    height: u32::try_from(job.block.height)?,
+                    hash: job.block.hash.as_slice().try_into()?,
+                    outputs,
+                    spends: scanner.spent_nullifiers(&job.block),
+                })
+            });
+        let _ = job.reply.send(result);
+    }
+}
+
@@ -0,0 +1,253 @@
+//! Compact set of vault outputs already handled
+//!
+//! The parse stage records every vault output it has finished with so that a
+//! restart or rescan does not fetch and parse it again. Each output is packed
+//! into 16 bytes (height, a 64-bit txid prefix, output index and a deposit
+//! flag) in one sorted vector, rather than a map of full records, so the set
+//! stays small after millions of outputs. It is checkpointed to a binary file
+//! every few blocks.
+//!
+//! The height and index are part of the key, so a false match needs two
+//! transactions in the same block sharing 64 bits of txid. Deposit entries
+//! are confirmed against the deposit store on a match anyway: a truncated
+//! collision, or a deposit checkpointed here before it reached the store,
+//! is then fetched again instead of skipped.
+
+use crate::error::SentinelError;
+use std::path::{Path, PathBuf};
+
+/// Default blocks between checkpoints of the seen-output set
+pub const DEFAULT_CHECKPOINT_BLOCKS: u32 = 1_000;
+
+/// Checkpoint file header
+const MAGIC: &[u8; 4] = b"SEEN";
+
+/// Checkpoint format version
+const VERSION: u8 = 1;
+
+/// Exact check for deposit entries, given the full txid and output index
+type Confirm = Box<dyn Fn(&[u8; 32], u32) -> bool + Send + Sync>;
+
+/// Pack an output into its sort key; the low bit is the deposit flag
+fn key(height: u32, txid: &[u8; 32], output_index: usize, deposit: bool) -> u128 {
+    let prefix = u64::from_le_bytes(txid[..8].try_into().unwrap());
+    let index = ((output_index as u32 & 0x7fff_ffff) << 1) | deposit as u32;
+    ((height as u128) << 96) | ((prefix as u128) << 32) | index as u128
+}
+
+/// Vault outputs already handled by the parse stage
+pub struct SeenOutputs {
+    /// Packed keys, sorted
+    keys: Vec<u128>,
+
+    /// Checkpoint file (none: memory only)
+    path: Option<PathBuf>,
+
+    /// Blocks between checkpoints
+    checkpoint_blocks: u32,
+
+    /// Blocks recorded since the last checkpoint
+    pending_blocks: u32,
+
+    /// Whether `keys` changed since the last checkpoint
+    dirty: bool,
+
+    /// Exact check for deposit entries
+    confirm: Option<Confirm>,
+}
+
+impl Default for SeenOutputs {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            path: None,
+            checkpoint_blocks: DEFAULT_CHECKPOINT_BLOCKS,
+            pending_blocks: 0,
+            dirty: false,
+            confirm: None,
+        }
+    }
+}
+
+impl SeenOutputs {
+    /// Open the set checkpointed at `path`, starting empty if there is none
+    pub fn open(path: impl AsRef<Path>, checkpoint_blocks: u32) -> Result<Self, SentinelError> {
+        let path = path.as_ref().to_path_buf();
+        let keys = match std::fs::read(&path) {
+            Ok(bytes) => decode(&bytes).ok_or_else(|| {
+                SentinelError::Storage(format!("{}: corrupt checkpoint", path.display()))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        Ok(Self {
+            keys,
+            path: Some(path),
+            checkpoint_blocks: checkpoint_blocks.max(1),
+            ..Self::default()
+        })
+    }
+
+    /// Confirm deposit entries with `confirm` (normally a deposit store lookup)
+    pub fn with_confirm(
+        mut self,
+        confirm: impl Fn(&[u8; 32], u32) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.confirm = Some(Box::new(confirm));
+        self
+    }
+
+    /// Number of outputs in the set
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the output has been handled already
+    pub fn contains(&self, height: u32, txid: &[u8; 32], output_index: usize) -> bool {
+        if self
+            .keys
+            .binary_search(&key(height, txid, output_index, false))
+            .is_ok()
+        {
+            return true;
+        }
+        if self
+            .keys
+            .binary_search(&key(height, txid, output_index, true))
+            .is_err()
+        {
+            return false;
+        }
+        match &self.confirm {
+            Some(confirm) => confirm(txid, output_index as u32),
+            None => true,
+        }
+    }
+
+    /// Record a handled output; `deposit` if it carried a bridge deposit
+    pub fn insert(&mut self, height: u32, txid: &[u8; 32], output_index: usize, deposit: bool) {
+        let key = key(height, txid, output_index, deposit);
+        // Outputs arrive in height order, so this is nearly always a push
+        if self.keys.last() < Some(&key) {
+            self.keys.push(key);
+        } else if let Err(position) = self.keys.binary_search(&key) {
+            self.keys.insert(position, key);
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    /// Forget the outputs above `height`, whose blocks were reorged out
+    pub fn rewind(&mut self, height: u32) {
+        let end = self.keys.partition_point(|k| (k >> 96) as u32 <= height);
+        if end < self.keys.len() {
+            self.keys.truncate(end);
+            self.dirty = true;
+        }
+    }
+
+    /// Note a finished block, checkpointing once enough have passed
+    pub fn record_block(&mut self) -> Result<(), SentinelError> {
+        self.pending_blocks += 1;
+        if self.pending_blocks >= self.checkpoint_blocks {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    /// Write the set atomically to its checkpoint file
+    pub fn checkpoint(&mut self) -> Result<(), SentinelError> {
+        self.pending_blocks = 0;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let tmp = path.with_extension("bin.tmp");
+        std::fs::write(&tmp, encode(&self.keys))
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, path).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Checkpoint bytes: magic, version, count, then the keys little-endian
+fn encode(keys: &[u128]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(13 + keys.len() * 16);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+    for key in keys {
+        bytes.extend_from_slice(&key.to_le_bytes());
+    }
+    bytes
+}
+
+/// Parse a checkpoint; `None` if it is malformed
+fn decode(bytes: &[u8]) -> Option<Vec<u128>> {
+    let body = bytes.strip_prefix(MAGIC.as_slice())?;
+    let (&version, body) = body.split_first()?;
+    if version != VERSION || body.len() < 8 {
+        return None;
+    }
+    let (count, body) = body.split_at(8);
+    let count = u64::from_le_bytes(count.try_into().ok()?) as usize;
+    if body.len() != count.checked_mul(16)? {
+        return None;
+    }
+
+    let keys: Vec<u128> = body
+        .chunks_exact(16)
+        .map(|chunk| u128::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    keys.windows(2)
+        .all(|pair| pair[0] < pair[1])
+        .then_some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip_and_confirm() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen_outputs.bin");
+
+        let mut seen = SeenOutputs::open(&path, 2).unwrap();
+        seen.insert(10, &[1; 32], 0, false);
+        seen.insert(12, &[2; 32], 1, true);
+        // Out of order and duplicate inserts keep the set sorted and unique
+        seen.insert(11, &[3; 32], 0, false);
+        seen.insert(10, &[1; 32], 0, false);
+        seen.record_block().unwrap();
+        assert!(!path.exists());
+        seen.record_block().unwrap();
+        assert!(path.exists());
+
+        let reopened = SeenOutputs::open(&path, 2).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert!(reopened.contains(10, &[1; 32], 0));
+        assert!(!reopened.contains(10, &[1; 32], 1));
+        assert!(!reopened.contains(13, &[1; 32], 0));
+        assert!(reopened.contains(12, &[2; 32], 1));
+
+        // Deposit entries defer to the store, which knows another note of the tx
+        let confirmed = SeenOutputs::open(&path, 2)
+            .unwrap()
+            .with_confirm(|txid, output_index| txid == &[2; 32] && output_index == 0);
+        assert!(!confirmed.contains(12, &[2; 32], 1));
+        let confirmed = SeenOutputs::open(&path, 2)
+            .unwrap()
+            .with_confirm(|txid, output_index| txid == &[2; 32] && output_index == 1);
+        assert!(confirmed.contains(12, &[2; 32], 1));
+        assert!(confirmed.contains(11, &[3; 32], 0));
+
+        std::fs::write(&path, b"SEEN\x01garbage").unwrap();
+        assert!(SeenOutputs::open(&path, 2).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Vault viewing-key derivation
+//!
+//! `sentinel derive-vk` derives the vault's viewing keys from its BIP-39
+//! mnemonic along a ZIP-32 path and prints them in the encodings the
+//! configuration expects: the Sapling extended full viewing key for
+//! `VAULT_VIEWING_KEY`, its default address for `VAULT_ADDRESS`, and for
+//! account-level paths the Orchard full viewing key and unified viewing key.
+//! The mnemonic is read from the environment, never from the command line.
+
+use anyhow::{bail, Context, Result};
+use zeroize::Zeroizing;
+use zcash_client_backend::encoding::{encode_extended_full_viewing_key, encode_payment_address};
+use zcash_client_backend::keys::UnifiedSpendingKey;
+use zcash_primitives::consensus::{Network, Parameters};
+use zcash_primitives::constants::regtest;
+use zcash_primitives::zip32::sapling::ExtendedSpendingKey;
+use zcash_primitives::zip32::{AccountId, ChildIndex};
+
+/// ZIP-32 purpose for shielded keys
+const ZIP32_PURPOSE: u32 = 32;
+
+/// Flags for `sentinel derive-vk`
+pub struct DeriveVkArgs {
+    /// Environment variable holding the mnemonic
+    pub mnemonic_env: String,
+
+    /// Environment variable holding the optional BIP-39 passphrase
+    pub passphrase_env: String,
+
+    /// ZIP-32 path, e.g. `m/32'/133'/0'`; defaults to account 0
+    pub path: Option<String>,
+
+    /// `mainnet`, `testnet` or `regtest`
+    pub network: String,
+}
+
+/// Encoding parameters for one network
+struct NetworkKeys {
+    /// Consensus parameters, where unified encodings exist
+    params: Option<Network>,
+
+    /// SLIP-44 coin type
+    coin_type: u32,
+
+    /// Sapling extended full viewing key HRP
+    hrp_efvk: &'static str,
+
+    /// Sapling payment address HRP
+    hrp_address: &'static str,
+}
+
+impl NetworkKeys {
+    /// Look up `network`
+    fn for_network(network: &str) -> Result<Self> {
+        let from_params = |params: Network| Self {
+            params: Some(params),
+            coin_type: params.coin_type(),
+            hrp_efvk: params.hrp_sapling_extended_full_viewing_key(),
+            hrp_address: params.hrp_sapling_payment_address(),
+        };
+        Ok(match network {
+            "mainnet" => from_params(Network::MainNetwork),
+            "testnet" => from_params(Network::TestNetwork),
+            "regtest" => Self {
+                params: None,
+                coin_type: regtest::COIN_TYPE,
+                hrp_efvk: regtest::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+                hrp_address: regtest::HRP_SAPLING_PAYMENT_ADDRESS,
+            },
+            other => bail!("Unknown network '{}'", other),
+        })
+    }
+}
+
+/// Parse a ZIP-32 path such as `m/32'/133'/0'`
+fn parse_path(path: &str) -> Result<Vec<ChildIndex>> {
+    let mut parts = path.split('/');
+    if parts.next() != Some("m") {
+        bail!("ZIP-32 path must start with m/");
+    }
+    parts
+        .map(|part| {
+            let (index, hardened) = match part.strip_suffix('\'').or_else(|| part.strip_suffix('h')) {
+                Some(index) => (index, true),
+                None => (part, false),
+            };
+            let index: u32 = index
+                .parse()
+                .with_context(|| format!("Invalid path component '{}'", part))?;
+            Ok(if hardened {
+                ChildIndex::Hardened(index)
+            } else {
+                ChildIndex::NonHardened(index)
+            })
+        })
+        .collect()
+}
+
+/// Account number if `path` is the standard `m/32'/<coin>'/<account>'`
+fn account_of(path: &[ChildIndex], coin_type: u32) -> Option<u32> {
+    match path {
+        [ChildIndex::Hardened(ZIP32_PURPOSE), ChildIndex::Hardened(coin), ChildIndex::Hardened(account)]
+            if *coin == coin_type =>
+        {
+            Some(*account)
+        }
+        _ => None,
+    }
+}
+
+/// Entry point for `sentinel derive-vk`
+pub fn derive_vk_command(args: DeriveVkArgs) -> Result<()> {
+    let phrase = Zeroizing::new(
+        std::env::var(&args.mnemonic_env)
+            .with_context(|| format!("Set {} to the vault mnemonic", args.mnemonic_env))?,
+    );
+    let passphrase = Zeroizing::new(std::env::var(&args.passphrase_env).unwrap_or_default());
+    let mnemonic = bip39::Mnemonic::parse(phrase.trim()).context("Invalid mnemonic")?;
+    let seed = Zeroizing::new(mnemonic.to_seed(passphrase.as_str()));
+
+    let network = NetworkKeys::for_network(&args.network)?;
+    let path = match &args.path {
+        Some(path) => parse_path(path)?,
+        None => vec![
+            ChildIndex::Hardened(ZIP32_PURPOSE),
+            ChildIndex::Hardened(network.coin_type),
+            ChildIndex::Hardened(0),
+        ],
+    };
+    if let Some(ChildIndex::Hardened(coin)) = path.get(1) {
+        if *coin != network.coin_type {
+            bail!("Path coin type {} does not match {} ({})", coin, args.network, network.coin_type);
+        }
+    }
+
+    let master = ExtendedSpendingKey::master(&seed[..]);
+    let efvk = ExtendedSpendingKey::from_path(&master, &path).to_extended_full_viewing_key();
+    let (_, address) = efvk.default_address();
+
+    println!("Network:           {}", args.network);
+    println!("Sapling viewing key (VAULT_VIEWING_KEY):");
+    println!("  {}", encode_extended_full_viewing_key(network.hrp_efvk, &efvk));
+    println!("Default vault address (VAULT_ADDRESS):");
+    println!("  {}", encode_payment_address(network.hrp_address, &address));
+
+    // Orchard keys only exist at account level
+    let Some(account) = account_of(&path, network.coin_type) else {
+        println!("Orchard: not derived (needs an account-level path m/32'/coin'/account')");
+        return Ok(());
+    };
+    let Some(params) = network.params else {
+        println!("Orchard: not derived (no unified encoding for regtest)");
+        return Ok(());
+    };
+    let usk = UnifiedSpendingKey::from_seed(&params, &seed[..], AccountId::from(account))
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {:?}", e))?;
+    let ufvk = usk.to_unified_full_viewing_key();
+    if let Some(orchard) = ufvk.orchard() {
+        println!("Orchard full viewing key (raw):");
+        println!("  {}", hex::encode(orchard.to_bytes()));
+    }
+    println!("Unified viewing key:");
+    println!("  {}", ufvk.encode(&params));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        let path = parse_path("m/32'/133'/7h").unwrap();
+        assert_eq!(account_of(&path, 133), Some(7));
+        assert_eq!(account_of(&path, 1), None);
+
+        let path = parse_path("m/32'/1'/0'/5").unwrap();
+        assert_eq!(path[3], ChildIndex::NonHardened(5));
+        assert_eq!(account_of(&path, 1), None);
+
+        assert!(parse_path("32'/133'").is_err());
+        assert!(parse_path("m/x'").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Local development mode
+//!
+//! `sentinel dev` turns a bare anvil node into a working bridge L1 in one
+//! step: it deploys BLSVerifier, Inbox and ServiceManager from the forge
+//! artifacts (`forge build` in `contracts/l1`), authorizes the ServiceManager
+//! on the inbox, funds and registers the operator, and exports the resulting
+//! addresses into the environment the configuration is loaded from.
+
+use anyhow::{bail, Context, Result};
+use ethers::abi::Abi;
+use ethers::prelude::*;
+use ethers::utils::parse_ether;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+/// Anvil's chain id
+const ANVIL_CHAIN_ID: u64 = 31337;
+
+/// Anvil dev account #0, used as contract owner
+const ANVIL_DEPLOYER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Anvil dev account #1, the operator unless `OPERATOR_PRIVATE_KEY` is set
+const ANVIL_OPERATOR_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+/// Placeholder L2 bridge address, as in `Deploy.s.sol`
+const DEV_L2_BRIDGE: u64 = 0x1234;
+
+/// Default quorum, as in `Deploy.s.sol`
+const DEV_QUORUM_BPS: u64 = 6700;
+
+/// Forge build artifact
+#[derive(Deserialize)]
+struct Artifact {
+    abi: Abi,
+    bytecode: ArtifactBytecode,
+}
+
+/// Creation bytecode section of a forge artifact
+#[derive(Deserialize)]
+struct ArtifactBytecode {
+    object: Bytes,
+}
+
+/// Load `<artifacts>/<name>.sol/<name>.json`
+fn load_artifact(artifacts: &Path, name: &str) -> Result<Artifact> {
+    let path = artifacts.join(format!("{}.sol", name)).join(format!("{}.json", name));
+    let bytes = std::fs::read(&path)
+        .with_context(|| format!("Missing {} (run `forge build` in contracts/l1)", path.display()))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Deploy the bridge contracts to anvil and export their addresses
+pub async fn prepare(artifacts: &Path) -> Result<()> {
+    let l1_rpc_url = std::env::var("L1_RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string());
+    let provider = Provider::<Http>::try_from(l1_rpc_url.as_str())?;
+    let chain_id = provider.get_chainid().await.context("L1 node unreachable")?;
+    if chain_id != U256::from(ANVIL_CHAIN_ID) {
+        bail!("sentinel dev only deploys to anvil (chain id {}), got {}", ANVIL_CHAIN_ID, chain_id);
+    }
+
+    let deployer: LocalWallet = ANVIL_DEPLOYER_KEY.parse()?;
+    let deployer = Arc::new(SignerMiddleware::new(
+        provider.clone(),
+        deployer.with_chain_id(ANVIL_CHAIN_ID),
+    ));
+
+    let bls = load_artifact(artifacts, "BLSVerifier")?;
+    let bls = ContractFactory::new(bls.abi, bls.bytecode.object, deployer.clone())
+        .deploy(())?
+        .send()
+        .await?;
+
+    let l2_bridge = H256::from_low_u64_be(DEV_L2_BRIDGE);
+    let inbox = load_artifact(artifacts, "Inbox")?;
+    let inbox = ContractFactory::new(inbox.abi, inbox.bytecode.object, deployer.clone())
+        .deploy((l2_bridge,))?
+        .send()
+        .await?;
+
+    let stake = parse_ether(1)?;
+    let manager = load_artifact(artifacts, "ServiceManager")?;
+    let manager_abi = manager.abi.clone();
+    let manager = ContractFactory::new(manager.abi, manager.bytecode.object, deployer.clone())
+        .deploy((
+            bls.address(),
+            inbox.address(),
+            l2_bridge,
+            Address::zero(),
+            stake,
+            U256::from(DEV_QUORUM_BPS),
+        ))?
+        .send()
+        .await?;
+
+    inbox
+        .method::<_, ()>("setAuthorizedConsumer", (manager.address(), true))?
+        .send()
+        .await?
+        .await?;
+
+    // Fund and register the operator
+    let operator_key = std::env::var("OPERATOR_PRIVATE_KEY").unwrap_or_else(|_| ANVIL_OPERATOR_KEY.to_string());
+    let operator: LocalWallet = operator_key.trim_start_matches("0x").parse()?;
+    let operator_address = operator.address();
+    deployer
+        .send_transaction(TransactionRequest::pay(operator_address, parse_ether(10)?), None)
+        .await?
+        .await?;
+
+    let operator_client = Arc::new(SignerMiddleware::new(
+        provider,
+        operator.with_chain_id(ANVIL_CHAIN_ID),
+    ));
+    Contract::new(manager.address(), manager_abi, operator_client)
+        .method::<_, ()>("registerOperator", (stake,))?
+        .value(stake)
+        .send()
+        .await?
+        .await?;
+
+    info!("Dev contracts deployed:");
+    info!("  BLSVerifier:    {:?}", bls.address());
+    info!("  Inbox:          {:?}", inbox.address());
+    info!("  ServiceManager: {:?}", manager.address());
+    info!("  Operator:       {:?} (registered, 1 ETH stake)", operator_address);
+
+    // The configuration is loaded from the environment after this
+    std::env::set_var("L1_RPC_URL", &l1_rpc_url);
+    std::env::set_var("SERVICE_MANAGER_ADDRESS", format!("{:?}", manager.address()));
+    std::env::set_var("OPERATOR_PRIVATE_KEY", operator_key);
+    Ok(())
+}
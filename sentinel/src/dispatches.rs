@@ -0,0 +1,188 @@
+//! `DepositVerified` events
+//!
+//! ServiceManager emits one `DepositVerified` per dispatched vault note.
+//! Everything that reads dispatches back from L1 decodes them here, so the
+//! event layout lives in one place.
+
+use crate::error::SentinelError;
+use crate::store::{deposit_key, DepositStatus, DepositStore};
+use ethers::abi::{decode, ParamType};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Filter, Log, H256, U256};
+use ethers::utils::keccak256;
+
+pub use sentinel_core::payload::DEPOSIT_VERIFIED;
+
+/// A decoded `DepositVerified` event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositVerified {
+    /// Zcash transaction hash
+    pub tx_hash: [u8; 32],
+
+    /// Index of the vault note in the transaction
+    pub output_index: u32,
+
+    /// Amount minted, in zatoshi, after the fee
+    pub amount: U256,
+
+    /// Claim secret hash
+    pub secret_hash: [u8; 32],
+
+    /// Aztec recipient
+    pub aztec_address: [u8; 32],
+
+    /// L1→L2 message hash
+    pub message_hash: [u8; 32],
+
+    /// L1 transaction that dispatched the note
+    pub l1_tx_hash: Option<H256>,
+
+    /// L1 block the dispatch was mined in
+    pub block_number: Option<u64>,
+}
+
+/// Topic of `DepositVerified`
+pub fn topic() -> H256 {
+    H256::from(keccak256(DEPOSIT_VERIFIED))
+}
+
+/// Filter for `DepositVerified` events emitted by `service_manager`
+pub fn filter(service_manager: Address) -> Filter {
+    Filter::new().address(service_manager).topic0(topic())
+}
+
+/// `DepositVerified` events of one vault note, searching from `from_block`
+/// (the ServiceManager's deployment block)
+pub async fn for_note(
+    provider: &Provider<Http>,
+    service_manager: Address,
+    from_block: u64,
+    tx_hash: &[u8; 32],
+    output_index: u32,
+) -> Result<Vec<DepositVerified>, SentinelError> {
+    let filter = filter(service_manager)
+        .topic1(H256::from(*tx_hash))
+        .from_block(from_block);
+
+    let mut events = Vec::new();
+    for log in provider.get_logs(&filter).await? {
+        let event = DepositVerified::decode(&log)?;
+        if event.output_index == output_index {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+impl DepositVerified {
+    /// Decode a `DepositVerified` log
+    pub fn decode(log: &Log) -> Result<Self, SentinelError> {
+        let tx_hash = log
+            .topics
+            .get(1)
+            .ok_or_else(|| SentinelError::L1("DepositVerified without a txHash topic".to_string()))?;
+        let tokens = decode(
+            &[
+                ParamType::Uint(256),
+                ParamType::FixedBytes(32),
+                ParamType::FixedBytes(32),
+                ParamType::FixedBytes(32),
+                ParamType::Uint(32),
+            ],
+            &log.data,
+        )
+        .map_err(SentinelError::l1)?;
+
+        let bytes32 = |index: usize| -> [u8; 32] {
+            tokens[index]
+                .clone()
+                .into_fixed_bytes()
+                .and_then(|b| b.try_into().ok())
+                .unwrap_or_default()
+        };
+        Ok(Self {
+            tx_hash: tx_hash.0,
+            amount: tokens[0].clone().into_uint().unwrap_or_default(),
+            secret_hash: bytes32(1),
+            aztec_address: bytes32(2),
+            message_hash: bytes32(3),
+            output_index: tokens[4].clone().into_uint().unwrap_or_default().low_u32(),
+            l1_tx_hash: log.transaction_hash,
+            block_number: log.block_number.map(|n| n.as_u64()),
+        })
+    }
+
+    /// Store key of the dispatched deposit
+    pub fn key(&self) -> String {
+        deposit_key(&hex::encode(self.tx_hash), self.output_index)
+    }
+
+    /// Record the dispatch on a deposit still waiting for attestation;
+    /// returns whether the deposit moved to `Submitted`
+    pub fn mark_submitted(&self, store: &DepositStore) -> Result<bool, SentinelError> {
+        let key = self.key();
+        match store.get(&key) {
+            Some(record) if record.status == DepositStatus::Detected => {}
+            _ => return Ok(false),
+        }
+        store.transition(&key, DepositStatus::Submitted, None)?;
+        store.update(&key, |record| {
+            record.l1_tx_hash = self.l1_tx_hash.map(|tx| format!("{:?}", tx));
+            record.message_hash = Some(hex::encode(self.message_hash));
+        })?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn test_decode_keys_by_note() {
+        let log = Log {
+            topics: vec![topic(), H256::repeat_byte(0xab)],
+            data: encode(&[
+                Token::Uint(U256::from(99_000u64)),
+                Token::FixedBytes(vec![0xcd; 32]),
+                Token::FixedBytes(vec![0xef; 32]),
+                Token::FixedBytes(vec![0x12; 32]),
+                Token::Uint(U256::from(2u32)),
+            ])
+            .into(),
+            ..Default::default()
+        };
+
+        let event = DepositVerified::decode(&log).unwrap();
+        assert_eq!(event.amount, U256::from(99_000u64));
+        assert_eq!(event.message_hash, [0x12; 32]);
+        assert_eq!(event.key(), deposit_key(&hex::encode([0xab; 32]), 2));
+    }
+
+    #[test]
+    fn test_dispatch_marks_detected_deposit_submitted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DepositStore::open(dir.path().join("deposits.json")).unwrap();
+        let record = store
+            .insert_detected(&crate::BridgePayload::sample())
+            .unwrap();
+        let event = DepositVerified {
+            tx_hash: [1; 32],
+            output_index: 0,
+            amount: U256::from(100_000u64),
+            secret_hash: [2; 32],
+            aztec_address: [3; 32],
+            message_hash: [0x12; 32],
+            l1_tx_hash: Some(H256::repeat_byte(0x34)),
+            block_number: Some(7),
+        };
+
+        assert!(event.mark_submitted(&store).unwrap());
+        let record = store.get(&record.key()).unwrap();
+        assert_eq!(record.status, DepositStatus::Submitted);
+        assert_eq!(record.message_hash, Some(hex::encode([0x12; 32])));
+        // Already recorded
+        assert!(!event.mark_submitted(&store).unwrap());
+    }
+}
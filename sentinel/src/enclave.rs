@@ -0,0 +1,298 @@
+//! Enclave-backed operator signing
+//!
+//! With `OPERATOR_ENCLAVE` set, the operator key never enters the sentinel
+//! process: it is generated or sealed inside a TEE (a gramine-hosted SGX
+//! signer, or a Nitro Enclave reached over vsock) and the sentinel only ever
+//! sends it 32-byte digests and receives signatures back. A compromised host
+//! can request signatures while it is compromised, but cannot exfiltrate
+//! the key.
+//!
+//! The enclave speaks newline-delimited JSON, one request per connection:
+//!
+//! - `{"op":"address"}` → `{"address":"0x…"}`
+//! - `{"op":"sign","digest":"0x…"}` → `{"signature":"0x…"}` (65 bytes, r‖s‖v
+//!   with v = 27 or 28), or `{"error":"…"}`
+//!
+//! Digests are signed raw: EIP-191 prefixing and transaction sighashes are
+//! computed here, so the enclave stays a minimal hash signer. Every
+//! signature is checked to recover to the enclave's address before use.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use ethers::signers::{Signer, WalletError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address, Signature, H256};
+use ethers::utils::{hash_message, to_eip155_v};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::info;
+
+/// Time allowed for one enclave round trip
+const ENCLAVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the enclave signer listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnclaveEndpoint {
+    /// `vsock://<cid>:<port>` (Nitro Enclaves)
+    Vsock { cid: u32, port: u32 },
+    /// `unix:///path/to/socket`
+    Unix(PathBuf),
+    /// `tcp://host:port` (e.g. a gramine signer on localhost)
+    Tcp(String),
+}
+
+impl std::str::FromStr for EnclaveEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(rest) = s.strip_prefix("vsock://") {
+            let (cid, port) = rest
+                .split_once(':')
+                .context("vsock endpoint needs <cid>:<port>")?;
+            return Ok(Self::Vsock {
+                cid: cid.parse().context("Invalid vsock CID")?,
+                port: port.parse().context("Invalid vsock port")?,
+            });
+        }
+        if let Some(path) = s.strip_prefix("unix://") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(Self::Tcp(addr.to_string()));
+        }
+        bail!("Enclave endpoint must start with vsock://, unix:// or tcp://")
+    }
+}
+
+/// Request to the enclave
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    Address,
+    Sign { digest: String },
+}
+
+/// Reply from the enclave
+#[derive(Debug, Default, Deserialize)]
+struct Reply {
+    address: Option<Address>,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+/// Protocol failure, reported through the wallet error type
+fn protocol_error(message: impl Into<String>) -> WalletError {
+    WalletError::IoError(io::Error::new(io::ErrorKind::InvalidData, message.into()))
+}
+
+/// Operator signer whose key lives in an enclave
+#[derive(Debug, Clone)]
+pub struct EnclaveSigner {
+    /// Enclave socket
+    endpoint: EnclaveEndpoint,
+
+    /// Operator address reported by the enclave
+    address: Address,
+
+    /// Chain id used for transactions that do not carry one
+    chain_id: u64,
+}
+
+impl EnclaveSigner {
+    /// Connect to the enclave and fetch the operator address
+    pub async fn connect(endpoint: EnclaveEndpoint) -> Result<Self> {
+        let reply = exchange(&endpoint, &Request::Address)
+            .await
+            .with_context(|| format!("Cannot reach signing enclave at {:?}", endpoint))?;
+        let address = reply
+            .address
+            .context("Signing enclave did not report an address")?;
+        info!(
+            "Signing enclave at {:?} holds operator {:?}",
+            endpoint, address
+        );
+        Ok(Self {
+            endpoint,
+            address,
+            chain_id: 1,
+        })
+    }
+
+    /// Have the enclave sign a raw 32-byte digest
+    async fn sign_digest(&self, digest: H256) -> Result<Signature, WalletError> {
+        let request = Request::Sign {
+            digest: format!("0x{}", hex::encode(digest)),
+        };
+        let reply = exchange(&self.endpoint, &request).await?;
+        if let Some(error) = reply.error {
+            return Err(protocol_error(format!(
+                "enclave refused to sign: {}",
+                error
+            )));
+        }
+        let signature = reply
+            .signature
+            .ok_or_else(|| protocol_error("enclave reply has no signature"))?;
+        let bytes = hex::decode(signature.trim_start_matches("0x"))
+            .map_err(|_| protocol_error("enclave signature is not hex"))?;
+        let mut signature = Signature::try_from(bytes.as_slice())
+            .map_err(|_| protocol_error("enclave signature is not 65 bytes"))?;
+        // Some signers return the bare recovery id
+        if signature.v < 27 {
+            signature.v += 27;
+        }
+
+        // Never pass on a signature the operator key did not make
+        match signature.recover(digest) {
+            Ok(signer) if signer == self.address => Ok(signature),
+            _ => Err(protocol_error(
+                "enclave signature does not recover to the operator",
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for EnclaveSigner {
+    type Error = WalletError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, WalletError> {
+        self.sign_digest(hash_message(message)).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, WalletError> {
+        // Same EIP-155 handling as `LocalWallet`
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        let mut tx = tx.clone();
+        tx.set_chain_id(chain_id);
+        let mut signature = self.sign_digest(tx.sighash()).await?;
+        signature.v = to_eip155_v(signature.v as u8 - 27, chain_id);
+        Ok(signature)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, WalletError> {
+        let digest = payload
+            .encode_eip712()
+            .map_err(|e| WalletError::Eip712Error(e.to_string()))?;
+        self.sign_digest(H256::from(digest)).await
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+/// One request/reply round trip over a fresh connection
+async fn exchange(endpoint: &EnclaveEndpoint, request: &Request) -> io::Result<Reply> {
+    let reply = async {
+        match endpoint {
+            EnclaveEndpoint::Vsock { cid, port } => {
+                round_trip(
+                    tokio_vsock::VsockStream::connect(*cid, *port).await?,
+                    request,
+                )
+                .await
+            }
+            EnclaveEndpoint::Unix(path) => {
+                round_trip(tokio::net::UnixStream::connect(path).await?, request).await
+            }
+            EnclaveEndpoint::Tcp(addr) => {
+                round_trip(tokio::net::TcpStream::connect(addr).await?, request).await
+            }
+        }
+    };
+    tokio::time::timeout(ENCLAVE_TIMEOUT, reply)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "signing enclave timed out"))?
+}
+
+/// Write one JSON line and read one back
+async fn round_trip<S>(stream: S, request: &Request) -> io::Result<Reply>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    stream.get_mut().write_all(&line).await?;
+    stream.get_mut().flush().await?;
+
+    let mut reply = String::new();
+    stream.read_line(&mut reply).await?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::LocalWallet;
+    use tokio::net::UnixListener;
+
+    /// Minimal enclave stand-in signing with `wallet`
+    async fn serve(listener: UnixListener, wallet: LocalWallet) {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let reply = match request["op"].as_str() {
+                Some("address") => serde_json::json!({ "address": wallet.address() }),
+                _ => {
+                    let digest: H256 = request["digest"].as_str().unwrap().parse().unwrap();
+                    let signature = wallet.sign_hash(digest).unwrap();
+                    serde_json::json!({ "signature": format!("0x{}", signature) })
+                }
+            };
+            let mut out = serde_json::to_vec(&reply).unwrap();
+            out.push(b'\n');
+            stream.get_mut().write_all(&out).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enclave_signatures_match_local_wallet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("enclave.sock");
+        let wallet: LocalWallet =
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .parse()
+                .unwrap();
+        tokio::spawn(serve(UnixListener::bind(&path).unwrap(), wallet.clone()));
+
+        let endpoint: EnclaveEndpoint = format!("unix://{}", path.display()).parse().unwrap();
+        let enclave = EnclaveSigner::connect(endpoint).await.unwrap();
+        assert_eq!(enclave.address(), wallet.address());
+
+        let expected = wallet.sign_message([7u8; 32]).await.unwrap();
+        assert_eq!(enclave.sign_message([7u8; 32]).await.unwrap(), expected);
+
+        assert!("http://enclave".parse::<EnclaveEndpoint>().is_err());
+        assert_eq!(
+            "vsock://16:5000".parse::<EnclaveEndpoint>().unwrap(),
+            EnclaveEndpoint::Vsock {
+                cid: 16,
+                port: 5000
+            }
+        );
+    }
+}
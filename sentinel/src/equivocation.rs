@@ -0,0 +1,245 @@
+//! Pre-sign equivocation check
+//!
+//! Signing two attestations for one vault note with different fields is
+//! slashable. Before each signature the sign stage looks for an earlier
+//! attestation of the same note (txid and output index) in:
+//!
+//! - this operator's signing log
+//! - the deposit store, for deposits already submitted
+//! - `DepositVerified` events on L1, which also cover other operators'
+//!   dispatches (skipped with a warning when L1 is unreachable)
+//!
+//! An earlier attestation with a different amount or recipient halts
+//! attestation and raises an alert instead of signing. Fields the operator
+//! derives itself, the fee and the block hash of a note mined again after a
+//! reorg, are left out: they change with config and chain state, not with
+//! double-signing. A matching dispatch on L1 means the note is done already:
+//! it is handed back so the caller can record it instead of signing again.
+//!
+//! The fee may also differ between operators, so the L1 comparison only uses
+//! fields the fee cannot change: the recipient, and the minted amount never
+//! exceeding the note's.
+
+use crate::dispatches::{self, DepositVerified};
+use crate::error::SentinelError;
+use crate::halt::HaltSwitch;
+use crate::signer::depositor_hash;
+use crate::signlog::SignedNote;
+use crate::store::{payload_key, DepositStatus, DepositStore};
+use crate::BridgePayload;
+use ethers::prelude::*;
+use ethers::types::Address;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Halt source name
+const SOURCE: &str = "equivocation";
+
+/// Pre-sign check for conflicting attestations of the same note
+pub struct EquivocationCheck {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// L1 block the ServiceManager was deployed in
+    start_block: u64,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Switch tripped on a conflict
+    halt: Arc<HaltSwitch>,
+}
+
+impl EquivocationCheck {
+    /// Create a check against `store` and the ServiceManager's events
+    /// since `start_block`
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        start_block: u64,
+        store: Arc<DepositStore>,
+        halt: Arc<HaltSwitch>,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            start_block,
+            store,
+            halt,
+        }
+    }
+
+    /// Fail, and halt attestation, if signing `payload` would equivocate
+    ///
+    /// `signed` is what this operator already signed for the note, from its
+    /// signing log. Returns the note's matching L1 dispatch
+    /// if it has one, in which case there is nothing left to sign.
+    pub async fn check(
+        &self,
+        payload: &BridgePayload,
+        signed: Option<SignedNote>,
+    ) -> Result<Option<DepositVerified>, SentinelError> {
+        let (conflict, dispatched) = match self.local_conflict(payload, signed) {
+            Some(conflict) => (Some(conflict), None),
+            None => match self.l1_dispatches(payload).await {
+                Ok(events) => match events
+                    .iter()
+                    .find_map(|event| event_conflict(payload, event))
+                {
+                    Some(conflict) => (Some(conflict), None),
+                    None => (None, events.into_iter().next()),
+                },
+                Err(e) => {
+                    warn!("On-chain attestation check unavailable: {}", e);
+                    (None, None)
+                }
+            },
+        };
+
+        match conflict {
+            Some(reason) => {
+                let reason = format!("deposit {}: {}", payload_key(payload), reason);
+                self.halt.trip(SOURCE, reason.clone());
+                Err(SentinelError::Equivocation(reason))
+            }
+            None => Ok(dispatched),
+        }
+    }
+
+    /// Conflict with the signing log or an already submitted stored deposit
+    fn local_conflict(
+        &self,
+        payload: &BridgePayload,
+        signed: Option<SignedNote>,
+    ) -> Option<String> {
+        if signed.is_some_and(|signed| signed.conflicts(payload)) {
+            return Some("signed earlier with different fields".to_string());
+        }
+
+        let record = self.store.get(&payload_key(payload))?;
+        let attested = matches!(
+            record.status,
+            DepositStatus::Submitted | DepositStatus::Claimed | DepositStatus::ClaimExpired
+        );
+        if !attested {
+            return None;
+        }
+        match record.to_payload() {
+            Ok(stored) if depositor_hash(&stored) == depositor_hash(payload) => None,
+            Ok(_) => Some("submitted earlier with different fields".to_string()),
+            Err(e) => Some(format!("stored attestation unreadable: {}", e)),
+        }
+    }
+
+    /// `DepositVerified` events for the same note
+    async fn l1_dispatches(
+        &self,
+        payload: &BridgePayload,
+    ) -> Result<Vec<DepositVerified>, SentinelError> {
+        dispatches::for_note(
+            &self.provider,
+            self.service_manager_address,
+            self.start_block,
+            &payload.tx_hash,
+            payload.output_index,
+        )
+        .await
+    }
+}
+
+/// How a `DepositVerified` event contradicts `payload`, if it does
+///
+/// The event carries the amount net of a fee this operator may have computed
+/// differently, so it only has to stay within the note's amount.
+fn event_conflict(payload: &BridgePayload, event: &DepositVerified) -> Option<String> {
+    if event.secret_hash != payload.secret_hash || event.aztec_address != payload.aztec_address {
+        return Some("dispatched on L1 to a different recipient".to_string());
+    }
+    if event.amount > U256::from(payload.amount) {
+        return Some(format!(
+            "dispatched on L1 for {}, more than the {} deposited",
+            event.amount, payload.amount
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> BridgePayload {
+        BridgePayload::sample()
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_attestation_halts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let halt = Arc::new(HaltSwitch::new());
+        let check = EquivocationCheck::new(
+            Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap()),
+            Address::zero(),
+            0,
+            store.clone(),
+            halt.clone(),
+        );
+
+        // Submitted with one amount, now asked to sign another
+        let mut earlier = payload();
+        earlier.amount = 90_000;
+        store.insert_detected(&earlier).unwrap();
+        store
+            .transition(&payload_key(&earlier), DepositStatus::Submitted, None)
+            .unwrap();
+
+        let p = payload();
+        assert!(check.local_conflict(&earlier, None).is_none());
+        assert!(check.local_conflict(&p, Some(SignedNote::of(&p))).is_some());
+        // A fee or block hash derived anew is not equivocation
+        let rederived = BridgePayload {
+            fee: 7,
+            block_hash: [9; 32],
+            ..earlier.clone()
+        };
+        assert!(check.local_conflict(&rederived, None).is_none());
+        assert!(check
+            .local_conflict(&rederived, Some(SignedNote::of(&earlier)))
+            .is_none());
+        assert!(check.local_conflict(&p, Some(SignedNote::of(&earlier))).is_some());
+        let err = check.check(&p, None).await.unwrap_err();
+        assert!(matches!(err, SentinelError::Equivocation(_)));
+        assert!(halt.is_halted());
+
+        let event = DepositVerified {
+            tx_hash: p.tx_hash,
+            output_index: p.output_index,
+            amount: U256::from(p.net_amount()),
+            secret_hash: p.secret_hash,
+            aztec_address: p.aztec_address,
+            message_hash: [0; 32],
+            l1_tx_hash: None,
+            block_number: None,
+        };
+        assert!(event_conflict(&p, &event).is_none());
+        // Another operator's fee is not a conflict, minting more than the note is
+        let other_fee = DepositVerified {
+            amount: U256::from(p.amount - 1),
+            ..event.clone()
+        };
+        assert!(event_conflict(&p, &other_fee).is_none());
+        let inflated = DepositVerified {
+            amount: U256::from(p.amount + 1),
+            ..event.clone()
+        };
+        assert!(event_conflict(&p, &inflated).is_some());
+        let other_recipient = DepositVerified {
+            aztec_address: [4; 32],
+            ..event
+        };
+        assert!(event_conflict(&p, &other_recipient).is_some());
+    }
+}
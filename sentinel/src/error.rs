@@ -1,56 +1,292 @@
 //! Error types for the Sentinel AVS
+//!
+//! Every error carries a stable string code (also shown in its message), a
+//! numeric code grouping it by [`ErrorClass`], and whether retrying the
+//! failed operation may help. Automation should key on these rather than on
+//! message text: the codes appear in logs, in admin API error bodies and, via
+//! the error's class, in the process exit status.
 
+use crate::redact::redact;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use std::fmt::Display;
 use thiserror::Error;
 
+/// Broad family of a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The sentinel is misconfigured
+    Config,
+    /// Chain data or a request could not be decoded or is invalid
+    Input,
+    /// A node, peer or service the sentinel depends on failed
+    Upstream,
+    /// Not enough agreement among operators yet
+    Consensus,
+    /// Signing was refused or failed
+    Safety,
+    /// Local state could not be read or written
+    Storage,
+}
+
+impl ErrorClass {
+    /// Process exit status for errors of this class (sysexits.h)
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Config => 78,    // EX_CONFIG
+            Self::Input => 65,     // EX_DATAERR
+            Self::Upstream => 69,  // EX_UNAVAILABLE
+            Self::Consensus => 75, // EX_TEMPFAIL
+            Self::Safety => 70,    // EX_SOFTWARE
+            Self::Storage => 74,   // EX_IOERR
+        }
+    }
+
+    /// HTTP status for API responses failing with errors of this class
+    fn http_status(self) -> StatusCode {
+        match self {
+            Self::Config | Self::Safety | Self::Storage => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Input => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Upstream => StatusCode::BAD_GATEWAY,
+            Self::Consensus => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
 /// Sentinel error types
 #[derive(Error, Debug)]
 pub enum SentinelError {
     /// Configuration error
-    #[error("Configuration error: {0}")]
+    #[error("Configuration error [config_invalid]: {0}")]
     Config(String),
 
     /// Scanner error
-    #[error("Scanner error: {0}")]
+    #[error("Scanner error [scanner_failed]: {0}")]
     Scanner(String),
 
     /// Memo parsing error
-    #[error("Memo parsing error: {0}")]
+    #[error("Memo parsing error [memo_unparseable]: {0}")]
     MemoParse(String),
 
     /// Signing error
-    #[error("Signing error: {0}")]
+    #[error("Signing error [signing_failed]: {0}")]
     Signing(String),
 
     /// L1 interaction error
-    #[error("L1 error: {0}")]
+    #[error("L1 error [l1_failed]: {0}")]
     L1(String),
 
     /// gRPC error
-    #[error("gRPC error: {0}")]
+    #[error("gRPC error [grpc_failed]: {0}")]
     Grpc(#[from] tonic::Status),
 
     /// Zcash decryption error
-    #[error("Decryption error: {0}")]
+    #[error("Decryption error [decryption_failed]: {0}")]
     Decryption(String),
 
     /// Invalid payload error
-    #[error("Invalid payload: {0}")]
+    #[error("Invalid payload [payload_invalid]: {0}")]
     InvalidPayload(String),
 
     /// Network error
-    #[error("Network error: {0}")]
+    #[error("Network error [network_failed]: {0}")]
     Network(String),
+
+    /// Stake-weighted quorum not reached
+    #[error("Quorum not met [quorum_not_met]: {0}")]
+    QuorumNotMet(String),
+
+    /// Peer sentinels disagree about a deposit
+    #[error("Peer disagreement [peer_disagreement]: {0}")]
+    PeerDisagreement(String),
+
+    /// Local storage error
+    #[error("Storage error [storage_failed]: {0}")]
+    Storage(String),
+
+    /// Price oracle error
+    #[error("Oracle error [oracle_failed]: {0}")]
+    Oracle(String),
+
+    /// Signing would contradict an earlier attestation
+    #[error("Equivocation [equivocation]: {0}")]
+    Equivocation(String),
+
+    /// Another error, with what was being done when it happened
+    #[error("{context}: {source}")]
+    Context {
+        /// What failed, e.g. the deposit or endpoint involved
+        context: String,
+
+        /// Underlying error
+        source: Box<SentinelError>,
+    },
+}
+
+/// Machine-readable description of an error, as served by the APIs
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    /// Stable string code
+    pub code: &'static str,
+
+    /// Stable numeric code
+    pub number: u16,
+
+    /// Error family
+    pub class: ErrorClass,
+
+    /// Whether retrying may succeed
+    pub retryable: bool,
+
+    /// Human readable message, including any context
+    pub message: String,
+}
+
+impl SentinelError {
+    /// Innermost error, beneath any context
+    fn root(&self) -> &SentinelError {
+        match self {
+            Self::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Stable string code, also shown in the error's message
+    pub fn code(&self) -> &'static str {
+        match self.root() {
+            Self::Config(_) => "config_invalid",
+            Self::Scanner(_) => "scanner_failed",
+            Self::MemoParse(_) => "memo_unparseable",
+            Self::Signing(_) => "signing_failed",
+            Self::L1(_) => "l1_failed",
+            Self::Grpc(_) => "grpc_failed",
+            Self::Decryption(_) => "decryption_failed",
+            Self::InvalidPayload(_) => "payload_invalid",
+            Self::Network(_) => "network_failed",
+            Self::QuorumNotMet(_) => "quorum_not_met",
+            Self::PeerDisagreement(_) => "peer_disagreement",
+            Self::Storage(_) => "storage_failed",
+            Self::Oracle(_) => "oracle_failed",
+            Self::Equivocation(_) => "equivocation",
+            Self::Context { .. } => unreachable!("root has no context"),
+        }
+    }
+
+    /// Stable numeric code: the class in the hundreds, the error below
+    pub fn number(&self) -> u16 {
+        match self.root() {
+            Self::Config(_) => 100,
+            Self::MemoParse(_) => 200,
+            Self::Decryption(_) => 201,
+            Self::InvalidPayload(_) => 202,
+            Self::Scanner(_) => 300,
+            Self::L1(_) => 301,
+            Self::Grpc(_) => 302,
+            Self::Network(_) => 303,
+            Self::Oracle(_) => 304,
+            Self::QuorumNotMet(_) => 400,
+            Self::PeerDisagreement(_) => 401,
+            Self::Signing(_) => 500,
+            Self::Equivocation(_) => 501,
+            Self::Storage(_) => 600,
+            Self::Context { .. } => unreachable!("root has no context"),
+        }
+    }
+
+    /// Error family
+    pub fn class(&self) -> ErrorClass {
+        match self.number() / 100 {
+            1 => ErrorClass::Config,
+            2 => ErrorClass::Input,
+            3 => ErrorClass::Upstream,
+            4 => ErrorClass::Consensus,
+            5 => ErrorClass::Safety,
+            _ => ErrorClass::Storage,
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self.root() {
+            Self::Grpc(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable
+                    | tonic::Code::DeadlineExceeded
+                    | tonic::Code::ResourceExhausted
+                    | tonic::Code::Aborted
+                    | tonic::Code::Unknown
+            ),
+            other => matches!(other.class(), ErrorClass::Upstream | ErrorClass::Consensus),
+        }
+    }
+
+    /// Wrap with what was being done, keeping code and class
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Machine-readable description
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.code(),
+            number: self.number(),
+            class: self.class(),
+            retryable: self.is_retryable(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for SentinelError {
+    fn into_response(self) -> Response {
+        (self.class().http_status(), Json(self.report())).into_response()
+    }
+}
+
+/// Exit status for a failed command: the class of the first sentinel error
+/// in the chain, or 1
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<SentinelError>())
+        .map_or(1, |e| e.class().exit_code())
+}
+
+/// Constructors for errors wrapping a foreign error's message
+///
+/// Provider, transport and wallet errors can quote RPC URLs carrying API
+/// tokens or the input that failed to parse, so their messages are redacted.
+impl SentinelError {
+    /// Signing error, redacted
+    pub fn signing(err: impl Display) -> Self {
+        SentinelError::Signing(redact(&err.to_string()).into_owned())
+    }
+
+    /// L1 error, redacted
+    pub fn l1(err: impl Display) -> Self {
+        SentinelError::L1(redact(&err.to_string()).into_owned())
+    }
+
+    /// Network error, redacted
+    pub fn network(err: impl Display) -> Self {
+        SentinelError::Network(redact(&err.to_string()).into_owned())
+    }
 }
 
 impl From<ethers::providers::ProviderError> for SentinelError {
     fn from(err: ethers::providers::ProviderError) -> Self {
-        SentinelError::L1(err.to_string())
+        SentinelError::l1(err)
     }
 }
 
 impl From<ethers::signers::WalletError> for SentinelError {
     fn from(err: ethers::signers::WalletError) -> Self {
-        SentinelError::Signing(err.to_string())
+        SentinelError::signing(err)
     }
 }
 
@@ -60,9 +296,49 @@ impl From<serde_json::Error> for SentinelError {
     }
 }
 
+impl From<sentinel_core::CoreError> for SentinelError {
+    fn from(err: sentinel_core::CoreError) -> Self {
+        match err {
+            sentinel_core::CoreError::MemoParse(e) => SentinelError::MemoParse(e),
+            sentinel_core::CoreError::InvalidPayload(e) => SentinelError::InvalidPayload(e),
+            sentinel_core::CoreError::Source(e) => SentinelError::network(e),
+        }
+    }
+}
+
 impl From<hex::FromHexError> for SentinelError {
     fn from(err: hex::FromHexError) -> Self {
         SentinelError::InvalidPayload(err.to_string())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_classes_and_context() {
+        let err = SentinelError::Network("connection refused".to_string());
+        assert_eq!(err.code(), "network_failed");
+        assert_eq!(err.number(), 303);
+        assert_eq!(err.class(), ErrorClass::Upstream);
+        assert!(err.is_retryable());
+        assert!(err.to_string().contains("[network_failed]"));
+
+        let err = err.context("fetching block 2500000");
+        assert_eq!(err.code(), "network_failed");
+        assert!(err.is_retryable());
+        assert!(err.to_string().starts_with("fetching block 2500000: "));
+
+        let err = SentinelError::Config("bad".to_string());
+        assert!(!err.is_retryable());
+        assert_eq!(exit_code(&anyhow::Error::new(err).context("loading")), 78);
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), 1);
+
+        assert!(!SentinelError::Grpc(tonic::Status::invalid_argument("x")).is_retryable());
+        assert!(SentinelError::Grpc(tonic::Status::unavailable("x")).is_retryable());
+        let report = SentinelError::Equivocation("nonce 4".to_string()).report();
+        assert_eq!((report.code, report.number), ("equivocation", 501));
+        assert_eq!(report.class, ErrorClass::Safety);
+    }
+}
@@ -0,0 +1,224 @@
+//! Deposit lifecycle events
+//!
+//! The pipeline emits an event when a deposit is detected, when its
+//! attestation is signed and when the attestation is finalized on L1. With
+//! `MEMPOOL_PREVIEW=true` a deposit also gets a `deposit_pending` event when
+//! it first shows up in the mempool (see `mempool.rs`).
+//! Events are fanned out to the configured sinks (event bus, webhooks), each
+//! behind its own bounded queue, so a slow or unreachable consumer never
+//! holds up attestation; when a queue is full the event is dropped for that
+//! sink with a warning.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::BridgePayload;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Events queued per sink before new ones are dropped
+pub const DEFAULT_EVENT_QUEUE: usize = 1024;
+
+/// What happened to a deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// Seen in the mempool, not yet mined; not an attestation
+    DepositPending,
+    /// Seen on the Zcash chain and recorded
+    DepositDetected,
+    /// Attestation signed by this operator
+    AttestationSigned,
+    /// Attestation dispatched on L1
+    AttestationFinalized,
+}
+
+impl EventKind {
+    /// Name used in topics, subjects and payloads
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DepositPending => "deposit_pending",
+            Self::DepositDetected => "deposit_detected",
+            Self::AttestationSigned => "attestation_signed",
+            Self::AttestationFinalized => "attestation_finalized",
+        }
+    }
+}
+
+/// A deposit lifecycle event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BridgeEvent {
+    /// What happened
+    pub kind: EventKind,
+
+    /// Zcash transaction hash (hex)
+    pub tx_hash: String,
+
+    /// Deposited amount in zatoshi
+    pub amount: u64,
+
+    /// Bridge fee in zatoshi
+    pub fee: u64,
+
+    /// Zcash block height of the deposit (0 while pending)
+    pub block_height: u32,
+
+    /// Aztec recipient (hex)
+    pub aztec_address: String,
+
+    /// Attestation nonce, once signed
+    pub nonce: Option<u64>,
+
+    /// L1 transaction that dispatched the deposit, once finalized by us
+    pub l1_tx_hash: Option<String>,
+
+    /// Unix timestamp of the event
+    pub at: u64,
+}
+
+impl BridgeEvent {
+    /// Event of `kind` about `payload`
+    pub fn new(kind: EventKind, payload: &BridgePayload) -> Self {
+        Self {
+            kind,
+            tx_hash: hex::encode(payload.tx_hash),
+            amount: payload.amount,
+            fee: payload.fee,
+            block_height: payload.block_height,
+            aztec_address: format!("0x{}", hex::encode(payload.aztec_address)),
+            nonce: None,
+            l1_tx_hash: None,
+            at: now_secs(),
+        }
+    }
+
+    /// Attach the attestation nonce
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Attach the dispatching L1 transaction
+    pub fn with_l1_tx(mut self, l1_tx_hash: String) -> Self {
+        self.l1_tx_hash = Some(l1_tx_hash);
+        self
+    }
+}
+
+/// A consumer of bridge events
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Sink name for logs
+    fn name(&self) -> &str;
+
+    /// Deliver one event
+    async fn publish(&self, event: &BridgeEvent) -> Result<(), SentinelError>;
+}
+
+/// Cheap handle the pipeline emits events through
+#[derive(Clone, Default)]
+pub struct EventEmitter {
+    /// Queue of each sink, with the sink's name
+    queues: Vec<(String, mpsc::Sender<BridgeEvent>)>,
+}
+
+impl EventEmitter {
+    /// Start a delivery task per sink
+    pub fn spawn(sinks: Vec<Arc<dyn EventSink>>, capacity: usize) -> Self {
+        let queues = sinks
+            .into_iter()
+            .map(|sink| {
+                let (tx, mut rx) = mpsc::channel::<BridgeEvent>(capacity);
+                let name = sink.name().to_string();
+                tokio::spawn(async move {
+                    while let Some(event) = rx.recv().await {
+                        if let Err(e) = sink.publish(&event).await {
+                            warn!(
+                                "{} event for {} not delivered to {}: {}",
+                                event.kind.as_str(),
+                                event.tx_hash,
+                                sink.name(),
+                                e
+                            );
+                        }
+                    }
+                });
+                (name, tx)
+            })
+            .collect();
+        Self { queues }
+    }
+
+    /// Whether any sink is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.queues.is_empty()
+    }
+
+    /// Queue `event` for every sink without waiting
+    pub fn emit(&self, event: BridgeEvent) {
+        for (name, queue) in &self.queues {
+            if queue.try_send(event.clone()).is_err() {
+                warn!(
+                    "Event queue for {} full, dropping {} event for {}",
+                    name,
+                    event.kind.as_str(),
+                    event.tx_hash
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Recorder {
+        events: Mutex<Vec<BridgeEvent>>,
+    }
+
+    #[async_trait]
+    impl EventSink for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        async fn publish(&self, event: &BridgeEvent) -> Result<(), SentinelError> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_reach_every_sink() {
+        let a = Arc::new(Recorder {
+            events: Mutex::new(Vec::new()),
+        });
+        let b = Arc::new(Recorder {
+            events: Mutex::new(Vec::new()),
+        });
+        let emitter = EventEmitter::spawn(vec![a.clone(), b.clone()], 8);
+        assert!(emitter.is_enabled());
+        assert!(!EventEmitter::default().is_enabled());
+
+        let payload = BridgePayload {
+            fee: 1_000,
+            ..BridgePayload::sample()
+        };
+        emitter.emit(BridgeEvent::new(EventKind::DepositDetected, &payload));
+        emitter.emit(BridgeEvent::new(EventKind::AttestationSigned, &payload).with_nonce(4));
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        for sink in [a, b] {
+            let events = sink.events.lock().unwrap();
+            assert_eq!(events.len(), 2);
+            assert_eq!(events[1].nonce, Some(4));
+        }
+        let json =
+            serde_json::to_value(BridgeEvent::new(EventKind::DepositDetected, &payload)).unwrap();
+        assert_eq!(json["kind"], "deposit_detected");
+    }
+}
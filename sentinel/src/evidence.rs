@@ -0,0 +1,223 @@
+//! Header-chain evidence for attested deposits
+//!
+//! Optionally collects SPV-style evidence for each deposit before it is
+//! attested: the raw block headers from the deposit block up to the
+//! confirmation tip, and the transaction's Merkle branch into the deposit
+//! block's header. Verifiers and challenge games can check this material
+//! independently of the operator's signature. Evidence is kept as one JSON
+//! file per deposit under the data directory and served by the status API.
+
+use crate::error::SentinelError;
+use crate::zcash_rpc::ZcashRpcClient;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Offset of `hashMerkleRoot` in a serialized header
+pub(crate) const MERKLE_ROOT_OFFSET: usize = 36;
+
+/// Offset of `hashPrevBlock` in a serialized header
+const PREV_HASH_OFFSET: usize = 4;
+
+/// SPV evidence for one deposit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositEvidence {
+    /// Zcash transaction id (display hex)
+    pub txid: String,
+
+    /// Height of the deposit block
+    pub block_height: u32,
+
+    /// Position of the transaction in the block
+    pub tx_index: usize,
+
+    /// Merkle branch from the txid to the header's Merkle root (internal byte order, hex)
+    pub merkle_branch: Vec<String>,
+
+    /// Raw headers from the deposit block to the confirmation tip (hex)
+    pub headers: Vec<String>,
+}
+
+/// Double SHA-256
+fn dsha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// Display hex (as used by RPC) to internal byte order
+pub(crate) fn from_display_hex(hex_str: &str) -> Result<[u8; 32], SentinelError> {
+    let mut bytes: [u8; 32] = hex::decode(hex_str)?
+        .try_into()
+        .map_err(|_| SentinelError::InvalidPayload("expected 32-byte hash".to_string()))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Merkle branch for `txids[index]` and the resulting root (Bitcoin-style tree)
+pub fn merkle_branch(txids: &[[u8; 32]], mut index: usize) -> (Vec<[u8; 32]>, [u8; 32]) {
+    let mut layer = txids.to_vec();
+    let mut branch = Vec::new();
+
+    while layer.len() > 1 {
+        if layer.len() % 2 == 1 {
+            layer.push(*layer.last().unwrap());
+        }
+        branch.push(layer[index ^ 1]);
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let mut buf = [0u8; 64];
+                buf[..32].copy_from_slice(&pair[0]);
+                buf[32..].copy_from_slice(&pair[1]);
+                dsha256(&buf)
+            })
+            .collect();
+        index /= 2;
+    }
+
+    (branch, layer.first().copied().unwrap_or_default())
+}
+
+/// Fold a Merkle branch back up to the root
+pub fn branch_root(txid: [u8; 32], branch: &[[u8; 32]], mut index: usize) -> [u8; 32] {
+    branch.iter().fold(txid, |acc, sibling| {
+        let mut buf = [0u8; 64];
+        if index % 2 == 0 {
+            buf[..32].copy_from_slice(&acc);
+            buf[32..].copy_from_slice(sibling);
+        } else {
+            buf[..32].copy_from_slice(sibling);
+            buf[32..].copy_from_slice(&acc);
+        }
+        index /= 2;
+        dsha256(&buf)
+    })
+}
+
+/// Check that each header commits to the hash of the one before it
+pub fn verify_header_chain(headers: &[Vec<u8>]) -> bool {
+    headers.windows(2).all(|pair| {
+        pair[1].len() >= PREV_HASH_OFFSET + 32
+            && pair[1][PREV_HASH_OFFSET..PREV_HASH_OFFSET + 32] == dsha256(&pair[0])
+    })
+}
+
+/// Collects and stores deposit evidence
+pub struct EvidenceCollector {
+    /// Zcash node RPC
+    rpc: ZcashRpcClient,
+
+    /// Confirmations covered by the header chain
+    confirmation_depth: u32,
+
+    /// Directory evidence files are written to
+    dir: PathBuf,
+}
+
+impl EvidenceCollector {
+    /// Create a collector writing to `dir`
+    pub fn new(rpc: ZcashRpcClient, confirmation_depth: u32, dir: PathBuf) -> Result<Self, SentinelError> {
+        std::fs::create_dir_all(&dir).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        Ok(Self {
+            rpc,
+            confirmation_depth,
+            dir,
+        })
+    }
+
+    /// Collect, check and persist evidence for a deposit
+    pub async fn collect(
+        &self,
+        tx_hash: &[u8; 32],
+        block_height: u32,
+    ) -> Result<DepositEvidence, SentinelError> {
+        let block = self.rpc.block(block_height).await?;
+        let txids = block
+            .tx
+            .iter()
+            .map(|t| from_display_hex(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tx_index = txids
+            .iter()
+            .position(|t| t == tx_hash)
+            .ok_or_else(|| {
+                SentinelError::InvalidPayload(format!(
+                    "transaction not found in block {}",
+                    block_height
+                ))
+            })?;
+        let (branch, _) = merkle_branch(&txids, tx_index);
+
+        let mut headers = vec![self.rpc.raw_header(&block.hash).await?];
+        for height in block_height + 1..=block_height + self.confirmation_depth {
+            let next = self.rpc.block(height).await?;
+            headers.push(self.rpc.raw_header(&next.hash).await?);
+        }
+
+        let root = branch_root(*tx_hash, &branch, tx_index);
+        if headers[0].get(MERKLE_ROOT_OFFSET..MERKLE_ROOT_OFFSET + 32) != Some(&root[..]) {
+            return Err(SentinelError::InvalidPayload(
+                "transaction Merkle root does not match the block header".to_string(),
+            ));
+        }
+        if !verify_header_chain(&headers) {
+            return Err(SentinelError::InvalidPayload(
+                "headers do not form a chain".to_string(),
+            ));
+        }
+
+        let mut display = *tx_hash;
+        display.reverse();
+        let evidence = DepositEvidence {
+            txid: hex::encode(display),
+            block_height,
+            tx_index,
+            merkle_branch: branch.iter().map(hex::encode).collect(),
+            headers: headers.iter().map(hex::encode).collect(),
+        };
+
+        let bytes = serde_json::to_vec_pretty(&evidence)?;
+        std::fs::write(self.dir.join(format!("{}.json", hex::encode(tx_hash))), bytes)
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+
+        Ok(evidence)
+    }
+
+    /// Previously collected evidence for a deposit (hex tx hash)
+    pub fn get(&self, tx_hash: &str) -> Option<DepositEvidence> {
+        let tx_hash = tx_hash.strip_prefix("0x").unwrap_or(tx_hash);
+        if tx_hash.len() != 64 || !tx_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let bytes = std::fs::read(self.dir.join(format!("{}.json", tx_hash))).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_branch_roundtrip() {
+        for n in 1..=7u8 {
+            let txids: Vec<[u8; 32]> = (0..n).map(|i| dsha256(&[i])).collect();
+            let (_, root) = merkle_branch(&txids, 0);
+
+            for (i, txid) in txids.iter().enumerate() {
+                let (branch, r) = merkle_branch(&txids, i);
+                assert_eq!(r, root);
+                assert_eq!(branch_root(*txid, &branch, i), root, "n={} i={}", n, i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_chain() {
+        let first = vec![0u8; 80];
+        let mut second = vec![0u8; 80];
+        second[PREV_HASH_OFFSET..PREV_HASH_OFFSET + 32].copy_from_slice(&dsha256(&first));
+
+        assert!(verify_header_chain(&[first.clone(), second.clone()]));
+        assert!(!verify_header_chain(&[second, first]));
+    }
+}
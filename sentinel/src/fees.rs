@@ -0,0 +1,64 @@
+//! Protocol fee engine
+//!
+//! Each attested deposit pays a bridge fee of a flat amount plus a share of
+//! the deposit in basis points. The fee is deducted from the amount minted on
+//! Aztec and signed alongside it, so the contract can check the split.
+
+/// Fee schedule for a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSchedule {
+    /// Flat fee per deposit in zatoshi
+    pub flat_zatoshi: u64,
+
+    /// Proportional fee in basis points of the deposit amount
+    pub bps: u32,
+}
+
+impl FeeSchedule {
+    /// Default schedule per network
+    pub fn default_for_network(network: &str) -> Self {
+        match network {
+            // 0.0001 ZEC + 0.1%
+            "mainnet" => Self {
+                flat_zatoshi: 10_000,
+                bps: 10,
+            },
+            // Keep test networks free
+            _ => Self {
+                flat_zatoshi: 0,
+                bps: 0,
+            },
+        }
+    }
+
+    /// Fee owed on a deposit of `amount` zatoshi, never more than the deposit
+    pub fn fee_for(&self, amount: u64) -> u64 {
+        let proportional = (amount as u128 * self.bps as u128 / 10_000) as u64;
+        self.flat_zatoshi.saturating_add(proportional).min(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_plus_bps() {
+        let schedule = FeeSchedule {
+            flat_zatoshi: 1_000,
+            bps: 25,
+        };
+        assert_eq!(schedule.fee_for(1_000_000), 1_000 + 2_500);
+        assert_eq!(schedule.fee_for(0), 0);
+    }
+
+    #[test]
+    fn test_fee_capped_at_amount() {
+        let schedule = FeeSchedule {
+            flat_zatoshi: 10_000,
+            bps: 10,
+        };
+        assert_eq!(schedule.fee_for(5_000), 5_000);
+        assert_eq!(FeeSchedule::default_for_network("regtest").fee_for(5_000), 0);
+    }
+}
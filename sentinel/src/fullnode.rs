@@ -0,0 +1,86 @@
+//! Full-node deposit verification
+//!
+//! Belt-and-suspenders check before signing: the deposit transaction is
+//! re-located through a trusted zebrad node, independently of lightwalletd.
+//! The transaction must be in the best-chain block at the claimed height,
+//! its Merkle branch must hash to that block's header, and the block must be
+//! buried at least as deep as the scanner's confirmation depth.
+
+use crate::error::SentinelError;
+use crate::evidence::{branch_root, from_display_hex, merkle_branch, MERKLE_ROOT_OFFSET};
+use crate::zcash_rpc::ZcashRpcClient;
+
+/// Verifies deposits against a trusted full node
+pub struct FullNodeVerifier {
+    /// Trusted zebrad RPC
+    rpc: ZcashRpcClient,
+
+    /// Blocks required on top of the deposit block
+    confirmation_depth: u32,
+}
+
+impl FullNodeVerifier {
+    /// Create a verifier
+    pub fn new(rpc: ZcashRpcClient, confirmation_depth: u32) -> Self {
+        Self {
+            rpc,
+            confirmation_depth,
+        }
+    }
+
+    /// Check that the deposit is buried in the full node's best chain
+    ///
+    /// Returns `InvalidPayload` when the node contradicts lightwalletd, and
+    /// another error when the deposit cannot be checked yet.
+    pub async fn verify(&self, tx_hash: &[u8; 32], block_height: u32) -> Result<(), SentinelError> {
+        let tip = self.rpc.block_count().await?;
+        if !is_buried(tip, block_height, self.confirmation_depth) {
+            return Err(SentinelError::Scanner(format!(
+                "block {} not yet {} deep on the full node (tip {})",
+                block_height, self.confirmation_depth, tip
+            )));
+        }
+
+        let block = self.rpc.block(block_height).await?;
+        let txids = block
+            .tx
+            .iter()
+            .map(|t| from_display_hex(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tx_index = txids.iter().position(|t| t == tx_hash).ok_or_else(|| {
+            SentinelError::InvalidPayload(format!(
+                "full node has no such transaction in block {}",
+                block_height
+            ))
+        })?;
+
+        let (branch, _) = merkle_branch(&txids, tx_index);
+        let root = branch_root(*tx_hash, &branch, tx_index);
+        let header = self.rpc.raw_header(&block.hash).await?;
+        if header.get(MERKLE_ROOT_OFFSET..MERKLE_ROOT_OFFSET + 32) != Some(&root[..]) {
+            return Err(SentinelError::InvalidPayload(format!(
+                "transaction Merkle root does not match block {} on the full node",
+                block_height
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a block at `height` has `depth` blocks on top of it (scanner semantics)
+fn is_buried(tip: u32, height: u32, depth: u32) -> bool {
+    height <= tip && tip - height >= depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_buried() {
+        assert!(is_buried(110, 100, 10));
+        assert!(!is_buried(109, 100, 10));
+        assert!(!is_buried(90, 100, 0));
+    }
+}
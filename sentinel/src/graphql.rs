@@ -0,0 +1,383 @@
+//! GraphQL query API
+//!
+//! A read-only GraphQL endpoint at `/graphql` on the status server, over the
+//! deposit store and peer operator statistics. Integrators filter, paginate
+//! and pick fields in one query instead of stitching REST calls together:
+//!
+//! ```graphql
+//! {
+//!   deposits(filter: { status: SUBMITTED, fromHeight: 2500000 }, limit: 20) {
+//!     total
+//!     items { txHash amount messageHash history { status at } }
+//!   }
+//! }
+//! ```
+
+use crate::reputation::ReputationTracker;
+use crate::store::{DepositRecord, DepositStatus, DepositStore, StatusChange};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, InputObject, Object, Schema, SimpleObject,
+};
+use async_graphql_axum::GraphQL;
+use axum::Router;
+use std::sync::Arc;
+
+/// Page size when a query gives none
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page a query may ask for
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Every lifecycle state, in declaration order
+const STATUSES: [Status; 9] = [
+    Status::Detected,
+    Status::Submitted,
+    Status::Rejected,
+    Status::RefundQueued,
+    Status::Refunded,
+    Status::Claimed,
+    Status::ClaimExpired,
+    Status::Stale,
+    Status::Orphaned,
+];
+
+/// Lifecycle state of a deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(remote = "crate::store::DepositStatus")]
+enum Status {
+    Detected,
+    Submitted,
+    Rejected,
+    RefundQueued,
+    Refunded,
+    Claimed,
+    ClaimExpired,
+    Stale,
+    Orphaned,
+}
+
+/// A status change in a deposit's history
+#[derive(SimpleObject)]
+struct StatusChangeObject {
+    /// Status entered
+    status: Status,
+
+    /// Unix timestamp of the change
+    at: u64,
+
+    /// Reason, if one was given
+    reason: Option<String>,
+}
+
+impl From<&StatusChange> for StatusChangeObject {
+    fn from(change: &StatusChange) -> Self {
+        Self {
+            status: change.status.into(),
+            at: change.at,
+            reason: change.reason.clone(),
+        }
+    }
+}
+
+/// A deposit and its attestation
+struct Deposit(DepositRecord);
+
+#[Object]
+impl Deposit {
+    /// Zcash transaction hash (hex)
+    async fn tx_hash(&self) -> &str {
+        &self.0.tx_hash
+    }
+
+    /// Amount in zatoshi
+    async fn amount(&self) -> u64 {
+        self.0.amount
+    }
+
+    /// Bridge fee in zatoshi
+    async fn fee(&self) -> u64 {
+        self.0.fee
+    }
+
+    /// Hash of the claim secret (hex)
+    async fn secret_hash(&self) -> &str {
+        &self.0.secret_hash
+    }
+
+    /// Recipient's Aztec address (hex)
+    async fn aztec_address(&self) -> &str {
+        &self.0.aztec_address
+    }
+
+    /// Zcash block height
+    async fn block_height(&self) -> u32 {
+        self.0.block_height
+    }
+
+    /// Vault shard that received the deposit
+    async fn vault_shard(&self) -> &str {
+        &self.0.vault_shard
+    }
+
+    /// L1 target requested by the memo
+    async fn target(&self) -> Option<&str> {
+        self.0.target.as_deref()
+    }
+
+    /// Current lifecycle state
+    async fn status(&self) -> Status {
+        self.0.status.into()
+    }
+
+    /// Why the deposit was rejected
+    async fn reason(&self) -> Option<&str> {
+        self.0.reason.as_deref()
+    }
+
+    /// L1 transaction that dispatched the attestation
+    async fn l1_tx_hash(&self) -> Option<&str> {
+        self.0.l1_tx_hash.as_deref()
+    }
+
+    /// L1→L2 message hash (hex)
+    async fn message_hash(&self) -> Option<&str> {
+        self.0.message_hash.as_deref()
+    }
+
+    /// L1 transaction in which the claim consumed the message
+    async fn claim_tx_hash(&self) -> Option<&str> {
+        self.0.claim_tx_hash.as_deref()
+    }
+
+    /// Every status change, oldest first
+    async fn history(&self) -> Vec<StatusChangeObject> {
+        self.0.history.iter().map(Into::into).collect()
+    }
+
+    /// Unix timestamp of first detection
+    async fn detected_at(&self) -> u64 {
+        self.0.detected_at
+    }
+
+    /// Unix timestamp of the last status change
+    async fn updated_at(&self) -> u64 {
+        self.0.updated_at
+    }
+}
+
+/// Deposit selection
+#[derive(Debug, Default, InputObject)]
+struct DepositFilter {
+    /// Only deposits in this state
+    status: Option<Status>,
+
+    /// Only deposits to this Aztec address (hex)
+    aztec_address: Option<String>,
+
+    /// Only deposits paid to this vault shard
+    vault_shard: Option<String>,
+
+    /// Lowest Zcash block height, inclusive
+    from_height: Option<u32>,
+
+    /// Highest Zcash block height, inclusive
+    to_height: Option<u32>,
+}
+
+impl DepositFilter {
+    /// Whether `record` passes the filter
+    fn matches(&self, record: &DepositRecord) -> bool {
+        let hex_eq = |a: &str, b: &str| {
+            a.strip_prefix("0x")
+                .unwrap_or(a)
+                .eq_ignore_ascii_case(b.strip_prefix("0x").unwrap_or(b))
+        };
+        self.status
+            .map_or(true, |s| DepositStatus::from(s) == record.status)
+            && self
+                .aztec_address
+                .as_deref()
+                .map_or(true, |a| hex_eq(a, &record.aztec_address))
+            && self
+                .vault_shard
+                .as_deref()
+                .map_or(true, |s| s == record.vault_shard)
+            && self.from_height.map_or(true, |h| record.block_height >= h)
+            && self.to_height.map_or(true, |h| record.block_height <= h)
+    }
+}
+
+/// One page of deposits
+#[derive(SimpleObject)]
+struct DepositPage {
+    /// Deposits matching the filter, across all pages
+    total: usize,
+
+    /// This page, newest first
+    items: Vec<Deposit>,
+}
+
+/// Number of deposits in one lifecycle state
+#[derive(SimpleObject)]
+struct StatusCount {
+    status: Status,
+    count: usize,
+}
+
+/// Peer operator statistics
+#[derive(SimpleObject)]
+struct Operator {
+    /// Operator address
+    address: String,
+
+    /// Attestations the operator took part in
+    attestations: u64,
+
+    /// Quorum-complete attestations the operator did not sign
+    missed_attestations: u64,
+
+    /// Signatures that failed verification
+    invalid_signatures: u64,
+
+    /// Share of attestations signed, in basis points
+    participation_bps: u64,
+
+    /// Average detection-to-dispatch latency in seconds
+    avg_latency_secs: Option<u64>,
+
+    /// Unix timestamp of the last attestation signed
+    last_seen: Option<u64>,
+}
+
+/// Root of every query
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A deposit by Zcash transaction hash, as `<txid>:<output_index>` when
+    /// the transaction holds several vault notes
+    async fn deposit(&self, ctx: &Context<'_>, tx_hash: String) -> Option<Deposit> {
+        ctx.data_unchecked::<Arc<DepositStore>>()
+            .resolve(&tx_hash)
+            .map(Deposit)
+    }
+
+    /// Deposits matching `filter`, newest first, at most 500 per page
+    async fn deposits(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<DepositFilter>,
+        #[graphql(default = 0)] offset: usize,
+        #[graphql(default_with = "DEFAULT_PAGE_SIZE")] limit: usize,
+    ) -> DepositPage {
+        let filter = filter.unwrap_or_default();
+        let mut matching: Vec<_> = ctx
+            .data_unchecked::<Arc<DepositStore>>()
+            .all()
+            .into_iter()
+            .filter(|record| filter.matches(record))
+            .collect();
+        matching
+            .sort_by(|a, b| (b.block_height, b.detected_at).cmp(&(a.block_height, a.detected_at)));
+
+        DepositPage {
+            total: matching.len(),
+            items: matching
+                .into_iter()
+                .skip(offset)
+                .take(limit.min(MAX_PAGE_SIZE))
+                .map(Deposit)
+                .collect(),
+        }
+    }
+
+    /// Deposit count per lifecycle state
+    async fn status_counts(&self, ctx: &Context<'_>) -> Vec<StatusCount> {
+        let records = ctx.data_unchecked::<Arc<DepositStore>>().all();
+        STATUSES
+            .into_iter()
+            .map(|status| StatusCount {
+                status,
+                count: records
+                    .iter()
+                    .filter(|r| r.status == DepositStatus::from(status))
+                    .count(),
+            })
+            .collect()
+    }
+
+    /// Statistics of every peer operator seen
+    async fn operators(&self, ctx: &Context<'_>) -> Vec<Operator> {
+        let mut operators: Vec<_> = ctx
+            .data_unchecked::<Arc<ReputationTracker>>()
+            .snapshot()
+            .into_iter()
+            .map(|(address, stats)| Operator {
+                address: format!("{:?}", address),
+                attestations: stats.attestations,
+                missed_attestations: stats.missed_attestations,
+                invalid_signatures: stats.invalid_signatures,
+                participation_bps: stats.participation_bps(),
+                avg_latency_secs: stats.avg_latency_secs,
+                last_seen: stats.last_seen,
+            })
+            .collect();
+        operators.sort_by(|a, b| a.address.cmp(&b.address));
+        operators
+    }
+}
+
+/// Read-only schema over the store and operator statistics
+pub type BridgeSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Build the schema
+pub fn schema(store: Arc<DepositStore>, reputation: Arc<ReputationTracker>) -> BridgeSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(store)
+        .data(reputation)
+        .limit_depth(8)
+        .limit_complexity(2_000)
+        .finish()
+}
+
+/// `POST /graphql`
+pub fn router(store: Arc<DepositStore>, reputation: Arc<ReputationTracker>) -> Router {
+    Router::new().route_service("/graphql", GraphQL::new(schema(store, reputation)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::deposit_key;
+
+    #[tokio::test]
+    async fn test_deposits_filtered_and_paginated() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        for (i, height) in [100u32, 200, 300].into_iter().enumerate() {
+            let payload = crate::BridgePayload {
+                tx_hash: [i as u8 + 1; 32],
+                block_height: height,
+                ..BridgePayload::sample()
+            };
+            store.insert_detected(&payload).unwrap();
+        }
+        store
+            .transition(&deposit_key(&hex::encode([3u8; 32]), 0), DepositStatus::Submitted, None)
+            .unwrap();
+
+        let schema = schema(store, Arc::new(ReputationTracker::new()));
+        let response = schema
+            .execute(
+                "{ deposits(filter: { status: DETECTED }, limit: 1) { total items { blockHeight } }
+                   statusCounts { status count } }",
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["deposits"]["total"], 2);
+        assert_eq!(data["deposits"]["items"][0]["blockHeight"], 200);
+        assert_eq!(data["statusCounts"][1]["status"], "SUBMITTED");
+        assert_eq!(data["statusCounts"][1]["count"], 1);
+    }
+}
@@ -0,0 +1,233 @@
+//! Attestation halt switch
+//!
+//! Safety jobs (reconciliation, circuit breakers, rate limits) trip a shared
+//! switch that stops the sentinel from signing. Once tripped, the switch stays
+//! tripped until an operator explicitly re-arms it, across restarts too: the
+//! halt state is kept in the data directory. Stages wait out a halt with
+//! their queues held, and carry on from where they stopped once re-armed.
+
+use crate::alerts::{Alert, AlertKind, Alerter};
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::lease::LeaseFence;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// Why attestation was halted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HaltState {
+    /// Component that tripped the switch
+    pub source: String,
+
+    /// Human readable reason
+    pub reason: String,
+
+    /// Unix timestamp the switch was tripped
+    pub tripped_at: u64,
+}
+
+/// Shared switch that halts attestation until re-armed
+#[derive(Debug, Default)]
+pub struct HaltSwitch {
+    state: RwLock<Option<HaltState>>,
+
+    /// Where trips are reported
+    alerts: Alerter,
+
+    /// File holding the halt state while halted
+    path: Option<PathBuf>,
+
+    /// Leader lease the halt file is only written under, in an HA pair
+    fence: Option<Arc<LeaseFence>>,
+
+    /// Wakes stages waiting out a halt once re-armed
+    rearmed: Notify,
+}
+
+impl HaltSwitch {
+    /// Create an armed (not halted) switch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report trips through `alerts`
+    pub fn with_alerts(mut self, alerts: Alerter) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Keep the halt state at `path`, resuming a halt left there by an
+    /// earlier run
+    pub fn persisted(mut self, path: impl Into<PathBuf>) -> Result<Self, SentinelError> {
+        let path = path.into();
+        match std::fs::read(&path) {
+            Ok(contents) => {
+                let state: HaltState = serde_json::from_slice(&contents)
+                    .map_err(|e| SentinelError::Storage(format!("{}: {}", path.display(), e)))?;
+                warn!(
+                    "Attestation still halted by {} since {}: {}",
+                    state.source, state.tripped_at, state.reason
+                );
+                *self.state.get_mut().unwrap() = Some(state);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// Only write the halt file while `fence` holds the leader lease, so a
+    /// deposed leader's halt stays its own
+    pub fn with_fence(mut self, fence: Arc<LeaseFence>) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+
+    /// Halt attestation; the first reason wins until re-armed
+    pub fn trip(&self, source: &str, reason: impl Into<String>) {
+        let mut state = self.state.write().unwrap();
+        if state.is_some() {
+            return;
+        }
+
+        let reason = reason.into();
+        error!("ALERT: attestation halted by {}: {}", source, reason);
+        self.alerts.raise(Alert::new(
+            AlertKind::CircuitBreaker,
+            format!("Attestation halted by {}", source),
+            format!(
+                "{}\n\nAttestation stays halted until an operator re-arms it.",
+                reason
+            ),
+        ));
+        let halted = HaltState {
+            source: source.to_string(),
+            reason,
+            tripped_at: now_secs(),
+        };
+        if let Err(e) = self.save(Some(&halted)) {
+            error!("Failed to persist attestation halt: {}", e);
+        }
+        *state = Some(halted);
+    }
+
+    /// Whether attestation is currently halted
+    pub fn is_halted(&self) -> bool {
+        self.state.read().unwrap().is_some()
+    }
+
+    /// Current halt state, if halted
+    pub fn state(&self) -> Option<HaltState> {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Wait until attestation is re-armed, holding `stage` and its queue
+    pub async fn wait_out(&self, stage: &str) {
+        if !self.is_halted() {
+            return;
+        }
+        warn!("Attestation halted, {} paused until re-armed", stage);
+        loop {
+            // Registered before the check so a re-arm in between is not missed
+            let rearmed = self.rearmed.notified();
+            if !self.is_halted() {
+                info!("Attestation re-armed, {} resumed", stage);
+                return;
+            }
+            rearmed.await;
+        }
+    }
+
+    /// Re-arm the switch after manual review
+    pub fn rearm(&self) -> Option<HaltState> {
+        let mut state = self.state.write().unwrap();
+        if let Err(e) = self.save(None) {
+            // Stay halted rather than halt again on the next start
+            error!("Failed to persist re-arm, attestation stays halted: {}", e);
+            return None;
+        }
+        let previous = state.take();
+        self.rearmed.notify_waiters();
+        if let Some(previous) = &previous {
+            info!(
+                "Attestation re-armed (was halted by {}: {})",
+                previous.source, previous.reason
+            );
+        }
+        previous
+    }
+
+    /// Write `state` to the halt file, or remove the file when re-armed
+    fn save(&self, state: Option<&HaltState>) -> Result<(), SentinelError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(fence) = &self.fence {
+            fence.check().map_err(SentinelError::Storage)?;
+        }
+        let result = match state {
+            Some(state) => {
+                let tmp = path.with_extension("json.tmp");
+                serde_json::to_vec(state)
+                    .map_err(std::io::Error::other)
+                    .and_then(|contents| std::fs::write(&tmp, contents))
+                    .and_then(|_| std::fs::rename(&tmp, path))
+            }
+            None => match std::fs::remove_file(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            },
+        };
+        result.map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halt_survives_restart_until_rearmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("halt.json");
+
+        let halt = HaltSwitch::new().persisted(&path).unwrap();
+        assert!(!halt.is_halted());
+        halt.trip("reconciler", "vault short");
+        drop(halt);
+
+        let halt = HaltSwitch::new().persisted(&path).unwrap();
+        let state = halt.state().unwrap();
+        assert_eq!(state.source, "reconciler");
+        assert_eq!(state.reason, "vault short");
+        assert!(halt.rearm().is_some());
+        drop(halt);
+
+        let halt = HaltSwitch::new().persisted(&path).unwrap();
+        assert!(!halt.is_halted());
+    }
+
+    #[tokio::test]
+    async fn test_wait_out_holds_until_rearmed() {
+        let halt = Arc::new(HaltSwitch::new());
+        halt.wait_out("signing").await;
+
+        halt.trip("anomaly detector", "surge");
+        let waiting = tokio::spawn({
+            let halt = halt.clone();
+            async move { halt.wait_out("signing").await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        halt.rearm();
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
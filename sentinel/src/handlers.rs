@@ -0,0 +1,243 @@
+//! Handlers for custom memo types
+//!
+//! Vault notes whose memo `type` is not `bridge_deposit` are routed to the
+//! handler registered for that type instead of being counted as memo
+//! failures, so new bridge message kinds can be processed without touching
+//! the scanner. Handlers are trait objects registered at startup; operators
+//! can also plug in external programs with `MEMO_PLUGINS=<type>=<command>`,
+//! which receive each message as JSON on standard input.
+//!
+//! Each handler runs on its own task behind a bounded queue, like event
+//! sinks: a slow handler never holds up the scan, and messages it cannot
+//! keep up with are dropped with a warning.
+
+use crate::error::SentinelError;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Messages queued per handler before new ones are dropped
+pub const DEFAULT_HANDLER_QUEUE: usize = 256;
+
+/// Memo type the core pipeline processes itself
+const BRIDGE_DEPOSIT: &str = "bridge_deposit";
+
+/// A vault note carrying a custom memo
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomMessage {
+    /// Memo `type`
+    #[serde(rename = "type")]
+    pub msg_type: String,
+
+    /// The whole memo JSON object
+    pub memo: Value,
+
+    /// Zcash transaction hash (hex)
+    pub tx_hash: String,
+
+    /// Zcash block height
+    pub block_height: u32,
+
+    /// Note value in zatoshi
+    pub amount: u64,
+
+    /// Vault shard the note was paid to
+    pub vault_shard: String,
+}
+
+/// Processor of one or more custom memo types
+#[async_trait]
+pub trait PayloadHandler: Send + Sync {
+    /// Handler name for logs
+    fn name(&self) -> &str;
+
+    /// Process one message
+    async fn handle(&self, message: &CustomMessage) -> Result<(), SentinelError>;
+}
+
+/// Handler running an external program per message
+pub struct CommandHandler {
+    /// Shell command
+    command: String,
+}
+
+impl CommandHandler {
+    /// Handler running `command` through `sh -c`
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+}
+
+#[async_trait]
+impl PayloadHandler for CommandHandler {
+    fn name(&self) -> &str {
+        &self.command
+    }
+
+    async fn handle(&self, message: &CustomMessage) -> Result<(), SentinelError> {
+        let input = serde_json::to_vec(message)?;
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| SentinelError::Scanner(format!("cannot run handler: {}", e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(&input)
+                .await
+                .map_err(|e| SentinelError::Scanner(e.to_string()))?;
+        }
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| SentinelError::Scanner(e.to_string()))?;
+        if !status.success() {
+            return Err(SentinelError::Scanner(format!(
+                "handler exited with {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Routes custom memo types to their handlers
+#[derive(Clone, Default)]
+pub struct HandlerRegistry {
+    /// Queue of each memo type's handler, with the handler's name
+    routes: HashMap<String, (String, mpsc::Sender<CustomMessage>)>,
+}
+
+impl HandlerRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route `msg_type` to `handler`, starting its delivery task
+    pub fn register(
+        &mut self,
+        msg_type: &str,
+        handler: Arc<dyn PayloadHandler>,
+    ) -> Result<(), SentinelError> {
+        if msg_type == BRIDGE_DEPOSIT {
+            return Err(SentinelError::Config(
+                "bridge_deposit memos cannot be handed to a plugin".to_string(),
+            ));
+        }
+        if self.routes.contains_key(msg_type) {
+            return Err(SentinelError::Config(format!(
+                "memo type {} already has a handler",
+                msg_type
+            )));
+        }
+
+        let (tx, mut rx) = mpsc::channel::<CustomMessage>(DEFAULT_HANDLER_QUEUE);
+        let name = handler.name().to_string();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                match handler.handle(&message).await {
+                    Ok(()) => debug!(
+                        "{} handled {} memo in {}",
+                        handler.name(),
+                        message.msg_type,
+                        message.tx_hash
+                    ),
+                    Err(e) => warn!(
+                        "{} failed on {} memo in {}: {}",
+                        handler.name(),
+                        message.msg_type,
+                        message.tx_hash,
+                        e
+                    ),
+                }
+            }
+        });
+        self.routes.insert(msg_type.to_string(), (name, tx));
+        Ok(())
+    }
+
+    /// Registered memo types
+    pub fn types(&self) -> Vec<&str> {
+        let mut types: Vec<_> = self.routes.keys().map(String::as_str).collect();
+        types.sort_unstable();
+        types
+    }
+
+    /// Queue `message` for its handler; false if no handler takes its type
+    pub fn route(&self, message: CustomMessage) -> bool {
+        let Some((name, queue)) = self.routes.get(&message.msg_type) else {
+            return false;
+        };
+        if queue.try_send(message.clone()).is_err() {
+            warn!(
+                "Handler queue for {} full, dropping {} memo in {}",
+                name, message.msg_type, message.tx_hash
+            );
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Recorder {
+        messages: Mutex<Vec<CustomMessage>>,
+    }
+
+    #[async_trait]
+    impl PayloadHandler for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        async fn handle(&self, message: &CustomMessage) -> Result<(), SentinelError> {
+            self.messages.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_routed_by_type() {
+        let recorder = Arc::new(Recorder {
+            messages: Mutex::new(Vec::new()),
+        });
+        let mut registry = HandlerRegistry::new();
+        registry.register("bridge_swap", recorder.clone()).unwrap();
+        assert!(registry.register("bridge_swap", recorder.clone()).is_err());
+        assert!(registry
+            .register("bridge_deposit", recorder.clone())
+            .is_err());
+        assert_eq!(registry.types(), vec!["bridge_swap"]);
+
+        let message = CustomMessage {
+            msg_type: "bridge_swap".to_string(),
+            memo: serde_json::json!({ "type": "bridge_swap", "pair": "ZEC/ETH" }),
+            tx_hash: hex::encode([1u8; 32]),
+            block_height: 100,
+            amount: 100_000,
+            vault_shard: crate::shards::primary_shard(),
+        };
+        assert!(registry.route(message.clone()));
+        assert!(!registry.route(CustomMessage {
+            msg_type: "unknown".to_string(),
+            ..message
+        }));
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let messages = recorder.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].memo["pair"], "ZEC/ETH");
+    }
+}
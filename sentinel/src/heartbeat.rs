@@ -0,0 +1,157 @@
+//! Operator heartbeat publishing
+//!
+//! Each sentinel periodically signs a small status message (version, synced
+//! height, health) and publishes it to the aggregator and the status API, so
+//! the AVS can tell an offline operator apart from one that is online but
+//! disagreeing.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::scanner::ScanProgress;
+use crate::signer::AttestationSigner;
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, U256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Default interval between heartbeats
+pub const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 60;
+
+/// Signed operator heartbeat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Operator address
+    pub operator: Address,
+
+    /// Sentinel version
+    pub version: String,
+
+    /// Highest Zcash block height scanned with sufficient confirmations
+    pub synced_height: u32,
+
+    /// Whether the scanner is making progress
+    pub healthy: bool,
+
+    /// Unix timestamp the heartbeat was produced at
+    pub timestamp: u64,
+
+    /// EIP-191 signature over [`Heartbeat::digest`] (hex encoded)
+    pub signature: String,
+}
+
+impl Heartbeat {
+    /// Hash of the heartbeat fields that gets signed
+    pub fn digest(
+        operator: Address,
+        version: &str,
+        synced_height: u32,
+        healthy: bool,
+        timestamp: u64,
+    ) -> [u8; 32] {
+        keccak256(encode(&[
+            Token::Address(operator),
+            Token::String(version.to_string()),
+            Token::Uint(U256::from(synced_height)),
+            Token::Bool(healthy),
+            Token::Uint(U256::from(timestamp)),
+        ]))
+    }
+}
+
+/// Periodically produces and publishes heartbeats
+pub struct HeartbeatPublisher {
+    /// Signer holding the operator key
+    signer: Arc<AttestationSigner>,
+
+    /// Scanner progress to report
+    progress: Arc<ScanProgress>,
+
+    /// Aggregator base URL, if heartbeats should be pushed
+    aggregator_url: Option<String>,
+
+    /// Interval between heartbeats
+    interval: Duration,
+
+    /// Most recent heartbeat, served by the status API
+    latest: Arc<RwLock<Option<Heartbeat>>>,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl HeartbeatPublisher {
+    /// Create a new publisher
+    pub fn new(
+        signer: Arc<AttestationSigner>,
+        progress: Arc<ScanProgress>,
+        aggregator_url: Option<String>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            signer,
+            progress,
+            aggregator_url,
+            interval,
+            latest: Arc::new(RwLock::new(None)),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Shared handle to the latest heartbeat
+    pub fn latest(&self) -> Arc<RwLock<Option<Heartbeat>>> {
+        self.latest.clone()
+    }
+
+    /// Produce and publish heartbeats forever
+    pub async fn run(self) {
+        loop {
+            match self.beat().await {
+                Ok(heartbeat) => debug!(
+                    "Heartbeat published: height {}, healthy {}",
+                    heartbeat.synced_height, heartbeat.healthy
+                ),
+                Err(e) => warn!("Failed to publish heartbeat: {}", e),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Produce, store and publish a single heartbeat
+    async fn beat(&self) -> Result<Heartbeat, SentinelError> {
+        let operator = self.signer.address();
+        let version = env!("CARGO_PKG_VERSION").to_string();
+        let synced_height = self.progress.synced_height();
+        // Unhealthy if the scanner missed several consecutive passes
+        let healthy = self.progress.is_healthy(self.interval * 3);
+        let timestamp = now_secs();
+
+        let digest = Heartbeat::digest(operator, &version, synced_height, healthy, timestamp);
+        let signature = self.signer.sign_hash(digest, "heartbeat").await?;
+
+        let heartbeat = Heartbeat {
+            operator,
+            version,
+            synced_height,
+            healthy,
+            timestamp,
+            signature: format!("0x{}", hex::encode(signature)),
+        };
+
+        *self.latest.write().unwrap() = Some(heartbeat.clone());
+
+        if let Some(url) = &self.aggregator_url {
+            self.client
+                .post(format!("{}/heartbeats", url.trim_end_matches('/')))
+                .json(&heartbeat)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(SentinelError::network)?;
+        }
+
+        Ok(heartbeat)
+    }
+}
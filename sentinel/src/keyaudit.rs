@@ -0,0 +1,263 @@
+//! Operator key audit trail
+//!
+//! Every use of the operator key — attestation signatures, heartbeat,
+//! batch-root and report signatures, L1 transactions — is appended to
+//! `key_audit.jsonl` before the key is used, separately from the general
+//! log. Each entry carries the SHA-256 of the previous one, so deleting,
+//! reordering or editing an entry breaks the chain; `sentinel key-audit`
+//! verifies it and lists what the key has signed.
+//!
+//! If an entry cannot be written the key is not used. Payout policy
+//! decisions are kept in the same trail.
+
+use crate::clock::now_secs;
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// One use of the operator key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the trail, from 0
+    pub seq: u64,
+
+    /// Unix timestamp
+    pub at: u64,
+
+    /// What the key was used for (`attestation`, `heartbeat`, `l1_transaction`, …)
+    pub purpose: String,
+
+    /// Digest that was signed, or hash of the transaction calldata (hex)
+    pub digest: String,
+
+    /// Attestation nonce, if any
+    pub nonce: Option<u64>,
+
+    /// Zcash transaction of the deposit that triggered it, if any (hex)
+    pub deposit: Option<String>,
+
+    /// Policy decision or other context, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// `hash` of the previous entry (zeros for the first)
+    pub prev: String,
+
+    /// SHA-256 over `prev` and this entry's fields
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Chain hash of the entry's fields
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev.as_bytes());
+        hasher.update(
+            format!(
+                "|{}|{}|{}|{}|{:?}|{:?}",
+                self.seq, self.at, self.purpose, self.digest, self.nonce, self.deposit
+            )
+            .as_bytes(),
+        );
+        if let Some(detail) = &self.detail {
+            hasher.update(format!("|{}", detail).as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Hash-chained, append-only record of operator key usage
+pub struct KeyAuditLog {
+    /// Trail file
+    path: PathBuf,
+
+    /// Next sequence number and the hash of the last entry
+    head: Mutex<(u64, String)>,
+}
+
+impl KeyAuditLog {
+    /// Open the trail at `path`, verifying what is already there
+    ///
+    /// A torn last line, left by a crash mid-append, is dropped: the key
+    /// was never used for it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SentinelError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        }
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            if !contents.is_empty() && !contents.ends_with('\n') {
+                warn!("Dropping torn last entry of {}", path.display());
+                let keep = contents.rfind('\n').map_or(0, |i| i + 1);
+                OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.set_len(keep as u64))
+                    .map_err(|e| SentinelError::Storage(e.to_string()))?;
+            }
+        }
+        let entries = verify(&path)?;
+        let head = match entries.last() {
+            Some(last) => (last.seq + 1, last.hash.clone()),
+            None => (0, hex::encode([0u8; 32])),
+        };
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+        })
+    }
+
+    /// Durably record a use of the key that is about to happen
+    pub fn record(
+        &self,
+        purpose: &str,
+        digest: &[u8; 32],
+        nonce: Option<u64>,
+        deposit: Option<&[u8; 32]>,
+    ) -> Result<(), SentinelError> {
+        self.append(purpose, digest, nonce, deposit, None)
+    }
+
+    /// Record a policy decision about `digest`
+    pub fn record_decision(
+        &self,
+        purpose: &str,
+        digest: &[u8; 32],
+        detail: String,
+    ) -> Result<(), SentinelError> {
+        self.append(purpose, digest, None, None, Some(detail))
+    }
+
+    fn append(
+        &self,
+        purpose: &str,
+        digest: &[u8; 32],
+        nonce: Option<u64>,
+        deposit: Option<&[u8; 32]>,
+        detail: Option<String>,
+    ) -> Result<(), SentinelError> {
+        let mut head = self.head.lock().unwrap();
+        let mut entry = AuditEntry {
+            seq: head.0,
+            at: now_secs(),
+            purpose: purpose.to_string(),
+            digest: hex::encode(digest),
+            nonce,
+            deposit: deposit.map(hex::encode),
+            detail,
+            prev: head.1.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        let mut line =
+            serde_json::to_vec(&entry).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+        file.write_all(&line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+
+        *head = (entry.seq + 1, entry.hash);
+        Ok(())
+    }
+}
+
+/// Read the trail at `path` and check its hash chain
+pub fn verify(path: &Path) -> Result<Vec<AuditEntry>, SentinelError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(SentinelError::Storage(e.to_string())),
+    };
+
+    let mut entries: Vec<AuditEntry> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let broken = |reason: &str| {
+            SentinelError::Storage(format!("{} line {}: {}", path.display(), i + 1, reason))
+        };
+        let entry: AuditEntry = serde_json::from_str(line).map_err(|e| broken(&e.to_string()))?;
+        let (seq, prev) = match entries.last() {
+            Some(last) => (last.seq + 1, last.hash.clone()),
+            None => (0, hex::encode([0u8; 32])),
+        };
+        if entry.seq != seq || entry.prev != prev {
+            return Err(broken("audit chain broken (entry missing or reordered)"));
+        }
+        if entry.hash != entry.compute_hash() {
+            return Err(broken("audit entry altered"));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Entry point for `sentinel key-audit`
+pub fn key_audit_command(config: &SentinelConfig, json_output: bool) -> anyhow::Result<()> {
+    let entries = verify(&config.data_path("key_audit.jsonl"))?;
+    for entry in &entries {
+        if json_output {
+            println!("{}", serde_json::to_string(entry)?);
+        } else {
+            println!(
+                "#{:<6} {} {:<15} {} nonce={} deposit={}",
+                entry.seq,
+                entry.at,
+                entry.purpose,
+                entry.digest,
+                entry.nonce.map_or("-".to_string(), |n| n.to_string()),
+                entry.deposit.as_deref().unwrap_or("-")
+            );
+            if let Some(detail) = &entry.detail {
+                println!("        {}", detail);
+            }
+        }
+    }
+    if !json_output {
+        println!("{} key uses, audit chain intact", entries.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key_audit.jsonl");
+
+        let log = KeyAuditLog::open(&path).unwrap();
+        log.record("attestation", &[1; 32], Some(0), Some(&[9; 32]))
+            .unwrap();
+        log.record("heartbeat", &[2; 32], None, None).unwrap();
+        drop(log);
+
+        // Reopening continues the chain
+        let log = KeyAuditLog::open(&path).unwrap();
+        log.record("attestation", &[3; 32], Some(1), Some(&[8; 32]))
+            .unwrap();
+        let entries = verify(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].seq, 2);
+
+        // Editing an entry, or dropping one, breaks verification
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replace("\"heartbeat\"", "\"attestation\"")).unwrap();
+        assert!(verify(&path).is_err());
+        let without_first: Vec<&str> = contents.lines().skip(1).collect();
+        std::fs::write(&path, without_first.join("\n")).unwrap();
+        assert!(verify(&path).is_err());
+    }
+}
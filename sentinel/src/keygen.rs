@@ -0,0 +1,95 @@
+//! Operator key generation
+//!
+//! `sentinel keygen` creates a fresh operator key, writes it straight to an
+//! encrypted Ethereum keystore and prints only the address, so the raw key
+//! never appears on screen or in shell history. With an AVS directory it also
+//! produces the signed EigenLayer operator-to-AVS registration digest.
+
+use anyhow::{bail, Context, Result};
+use ethers::abi::{encode, Token};
+use ethers::core::rand::{thread_rng, RngCore};
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::keccak256;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Flags for `sentinel keygen`
+pub struct KeygenArgs {
+    /// Directory the keystore is written to
+    pub dir: PathBuf,
+
+    /// Keystore file name (defaults to a UUID)
+    pub name: Option<String>,
+
+    /// Environment variable holding the keystore password
+    pub password_env: String,
+
+    /// EigenLayer AVS directory, to produce the registration digest
+    pub avs_directory: Option<Address>,
+
+    /// ServiceManager (the AVS) the operator registers with
+    pub service_manager: Option<Address>,
+
+    /// L1 RPC used to compute the digest
+    pub l1_rpc_url: String,
+
+    /// Seconds the registration signature stays valid
+    pub expiry_secs: u64,
+}
+
+/// Entry point for `sentinel keygen`
+pub async fn keygen_command(args: KeygenArgs) -> Result<()> {
+    let password = Zeroizing::new(
+        std::env::var(&args.password_env)
+            .with_context(|| format!("Set {} to the keystore password", args.password_env))?,
+    );
+    if password.is_empty() {
+        bail!("{} must not be empty", args.password_env);
+    }
+
+    std::fs::create_dir_all(&args.dir)?;
+    let (wallet, file) = LocalWallet::new_keystore(&args.dir, &mut thread_rng(), password.as_bytes(), args.name.as_deref())?;
+    println!("Operator address: {:?}", wallet.address());
+    println!("Keystore:         {}", args.dir.join(file).display());
+
+    let Some(avs_directory) = args.avs_directory else {
+        return Ok(());
+    };
+    let Some(avs) = args.service_manager else {
+        bail!("--avs-directory needs SERVICE_MANAGER_ADDRESS");
+    };
+
+    let mut salt = [0u8; 32];
+    thread_rng().fill_bytes(&mut salt);
+    let expiry = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + args.expiry_secs;
+
+    // calculateOperatorAVSRegistrationDigestHash(address,address,bytes32,uint256) returns bytes32
+    let mut calldata =
+        keccak256(b"calculateOperatorAVSRegistrationDigestHash(address,address,bytes32,uint256)")[0..4].to_vec();
+    calldata.extend_from_slice(&encode(&[
+        Token::Address(wallet.address()),
+        Token::Address(avs),
+        Token::FixedBytes(salt.to_vec()),
+        Token::Uint(U256::from(expiry)),
+    ]));
+    let call = TransactionRequest::new()
+        .to(avs_directory)
+        .data(Bytes::from(calldata));
+    let provider = Provider::<Http>::try_from(args.l1_rpc_url.as_str())?;
+    let result = provider.call(&call.into(), None).await?;
+    if result.len() < 32 {
+        bail!("Malformed calculateOperatorAVSRegistrationDigestHash response");
+    }
+    let digest = H256::from_slice(&result[..32]);
+    let signature = wallet.sign_hash(digest)?;
+
+    println!();
+    println!("EigenLayer registration (registerOperatorToAVS):");
+    println!("  digest:    {:?}", digest);
+    println!("  salt:      0x{}", hex::encode(salt));
+    println!("  expiry:    {}", expiry);
+    println!("  signature: 0x{}", hex::encode(signature.to_vec()));
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! Operator key loading
+//!
+//! The operator key comes from `OPERATOR_PRIVATE_KEY`, from an encrypted
+//! Ethereum keystore (`OPERATOR_KEYSTORE`, as written by `sentinel keygen`),
+//! or stays inside a signing enclave (`OPERATOR_ENCLAVE`, see `enclave`). A
+//! keystore is unlocked with, in order of preference:
+//!
+//! - the passphrase in `OPERATOR_KEYSTORE_PASSWORD_FILE`
+//! - the output of `OPERATOR_KEYSTORE_KMS_CMD`, a command that unwraps a
+//!   KMS-encrypted data key (e.g. `aws kms decrypt … --query Plaintext`)
+//! - an interactive prompt, when standard input is a terminal
+//!
+//! The unlocked key lives only in a `Locked` allocation, which is `mlock`ed
+//! so it is never swapped out, and is zeroized when the signer is dropped.
+//! Signing middleware shares that allocation instead of copying the key.
+
+use crate::config::SentinelConfig;
+use crate::enclave::EnclaveSigner;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer, WalletError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address, Signature};
+use secrecy::ExposeSecret;
+use std::io::IsTerminal;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+use zeroize::Zeroizing;
+
+/// A heap value pinned in RAM with `mlock`
+pub struct Locked<T> {
+    inner: Box<T>,
+}
+
+impl<T> Locked<T> {
+    /// Move `value` to the heap and lock its pages
+    ///
+    /// Locking can fail under a low `RLIMIT_MEMLOCK`; the value is still
+    /// usable and a warning is logged.
+    pub fn new(value: T) -> Self {
+        let inner = Box::new(value);
+        #[cfg(unix)]
+        {
+            let ptr = &*inner as *const T as *const libc::c_void;
+            // SAFETY: the range is exactly the live allocation behind `inner`
+            if unsafe { libc::mlock(ptr, std::mem::size_of::<T>()) } != 0 {
+                warn!(
+                    "Could not lock key memory: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+        Self { inner }
+    }
+}
+
+impl<T> Deref for Locked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Drop for Locked<T> {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            let ptr = &*self.inner as *const T as *const libc::c_void;
+            // SAFETY: same range as locked in `new`
+            unsafe { libc::munlock(ptr, std::mem::size_of::<T>()) };
+        }
+    }
+}
+
+/// Operator wallet held in locked memory; clones share the same key
+#[derive(Clone)]
+pub struct LockedWallet {
+    /// The wallet; its signing key zeroizes on drop
+    wallet: Arc<Locked<LocalWallet>>,
+
+    /// Chain id used for transactions that do not carry one
+    chain_id: u64,
+}
+
+impl LockedWallet {
+    /// Lock `wallet` in memory
+    pub fn new(wallet: LocalWallet) -> Self {
+        let chain_id = wallet.chain_id();
+        Self {
+            wallet: Arc::new(Locked::new(wallet)),
+            chain_id,
+        }
+    }
+}
+
+impl std::fmt::Debug for LockedWallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LockedWallet")
+            .field("address", &self.wallet.address())
+            .field("chain_id", &self.chain_id)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Signer for LockedWallet {
+    type Error = WalletError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, WalletError> {
+        self.wallet.sign_message(message).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, WalletError> {
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        self.wallet.sign_transaction(&tx).await
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, WalletError> {
+        self.wallet.sign_typed_data(payload).await
+    }
+
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+/// The operator's signing key, wherever it is held
+#[derive(Debug, Clone)]
+pub enum OperatorSigner {
+    /// Key in this process's locked memory
+    Local(LockedWallet),
+    /// Key sealed in an enclave; only digests and signatures cross over
+    Enclave(EnclaveSigner),
+}
+
+#[async_trait]
+impl Signer for OperatorSigner {
+    type Error = WalletError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, WalletError> {
+        match self {
+            Self::Local(wallet) => wallet.sign_message(message).await,
+            Self::Enclave(enclave) => enclave.sign_message(message).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, WalletError> {
+        match self {
+            Self::Local(wallet) => wallet.sign_transaction(tx).await,
+            Self::Enclave(enclave) => enclave.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, WalletError> {
+        match self {
+            Self::Local(wallet) => wallet.sign_typed_data(payload).await,
+            Self::Enclave(enclave) => enclave.sign_typed_data(payload).await,
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            Self::Local(wallet) => wallet.address(),
+            Self::Enclave(enclave) => enclave.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            Self::Local(wallet) => wallet.chain_id(),
+            Self::Enclave(enclave) => enclave.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            Self::Local(wallet) => Self::Local(wallet.with_chain_id(chain_id)),
+            Self::Enclave(enclave) => Self::Enclave(enclave.with_chain_id(chain_id)),
+        }
+    }
+}
+
+/// Load the operator signer from the configured key, keystore or enclave
+pub async fn operator_signer(config: &SentinelConfig) -> Result<OperatorSigner> {
+    if let Some(endpoint) = &config.operator_enclave {
+        let enclave = EnclaveSigner::connect(endpoint.parse()?).await?;
+        return Ok(OperatorSigner::Enclave(enclave));
+    }
+
+    if let Some(key) = &config.operator_private_key {
+        let key = key.expose_secret();
+        let wallet: LocalWallet = key.strip_prefix("0x").unwrap_or(key).parse()?;
+        return Ok(OperatorSigner::Local(LockedWallet::new(wallet)));
+    }
+
+    let Some(keystore) = &config.operator_keystore else {
+        bail!("Set OPERATOR_PRIVATE_KEY, OPERATOR_KEYSTORE or OPERATOR_ENCLAVE");
+    };
+    let passphrase = keystore_passphrase(config)?;
+    let wallet = LocalWallet::decrypt_keystore(keystore, passphrase.as_bytes())
+        .with_context(|| format!("Cannot unlock operator keystore {}", keystore))?;
+    info!("Unlocked operator keystore {}", keystore);
+    Ok(OperatorSigner::Local(LockedWallet::new(wallet)))
+}
+
+/// Passphrase for the operator keystore
+fn keystore_passphrase(config: &SentinelConfig) -> Result<Zeroizing<String>> {
+    if let Some(path) = &config.operator_keystore_password_file {
+        let contents =
+            Zeroizing::new(std::fs::read_to_string(Path::new(path)).with_context(|| {
+                format!("Cannot read OPERATOR_KEYSTORE_PASSWORD_FILE {}", path)
+            })?);
+        return Ok(Zeroizing::new(
+            contents.trim_end_matches(['\r', '\n']).to_string(),
+        ));
+    }
+
+    if let Some(command) = &config.operator_keystore_kms_cmd {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .context("Cannot run OPERATOR_KEYSTORE_KMS_CMD")?;
+        let stdout = Zeroizing::new(output.stdout);
+        if !output.status.success() {
+            bail!("OPERATOR_KEYSTORE_KMS_CMD failed ({})", output.status);
+        }
+        let data_key = std::str::from_utf8(&stdout).context("KMS data key is not UTF-8")?;
+        return Ok(Zeroizing::new(data_key.trim().to_string()));
+    }
+
+    if std::io::stdin().is_terminal() {
+        return Ok(Zeroizing::new(rpassword::prompt_password(
+            "Operator keystore passphrase: ",
+        )?));
+    }
+    bail!("OPERATOR_KEYSTORE needs OPERATOR_KEYSTORE_PASSWORD_FILE, OPERATOR_KEYSTORE_KMS_CMD or a terminal")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_locked_wallet_signs_like_the_wallet() {
+        let wallet: LocalWallet =
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .parse()
+                .unwrap();
+        let locked = LockedWallet::new(wallet.clone()).with_chain_id(5u64);
+        assert_eq!(locked.address(), wallet.address());
+        assert_eq!(locked.chain_id(), 5);
+        assert!(!format!("{:?}", locked).contains("ac0974"));
+
+        let expected = wallet.sign_message([7u8; 32]).await.unwrap();
+        assert_eq!(
+            locked.clone().sign_message([7u8; 32]).await.unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Deterministic leader election for L1 submission
+//!
+//! Every operator can submit a quorum-complete attestation, but having all of
+//! them race wastes gas. Each deposit is assigned a leader by taking the hash
+//! of its note (see [`note_seed`]) modulo the number of active operators; if
+//! the leader has not dispatched within a timeout, the next operator in the
+//! rotation takes over. The note also fixes the attestation nonce (see
+//! [`crate::signer::note_nonce`]), so operators agree on the signed payload
+//! as well.
+
+use ethers::types::{Address, U256};
+use ethers::utils::keccak256;
+use std::time::Duration;
+
+/// Default time each operator gets to dispatch before the next one takes over
+pub const DEFAULT_LEADER_TIMEOUT_SECS: u64 = 60;
+
+/// Seed of the schedule for the vault note at `output_index` of `tx_hash`
+pub fn note_seed(tx_hash: &[u8; 32], output_index: u32) -> [u8; 32] {
+    let mut note = tx_hash.to_vec();
+    note.extend_from_slice(&output_index.to_be_bytes());
+    keccak256(note)
+}
+
+/// Rotation of active operators for one attestation
+#[derive(Debug, Clone)]
+pub struct LeaderSchedule {
+    /// Active operators in canonical (ascending address) order
+    operators: Vec<Address>,
+
+    /// Index of the first leader for this payload
+    start: usize,
+
+    /// Time each leader gets before falling back to the next
+    timeout: Duration,
+}
+
+impl LeaderSchedule {
+    /// Build the schedule for `seed` over the given active operators
+    pub fn new(seed: [u8; 32], operators: &[Address], timeout: Duration) -> Self {
+        let mut operators = operators.to_vec();
+        operators.sort();
+        operators.dedup();
+
+        let start = if operators.is_empty() {
+            0
+        } else {
+            (U256::from_big_endian(&seed) % U256::from(operators.len())).as_usize()
+        };
+
+        Self {
+            operators,
+            start,
+            timeout,
+        }
+    }
+
+    /// Operator responsible for dispatch in the given fallback round
+    pub fn leader(&self, round: usize) -> Option<Address> {
+        if self.operators.is_empty() {
+            return None;
+        }
+        Some(self.operators[(self.start + round) % self.operators.len()])
+    }
+
+    /// How long `operator` must wait before it becomes the leader
+    ///
+    /// Returns `None` if the operator is not part of the active set.
+    pub fn wait_for(&self, operator: Address) -> Option<Duration> {
+        let position = self.operators.iter().position(|op| *op == operator)?;
+        let round = (position + self.operators.len() - self.start) % self.operators.len();
+        Some(self.timeout * round as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_deterministic_and_order_independent() {
+        let ops = vec![
+            Address::repeat_byte(3),
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+        ];
+        let mut reversed = ops.clone();
+        reversed.reverse();
+
+        let timeout = Duration::from_secs(10);
+        let a = LeaderSchedule::new([0x42; 32], &ops, timeout);
+        let b = LeaderSchedule::new([0x42; 32], &reversed, timeout);
+
+        assert_eq!(a.leader(0), b.leader(0));
+        assert_eq!(a.wait_for(a.leader(0).unwrap()), Some(Duration::ZERO));
+        assert_eq!(a.wait_for(a.leader(1).unwrap()), Some(timeout));
+        assert_eq!(a.wait_for(a.leader(2).unwrap()), Some(timeout * 2));
+        assert_eq!(a.leader(3), a.leader(0));
+    }
+
+    #[test]
+    fn test_notes_seed_their_own_schedule() {
+        assert_eq!(note_seed(&[1; 32], 0), note_seed(&[1; 32], 0));
+        assert_ne!(note_seed(&[1; 32], 0), note_seed(&[1; 32], 1));
+        assert_ne!(note_seed(&[1; 32], 0), note_seed(&[2; 32], 0));
+    }
+
+    #[test]
+    fn test_unknown_operator_never_leads() {
+        let schedule = LeaderSchedule::new(
+            [1; 32],
+            &[Address::repeat_byte(1)],
+            Duration::from_secs(1),
+        );
+        assert_eq!(schedule.wait_for(Address::repeat_byte(9)), None);
+        assert!(LeaderSchedule::new([1; 32], &[], Duration::from_secs(1))
+            .leader(0)
+            .is_none());
+    }
+}
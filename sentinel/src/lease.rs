@@ -0,0 +1,285 @@
+//! Leader lease for active-passive high availability
+//!
+//! Two sentinels can share one data directory (a shared volume) with
+//! `HA_LEADER_LOCK=true`. Only the holder of the lease in
+//! `<DATA_DIR>/leader.lease` opens the stores, signs and submits; the other
+//! stands by with a scanner of its own running from the leader's checkpoint,
+//! and polls the lease. On takeover it carries on with that scanner. The
+//! leader renews the lease every third of `HA_LEASE_SECS`. If it dies or
+//! hangs, the standby takes over once the lease expires, so failover
+//! completes within one lease period and one poll.
+//!
+//! A leader that cannot renew halts attestation and stops before its lease
+//! runs out. Each takeover bumps the lease epoch. A new holder re-reads the
+//! lease after writing it, so a standby that raced another never acts on a
+//! lease it lost. Signing, sending and writing the shared stores go through a
+//! [`LeaseFence`], which refuses them once the lease file names another
+//! holder or epoch, so a deposed leader that has not noticed yet cannot
+//! overwrite its successor's state. Hosts must keep their clocks
+//! synchronised (NTP).
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Default seconds a lease stays valid without renewal
+pub const DEFAULT_LEASE_SECS: u64 = 15;
+
+/// Wait between writing a lease and confirming it was not overwritten
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// Contents of the lease file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// Instance holding the lease
+    pub holder: String,
+
+    /// Incremented on every change of holder
+    pub epoch: u64,
+
+    /// Unix timestamp the lease lapses unless renewed
+    pub expires_at: u64,
+}
+
+/// Outcome of one attempt to take or renew the lease
+#[derive(Debug, PartialEq, Eq)]
+enum Claim {
+    /// The lease was written for this instance
+    Written(Lease),
+
+    /// Another instance holds a live lease
+    Held(Lease),
+}
+
+/// This instance's handle on the shared lease file
+#[derive(Debug)]
+pub struct LeaderLease {
+    /// Lease file on the shared volume
+    path: PathBuf,
+
+    /// This instance's name
+    holder: String,
+
+    /// Seconds a written lease stays valid
+    ttl: u64,
+}
+
+impl LeaderLease {
+    /// Lease at `path`, claimed as `holder` for `ttl` at a time
+    pub fn new(path: PathBuf, holder: String, ttl: Duration) -> Self {
+        Self {
+            path,
+            holder,
+            ttl: ttl.as_secs().max(3),
+        }
+    }
+
+    /// Wait between renewals and polls
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.ttl / 3)
+    }
+
+    /// Current lease, if the file exists
+    fn read(&self) -> Result<Option<Lease>, SentinelError> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SentinelError::Storage(e.to_string())),
+        }
+    }
+
+    /// Replace the lease file in one rename
+    fn write(&self, lease: &Lease) -> Result<(), SentinelError> {
+        let tmp = self.path.with_extension(format!("{}.tmp", self.holder));
+        std::fs::write(&tmp, serde_json::to_vec(lease)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// Take the lease at `now` if it is free, lapsed or already ours
+    fn claim(&self, now: u64) -> Result<Claim, SentinelError> {
+        let current = self.read()?;
+        let epoch = match current {
+            Some(lease) if lease.holder != self.holder && lease.expires_at > now => {
+                return Ok(Claim::Held(lease))
+            }
+            Some(lease) if lease.holder == self.holder => lease.epoch,
+            Some(lease) => lease.epoch + 1,
+            None => 1,
+        };
+        let lease = Lease {
+            holder: self.holder.clone(),
+            epoch,
+            expires_at: now + self.ttl,
+        };
+        self.write(&lease)?;
+        Ok(Claim::Written(lease))
+    }
+
+    /// Wait until this instance holds the lease
+    ///
+    /// `standby` is called with the current holder after every poll that
+    /// finds the lease taken.
+    pub async fn acquire(&self, mut standby: impl FnMut(&Lease)) -> Result<Lease, SentinelError> {
+        loop {
+            match self.claim(now_secs())? {
+                Claim::Written(lease) => {
+                    // Another standby may have written over ours meanwhile
+                    tokio::time::sleep(SETTLE).await;
+                    if self.read()?.as_ref() == Some(&lease) {
+                        info!(
+                            "Holding the leader lease as {} (epoch {})",
+                            self.holder, lease.epoch
+                        );
+                        return Ok(lease);
+                    }
+                }
+                Claim::Held(lease) => standby(&lease),
+            }
+            tokio::time::sleep(self.interval()).await;
+        }
+    }
+
+    /// Renew `lease` until it is lost, then say why
+    pub async fn hold(self: Arc<Self>, mut lease: Lease) -> String {
+        loop {
+            tokio::time::sleep(self.interval()).await;
+            let now = now_secs();
+            match self.claim(now) {
+                Ok(Claim::Written(renewed)) if renewed.epoch == lease.epoch => lease = renewed,
+                Ok(Claim::Written(renewed)) => {
+                    return format!("lease lapsed and was retaken at epoch {}", renewed.epoch)
+                }
+                Ok(Claim::Held(other)) => {
+                    return format!(
+                        "lease taken over by {} (epoch {})",
+                        other.holder, other.epoch
+                    )
+                }
+                // Give up while the lease still covers us, before a standby may take it
+                Err(e) if now + self.interval().as_secs() >= lease.expires_at => {
+                    return format!("cannot renew the lease: {}", e)
+                }
+                Err(e) => warn!("Failed to renew the leader lease: {}", e),
+            }
+        }
+    }
+}
+
+/// Check that this instance still holds the lease it took over
+#[derive(Debug)]
+pub struct LeaseFence {
+    /// The shared lease
+    lease: Arc<LeaderLease>,
+
+    /// Epoch this instance took the lease at (0: not taken yet)
+    epoch: AtomicU64,
+}
+
+impl LeaseFence {
+    /// Fence on `lease`, closed until [`LeaseFence::open`]
+    pub fn new(lease: Arc<LeaderLease>) -> Self {
+        Self {
+            lease,
+            epoch: AtomicU64::new(0),
+        }
+    }
+
+    /// Let writes through while the lease taken at `held` lasts
+    pub fn open(&self, held: &Lease) {
+        self.epoch.store(held.epoch, Ordering::SeqCst);
+    }
+
+    /// Fail unless the lease file still names this instance at the epoch it
+    /// took over with, and has not lapsed
+    pub fn check(&self) -> Result<(), String> {
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        if epoch == 0 {
+            return Err("standing by, the leader lease is not held".to_string());
+        }
+        match self.lease.read() {
+            Ok(Some(lease))
+                if lease.holder == self.lease.holder
+                    && lease.epoch == epoch
+                    && lease.expires_at > now_secs() =>
+            {
+                Ok(())
+            }
+            Ok(Some(lease)) => Err(format!(
+                "leader lease at epoch {} held by {}, taken at epoch {}",
+                lease.epoch, lease.holder, epoch
+            )),
+            Ok(None) => Err("leader lease file is gone".to_string()),
+            Err(e) => Err(format!("cannot read the leader lease: {}", e)),
+        }
+    }
+}
+
+/// Default instance name: host name and process ID
+pub fn default_holder() -> String {
+    let host = std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "sentinel".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_claimed_renewed_and_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leader.lease");
+        let a = LeaderLease::new(path.clone(), "a".to_string(), Duration::from_secs(15));
+        let b = LeaderLease::new(path, "b".to_string(), Duration::from_secs(15));
+
+        let Claim::Written(lease) = a.claim(1_000).unwrap() else {
+            panic!("free lease not taken");
+        };
+        assert_eq!((lease.epoch, lease.expires_at), (1, 1_015));
+
+        // The standby waits while the lease is live; the holder renews it
+        assert_eq!(b.claim(1_010).unwrap(), Claim::Held(lease));
+        let Claim::Written(renewed) = a.claim(1_010).unwrap() else {
+            panic!("own lease not renewed");
+        };
+        assert_eq!((renewed.epoch, renewed.expires_at), (1, 1_025));
+
+        // Once it lapses the standby takes over with a new epoch
+        let Claim::Written(taken) = b.claim(1_025).unwrap() else {
+            panic!("lapsed lease not taken");
+        };
+        assert_eq!((taken.holder.as_str(), taken.epoch), ("b", 2));
+        assert!(matches!(a.claim(1_026).unwrap(), Claim::Held(_)));
+    }
+
+    #[test]
+    fn test_fence_closes_on_takeover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leader.lease");
+        let a = Arc::new(LeaderLease::new(path.clone(), "a".to_string(), Duration::from_secs(15)));
+        let b = LeaderLease::new(path, "b".to_string(), Duration::from_secs(15));
+
+        let fence = LeaseFence::new(a.clone());
+        assert!(fence.check().is_err());
+        let Claim::Written(lease) = a.claim(now_secs()).unwrap() else {
+            panic!("free lease not taken");
+        };
+        fence.open(&lease);
+        assert_eq!(fence.check(), Ok(()));
+
+        // Once the standby has taken over, the deposed leader is fenced off
+        let Claim::Written(_) = b.claim(now_secs() + 15).unwrap() else {
+            panic!("lapsed lease not taken");
+        };
+        assert!(fence.check().is_err());
+    }
+}
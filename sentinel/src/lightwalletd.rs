@@ -0,0 +1,291 @@
+//! Lightwalletd gRPC connections
+//!
+//! Every lightwalletd client is opened here. `https://` endpoints are
+//! verified against the web PKI roots unless a private CA is configured, and
+//! operators running their own lightwalletd behind mutual TLS can give the
+//! sentinel a client certificate so only authenticated sentinels can scan.
+//!
+//! The scanner reaches lightwalletd through a `LightwalletdPool`, which moves
+//! to the next endpoint when the active one errors, times out, trails the
+//! best tip seen by more than `LIGHTWALLETD_MAX_LAG_BLOCKS`, or its tip
+//! stands still for `MAX_TIP_AGE`.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tracing::warn;
+use zcash_client_backend::proto::service::compact_tx_streamer_client::CompactTxStreamerClient;
+use zeroize::Zeroizing;
+
+/// Default blocks an endpoint may trail the best tip seen
+pub const DEFAULT_MAX_LAG_BLOCKS: u32 = 3;
+
+/// Longest an endpoint's tip may stand still while others are configured
+const MAX_TIP_AGE: Duration = Duration::from_secs(15 * 60);
+
+/// Longest a connection attempt may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// gRPC client for a lightwalletd server
+pub type LightwalletdClient = CompactTxStreamerClient<Channel>;
+
+/// TLS settings for the lightwalletd channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientTls {
+    /// PEM CA bundle to verify the server with (default: web PKI roots)
+    pub ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate and private key presented for mutual TLS
+    pub identity: Option<(PathBuf, PathBuf)>,
+
+    /// Name to verify the server certificate against (default: the URL host)
+    pub domain: Option<String>,
+}
+
+impl ClientTls {
+    /// Settings from the configuration
+    pub fn from_config(config: &SentinelConfig) -> Self {
+        let identity = config
+            .lightwalletd_client_cert
+            .clone()
+            .zip(config.lightwalletd_client_key.clone());
+        Self {
+            ca_cert: config.lightwalletd_ca_cert.clone().map(PathBuf::from),
+            identity: identity.map(|(cert, key)| (PathBuf::from(cert), PathBuf::from(key))),
+            domain: config.lightwalletd_tls_domain.clone(),
+        }
+    }
+
+    /// Whether a client certificate is configured
+    pub fn is_mutual(&self) -> bool {
+        self.identity.is_some()
+    }
+
+    /// Read the certificate files into a tonic TLS config
+    fn load(&self) -> Result<ClientTlsConfig, SentinelError> {
+        let read = |path: &Path| {
+            std::fs::read(path)
+                .map(Zeroizing::new)
+                .map_err(|e| SentinelError::Config(format!("Cannot read {}: {}", path.display(), e)))
+        };
+
+        let mut tls = ClientTlsConfig::new();
+        if let Some(path) = &self.ca_cert {
+            tls = tls.ca_certificate(Certificate::from_pem(read(path)?.as_slice()));
+        }
+        if let Some((cert, key)) = &self.identity {
+            tls = tls.identity(Identity::from_pem(read(cert)?.as_slice(), read(key)?.as_slice()));
+        }
+        if let Some(domain) = &self.domain {
+            tls = tls.domain_name(domain.clone());
+        }
+        Ok(tls)
+    }
+}
+
+/// Connect to lightwalletd at `url`, using TLS for `https://` endpoints
+pub async fn connect(url: &str, tls: &ClientTls) -> Result<LightwalletdClient, SentinelError> {
+    let mut endpoint = Endpoint::from_shared(url.to_string()).map_err(SentinelError::network)?;
+    if url.starts_with("https://") {
+        endpoint = endpoint.tls_config(tls.load()?).map_err(SentinelError::network)?;
+    }
+    let channel = endpoint
+        .connect_timeout(CONNECT_TIMEOUT)
+        .connect()
+        .await
+        .map_err(SentinelError::network)?;
+    Ok(CompactTxStreamerClient::new(channel))
+}
+
+/// The endpoint in use and its connection
+#[derive(Default)]
+struct ActiveEndpoint {
+    /// Index into the pool's URLs
+    index: usize,
+
+    /// Open connection, if any
+    client: Option<LightwalletdClient>,
+
+    /// Last tip the endpoint reported, and since when
+    tip: Option<(u32, Instant)>,
+}
+
+/// Lightwalletd endpoints, one in use at a time, failed over in order
+pub struct LightwalletdPool {
+    /// Primary URL first, then the fallbacks
+    urls: Vec<String>,
+
+    /// TLS settings for every endpoint
+    tls: ClientTls,
+
+    /// Blocks the active endpoint may trail the best tip seen
+    max_lag: u32,
+
+    /// Endpoint in use
+    active: Mutex<ActiveEndpoint>,
+
+    /// Highest tip any endpoint has reported
+    best_tip: AtomicU32,
+}
+
+impl LightwalletdPool {
+    /// Pool of the single endpoint `url`
+    pub fn new(url: String) -> Self {
+        Self {
+            urls: vec![url],
+            tls: ClientTls::default(),
+            max_lag: DEFAULT_MAX_LAG_BLOCKS,
+            active: Mutex::new(ActiveEndpoint::default()),
+            best_tip: AtomicU32::new(0),
+        }
+    }
+
+    /// Connect with `tls`
+    pub fn with_tls(mut self, tls: ClientTls) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Fail over to `urls`, in order, after the primary endpoint
+    pub fn with_fallbacks(mut self, urls: Vec<String>) -> Self {
+        for url in urls {
+            if !self.urls.contains(&url) {
+                self.urls.push(url);
+            }
+        }
+        self
+    }
+
+    /// Fail over when the active endpoint trails the best tip by more than `blocks`
+    pub fn with_max_lag(mut self, blocks: u32) -> Self {
+        self.max_lag = blocks;
+        self
+    }
+
+    /// TLS settings in use
+    pub fn tls(&self) -> &ClientTls {
+        &self.tls
+    }
+
+    /// Connection to the active endpoint, with its index for `fail_over`
+    ///
+    /// Endpoints that cannot be reached are skipped; this fails only once
+    /// every endpoint has been tried.
+    pub async fn client(&self) -> Result<(usize, LightwalletdClient), SentinelError> {
+        for _ in 0..self.urls.len() {
+            let index = {
+                let active = self.active.lock().unwrap();
+                if let Some(client) = &active.client {
+                    return Ok((active.index, client.clone()));
+                }
+                active.index
+            };
+            match connect(&self.urls[index], &self.tls).await {
+                Ok(client) => {
+                    let mut active = self.active.lock().unwrap();
+                    if active.index == index {
+                        active.client = Some(client.clone());
+                    }
+                    return Ok((index, client));
+                }
+                Err(e) => self.fail_over(index, &e.to_string()),
+            }
+        }
+        Err(SentinelError::Network(format!(
+            "none of {} lightwalletd endpoints reachable",
+            self.urls.len()
+        )))
+    }
+
+    /// Move on from endpoint `index` after `reason`, unless that already happened
+    pub fn fail_over(&self, index: usize, reason: &str) {
+        let mut active = self.active.lock().unwrap();
+        if active.index != index {
+            return;
+        }
+        let next = (index + 1) % self.urls.len();
+        if next != index {
+            warn!(
+                "Lightwalletd {} failed ({}), switching to {}",
+                self.urls[index], reason, self.urls[next]
+            );
+        }
+        *active = ActiveEndpoint {
+            index: next,
+            ..ActiveEndpoint::default()
+        };
+    }
+
+    /// Check a tip reported by the active endpoint
+    ///
+    /// Errors if it trails the best tip seen by more than the allowed lag,
+    /// or has not moved for `MAX_TIP_AGE` while other endpoints exist.
+    pub fn check_tip(&self, tip: u32) -> Result<(), SentinelError> {
+        let best = self.best_tip.fetch_max(tip, Ordering::Relaxed).max(tip);
+        if best > tip.saturating_add(self.max_lag) {
+            return Err(SentinelError::Network(format!(
+                "lightwalletd tip {} is {} blocks behind {}",
+                tip,
+                best - tip,
+                best
+            )));
+        }
+
+        let mut active = self.active.lock().unwrap();
+        match active.tip {
+            Some((last, since)) if last == tip => {
+                if self.urls.len() > 1 && since.elapsed() > MAX_TIP_AGE {
+                    return Err(SentinelError::Network(format!(
+                        "lightwalletd tip stuck at {} for {} s",
+                        tip,
+                        since.elapsed().as_secs()
+                    )));
+                }
+            }
+            _ => active.tip = Some((tip, Instant::now())),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_lightwalletd::MockLightwalletd;
+
+    #[test]
+    fn test_missing_certificate_is_a_config_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let tls = ClientTls {
+            identity: Some((dir.path().join("client.pem"), dir.path().join("client.key"))),
+            ..ClientTls::default()
+        };
+        let err = tls.load().unwrap_err();
+        assert!(matches!(err, SentinelError::Config(ref m) if m.contains("client.pem")));
+    }
+
+    #[tokio::test]
+    async fn test_pool_fails_over() {
+        let mock = MockLightwalletd::new(100);
+        let url = mock.serve().await.unwrap();
+        let pool = LightwalletdPool::new("http://127.0.0.1:1".to_string())
+            .with_fallbacks(vec![url.clone(), url]);
+        assert_eq!(pool.urls.len(), 2);
+
+        // The unreachable primary is skipped
+        let (index, _) = pool.client().await.unwrap();
+        assert_eq!(index, 1);
+
+        // A lagging tip is refused; a stale failure report is ignored
+        pool.check_tip(100).unwrap();
+        assert!(pool.check_tip(96).is_err());
+        pool.fail_over(0, "stale");
+        assert_eq!(pool.client().await.unwrap().0, 1);
+        pool.fail_over(1, "lagging");
+        assert_eq!(pool.active.lock().unwrap().index, 0);
+    }
+}
@@ -0,0 +1,192 @@
+//! Per-deposit amount bounds and rolling daily cap
+//!
+//! Deposits outside the configured bounds are never attested; they are marked
+//! rejected in the store and handed to the refund flow instead. A deposit only
+//! counts towards the daily cap once it is handed to the signer, keyed so a
+//! resumed deposit is not counted twice, and the window survives restarts.
+
+use crate::error::SentinelError;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Length of the rolling cap window
+pub const DAILY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default bounds per network as (min, max) zatoshi
+pub fn default_bounds(network: &str) -> (u64, u64) {
+    match network {
+        // 0.001 ZEC .. 100 ZEC
+        "mainnet" => (100_000, 10_000_000_000),
+        // Keep test networks permissive
+        _ => (1, u64::MAX),
+    }
+}
+
+/// Persisted daily cap window
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LimitsState {
+    /// Counted deposits within the window as (unix secs, amount, deposit key)
+    window: VecDeque<(u64, u64, String)>,
+}
+
+/// Deposit amount limits
+#[derive(Debug)]
+pub struct DepositLimits {
+    /// Minimum deposit in zatoshi
+    min: u64,
+
+    /// Maximum deposit in zatoshi
+    max: u64,
+
+    /// Maximum total accepted within [`DAILY_WINDOW`], if capped
+    daily_cap: Option<u64>,
+
+    /// Deposits counted within the window
+    state: LimitsState,
+
+    /// Where `state` is persisted
+    path: PathBuf,
+}
+
+impl DepositLimits {
+    /// Open limits, resuming the daily window persisted at `path`
+    pub fn open(
+        min: u64,
+        max: u64,
+        daily_cap: Option<u64>,
+        path: PathBuf,
+    ) -> Result<Self, SentinelError> {
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| SentinelError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => LimitsState::default(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        Ok(Self {
+            min,
+            max,
+            daily_cap,
+            state,
+            path,
+        })
+    }
+
+    /// Replace the per-deposit bounds (e.g. after a price update)
+    pub fn set_bounds(&mut self, min: u64, max: u64) {
+        self.min = min;
+        self.max = max;
+    }
+
+    /// Check a deposit against the bounds and the room left under the daily cap
+    ///
+    /// Returns the rejection reason for out-of-bounds deposits. Nothing is
+    /// counted until [`DepositLimits::record`].
+    pub fn admit(&mut self, key: &str, amount: u64, now: u64) -> Result<(), String> {
+        if amount < self.min {
+            return Err(format!("below minimum deposit of {} zatoshi", self.min));
+        }
+        if amount > self.max {
+            return Err(format!("above maximum deposit of {} zatoshi", self.max));
+        }
+
+        if let Some(cap) = self.daily_cap {
+            self.prune(now);
+            if self.is_counted(key) {
+                return Ok(());
+            }
+            let used: u64 = self.state.window.iter().map(|(_, a, _)| a).sum();
+            if used.saturating_add(amount) > cap {
+                return Err(format!(
+                    "daily cap of {} zatoshi reached ({} used)",
+                    cap, used
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a deposit handed to the signer towards the daily cap
+    pub fn record(&mut self, key: &str, amount: u64, now: u64) -> Result<(), SentinelError> {
+        if self.daily_cap.is_none() || self.is_counted(key) {
+            return Ok(());
+        }
+        self.prune(now);
+        self.state.window.push_back((now, amount, key.to_string()));
+        self.persist()
+    }
+
+    /// Whether `key` already counts towards the window
+    fn is_counted(&self, key: &str) -> bool {
+        self.state.window.iter().any(|(_, _, k)| k == key)
+    }
+
+    /// Drop deposits older than the window
+    fn prune(&mut self, now: u64) {
+        while let Some((ts, _, _)) = self.state.window.front() {
+            if now.saturating_sub(*ts) < DAILY_WINDOW.as_secs() {
+                break;
+            }
+            self.state.window.pop_front();
+        }
+    }
+
+    /// Replace the state file in one rename
+    fn persist(&self) -> Result<(), SentinelError> {
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.state)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(dir: &tempfile::TempDir, daily_cap: Option<u64>) -> DepositLimits {
+        DepositLimits::open(1, u64::MAX, daily_cap, dir.path().join("limits.json")).unwrap()
+    }
+
+    #[test]
+    fn test_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut limits =
+            DepositLimits::open(100, 1_000, None, dir.path().join("limits.json")).unwrap();
+        assert!(limits.admit("a", 99, 0).is_err());
+        assert!(limits.admit("a", 100, 0).is_ok());
+        assert!(limits.admit("a", 1_000, 0).is_ok());
+        assert!(limits.admit("a", 1_001, 0).is_err());
+    }
+
+    #[test]
+    fn test_daily_cap_rolls() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut limits = open(&dir, Some(1_000));
+        limits.record("a", 600, 0).unwrap();
+        assert!(limits.admit("b", 500, 10).is_err());
+        assert!(limits.admit("c", 400, 20).is_ok());
+        limits.record("c", 400, 20).unwrap();
+
+        // After the window passes, the first deposit no longer counts
+        assert!(limits.admit("d", 500, DAILY_WINDOW.as_secs() + 1).is_ok());
+    }
+
+    #[test]
+    fn test_daily_cap_counts_recorded_deposits_once_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut limits = open(&dir, Some(1_000));
+        // Checked but never handed to the signer
+        assert!(limits.admit("a", 900, 0).is_ok());
+        assert!(limits.admit("b", 600, 0).is_ok());
+        limits.record("b", 600, 0).unwrap();
+
+        let mut limits = open(&dir, Some(1_000));
+        // A resumed deposit is not counted against itself
+        assert!(limits.admit("b", 600, 10).is_ok());
+        limits.record("b", 600, 10).unwrap();
+        assert!(limits.admit("c", 500, 10).is_err());
+        assert!(limits.admit("c", 400, 10).is_ok());
+    }
+}
@@ -4,123 +4,1290 @@
 //! decrypts memo fields to extract bridge payloads, and signs attestations
 //! for submission to the L1 ServiceManager contract.
 
+mod admin;
+mod aggregate;
+mod alerts;
+mod anomaly;
+mod aztec;
+mod backend;
+mod batch;
+mod bench;
+mod blockcache;
+mod bus;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod checkpoint;
+mod claims;
+mod cli;
+mod clock;
 mod config;
+mod consistency;
+mod decrypt;
+mod dedup;
+mod derive;
+mod dev;
+mod dispatches;
+mod enclave;
+mod equivocation;
 mod error;
-mod memo;
+mod events;
+mod evidence;
+mod fees;
+mod fullnode;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod halt;
+mod handlers;
+mod heartbeat;
+mod keyaudit;
+mod keygen;
+mod keystore;
+mod lease;
+mod leader;
+mod lightwalletd;
+mod limits;
+mod maintenance;
+mod mempool;
+#[cfg(any(test, feature = "mock-l1"))]
+mod mock_l1;
+#[cfg(any(test, feature = "mock-lightwalletd"))]
+mod mock_lightwalletd;
+mod oracle;
+mod payout;
+mod pipeline;
+mod portal;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod quorum;
+mod ratelimit;
+mod reconcile;
+mod record;
+mod redact;
+mod refund;
+mod reorg;
+mod replay;
+mod report;
+mod reputation;
+mod rescan;
+mod reserves;
+mod retry;
+mod rewards;
+#[cfg(any(test, feature = "mock-lightwalletd"))]
+mod scenario;
 mod scanner;
+mod screening;
+mod shards;
 mod signer;
+mod signlog;
+mod sources;
+#[cfg(test)]
+mod sim;
+mod spool;
+mod stale;
+mod status;
+mod store;
+mod systemd;
+mod taddr;
+mod targets;
+mod tenants;
+mod test_deposit;
+mod trace;
+mod vectors;
+mod verify;
+mod viewkey;
+mod watchdog;
+mod webhooks;
+mod withdrawal;
+mod zcash_rpc;
 
+use alerts::{Alerter, EmailNotifier, SlashingMonitor};
+use anomaly::{AnomalyDetector, AnomalyThresholds};
 use anyhow::Result;
+use aztec::AztecClient;
+use batch::BatchAttester;
+use blockcache::BlockCache;
+use checkpoint::CheckpointStore;
+use claims::ClaimMonitor;
+use clap::Parser;
+use cli::{BenchCommand, Cli, Command, ReservesCommand, RewardsCommand};
+use clock::now_secs;
 use config::SentinelConfig;
+use consistency::{PeerChecker, SignedDigests};
+use decrypt::DecryptSettings;
+use dedup::SeenOutputs;
+use error::SentinelError;
+use events::{BridgeEvent, EventEmitter, EventKind};
+use evidence::EvidenceCollector;
+use fees::FeeSchedule;
+use fullnode::FullNodeVerifier;
+use futures::FutureExt;
+use halt::HaltSwitch;
+use handlers::{CommandHandler, HandlerRegistry};
+use heartbeat::HeartbeatPublisher;
+use lease::{LeaderLease, Lease, LeaseFence};
+use lightwalletd::ClientTls;
+use limits::DepositLimits;
+use maintenance::MaintenanceSchedule;
+use mempool::MempoolWatcher;
+use oracle::PriceOracle;
+use payout::{PayoutPolicy, PayoutPolicyEngine};
+use pipeline::StageCapacities;
+use portal::PortalTracker;
+use quorum::{QuorumCalculator, StakeRegistry};
+use ratelimit::{SigningCaps, SigningRateLimiter};
+use reconcile::Reconciler;
+use refund::RefundProcessor;
+use reorg::ReorgLog;
+use reputation::{DispatchObserver, ReputationTracker};
+use rescan::Rescan;
+use retry::RetryPolicy;
+use rewards::RewardsClaimer;
 use scanner::Scanner;
-use signer::AttestationSigner;
+use screening::{HttpScreening, RiskVerdict, Screener};
+use shards::ShardSet;
+use signer::{AttestationSigner, TxNonces};
+use signlog::SigningLog;
+use sources::SourceConfirmer;
+use stale::StaleDepositMonitor;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use store::{deposit_key, payload_key, DepositStatus, DepositStore};
+use taddr::TransparentWatcher;
+use targets::{load_targets, GasPolicy, StageContext, TargetRouter};
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tracing::{error, info, warn, Instrument};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use watchdog::ConsistencyWatchdog;
+use withdrawal::WithdrawalWatcher;
+use zcash_rpc::{RpcAuth, ZcashRpcClient};
 
-/// Bridge payload extracted from Zcash memo
-#[derive(Debug, Clone)]
-pub struct BridgePayload {
-    /// Zcash transaction hash
-    pub tx_hash: [u8; 32],
-    /// Amount in zatoshi
-    pub amount: u64,
-    /// Hash of the claim secret
-    pub secret_hash: [u8; 32],
-    /// Recipient's Aztec address
-    pub aztec_address: [u8; 32],
-    /// Block height where deposit was confirmed
-    pub block_height: u32,
-}
+#[cfg(feature = "bench-alloc")]
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
 
-/// Attestation signed by the operator
-#[derive(Debug, Clone)]
-pub struct Attestation {
-    /// The deposit payload being attested
-    pub payload: BridgePayload,
-    /// Unique nonce for replay protection
-    pub nonce: u64,
-    /// ECDSA signature
-    pub signature: Vec<u8>,
-}
+pub use sentinel_core::{Attestation, BridgePayload};
+
+/// How long a deposit left pending on an unavailable check waits to be
+/// processed again
+const PENDING_RETRY: Duration = Duration::from_secs(60);
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> std::process::ExitCode {
+    match cli_main().await {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            // Printed outside the tracing layer, so redact it the same way
+            eprintln!("Error: {}", redact::redact(&format!("{:?}", e)));
+            std::process::ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+/// Parse the command line and run the selected command
+async fn cli_main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "sentinel=info".into()),
         ))
-        .with(tracing_subscriber::fmt::layer())
+        .with(redact::layer())
         .init();
 
-    info!("Starting Sentinel AVS...");
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Run);
+
+    // Key generation runs before there is an operator key to configure
+    if let Command::Keygen {
+        dir,
+        name,
+        password_env,
+        avs_directory,
+        service_manager,
+        l1_rpc_url,
+        expiry_secs,
+    } = command
+    {
+        return keygen::keygen_command(keygen::KeygenArgs {
+            dir,
+            name,
+            password_env,
+            avs_directory,
+            service_manager,
+            l1_rpc_url,
+            expiry_secs,
+        })
+        .await;
+    }
+
+    // Key derivation needs only the mnemonic
+    if let Command::DeriveVk {
+        mnemonic_env,
+        passphrase_env,
+        path,
+        network,
+    } = command
+    {
+        return derive::derive_vk_command(derive::DeriveVkArgs {
+            mnemonic_env,
+            passphrase_env,
+            path,
+            network,
+        });
+    }
+
+    // Attestation checks are offline and need no configuration
+    if let Command::VerifyAttestation {
+        payload,
+        tx_hash,
+        output_index,
+        amount,
+        fee,
+        secret_hash,
+        aztec_address,
+        block_height,
+        block_hash,
+        nonce,
+        signature,
+        operator,
+        chain_id,
+        verifying_contract,
+    } = command
+    {
+        return verify::verify_attestation_command(verify::VerifyAttestationArgs {
+            payload,
+            fields: verify::PayloadFields {
+                tx_hash,
+                output_index,
+                amount,
+                fee,
+                secret_hash,
+                aztec_address,
+                block_height,
+                block_hash,
+                nonce: None,
+            },
+            nonce,
+            signature,
+            operator,
+            chain_id,
+            verifying_contract,
+        });
+    }
+
+    // Test vectors are fixed inputs, independent of any deployment
+    if let Command::TestVectors {
+        output,
+        chain_id,
+        verifying_contract,
+    } = &command
+    {
+        return vectors::vectors_command(output.as_deref(), *chain_id, *verifying_contract);
+    }
+
+    // Scenarios run against a scripted chain, not the configured endpoints
+    #[cfg(feature = "mock-lightwalletd")]
+    if let Command::Scenario { file } = &command {
+        return scenario::scenario_command(file);
+    }
+
+    // Dev mode deploys the contracts the configuration points at
+    if let Command::Dev { artifacts } = &command {
+        dev::prepare(artifacts).await?;
+    }
 
     // Load configuration
     let config = SentinelConfig::load()?;
+
+    match command {
+        Command::Keygen { .. }
+        | Command::DeriveVk { .. }
+        | Command::TestVectors { .. }
+        | Command::VerifyAttestation { .. } => {
+            unreachable!("handled before loading configuration")
+        }
+        #[cfg(feature = "mock-lightwalletd")]
+        Command::Scenario { .. } => unreachable!("handled before loading configuration"),
+        Command::Dev { .. } => run(config).await,
+        Command::Run => run(config).await,
+        Command::Rescan { from_height } => {
+            let from_height = from_height.max(config.vault_birthday_height);
+            info!("Rescanning from height {}", from_height);
+            run(SentinelConfig {
+                rescan_from_height: Some(from_height),
+                ..config
+            })
+            .await
+        }
+        Command::Rewards {
+            action: RewardsCommand::Claim { force },
+        } => rewards::claim_command(&config, force).await,
+        Command::Reserves {
+            action: ReservesCommand::Report { height, output },
+        } => reserves::report_command(&config, height, output.as_deref()).await,
+        Command::Trace { tx_hash, json } => trace::trace_command(&config, &tx_hash, json),
+        Command::Report {
+            period,
+            format,
+            output,
+        } => report::report_command(&config, period, format, output.as_deref()),
+        Command::KeyAudit { json } => keyaudit::key_audit_command(&config, json),
+        Command::Bench {
+            action:
+                BenchCommand::Scan {
+                    blocks,
+                    outputs_per_block,
+                    fixtures,
+                    iterations,
+                },
+        } => bench::scan_command(
+            &config,
+            bench::ScanBenchArgs {
+                blocks,
+                outputs_per_block,
+                fixtures,
+                iterations,
+            },
+        ),
+        Command::Record {
+            fixture_dir,
+            from,
+            to,
+        } => record::record_command(&config, &fixture_dir, from, to).await,
+        Command::Replay { fixture_dir, json } => replay::replay_command(&config, &fixture_dir, json),
+        Command::SendTestDeposit {
+            amount,
+            aztec_address,
+            secret_hash,
+            from,
+            transparent_key,
+            params_dir,
+        } => {
+            let args = test_deposit::TestDepositArgs {
+                amount,
+                aztec_address,
+                secret_hash,
+                from,
+                transparent_key,
+                params_dir,
+            };
+            test_deposit::send_test_deposit(&config, args).await
+        }
+    }
+}
+
+/// Run the sentinel for the primary bridge deployment and every tenant
+async fn run(config: SentinelConfig) -> Result<()> {
+    // Every deployment sends from the operator's account
+    let nonces = Arc::new(TxNonces::default());
+    let Some(path) = config.tenants_path.clone() else {
+        return run_bridge(config, nonces).await;
+    };
+
+    let tenants =
+        tenants::load_tenants(&path, config.memo_domain.as_deref(), &config.status_addr)?;
+    let mut bridges = Vec::new();
+    for tenant in &tenants {
+        let tenant_config = tenants::tenant_config(&config, tenant)?;
+        let span = tracing::info_span!("tenant", name = %tenant.name);
+        let bridge = run_bridge(tenant_config, nonces.clone());
+        bridges.push(bridge.instrument(span).boxed_local());
+    }
+    info!("Serving {} bridge deployments", bridges.len() + 1);
+    bridges.insert(0, run_bridge(config, nonces).boxed_local());
+
+    // Deployments are independent; one stopping leaves the others running
+    for result in futures::future::join_all(bridges).await {
+        result?;
+    }
+    Ok(())
+}
+
+/// Run one bridge deployment: scan, sign and submit attestations until shutdown
+///
+/// `nonces` hands out the operator account's transaction nonces, shared with
+/// the other deployments in the process.
+async fn run_bridge(config: SentinelConfig, nonces: Arc<TxNonces>) -> Result<()> {
+    let config = record::install(config).await?;
+    #[cfg(feature = "chaos")]
+    let config = chaos::install(config).await?;
+
+    info!("Starting Sentinel AVS...");
     info!("Configuration loaded successfully");
     info!("  Lightwalletd URL: {}", config.lightwalletd_url);
     info!("  L1 RPC URL: {}", config.l1_rpc_url);
     info!("  Confirmation depth: {} blocks", config.confirmation_depth);
+    info!(
+        "  Viewing key from: {}",
+        config.viewing_key_source.as_deref().unwrap_or("environment")
+    );
+    info!("  Quorum threshold: {} bps of stake", config.quorum_threshold_bps);
+    std::fs::create_dir_all(&config.data_dir)?;
 
-    // Create channel for deposit notifications
-    let (deposit_tx, mut deposit_rx) = mpsc::channel::<BridgePayload>(100);
+    // In an active-passive pair, scan as a warm standby until this instance
+    // holds the leader lease, then carry on with the standby's scanner
+    let standby = if config.ha_leader_lock {
+        Some(stand_by(&config).await?)
+    } else {
+        None
+    };
+    let fence = standby.as_ref().map(|standby| standby.fence.clone());
+    let lease_lost = standby
+        .as_ref()
+        .map(|standby| tokio::spawn(standby.lease.clone().hold(standby.held.clone())));
 
-    // Initialize scanner
-    let scanner = Scanner::new(
-        config.lightwalletd_url.clone(),
-        config.viewing_key.clone(),
-        config.vault_address.clone(),
-        config.confirmation_depth,
-        deposit_tx,
-    )?;
+    // Open the persistent deposit store
+    let mut store = DepositStore::open(config.data_path("deposits.json"))?;
+    if let Some(fence) = &fence {
+        store = store.with_fence(fence.clone());
+    }
+    let store = Arc::new(store);
+
+    // Zcash blocks reorged out after scanning; their deposits are orphaned
+    let reorgs = match &standby {
+        Some(standby) => {
+            standby.reorgs.attach(store.clone());
+            standby.reorgs.clone()
+        }
+        None => Arc::new(ReorgLog::new(store.clone())),
+    };
+
+    // Bounded queues between the pipeline stages
+    let capacities = StageCapacities::from_config(&config);
+
+    // Scan checkpoint; a rescan starts below it
+    let checkpoint = CheckpointStore::open(config.data_path("scan_checkpoint.json"))?;
+    let rescan = config
+        .rescan_from_height
+        .map(|height| Rescan::new(height, checkpoint.loaded()));
+
+    let (scanner, warm_scanner, deposit_tx, mut deposit_rx) = match standby {
+        // Deposits the standby found above the old leader's checkpoint go
+        // through the pipeline again; those already stored are skipped there
+        Some(standby) => {
+            let replay_tx = standby.deposit_tx.clone();
+            let replayed = standby.deposits;
+            tokio::spawn(async move {
+                for payload in replayed {
+                    if replay_tx.send(payload).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            (
+                standby.scanner,
+                Some(standby.scanner_handle),
+                standby.deposit_tx,
+                standby.deposit_rx,
+            )
+        }
+        None => {
+            let (deposit_tx, deposit_rx) = mpsc::channel::<BridgePayload>(capacities.deposits);
+
+            // Vault outputs parsed on earlier runs; deposits are confirmed against the store.
+            // A rescan parses them all again, so its deposits are reconciled.
+            let seen = match rescan {
+                Some(_) => SeenOutputs::default(),
+                None => {
+                    let confirm_store = store.clone();
+                    let seen = SeenOutputs::open(
+                        config.data_path("seen_outputs.bin"),
+                        config.seen_checkpoint_blocks,
+                    )?
+                    .with_confirm(move |txid, output_index| {
+                        confirm_store
+                            .get(&deposit_key(&hex::encode(txid), output_index))
+                            .is_some()
+                    });
+                    info!("{} vault outputs already parsed", seen.len());
+                    seen
+                }
+            };
+
+            let scanner = vault_scanner(&config, deposit_tx.clone(), reorgs.clone(), checkpoint)?
+                .with_seen_outputs(seen);
+            (Arc::new(scanner), None, deposit_tx, deposit_rx)
+        }
+    };
+    // Deposits left pending on an unavailable check come back through here
+    let pending_tx = deposit_tx.clone();
+    let admin_deposit_tx = deposit_tx;
+
+    // One retry policy (MAX_RETRIES, RETRY_DELAY_MS) for lightwalletd and L1 calls
+    let retry = RetryPolicy::from_config(&config);
 
     // Initialize signer
-    let signer = Arc::new(AttestationSigner::new(
-        config.operator_private_key.clone(),
-        config.l1_rpc_url.clone(),
-        config.service_manager_address.clone(),
-    )?);
+    let signing_log = SigningLog::open(config.data_path("signed_attestations.jsonl"))?;
+    info!("Signing log holds {} attestations", signing_log.len());
+    let mut signer = AttestationSigner::from_config(&config)
+        .await?
+        .with_signing_log(signing_log)
+        .with_nonces(nonces)
+        .with_gas_policy(GasPolicy {
+            max_gas_price_gwei: config.l1_max_gas_price_gwei,
+            gas_limit: config.l1_gas_limit,
+        })
+        .with_retry_policy(retry.clone());
+    if let Some(fence) = &fence {
+        signer = signer.with_fence(fence.clone());
+    }
+    let signer = Arc::new(signer);
 
-    // Spawn scanner task
-    let scanner_handle = tokio::spawn(async move {
-        if let Err(e) = scanner.run().await {
-            error!("Scanner error: {}", e);
+    // Stake-weighted quorum check against the operator registry
+    let stake_registry = Arc::new(StakeRegistry::new(
+        signer.provider(),
+        signer.service_manager_address(),
+    ));
+
+    // Peer operator reputation, fed from on-chain dispatches
+    let reputation = Arc::new(ReputationTracker::new());
+    let mut observer = DispatchObserver::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        reputation.clone(),
+        config.l1_start_block,
+    );
+    let observer_registry = stake_registry.clone();
+    tokio::spawn(async move {
+        loop {
+            match observer_registry.operators().await {
+                Ok(operators) => {
+                    let active: Vec<_> = operators
+                        .iter()
+                        .filter(|op| op.is_active)
+                        .map(|op| op.address)
+                        .collect();
+                    if let Err(e) = observer.poll(&active).await {
+                        warn!("Dispatch observer error: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to read operator registry: {}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        }
+    });
+
+    // Cross-check deposits with peer sentinels before signing
+    let signed = Arc::new(SignedDigests::new());
+    let peer_checker = Arc::new(PeerChecker::new(
+        config.peer_sentinels.clone(),
+        config.peer_confirmations_required,
+        Duration::from_secs(10),
+    )?
+    .with_confirm_deadline(Duration::from_secs(config.peer_confirm_deadline_secs))
+    .with_reputation(reputation.clone()));
+
+    // Publish signed heartbeats
+    let heartbeat = HeartbeatPublisher::new(
+        signer.clone(),
+        scanner.progress(),
+        config.aggregator_url.clone(),
+        Duration::from_secs(config.heartbeat_interval_secs),
+    );
+    let latest_heartbeat = heartbeat.latest();
+    tokio::spawn(heartbeat.run());
+
+    // Claim AVS rewards on a schedule
+    if let Some(claimer) = RewardsClaimer::from_config(&config, signer.clone())? {
+        tokio::spawn(claimer.run());
+    }
+
+    // Per-shard vault accounting
+    let shards = Arc::new(ShardSet::new(
+        config.shards(),
+        store.clone(),
+        config.release_queue_path.as_ref().map(Into::into),
+    ));
+
+    // Watch L1 for finalized withdrawals and queue ZEC releases
+    let mut payouts = None;
+    if let Some(queue_path) = &config.release_queue_path {
+        let mut watcher = WithdrawalWatcher::new(
+            signer.provider(),
+            signer.service_manager_address(),
+            config.l1_finality_depth,
+            config.l1_start_block,
+            queue_path.into(),
+            shards.clone(),
+        )?;
+        if let Some(path) = &config.payout_policy_path {
+            let engine = Arc::new(PayoutPolicyEngine::new(
+                PayoutPolicy::load(path)?,
+                queue_path.into(),
+                signer.audit_log(),
+            )?);
+            info!("Releases checked against payout policy {}", path);
+            watcher = watcher.with_policy(engine.clone());
+            payouts = Some(engine);
+        }
+        tokio::spawn(watcher.run(Duration::from_secs(30)));
+    }
+
+    // Refund rejected deposits through the payout queue
+    let refunds = Arc::new(RefundProcessor::new(
+        store.clone(),
+        config.data_path("refunds.jsonl"),
+        config.refund_fee_zatoshi,
+    ));
+    tokio::spawn(refunds.clone().run(Duration::from_secs(60)));
+
+    // Flag deposits sent to the vault's transparent address for refund
+    if let Some(address) = &config.vault_transparent_address {
+        let watcher = TransparentWatcher::new(
+            config.lightwalletd_url.clone(),
+            ClientTls::from_config(&config),
+            address,
+            store.clone(),
+            config.confirmation_depth,
+            config.vault_birthday_height,
+        )?
+        .with_retry_policy(retry.clone());
+        tokio::spawn(watcher.run(Duration::from_secs(60)));
+    }
+
+    // Track claims of attested deposits
+    let claims = ClaimMonitor::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        config.l1_start_block,
+        store.clone(),
+        Duration::from_secs(config.claim_timeout_secs),
+        config.data_path("claims.json"),
+    )?;
+    tokio::spawn(claims.run(Duration::from_secs(30)));
+
+    // Optional Aztec node for recipient validation
+    let aztec = config
+        .aztec_node_url
+        .clone()
+        .map(|url| AztecClient::new(url, Duration::from_secs(10)))
+        .transpose()?;
+
+    // Follow dispatched L1→L2 messages through the inbox to L2
+    if config.portal_tracking {
+        let portal = PortalTracker::new(
+            signer.provider(),
+            signer.service_manager_address(),
+            config
+                .aztec_node_url
+                .clone()
+                .map(|url| AztecClient::new(url, Duration::from_secs(10)))
+                .transpose()?,
+            store.clone(),
+        );
+        tokio::spawn(portal.run(Duration::from_secs(30)));
+    }
+
+    // Compliance screening of recipients, if the operator plugged a provider in
+    let screener = match &config.screening_url {
+        Some(url) => Screener::new(
+            Arc::new(HttpScreening::new(url.clone(), config.screening_api_key.clone())?),
+            Duration::from_secs(config.screening_cache_secs),
+            Duration::from_secs(config.screening_timeout_secs),
+        ),
+        None => Screener::disabled(),
+    };
+    info!("Recipient screening: {}", screener.provider_name());
+
+    // Lifecycle events for downstream consumers
+    let mut sinks: Vec<Arc<dyn events::EventSink>> = Vec::new();
+    if let Some(url) = &config.event_bus_url {
+        let publisher = bus::publisher(
+            url,
+            &config.event_bus_topic_prefix,
+            config.event_bus_format,
+        )?;
+        info!(
+            "Publishing {:?} events to {} under {}.*",
+            config.event_bus_format,
+            publisher.name(),
+            config.event_bus_topic_prefix
+        );
+        sinks.push(publisher);
+    }
+    for url in &config.webhook_urls {
+        sinks.push(Arc::new(webhooks::WebhookSink::new(
+            url.clone(),
+            config
+                .webhook_secret
+                .clone()
+                .map(secrecy::SecretString::new),
+            RetryPolicy::new(
+                config.webhook_max_retries,
+                Duration::from_millis(config.retry_delay_ms),
+            ),
+            config.data_path("webhook_dead_letter.jsonl"),
+        )?));
+    }
+    if !config.webhook_urls.is_empty() {
+        info!("Sending webhooks to {} URL(s)", config.webhook_urls.len());
+    }
+    let events = EventEmitter::spawn(sinks, events::DEFAULT_EVENT_QUEUE);
+
+    // Announce deposits still in the mempool
+    if config.mempool_preview {
+        let mempool = MempoolWatcher::new(
+            scanner.clone(),
+            config.lightwalletd_url.clone(),
+            ClientTls::from_config(&config),
+            events.clone(),
+        );
+        tokio::spawn(mempool.run());
+    }
+
+    // Mail critical alerts, if an SMTP server is configured
+    let alerter = match (&config.smtp_url, &config.alert_email_from) {
+        (Some(url), Some(from)) => {
+            info!("Mailing alerts to {}", config.alert_email_to.join(", "));
+            Alerter::spawn(
+                EmailNotifier::new(
+                    url,
+                    from,
+                    &config.alert_email_to,
+                    config.alert_email_subject.clone(),
+                    config.network.clone(),
+                )?,
+                Duration::from_secs(config.alert_email_interval_secs),
+            )
+        }
+        _ => Alerter::default(),
+    };
+    tokio::spawn(alerts::watch_scanner(
+        scanner.progress(),
+        Duration::from_secs(config.scanner_stall_minutes * 60),
+        alerter.clone(),
+    ));
+    let slashing = SlashingMonitor::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        signer.address(),
+        alerter.clone(),
+    );
+    tokio::spawn(slashing.run(Duration::from_secs(60)));
+
+    // Halt attestation if the vault's books stop adding up
+    let mut halt = HaltSwitch::new()
+        .with_alerts(alerter)
+        .persisted(config.data_path("halt.json"))?;
+    if let Some(fence) = &fence {
+        halt = halt.with_fence(fence.clone());
+    }
+    let halt = Arc::new(halt);
+    let lease_halt = halt.clone();
+    let reconciler = Reconciler::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        config.l1_start_block,
+        store.clone(),
+        config.release_queue_path.as_ref().map(Into::into),
+        config.reconcile_tolerance_zatoshi,
+        halt.clone(),
+    );
+    tokio::spawn(reconciler.run(Duration::from_secs(config.reconcile_interval_secs)));
+
+    // Tell systemd when we are ready and still making progress (primary deployment only)
+    if let Some(notifier) = systemd::Notifier::from_env().filter(|_| config.tenant.is_none()) {
+        tokio::spawn(systemd::run(
+            notifier,
+            scanner.progress(),
+            halt.clone(),
+            config.systemd_ready_lag_blocks,
+        ));
+    }
+
+    // Halt attestation if L1 dispatches contradict what the scanner saw
+    let watchdog = ConsistencyWatchdog::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        config.l1_start_block,
+        store.clone(),
+        scanner.progress(),
+        config.vault_birthday_height,
+        halt.clone(),
+    );
+    tokio::spawn(watchdog.run(Duration::from_secs(config.watchdog_interval_secs)));
+
+    // Halt attestation on anomalous deposit patterns
+    let anomalies = Arc::new(AnomalyDetector::new(
+        Duration::from_secs(config.anomaly_window_secs),
+        AnomalyThresholds {
+            max_deposits: config.anomaly_max_deposits,
+            max_value: config.anomaly_max_value_zatoshi,
+            max_secret_repeats: config.anomaly_max_secret_repeats,
+            max_memo_failures: config.anomaly_max_memo_failures,
+        },
+        halt.clone(),
+    ));
+    tokio::spawn(
+        anomalies
+            .clone()
+            .run(scanner.progress(), Duration::from_secs(30)),
+    );
+
+    // Header-chain evidence from a full node, if enabled
+    let evidence = if config.attach_evidence {
+        let rpc = ZcashRpcClient::new(
+            config.zcash_rpc_url.clone().unwrap_or_default(),
+            Duration::from_secs(30),
+        )?
+        .with_auth(RpcAuth::from_config(&config));
+        Some(Arc::new(EvidenceCollector::new(
+            rpc,
+            config.confirmation_depth,
+            config.data_path("evidence"),
+        )?))
+    } else {
+        None
+    };
+
+    // Independent verification against a trusted full node, if enabled
+    let full_node = if config.verify_full_node {
+        let rpc = ZcashRpcClient::new(
+            config.zcash_rpc_url.clone().unwrap_or_default(),
+            Duration::from_secs(30),
+        )?
+        .with_auth(RpcAuth::from_config(&config));
+        Some(FullNodeVerifier::new(rpc, config.confirmation_depth))
+    } else {
+        None
+    };
+
+    // High-assurance mode: several independent sources must confirm each deposit
+    let sources = if config.confirmation_sources_required > 0 {
+        Some(SourceConfirmer::new(
+            &config.confirmation_sources,
+            config.confirmation_sources_required,
+            config.confirmation_depth,
+        )?)
+    } else {
+        None
+    };
+
+    // Per-deposit bounds and rolling daily cap
+    let mut limits = DepositLimits::open(
+        config.min_deposit_zatoshi,
+        config.max_deposit_zatoshi,
+        config.daily_deposit_cap_zatoshi,
+        config.data_path("limits.json"),
+    )?;
+
+    // Bridge fee charged on every attested deposit
+    let fee_schedule = FeeSchedule {
+        flat_zatoshi: config.fee_flat_zatoshi,
+        bps: config.fee_bps,
+    };
+
+    // Optional price feed for quote-denominated bounds and fees
+    let oracle = config
+        .price_oracle
+        .as_deref()
+        .map(|spec| {
+            PriceOracle::new(
+                spec,
+                signer.provider(),
+                Duration::from_secs(config.price_max_age_secs),
+            )
+        })
+        .transpose()?;
+    let (min_deposit, max_deposit) = (config.min_deposit_zatoshi, config.max_deposit_zatoshi);
+    let (min_deposit_quote, max_deposit_quote, fee_flat_quote) = (
+        config.min_deposit_quote,
+        config.max_deposit_quote,
+        config.fee_flat_quote,
+    );
+
+    // Park deposits that blow their attestation budget
+    let stale = StaleDepositMonitor::new(
+        store.clone(),
+        scanner.progress(),
+        Duration::from_secs(config.deposit_timeout_secs),
+        config.deposit_timeout_blocks,
+    );
+    tokio::spawn(stale.run(Duration::from_secs(60)));
+
+    // Planned maintenance pauses signing and submission; deposits queue meanwhile
+    let maintenance = Arc::new(MaintenanceSchedule::parse(&config.maintenance_windows)?);
+    if !maintenance.is_empty() {
+        info!(
+            "Maintenance windows (UTC): {}",
+            config.maintenance_windows.join("; ")
+        );
+    }
+
+    // Hard caps on signing; crossing one halts attestation until re-armed
+    let rate_limiter = SigningRateLimiter::new(
+        SigningCaps {
+            per_minute: config.max_signatures_per_minute,
+            per_hour: config.max_signatures_per_hour,
+            daily_value: config.max_signed_value_per_day_zatoshi,
+        },
+        halt.clone(),
+    )
+    .persisted(config.data_path("signing_window.json"))?;
+    let rate_limiter = Arc::new(std::sync::Mutex::new(rate_limiter));
+
+    // What the sign and submit stages of every L1 target share with the batcher
+    let stage_context = StageContext {
+        halt: halt.clone(),
+        rate_limiter,
+        store: store.clone(),
+        events: events.clone(),
+        quorum_threshold_bps: config.quorum_threshold_bps,
+        leader_timeout: Duration::from_secs(config.leader_timeout_secs),
+        submission_capacity: capacities.submission,
+        maintenance,
+        reorgs: reorgs.clone(),
+        peers: peer_checker.clone(),
+        signed,
+    };
+
+    // Merkle-batched attestation, if enabled
+    let batcher = if config.attestation_mode == "batch" {
+        let batcher = Arc::new(BatchAttester::open(
+            signer.clone(),
+            config.l1_start_block,
+            stake_registry.clone(),
+            &stage_context,
+            config.batch_max_size,
+            config.data_path("batches.json"),
+        )?);
+        tokio::spawn(
+            batcher
+                .clone()
+                .run(Duration::from_secs(config.batch_epoch_secs)),
+        );
+        Some(batcher)
+    } else {
+        None
+    };
+
+    // Operator-only admin routes
+    let admin_router = config.admin_token.clone().map(|token| {
+        let router = admin::router(admin::AdminState {
+            token: token.clone(),
+            store: store.clone(),
+            deposit_sender: admin_deposit_tx,
+            halt: halt.clone(),
+            payouts: payouts.clone(),
+            refunds: refunds.clone(),
+        });
+        #[cfg(feature = "profiling")]
+        let router = router.merge(profiling::router(token));
+        router
+    });
+
+    // Serve the status API
+    let status_state = status::StatusState {
+        version: env!("CARGO_PKG_VERSION"),
+        network: config.network.clone(),
+        tenant: config.tenant.clone(),
+        reputation: reputation.clone(),
+        signed: stage_context.signed.clone(),
+        heartbeat: latest_heartbeat,
+        halt: halt.clone(),
+        shards: shards.clone(),
+        batches: batcher.clone(),
+        evidence: evidence.clone(),
+        store: store.clone(),
+        maintenance: stage_context.maintenance.clone(),
+    };
+    let status_addr = config.status_addr.parse()?;
+    tokio::spawn(async move {
+        if let Err(e) = status::serve(status_addr, status_state, admin_router).await {
+            error!("Status API error: {}", e);
         }
     });
 
-    // Process deposits and sign attestations
-    let signer_clone = signer.clone();
-    let attestation_handle = tokio::spawn(async move {
-        let mut nonce: u64 = 0;
+    // Fetch, decrypt and parse stages, unless the standby's are running already
+    let scanner_handle = match warm_scanner {
+        Some(handle) => handle,
+        None => {
+            let spill_dir = config.data_path("spill");
+            let decrypt = DecryptSettings::from_config(&config);
+            let scanner = scanner.clone();
+            tokio::spawn(async move {
+                if let Err(e) = scanner.run(capacities, spill_dir, decrypt).await {
+                    error!("Scanner error: {}", e);
+                }
+            })
+        }
+    };
 
-        while let Some(payload) = deposit_rx.recv().await {
+    // Sign and submit stages of every L1 target, each with its own queues
+    let extra_targets = match &config.l1_targets_path {
+        Some(path) => load_targets(path)?,
+        None => Vec::new(),
+    };
+    let router = TargetRouter::new(&extra_targets);
+    let mut admitted = HashMap::new();
+    let mut stage_handles = Vec::new();
+    let mut target_signers = vec![(
+        targets::PRIMARY_TARGET.to_string(),
+        signer.clone(),
+        config.l1_start_block,
+    )];
+    for target in &extra_targets {
+        let log = SigningLog::open(config.data_path(&format!("signed_attestations_{}.jsonl", target.name)))?;
+        let target_signer = Arc::new(signer.for_target(target, log).await?);
+        info!(
+            "  L1 target {}: {:?} (shards {:?})",
+            target.name,
+            target_signer.service_manager_address(),
+            target.shards
+        );
+        target_signers.push((target.name.clone(), target_signer, target.start_block));
+    }
+    let rescan_signers: Vec<_> = target_signers
+        .iter()
+        .map(|(_, signer, start_block)| (signer.clone(), *start_block))
+        .collect();
+    for (name, target_signer, start_block) in target_signers {
+        let (admitted_tx, admitted_rx) = mpsc::channel::<BridgePayload>(capacities.signing);
+        let (sign_handle, submit_handle) = targets::spawn_stages(
+            name.clone(),
+            target_signer,
+            start_block,
+            admitted_rx,
+            &stage_context,
+        );
+        stage_handles.push(sign_handle);
+        stage_handles.push(submit_handle);
+        admitted.insert(name, admitted_tx);
+    }
+
+    // Persist stage: record each deposit and screen it before signing
+    let birthday_height = config.vault_birthday_height;
+    let persist_handle = tokio::spawn(async move {
+        while let Some(mut payload) = deposit_rx.recv().await {
             info!(
                 "Processing deposit: {} zatoshi from tx {}",
                 payload.amount,
                 hex::encode(&payload.tx_hash[..8])
             );
+            let key = payload_key(&payload);
+            reputation.note_deposit_detected(key.clone());
 
-            // Sign attestation
-            match signer_clone.sign_attestation(&payload, nonce).await {
-                Ok(attestation) => {
-                    info!("Attestation signed successfully");
+            if reorgs.is_orphaned(&payload) {
+                warn!("Deposit {} is from a reorged-out block, skipping", key);
+                continue;
+            }
+            match store.insert_detected(&payload) {
+                Ok(record) if record.status != DepositStatus::Detected => {
+                    info!("Deposit {} already {:?}, skipping", key, record.status);
+                    continue;
+                }
+                Ok(_) => events.emit(BridgeEvent::new(EventKind::DepositDetected, &payload)),
+                Err(e) => {
+                    error!("Failed to record deposit: {}", e);
+                    continue;
+                }
+            }
+            if let Some(rescan) = rescan.filter(|r| r.already_signed(&payload, &rescan_signers)) {
+                match rescan.dispatch(&payload, &rescan_signers).await {
+                    Ok(Some(event)) => match event.mark_submitted(&store) {
+                        Ok(_) => info!(
+                            "Deposit {} was dispatched before the rescan in L1 tx {:?}",
+                            key, event.l1_tx_hash
+                        ),
+                        Err(e) => error!("Failed to record dispatch of {}: {}", key, e),
+                    },
+                    Ok(None) => error!(
+                        "Deposit {} was signed before the rescan but not dispatched; check L1",
+                        key
+                    ),
+                    Err(e) => {
+                        warn!("L1 dispatch of {} unavailable, retrying: {}", key, e);
+                        retry_later(&pending_tx, payload);
+                    }
+                }
+                continue;
+            }
+            anomalies.observe_deposit(&payload);
 
-                    // Submit to L1
-                    match signer_clone.submit_attestation(&attestation).await {
-                        Ok(tx_hash) => {
-                            info!("Attestation submitted to L1: {}", tx_hash);
-                            nonce += 1;
-                        }
-                        Err(e) => {
-                            error!("Failed to submit attestation: {}", e);
+            // Reject payloads the contract would never accept
+            if let Some(reason) = rejection_reason(&payload) {
+                warn!("Rejecting deposit {}: {}", key, reason);
+                if let Err(e) = store.reject(&key, reason) {
+                    error!("Failed to record rejection: {}", e);
+                }
+                continue;
+            }
+
+            // Reject deposits mined before the vault existed
+            if payload.block_height < birthday_height {
+                warn!("Rejecting deposit {}: below the vault birthday height", key);
+                if let Err(e) = store.reject(&key, "below the vault birthday height") {
+                    error!("Failed to record rejection: {}", e);
+                }
+                continue;
+            }
+
+            // Reject deposits for an L1 target this sentinel does not serve
+            let target = match router.route(&payload) {
+                Ok(target) => target.to_string(),
+                Err(reason) => {
+                    warn!("Rejecting deposit {}: {}", key, reason);
+                    if let Err(e) = store.reject(&key, reason) {
+                        error!("Failed to record rejection: {}", e);
+                    }
+                    continue;
+                }
+            };
+
+            // Convert quote-denominated bounds and fees at the current price
+            let mut deposit_fees = fee_schedule;
+            if let Some(oracle) = &oracle {
+                let price = match oracle.price().await {
+                    Ok(price) => price,
+                    Err(e) => {
+                        warn!("Price unavailable, deposit {} retried later: {}", key, e);
+                        retry_later(&pending_tx, payload);
+                        continue;
+                    }
+                };
+                let to_zatoshi = |quote: Option<u128>, default: u64| match quote {
+                    Some(q) => price.to_zatoshi(q),
+                    None => Some(default),
+                };
+                // Fail closed on an unusable price rather than refunding everything
+                let (Some(min), Some(max), Some(flat)) = (
+                    to_zatoshi(min_deposit_quote, min_deposit),
+                    to_zatoshi(max_deposit_quote, max_deposit),
+                    to_zatoshi(fee_flat_quote, deposit_fees.flat_zatoshi),
+                ) else {
+                    warn!("Price {:?} out of range, deposit {} retried later", price, key);
+                    retry_later(&pending_tx, payload);
+                    continue;
+                };
+                limits.set_bounds(min, max);
+                deposit_fees.flat_zatoshi = flat;
+            }
+
+            // Out-of-bounds deposits go to the refund flow instead of the signer
+            if let Err(reason) = limits.admit(&key, payload.amount, now_secs()) {
+                warn!("Rejecting deposit {}: {}", key, reason);
+                if let Err(e) = store.reject(&key, reason) {
+                    error!("Failed to record rejection: {}", e);
+                }
+                continue;
+            }
+
+            // Deduct the bridge fee, fixed at first admission so a resumed
+            // deposit signs the same fields; deposits that only cover the fee
+            // are refunded
+            payload.fee = match store.fix_fee(&key, deposit_fees.fee_for(payload.amount)) {
+                Ok(fee) => fee,
+                Err(e) => {
+                    error!("Failed to record fee: {}", e);
+                    continue;
+                }
+            };
+            if payload.net_amount() == 0 {
+                warn!("Rejecting deposit {}: does not cover the bridge fee", key);
+                if let Err(e) = store.reject(&key, "does not cover the bridge fee") {
+                    error!("Failed to record rejection: {}", e);
+                }
+                continue;
+            }
+
+            // Don't mint to Aztec addresses nobody can reach
+            if let Some(aztec) = &aztec {
+                match aztec.is_registered(&payload.aztec_address).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        warn!("Rejecting deposit {}: unknown Aztec recipient", key);
+                        if let Err(e) = store.reject(&key, "unknown Aztec recipient") {
+                            error!("Failed to record rejection: {}", e);
                         }
+                        continue;
+                    }
+                    Err(e) => warn!("Aztec recipient check unavailable: {}", e),
+                }
+            }
+
+            // Screen recipients with the operator's compliance provider
+            match screener.check(&payload).await {
+                Ok(RiskVerdict::Allow) => {}
+                Ok(RiskVerdict::Deny(reason)) => {
+                    let reason = format!("screening: {}", reason);
+                    warn!("Rejecting deposit {}: {}", key, reason);
+                    if let Err(e) = store.reject(&key, reason) {
+                        error!("Failed to record rejection: {}", e);
                     }
+                    continue;
                 }
                 Err(e) => {
-                    error!("Failed to sign attestation: {}", e);
+                    warn!("Screening unavailable, deposit {} retried later: {}", key, e);
+                    retry_later(&pending_tx, payload);
+                    continue;
+                }
+            }
+
+            // Hold the deposit, and the queue behind it, while attestation is halted
+            halt.wait_out("deposit processing").await;
+
+            // Refuse to sign if peers saw something different
+            if let Err(e) = peer_checker.confirm(&payload).await {
+                error!("Refusing to sign deposit: {}", e);
+                continue;
+            }
+
+            // Never sign on lightwalletd's word alone when a trusted node is configured
+            if let Some(full_node) = &full_node {
+                match full_node.verify(&payload.tx_hash, payload.block_height).await {
+                    Ok(()) => {}
+                    Err(SentinelError::InvalidPayload(e)) => {
+                        error!("Refusing to sign deposit {}: {}", key, e);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Full-node check unavailable, deposit {} retried later: {}", key, e);
+                        retry_later(&pending_tx, payload);
+                        continue;
+                    }
+                }
+            }
+
+            // Wait until enough independent sources have confirmed the deposit
+            if let Some(sources) = &sources {
+                if let Err(e) = sources.confirm(&payload.tx_hash, payload.block_height).await {
+                    warn!("Deposit {} retried later: {}", key, e);
+                    retry_later(&pending_tx, payload);
+                    continue;
+                }
+            }
+
+            // Attach header-chain evidence; refuse to sign if it contradicts the deposit
+            if let Some(evidence) = &evidence {
+                match evidence.collect(&payload.tx_hash, payload.block_height).await {
+                    Ok(_) => {}
+                    Err(SentinelError::InvalidPayload(e)) => {
+                        error!("Refusing to sign deposit {}: {}", key, e);
+                        continue;
+                    }
+                    Err(e) => warn!("No header-chain evidence for deposit {}: {}", key, e),
+                }
+            }
+
+            // Count towards the daily cap only once nothing holds the deposit back
+            if let Err(e) = limits.record(&key, payload.amount, now_secs()) {
+                error!("Failed to record deposit {} against the daily cap: {}", key, e);
+                continue;
+            }
+
+            // In batch mode the deposit waits for the next Merkle root
+            if let Some(batcher) = &batcher {
+                if let Err(e) = batcher.push(payload) {
+                    error!("Refusing to batch deposit {}: {}", key, e);
                 }
+                continue;
+            }
+
+            let Some(admitted_tx) = admitted.get(&target) else {
+                continue;
+            };
+            if admitted_tx.send(payload).await.is_err() {
+                break;
             }
         }
     });
@@ -135,13 +1302,231 @@ async fn main() -> Result<()> {
                 error!("Scanner task panicked: {}", e);
             }
         }
-        result = attestation_handle => {
+        result = persist_handle => {
             if let Err(e) = result {
-                error!("Attestation task panicked: {}", e);
+                error!("Persist stage panicked: {}", e);
             }
         }
+        (result, _, _) = futures::future::select_all(stage_handles) => {
+            if let Err(e) = result {
+                error!("Sign or submit stage panicked: {}", e);
+            }
+        }
+        Some(reason) = async { Some(lease_lost?.await.unwrap_or_else(|e| e.to_string())) } => {
+            lease_halt.trip("leader_lease", reason.clone());
+            anyhow::bail!("Lost the leader lease: {}", reason);
+        }
     }
 
     info!("Sentinel shutting down...");
     Ok(())
 }
+
+/// Hand `payload` back to the persist stage after [`PENDING_RETRY`]; it stops
+/// coming back once it is attested, rejected or stale
+fn retry_later(pending_tx: &mpsc::Sender<BridgePayload>, payload: BridgePayload) {
+    let pending_tx = pending_tx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(PENDING_RETRY).await;
+        let _ = pending_tx.send(payload).await;
+    });
+}
+
+/// Scanner of the deployment's vault, delivering deposits to `deposit_tx`
+fn vault_scanner(
+    config: &SentinelConfig,
+    deposit_tx: mpsc::Sender<BridgePayload>,
+    reorgs: Arc<ReorgLog>,
+    checkpoint: CheckpointStore,
+) -> Result<Scanner> {
+    // Plugins for memo types other than bridge deposits
+    let mut memo_handlers = HandlerRegistry::new();
+    for plugin in &config.memo_plugins {
+        if let Some((msg_type, command)) = plugin.split_once('=') {
+            memo_handlers.register(msg_type, Arc::new(CommandHandler::new(command.to_string())))?;
+        }
+    }
+    if !config.memo_plugins.is_empty() {
+        info!("  Memo plugins: {}", memo_handlers.types().join(", "));
+    }
+
+    // Confirmed blocks kept on disk across restarts and rescans
+    let block_cache = match config.block_cache_mb {
+        0 => None,
+        mb => {
+            let cache = BlockCache::open(config.data_path("block_cache"), mb * 1024 * 1024)?;
+            info!("{} blocks cached on disk", cache.len());
+            Some(cache)
+        }
+    };
+
+    Ok(Scanner::new(
+        config.lightwalletd_url.clone(),
+        &config.shards(),
+        config.confirmation_depth,
+        deposit_tx,
+    )?
+    .with_spend_detection(config.detect_spends)
+    .with_tls(ClientTls::from_config(config))
+    .with_fallback_endpoints(config.lightwalletd_fallbacks(), config.lightwalletd_max_lag_blocks)
+    .with_block_source(backend::block_source(config)?)
+    .with_handlers(memo_handlers)
+    .with_retry_policy(RetryPolicy::from_config(config))
+    .with_memo_domain(config.memo_domain.clone())
+    .with_reorg_log(reorgs)
+    .with_birthday_height(config.vault_birthday_height)
+    .with_checkpoint(checkpoint)
+    .with_rescan_from(config.rescan_from_height)
+    .with_block_cache(block_cache))
+}
+
+/// A standby that has taken the leader lease, with the scanner it kept warm
+struct WarmStandby {
+    /// The leader lease
+    lease: Arc<LeaderLease>,
+
+    /// The lease as taken over
+    held: Lease,
+
+    /// Fence open while the lease is held
+    fence: Arc<LeaseFence>,
+
+    /// The standby's scanner, still running
+    scanner: Arc<Scanner>,
+
+    /// Task running the scanner
+    scanner_handle: tokio::task::JoinHandle<()>,
+
+    /// Reorgs the scanner saw, to be applied to the store once opened
+    reorgs: Arc<ReorgLog>,
+
+    /// Sender the scanner delivers deposits through
+    deposit_tx: mpsc::Sender<BridgePayload>,
+
+    /// Receiver of the scanner's deposits
+    deposit_rx: mpsc::Receiver<BridgePayload>,
+
+    /// Deposits found above the old leader's last checkpoint
+    deposits: Vec<BridgePayload>,
+}
+
+/// Scan as a warm standby until this instance takes the leader lease
+///
+/// The standby's scanner resumes from the leader's checkpoint but leaves the
+/// checkpoint and the shared stores alone until the lease is held. It keeps
+/// the deposits it finds above the leader's checkpoint, which the leader has
+/// not yet taken in, and hands them over with the running scanner.
+async fn stand_by(config: &SentinelConfig) -> Result<WarmStandby> {
+    let holder = config
+        .ha_instance_id
+        .clone()
+        .unwrap_or_else(lease::default_holder);
+    let lease = Arc::new(LeaderLease::new(
+        config.data_path("leader.lease"),
+        holder,
+        Duration::from_secs(config.ha_lease_secs),
+    ));
+    let fence = Arc::new(LeaseFence::new(lease.clone()));
+
+    let capacities = StageCapacities::from_config(config);
+    let (deposit_tx, mut deposit_rx) = mpsc::channel::<BridgePayload>(capacities.deposits);
+    let reorgs = Arc::new(ReorgLog::default());
+    let checkpoint_path = config.data_path("scan_checkpoint.json");
+    let checkpoint = CheckpointStore::open(&checkpoint_path)?.with_fence(fence.clone());
+    let scanner = Arc::new(vault_scanner(
+        config,
+        deposit_tx.clone(),
+        reorgs.clone(),
+        checkpoint,
+    )?);
+    let progress = scanner.progress();
+    let spill_dir = std::env::temp_dir().join(format!("sentinel-standby-{}", std::process::id()));
+    let decrypt = DecryptSettings::from_config(config);
+    let warm = scanner.clone();
+    let scanner_handle = tokio::spawn(async move {
+        if let Err(e) = warm.run(capacities, spill_dir, decrypt).await {
+            error!("Scanner error: {}", e);
+        }
+    });
+
+    // The leader has taken in every deposit at or below its checkpoint
+    let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let held_deposits = tokio::spawn(async move {
+        let mut deposits: Vec<BridgePayload> = Vec::new();
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => break,
+                Some(payload) = deposit_rx.recv() => {
+                    let checkpointed = CheckpointStore::open(&checkpoint_path)
+                        .ok()
+                        .and_then(|checkpoint| checkpoint.loaded().map(|c| c.height))
+                        .unwrap_or(0);
+                    deposits.retain(|held| held.block_height > checkpointed);
+                    if payload.block_height > checkpointed {
+                        deposits.push(payload);
+                    }
+                }
+            }
+        }
+        (deposit_rx, deposits)
+    });
+
+    // systemd sees the standby as ready and alive while it polls the lease
+    let notifier = systemd::Notifier::from_env().filter(|_| config.tenant.is_none());
+    let mut last_holder = String::new();
+    let held = lease
+        .acquire(|current| {
+            if current.holder != last_holder {
+                info!("Standing by, leader lease held by {}", current.holder);
+                last_holder = current.holder.clone();
+            }
+            if let Some(notifier) = &notifier {
+                let status = format!(
+                    "READY=1\nWATCHDOG=1\nSTATUS=Standby at height {}; leader lease held by {}",
+                    progress.synced_height(),
+                    current.holder
+                );
+                if let Err(e) = notifier.notify(&status) {
+                    warn!("systemd notification failed: {}", e);
+                }
+            }
+        })
+        .await?;
+    fence.open(&held);
+
+    let _ = stop_tx.send(());
+    let (deposit_rx, deposits) = held_deposits.await?;
+    info!(
+        "Taking over at scanned height {} with {} deposits above the old leader's checkpoint",
+        progress.synced_height(),
+        deposits.len()
+    );
+    Ok(WarmStandby {
+        lease,
+        held,
+        fence,
+        scanner,
+        scanner_handle,
+        reorgs,
+        deposit_tx,
+        deposit_rx,
+        deposits,
+    })
+}
+
+/// Reason a deposit can never be attested, if any
+fn rejection_reason(payload: &BridgePayload) -> Option<&'static str> {
+    if payload.amount == 0 {
+        return Some("zero amount");
+    }
+    if payload.secret_hash == [0u8; 32] {
+        return Some("empty secret hash");
+    }
+    if payload.aztec_address == [0u8; 32] {
+        return Some("empty Aztec address");
+    }
+    if payload.memo_amount.is_some_and(|a| a != payload.amount) {
+        return Some("note value does not match memo amount");
+    }
+    None
+}
@@ -0,0 +1,338 @@
+//! Scheduled maintenance windows
+//!
+//! Planned work on the contracts, such as an upgrade, should not race live
+//! attestations. `MAINTENANCE_WINDOWS` lists recurring windows, separated by
+//! `;`, each a five-field cron expression (UTC) followed by a duration:
+//!
+//! ```text
+//! MAINTENANCE_WINDOWS=0 2 * * sun 90m; 30 14 1 * * 2h
+//! ```
+//!
+//! While a window is open the sign and submit stages hold their next item.
+//! Deposits keep being scanned and recorded and wait in the pipeline's queues
+//! (spilling to disk past the spool limit), and are flushed in order once the
+//! window closes. The schedule and the open window are served at
+//! `GET /maintenance`.
+
+use crate::clock::{civil_date, now_secs};
+use crate::error::SentinelError;
+use serde::Serialize;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::info;
+
+/// Seconds per day
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Days searched for the next start; covers patterns like February 29
+const SEARCH_DAYS: u64 = 4 * 366 + 1;
+
+/// Day-of-week names, from Sunday
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Month names, from January
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Values one cron field matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    /// Bit `n` set if value `n` matches
+    bits: u64,
+
+    /// Written as `*` or `*/n`, which matters for day matching
+    star: bool,
+}
+
+impl Field {
+    /// Parse `a`, `a-b`, `*`, each with an optional `/step`, comma-separated
+    fn parse(s: &str, min: u32, max: u32, names: &[&str]) -> Result<Self, String> {
+        let value = |v: &str| -> Result<u32, String> {
+            let lower = v.to_ascii_lowercase();
+            let n = match names.iter().position(|name| *name == lower) {
+                Some(i) => i as u32 + min,
+                None => v.parse().map_err(|_| format!("invalid value {}", v))?,
+            };
+            if n < min || n > max {
+                return Err(format!("{} out of range {}-{}", n, min, max));
+            }
+            Ok(n)
+        };
+
+        let mut bits = 0u64;
+        for part in s.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (range, step),
+                    _ => return Err(format!("invalid step {}", step)),
+                },
+                None => (part, 1),
+            };
+            let (lo, hi) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((lo, hi)) => (value(lo)?, value(hi)?),
+                // `a/n` runs from a to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            };
+            if lo > hi {
+                return Err(format!("empty range {}", range));
+            }
+            for v in (lo..=hi).step_by(step as usize) {
+                bits |= 1 << v;
+            }
+        }
+        Ok(Self {
+            bits,
+            star: s.starts_with('*'),
+        })
+    }
+
+    fn contains(self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+/// Parse a duration such as `90m`, `2h` or `3600s`; plain numbers are minutes
+fn parse_duration(s: &str) -> Result<u64, String> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "m"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration {}", s))?;
+    let secs = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 3_600,
+        "d" => number * DAY_SECS,
+        _ => return Err(format!("invalid duration unit in {}", s)),
+    };
+    if secs < 60 {
+        return Err(format!("duration {} shorter than a minute", s));
+    }
+    Ok(secs)
+}
+
+/// A recurring maintenance window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// The window as configured
+    spec: String,
+
+    minute: Field,
+    hour: Field,
+    day_of_month: Field,
+    month: Field,
+    day_of_week: Field,
+
+    /// Seconds the window stays open
+    duration: u64,
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week, duration] = fields[..] else {
+            return Err(format!(
+                "maintenance window {:?} needs five cron fields and a duration",
+                s
+            ));
+        };
+        let context = |e: String| format!("maintenance window {:?}: {}", s, e);
+
+        let mut day_of_week = Field::parse(day_of_week, 0, 7, &WEEKDAYS).map_err(context)?;
+        // Both 0 and 7 are Sunday
+        if day_of_week.contains(7) {
+            day_of_week.bits |= 1;
+        }
+        let window = Self {
+            spec: fields.join(" "),
+            minute: Field::parse(minute, 0, 59, &[]).map_err(context)?,
+            hour: Field::parse(hour, 0, 23, &[]).map_err(context)?,
+            day_of_month: Field::parse(day_of_month, 1, 31, &[]).map_err(context)?,
+            month: Field::parse(month, 1, 12, &MONTHS).map_err(context)?,
+            day_of_week,
+            duration: parse_duration(duration).map_err(context)?,
+        };
+        if window.next_start(0).is_none() {
+            return Err(context("never occurs".to_string()));
+        }
+        Ok(window)
+    }
+}
+
+impl MaintenanceWindow {
+    /// Whether the window opens on day `days` after the epoch
+    fn on_day(&self, days: u64) -> bool {
+        let (_, month, day) = civil_date(days);
+        // 1970-01-01 was a Thursday
+        let weekday = ((days + 4) % 7) as u32;
+        if !self.month.contains(month) {
+            return false;
+        }
+        // As in cron: if both day fields are restricted, either may match
+        if self.day_of_month.star || self.day_of_week.star {
+            self.day_of_month.contains(day) && self.day_of_week.contains(weekday)
+        } else {
+            self.day_of_month.contains(day) || self.day_of_week.contains(weekday)
+        }
+    }
+
+    /// First time the window opens at or after `from`
+    fn next_start(&self, from: u64) -> Option<u64> {
+        let first_day = from / DAY_SECS;
+        for days in (first_day..first_day + SEARCH_DAYS).filter(|d| self.on_day(*d)) {
+            for hour in (0..24).filter(|h| self.hour.contains(*h)) {
+                for minute in (0..60).filter(|m| self.minute.contains(*m)) {
+                    let start = days * DAY_SECS + u64::from(hour) * 3_600 + u64::from(minute) * 60;
+                    if start >= from {
+                        return Some(start);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Occurrence of the window open at `now`
+    fn open_at(&self, now: u64) -> Option<ScheduledWindow> {
+        let start = self.next_start((now + 1).saturating_sub(self.duration))?;
+        (start <= now).then(|| self.occurrence(start))
+    }
+
+    fn occurrence(&self, start: u64) -> ScheduledWindow {
+        ScheduledWindow {
+            spec: self.spec.clone(),
+            start,
+            end: start + self.duration,
+        }
+    }
+}
+
+/// One occurrence of a maintenance window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledWindow {
+    /// The window as configured
+    pub spec: String,
+
+    /// Unix timestamp the window opens
+    pub start: u64,
+
+    /// Unix timestamp the window closes
+    pub end: u64,
+}
+
+/// Schedule and state served by the status API
+#[derive(Debug, Serialize)]
+pub struct MaintenanceStatus {
+    /// Configured windows
+    pub windows: Vec<String>,
+
+    /// Window open now, if any
+    pub active: Option<ScheduledWindow>,
+
+    /// Next window to open
+    pub next: Option<ScheduledWindow>,
+}
+
+/// Every configured maintenance window
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceSchedule {
+    windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceSchedule {
+    /// Parse the windows of `MAINTENANCE_WINDOWS`
+    pub fn parse(specs: &[String]) -> Result<Self, SentinelError> {
+        Ok(Self {
+            windows: specs
+                .iter()
+                .map(|spec| spec.parse())
+                .collect::<Result<_, String>>()
+                .map_err(SentinelError::Config)?,
+        })
+    }
+
+    /// Whether no window is configured
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Window open at `now`, closing last if several overlap
+    pub fn active(&self, now: u64) -> Option<ScheduledWindow> {
+        self.windows
+            .iter()
+            .filter_map(|w| w.open_at(now))
+            .max_by_key(|w| w.end)
+    }
+
+    /// Next window to open after `now`
+    pub fn next(&self, now: u64) -> Option<ScheduledWindow> {
+        self.windows
+            .iter()
+            .filter_map(|w| w.next_start(now + 1).map(|start| w.occurrence(start)))
+            .min_by_key(|w| w.start)
+    }
+
+    /// Schedule and state at `now`
+    pub fn status(&self, now: u64) -> MaintenanceStatus {
+        MaintenanceStatus {
+            windows: self.windows.iter().map(|w| w.spec.clone()).collect(),
+            active: self.active(now),
+            next: self.next(now),
+        }
+    }
+
+    /// Return once no maintenance window is open, holding `stage` meanwhile
+    pub async fn wait_out(&self, stage: &str) {
+        while let Some(window) = self.active(now_secs()) {
+            info!(
+                "Maintenance window \"{}\" open until {}, {} paused",
+                window.spec, window.end, stage
+            );
+            let remaining = window.end.saturating_sub(now_secs()).max(1);
+            tokio::time::sleep(Duration::from_secs(remaining)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_windows() {
+        let schedule = MaintenanceSchedule::parse(&[
+            "0 2 * * sun 90m".to_string(),
+            "*/15 9-17 1,15 * * 5".to_string(),
+        ])
+        .unwrap();
+
+        // 2024-03-03 was a Sunday
+        let sunday = 19_785 * DAY_SECS;
+        assert_eq!(schedule.active(sunday + 3_600), None);
+        let open = schedule.active(sunday + 2 * 3_600 + 600).unwrap();
+        assert_eq!(open.start, sunday + 2 * 3_600);
+        assert_eq!(open.end, sunday + 2 * 3_600 + 90 * 60);
+        assert_eq!(schedule.active(open.end), None);
+        assert_eq!(schedule.next(sunday).unwrap().start, open.start);
+
+        // Next Sunday window is a week on; the first of the month comes earlier
+        let next = schedule.next(open.end).unwrap();
+        assert_eq!(next.start, sunday + 7 * DAY_SECS + 2 * 3_600);
+        let first = 19_814 * DAY_SECS; // 2024-04-01
+        let next = schedule.next(first).unwrap();
+        assert_eq!(next.start, first + 9 * 3_600);
+        assert_eq!(next.spec, "*/15 9-17 1,15 * * 5");
+
+        assert!("0 2 * *".parse::<MaintenanceWindow>().is_err());
+        assert!("61 2 * * * 1h".parse::<MaintenanceWindow>().is_err());
+        assert!("0 2 * * * 30s".parse::<MaintenanceWindow>().is_err());
+        assert!("0 0 30 feb * 1h".parse::<MaintenanceWindow>().is_err());
+    }
+}
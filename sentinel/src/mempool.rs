@@ -0,0 +1,148 @@
+//! Pending deposit preview from the mempool
+//!
+//! With `MEMPOOL_PREVIEW=true` the sentinel follows lightwalletd's
+//! `GetMempoolStream` and trial-decrypts every unconfirmed transaction
+//! against the vault keys. A deposit seen there gets a `deposit_pending`
+//! event so front-ends can show the bridge in flight. Nothing else happens
+//! to it: it is not stored, screened or attested until the scanner finds it
+//! `CONFIRMATION_DEPTH` deep, and a pending deposit may never be mined.
+//!
+//! The stream ends with every new block; the watcher reconnects and only
+//! announces transactions it did not see in the previous round.
+
+use crate::error::SentinelError;
+use crate::events::{BridgeEvent, EventEmitter, EventKind};
+use crate::lightwalletd::ClientTls;
+use crate::scanner::Scanner;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use zcash_client_backend::proto::service::{ChainSpec, Empty};
+
+/// Wait before reconnecting after the stream failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Unconfirmed transactions already looked at
+#[derive(Debug, Default)]
+struct SeenTransactions {
+    /// Seen in the previous stream
+    previous: HashSet<[u8; 32]>,
+
+    /// Seen in the current stream
+    current: HashSet<[u8; 32]>,
+}
+
+impl SeenTransactions {
+    /// Record `txid`; false if an earlier stream or this one already had it
+    fn first_sighting(&mut self, txid: [u8; 32]) -> bool {
+        self.current.insert(txid) && !self.previous.contains(&txid)
+    }
+
+    /// Start a new stream, forgetting transactions that left the mempool
+    fn next_stream(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+/// Announces vault deposits still in the mempool
+pub struct MempoolWatcher {
+    /// Scanner holding the vault keys and memo parser
+    scanner: Arc<Scanner>,
+
+    /// Lightwalletd URL
+    url: String,
+
+    /// TLS settings for lightwalletd
+    tls: ClientTls,
+
+    /// Where `deposit_pending` events go
+    events: EventEmitter,
+
+    /// Transactions already looked at
+    seen: SeenTransactions,
+}
+
+impl MempoolWatcher {
+    /// Watch the mempool of the lightwalletd at `url`
+    pub fn new(scanner: Arc<Scanner>, url: String, tls: ClientTls, events: EventEmitter) -> Self {
+        Self {
+            scanner,
+            url,
+            tls,
+            events,
+            seen: SeenTransactions::default(),
+        }
+    }
+
+    /// Follow one mempool stream until the next block ends it
+    async fn follow(&mut self) -> Result<(), SentinelError> {
+        let mut client = crate::lightwalletd::connect(&self.url, &self.tls).await?;
+        let tip = client
+            .get_latest_block(ChainSpec {})
+            .await?
+            .into_inner()
+            .height as u32;
+        let mut stream = client.get_mempool_stream(Empty {}).await?.into_inner();
+
+        while let Some(raw) = stream.message().await? {
+            let deposits = match self.scanner.preview_deposits(&raw.data, tip + 1) {
+                Ok(deposits) => deposits,
+                Err(e) => {
+                    debug!("Skipping unparseable mempool transaction: {}", e);
+                    continue;
+                }
+            };
+            let Some(first) = deposits.first() else {
+                continue;
+            };
+            if !self.seen.first_sighting(first.tx_hash) {
+                continue;
+            }
+            for deposit in &deposits {
+                info!(
+                    "Pending deposit of {} zatoshi in the mempool",
+                    deposit.amount
+                );
+                self.events
+                    .emit(BridgeEvent::new(EventKind::DepositPending, deposit));
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow the mempool forever
+    pub async fn run(mut self) {
+        info!("Previewing pending deposits from the mempool");
+        loop {
+            let followed = self.follow().await;
+            self.seen.next_stream();
+            if let Err(e) = followed {
+                warn!("Mempool stream failed: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_deposit_announced_once() {
+        let mut seen = SeenTransactions::default();
+        assert!(seen.first_sighting([1; 32]));
+        assert!(!seen.first_sighting([1; 32]));
+
+        // Still in the mempool after a block: not announced again
+        seen.next_stream();
+        assert!(!seen.first_sighting([1; 32]));
+        assert!(seen.first_sighting([2; 32]));
+
+        // Gone for a whole round, then back (e.g. after a reorg)
+        seen.next_stream();
+        seen.next_stream();
+        assert!(seen.first_sighting([1; 32]));
+    }
+}
@@ -0,0 +1,495 @@
+//! In-process mock L1 node
+//!
+//! Serves the slice of Ethereum JSON-RPC the signer and the L1 watchers use
+//! (transaction submission, receipts, `eth_call`, logs) from scriptable
+//! in-memory state, so signer logic is tested without anvil. Tests can make
+//! calls revert, hold transactions until blocks are mined, add latency, and
+//! reorg mined transactions back into the mempool.
+//!
+//! Built for tests and with the `mock-l1` feature.
+
+#![allow(dead_code)]
+
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use ethers::types::{Address, Bytes, Log, Transaction, TransactionReceipt, H256, U256, U64};
+use ethers::utils::{keccak256, rlp};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Gas reported by `eth_estimateGas`
+const MOCK_GAS: u64 = 500_000;
+
+/// Gas price reported by `eth_gasPrice` (1 gwei)
+const MOCK_GAS_PRICE: u64 = 1_000_000_000;
+
+/// A submitted transaction
+#[derive(Debug, Clone)]
+struct SentTx {
+    /// Decoded transaction
+    tx: Transaction,
+
+    /// Block it was mined in, if any
+    block: Option<u64>,
+}
+
+/// Scripted node state
+#[derive(Debug)]
+struct L1State {
+    /// Chain id
+    chain_id: u64,
+
+    /// Current block number
+    block_number: u64,
+
+    /// Bumped on every reorg so replacement blocks get fresh hashes
+    fork: u64,
+
+    /// Mine every transaction into its own block on arrival
+    automine: bool,
+
+    /// Delay before every response
+    latency: Duration,
+
+    /// Every submitted transaction by hash
+    txs: HashMap<H256, SentTx>,
+
+    /// Submitted but not yet mined, in arrival order
+    mempool: Vec<H256>,
+
+    /// Canned `eth_call` results by selector
+    calls: HashMap<[u8; 4], Vec<u8>>,
+
+    /// Revert reasons by selector, for calls and submissions
+    reverts: HashMap<[u8; 4], String>,
+
+    /// Nonces marked used without a dispatch
+    used_nonces: HashSet<u64>,
+
+    /// Logs returned by `eth_getLogs`
+    logs: Vec<Log>,
+}
+
+impl L1State {
+    /// Hash of block `number` on the current fork
+    fn block_hash(&self, number: u64) -> H256 {
+        let mut preimage = number.to_be_bytes().to_vec();
+        preimage.extend_from_slice(&self.fork.to_be_bytes());
+        H256(keccak256(preimage))
+    }
+
+    /// Mine the mempool into `count` new blocks (all of it into the first)
+    fn mine(&mut self, count: u64) {
+        for i in 0..count {
+            self.block_number += 1;
+            if i == 0 {
+                for hash in std::mem::take(&mut self.mempool) {
+                    if let Some(sent) = self.txs.get_mut(&hash) {
+                        sent.block = Some(self.block_number);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether `nonce` was used by a mined `verifyAndDispatch` or scripted
+    fn is_nonce_used(&self, nonce: u64) -> bool {
+        let selector = selector(crate::signer::VERIFY_AND_DISPATCH);
+        self.used_nonces.contains(&nonce)
+            || self.txs.values().any(|sent| {
+                let data = sent.tx.input.as_ref();
+                sent.block.is_some()
+                    && data.len() >= 4 + 8 * 32
+                    && data[..4] == selector
+                    && U256::from_big_endian(&data[4 + 7 * 32..4 + 8 * 32]) == U256::from(nonce)
+            })
+    }
+
+    /// Whether a mined `verifyAndDispatch` dispatched the note at
+    /// `output_index` of `tx_hash`
+    fn is_deposit_processed(&self, tx_hash: &[u8], output_index: U256) -> bool {
+        let selector = selector(crate::signer::VERIFY_AND_DISPATCH);
+        self.txs.values().any(|sent| {
+            let data = sent.tx.input.as_ref();
+            sent.block.is_some()
+                && data.len() >= 4 + 3 * 32
+                && data[..4] == selector
+                && data[4 + 32..4 + 2 * 32] == *tx_hash
+                && U256::from_big_endian(&data[4 + 2 * 32..4 + 3 * 32]) == output_index
+        })
+    }
+
+    /// Receipt for a mined transaction
+    fn receipt(&self, sent: &SentTx) -> Option<TransactionReceipt> {
+        let block = sent.block?;
+        Some(TransactionReceipt {
+            transaction_hash: sent.tx.hash,
+            block_number: Some(U64::from(block)),
+            block_hash: Some(self.block_hash(block)),
+            from: sent.tx.from,
+            to: sent.tx.to,
+            gas_used: Some(U256::from(MOCK_GAS / 2)),
+            cumulative_gas_used: U256::from(MOCK_GAS / 2),
+            effective_gas_price: Some(U256::from(MOCK_GAS_PRICE)),
+            status: Some(U64::one()),
+            ..TransactionReceipt::default()
+        })
+    }
+}
+
+/// Handle for scripting a mock L1 node
+#[derive(Clone)]
+pub struct MockL1 {
+    /// Shared node state
+    state: Arc<Mutex<L1State>>,
+}
+
+impl MockL1 {
+    /// Create a node at block 1 with the given chain id
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(L1State {
+                chain_id,
+                block_number: 1,
+                fork: 0,
+                automine: true,
+                latency: Duration::ZERO,
+                txs: HashMap::new(),
+                mempool: Vec::new(),
+                calls: HashMap::new(),
+                reverts: HashMap::new(),
+                used_nonces: HashSet::new(),
+                logs: Vec::new(),
+            })),
+        }
+    }
+
+    /// Answer `eth_call`s to `signature` (e.g. `"blsVerifier()"`) with `result`
+    pub fn on_call(&self, signature: &str, result: Vec<u8>) {
+        self.state.lock().unwrap().calls.insert(selector(signature), result);
+    }
+
+    /// Make calls and submissions to `signature` revert with `reason`
+    pub fn revert(&self, signature: &str, reason: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .reverts
+            .insert(selector(signature), reason.to_string());
+    }
+
+    /// Stop reverting `signature`
+    pub fn clear_revert(&self, signature: &str) {
+        self.state.lock().unwrap().reverts.remove(&selector(signature));
+    }
+
+    /// Mark an attestation nonce as used
+    pub fn set_nonce_used(&self, nonce: u64) {
+        self.state.lock().unwrap().used_nonces.insert(nonce);
+    }
+
+    /// Delay every response by `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Whether transactions are mined on arrival; otherwise they wait for [`mine`](Self::mine)
+    pub fn set_automine(&self, automine: bool) {
+        self.state.lock().unwrap().automine = automine;
+    }
+
+    /// Mine `count` blocks, the first including every pending transaction
+    pub fn mine(&self, count: u64) {
+        self.state.lock().unwrap().mine(count);
+    }
+
+    /// Drop the top `depth` blocks; their transactions return to the mempool
+    pub fn reorg(&self, depth: u64) {
+        let mut state = self.state.lock().unwrap();
+        let fork_point = state.block_number.saturating_sub(depth);
+        let mut orphaned: Vec<(u64, H256)> = state
+            .txs
+            .iter_mut()
+            .filter(|(_, sent)| sent.block.is_some_and(|b| b > fork_point))
+            .map(|(hash, sent)| (sent.block.take().unwrap_or_default(), *hash))
+            .collect();
+        orphaned.sort();
+        let pending = std::mem::take(&mut state.mempool);
+        state.mempool = orphaned.into_iter().map(|(_, hash)| hash).chain(pending).collect();
+        state.block_number = fork_point;
+        state.fork += 1;
+    }
+
+    /// Add a log for `eth_getLogs`
+    pub fn push_log(&self, log: Log) {
+        self.state.lock().unwrap().logs.push(log);
+    }
+
+    /// Every transaction submitted so far, in no particular order
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().txs.values().map(|sent| sent.tx.clone()).collect()
+    }
+
+    /// Current block number
+    pub fn block_number(&self) -> u64 {
+        self.state.lock().unwrap().block_number
+    }
+
+    /// Serve on an ephemeral localhost port; returns the `http://` URL
+    pub async fn serve(&self) -> anyhow::Result<String> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let app = Router::new().route("/", post(handle)).with_state(self.clone());
+        let server = axum::Server::from_tcp(listener)?.serve(app.into_make_service());
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::warn!("Mock L1 stopped: {}", e);
+            }
+        });
+
+        Ok(url)
+    }
+
+    /// Answer one JSON-RPC method
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let reverted = |state: &L1State, data: &[u8]| -> Result<(), (i64, String)> {
+            let Some(selector) = data.get(..4) else {
+                return Ok(());
+            };
+            match state.reverts.get(selector) {
+                Some(reason) => Err((3, format!("execution reverted: {}", reason))),
+                None => Ok(()),
+            }
+        };
+
+        match method {
+            "eth_chainId" => Ok(json!(U64::from(state.chain_id))),
+            "net_version" => Ok(json!(state.chain_id.to_string())),
+            "eth_blockNumber" => Ok(json!(U64::from(state.block_number))),
+            "eth_gasPrice" => Ok(json!(U256::from(MOCK_GAS_PRICE))),
+            "eth_getTransactionCount" => {
+                let from: Address = param(params, 0)?;
+                let count = state.txs.values().filter(|sent| sent.tx.from == from).count();
+                Ok(json!(U256::from(count)))
+            }
+            "eth_estimateGas" => {
+                reverted(state, &call_data(&params[0]))?;
+                Ok(json!(U256::from(MOCK_GAS)))
+            }
+            "eth_call" => {
+                let data = call_data(&params[0]);
+                reverted(state, &data)?;
+                if data.get(..4) == Some(&selector("isNonceUsed(uint64)")[..]) && data.len() >= 36 {
+                    let nonce = U256::from_big_endian(&data[4..36]).low_u64();
+                    let used = state.is_nonce_used(nonce);
+                    return Ok(json!(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Bool(used)]))));
+                }
+                if data.get(..4) == Some(&selector("isDepositProcessed(bytes32,uint32)")[..])
+                    && data.len() >= 68
+                {
+                    let output_index = U256::from_big_endian(&data[36..68]);
+                    let processed = state.is_deposit_processed(&data[4..36], output_index);
+                    return Ok(json!(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Bool(processed)]))));
+                }
+                let result = data
+                    .get(..4)
+                    .and_then(|s| state.calls.get(s))
+                    .cloned()
+                    .unwrap_or_else(|| vec![0; 32]);
+                Ok(json!(Bytes::from(result)))
+            }
+            "eth_sendRawTransaction" => {
+                let raw: Bytes = param(params, 0)?;
+                let mut tx: Transaction =
+                    rlp::decode(&raw).map_err(|e| (-32602, format!("invalid transaction: {}", e)))?;
+                tx.hash = H256(keccak256(&raw));
+                tx.from = tx
+                    .recover_from()
+                    .map_err(|e| (-32602, format!("invalid signature: {}", e)))?;
+                reverted(state, &tx.input)?;
+
+                let hash = tx.hash;
+                state.txs.insert(hash, SentTx { tx, block: None });
+                state.mempool.push(hash);
+                if state.automine {
+                    state.mine(1);
+                }
+                Ok(json!(hash))
+            }
+            "eth_getTransactionByHash" => {
+                let hash: H256 = param(params, 0)?;
+                let Some(sent) = state.txs.get(&hash) else {
+                    return Ok(Value::Null);
+                };
+                let mut tx = sent.tx.clone();
+                tx.block_number = sent.block.map(U64::from);
+                tx.block_hash = sent.block.map(|b| state.block_hash(b));
+                Ok(json!(tx))
+            }
+            "eth_getTransactionReceipt" => {
+                let hash: H256 = param(params, 0)?;
+                let receipt = state.txs.get(&hash).and_then(|sent| state.receipt(sent));
+                Ok(json!(receipt))
+            }
+            "eth_getLogs" => {
+                let filter = &params[0];
+                let bound = |key: &str, default: u64| {
+                    filter[key]
+                        .as_str()
+                        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+                        .unwrap_or(default)
+                };
+                let (from, to) = (bound("fromBlock", 0), bound("toBlock", state.block_number));
+                let addresses: Vec<Address> = match &filter["address"] {
+                    Value::Null => Vec::new(),
+                    Value::Array(_) => serde_json::from_value(filter["address"].clone()).unwrap_or_default(),
+                    single => serde_json::from_value(single.clone()).map(|a| vec![a]).unwrap_or_default(),
+                };
+                let topic0: Option<H256> = serde_json::from_value(filter["topics"][0].clone()).ok();
+
+                let logs: Vec<&Log> = state
+                    .logs
+                    .iter()
+                    .filter(|log| {
+                        let block = log.block_number.map_or(0, |b| b.as_u64());
+                        (from..=to).contains(&block)
+                            && (addresses.is_empty() || addresses.contains(&log.address))
+                            && topic0.map_or(true, |t| log.topics.first() == Some(&t))
+                    })
+                    .collect();
+                Ok(json!(logs))
+            }
+            other => Err((-32601, format!("method {} not supported by mock L1", other))),
+        }
+    }
+}
+
+/// 4-byte selector of a function signature
+fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Calldata of a call object (`data` or `input`)
+fn call_data(call: &Value) -> Vec<u8> {
+    let data = if call["data"].is_null() { &call["input"] } else { &call["data"] };
+    serde_json::from_value::<Bytes>(data.clone())
+        .map(|b| b.to_vec())
+        .unwrap_or_default()
+}
+
+/// Positional parameter `index`
+fn param<T: serde::de::DeserializeOwned>(params: &Value, index: usize) -> Result<T, (i64, String)> {
+    serde_json::from_value(params[index].clone()).map_err(|e| (-32602, format!("invalid params: {}", e)))
+}
+
+/// JSON-RPC endpoint
+async fn handle(State(mock): State<MockL1>, Json(request): Json<Value>) -> Json<Value> {
+    let latency = mock.state.lock().unwrap().latency;
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+
+    let id = request["id"].clone();
+    let method = request["method"].as_str().unwrap_or_default();
+    Json(match mock.dispatch(method, &request["params"]) {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}}),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::AttestationSigner;
+    use crate::BridgePayload;
+    use ethers::providers::Middleware;
+
+    const SERVICE_MANAGER: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+
+    async fn signer(mock: &MockL1) -> AttestationSigner {
+        let url = mock.serve().await.unwrap();
+        AttestationSigner::new(
+            &"ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .to_string()
+                .into(),
+            url,
+            SERVICE_MANAGER.to_string(),
+        )
+        .unwrap()
+    }
+
+    fn payload() -> BridgePayload {
+        BridgePayload {
+            tx_hash: [0xab; 32],
+            amount: 5_000,
+            secret_hash: [0xcd; 32],
+            aztec_address: [0xef; 32],
+            block_height: 10,
+            ..BridgePayload::sample()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_marks_nonce_used() {
+        let mock = MockL1::new(31337);
+        let signer = signer(&mock).await;
+
+        // Every signer has a registered key on the verifier
+        let verifier = ethers::abi::encode(&[ethers::abi::Token::Address(Address::repeat_byte(0x42))]);
+        mock.on_call("blsVerifier()", verifier);
+        mock.on_call("hasRegisteredKey(address)", ethers::abi::encode(&[ethers::abi::Token::Bool(true)]));
+
+        assert!(!signer.is_nonce_used(7).await.unwrap());
+        let attestation = signer.sign_attestation(&payload(), 7).await.unwrap();
+        signer.submit_attestation(&attestation, &[]).await.unwrap();
+        assert!(signer.is_nonce_used(7).await.unwrap());
+        assert!(signer.is_deposit_processed(&[0xab; 32], 0).await.unwrap());
+        assert!(!signer.is_deposit_processed(&[0xab; 32], 1).await.unwrap());
+        assert_eq!(mock.transactions().len(), 1);
+
+        // A reorg un-mines the dispatch until the next block
+        mock.reorg(1);
+        assert!(!signer.is_nonce_used(7).await.unwrap());
+        mock.mine(1);
+        assert!(signer.is_nonce_used(7).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revert_and_delayed_inclusion() {
+        let mock = MockL1::new(31337);
+        let signer = Arc::new(signer(&mock).await);
+        let to: Address = SERVICE_MANAGER.parse().unwrap();
+        let calldata = selector("verifyBatch(bytes32,uint64,uint32,bytes,address[])").to_vec();
+
+        mock.revert("verifyBatch(bytes32,uint64,uint32,bytes,address[])", "BatchTooLarge");
+        let err = signer.send_call(to, calldata.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("BatchTooLarge"));
+        mock.clear_revert("verifyBatch(bytes32,uint64,uint32,bytes,address[])");
+
+        // Without automine the receipt only appears once a block is mined
+        mock.set_automine(false);
+        let pending = tokio::spawn({
+            let signer = signer.clone();
+            async move { signer.send_call(to, calldata).await }
+        });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!pending.is_finished());
+
+        mock.mine(1);
+        let tx_hash: H256 = pending.await.unwrap().unwrap().parse().unwrap();
+        let receipt = signer
+            .provider()
+            .get_transaction_receipt(tx_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.block_number, Some(U64::from(mock.block_number())));
+    }
+}
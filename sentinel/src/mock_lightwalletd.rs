@@ -0,0 +1,372 @@
+//! In-process mock lightwalletd
+//!
+//! Serves the read-side of the `CompactTxStreamer` gRPC service from a
+//! scriptable in-memory chain, so scanner, reorg and memo-extraction tests run
+//! hermetically without Docker. Tests append blocks, rewrite the chain from a
+//! fork height, register raw transactions, and inject transient failures.
+//!
+//! Built for tests and with the `mock-lightwalletd` feature.
+
+#![allow(dead_code)]
+
+use futures::Stream;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, Body, BoxFuture, Service, StdError};
+use tonic::server::{Grpc, NamedService, ServerStreamingService, UnaryService};
+use tonic::{Request, Response, Status};
+use zcash_client_backend::proto::compact_formats::{CompactBlock, CompactTx};
+use zcash_client_backend::proto::service::{
+    BlockId, BlockRange, ChainSpec, Empty, LightdInfo, RawTransaction, TxFilter,
+};
+
+/// Fully-qualified gRPC service name
+const SERVICE_NAME: &str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+
+/// Scripted chain state
+#[derive(Debug, Default)]
+struct MockChain {
+    /// Blocks in height order, starting at `base_height`
+    blocks: Vec<CompactBlock>,
+
+    /// Height of the first block
+    base_height: u64,
+
+    /// Raw transactions by txid (internal byte order)
+    transactions: HashMap<Vec<u8>, RawTransaction>,
+
+    /// Requests left to fail with `Unavailable`
+    failures: u32,
+
+    /// Bumped on every reorg so replacement blocks get fresh hashes
+    fork: u32,
+}
+
+impl MockChain {
+    /// Tip height, if any block has been pushed
+    fn tip(&self) -> Option<u64> {
+        self.blocks.last().map(|b| b.height)
+    }
+
+    /// Block at `height`
+    fn block(&self, height: u64) -> Option<&CompactBlock> {
+        let offset = height.checked_sub(self.base_height)?;
+        self.blocks.get(offset as usize)
+    }
+
+    /// Fail the request if a failure is scripted
+    fn check_failure(&mut self) -> Result<(), Status> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(Status::unavailable("scripted failure"));
+        }
+        Ok(())
+    }
+}
+
+/// Handle for scripting a mock lightwalletd
+#[derive(Clone)]
+pub struct MockLightwalletd {
+    /// Shared chain state
+    chain: Arc<Mutex<MockChain>>,
+}
+
+impl MockLightwalletd {
+    /// Create a server whose first block will be at `base_height`
+    pub fn new(base_height: u64) -> Self {
+        Self {
+            chain: Arc::new(Mutex::new(MockChain {
+                base_height,
+                ..MockChain::default()
+            })),
+        }
+    }
+
+    /// Append a block with the given transactions; returns its height
+    pub fn push_block(&self, vtx: Vec<CompactTx>) -> u64 {
+        let mut chain = self.chain.lock().unwrap();
+        let height = chain.base_height + chain.blocks.len() as u64;
+        let prev_hash = chain.blocks.last().map(|b| b.hash.clone()).unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(height.to_le_bytes());
+        hasher.update(&prev_hash);
+        hasher.update(chain.fork.to_le_bytes());
+        let hash = hasher.finalize().to_vec();
+
+        chain.blocks.push(CompactBlock {
+            height,
+            hash,
+            prev_hash,
+            time: height as u32,
+            vtx,
+            ..CompactBlock::default()
+        });
+        height
+    }
+
+    /// Drop every block from `height` up, so the next pushes build a competing chain
+    pub fn reorg(&self, height: u64) {
+        let mut chain = self.chain.lock().unwrap();
+        let keep = height.saturating_sub(chain.base_height) as usize;
+        chain.blocks.truncate(keep);
+        chain.fork += 1;
+    }
+
+    /// Register a full transaction for `GetTransaction`
+    pub fn add_transaction(&self, txid: Vec<u8>, height: u64, data: Vec<u8>) {
+        self.chain
+            .lock()
+            .unwrap()
+            .transactions
+            .insert(txid, RawTransaction { data, height });
+    }
+
+    /// Fail the next `count` requests with `Unavailable`
+    pub fn fail_next(&self, count: u32) {
+        self.chain.lock().unwrap().failures = count;
+    }
+
+    /// Hash of the block at `height`
+    pub fn block_hash(&self, height: u64) -> Option<Vec<u8>> {
+        self.chain.lock().unwrap().block(height).map(|b| b.hash.clone())
+    }
+
+    /// Block at `height`
+    pub fn block(&self, height: u64) -> Option<CompactBlock> {
+        self.chain.lock().unwrap().block(height).cloned()
+    }
+
+    /// Tip height, if any block has been pushed
+    pub fn tip(&self) -> Option<u64> {
+        self.chain.lock().unwrap().tip()
+    }
+
+    /// Serve on an ephemeral localhost port; returns the `http://` URL
+    pub async fn serve(&self) -> anyhow::Result<String> {
+        let addr: SocketAddr = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+            listener.local_addr()?
+        };
+
+        let router = tonic::transport::Server::builder().add_service(self.clone());
+        tokio::spawn(async move {
+            if let Err(e) = router.serve(addr).await {
+                tracing::error!("Mock lightwalletd stopped: {}", e);
+            }
+        });
+
+        // Wait until the listener is up
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        Ok(format!("http://{}", addr))
+    }
+
+    fn latest_block(&self, _: ChainSpec) -> Result<BlockId, Status> {
+        let mut chain = self.chain.lock().unwrap();
+        chain.check_failure()?;
+        let block = chain
+            .blocks
+            .last()
+            .ok_or_else(|| Status::unavailable("no blocks"))?;
+        Ok(BlockId {
+            height: block.height,
+            hash: block.hash.clone(),
+        })
+    }
+
+    fn get_block(&self, id: BlockId) -> Result<CompactBlock, Status> {
+        let mut chain = self.chain.lock().unwrap();
+        chain.check_failure()?;
+        chain
+            .block(id.height)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("no block at height {}", id.height)))
+    }
+
+    fn block_range(&self, range: BlockRange) -> Result<Vec<CompactBlock>, Status> {
+        let mut chain = self.chain.lock().unwrap();
+        chain.check_failure()?;
+        let (Some(start), Some(end)) = (range.start, range.end) else {
+            return Err(Status::invalid_argument("range needs start and end"));
+        };
+        if chain.tip().map_or(true, |tip| end.height > tip) || start.height < chain.base_height {
+            return Err(Status::out_of_range("range outside the chain"));
+        }
+        Ok((start.height..=end.height)
+            .filter_map(|h| chain.block(h).cloned())
+            .collect())
+    }
+
+    fn transaction(&self, filter: TxFilter) -> Result<RawTransaction, Status> {
+        let mut chain = self.chain.lock().unwrap();
+        chain.check_failure()?;
+        chain
+            .transactions
+            .get(&filter.hash)
+            .cloned()
+            .ok_or_else(|| Status::not_found("unknown transaction"))
+    }
+
+    fn lightd_info(&self, _: Empty) -> Result<LightdInfo, Status> {
+        let mut chain = self.chain.lock().unwrap();
+        chain.check_failure()?;
+        Ok(LightdInfo {
+            vendor: "sentinel-mock".to_string(),
+            chain_name: "regtest".to_string(),
+            block_height: chain.tip().unwrap_or_default(),
+            ..LightdInfo::default()
+        })
+    }
+}
+
+/// Unary gRPC method backed by a closure
+struct Unary<F, Resp>(F, PhantomData<fn() -> Resp>);
+
+impl<F, Req, Resp> UnaryService<Req> for Unary<F, Resp>
+where
+    F: FnMut(Req) -> Result<Resp, Status>,
+    Resp: Send + 'static,
+{
+    type Response = Resp;
+    type Future = BoxFuture<Response<Resp>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let result = (self.0)(request.into_inner()).map(Response::new);
+        Box::pin(async move { result })
+    }
+}
+
+/// Server-streaming gRPC method backed by a closure returning every item
+struct Streaming<F, Resp>(F, PhantomData<fn() -> Resp>);
+
+impl<F, Req, Resp> ServerStreamingService<Req> for Streaming<F, Resp>
+where
+    F: FnMut(Req) -> Result<Vec<Resp>, Status>,
+    Resp: Send + 'static,
+{
+    type Response = Resp;
+    type ResponseStream = Pin<Box<dyn Stream<Item = Result<Resp, Status>> + Send>>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let result = (self.0)(request.into_inner()).map(|items| {
+            let stream: Self::ResponseStream = Box::pin(futures::stream::iter(items.into_iter().map(Ok)));
+            Response::new(stream)
+        });
+        Box::pin(async move { result })
+    }
+}
+
+impl NamedService for MockLightwalletd {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for MockLightwalletd
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let mock = self.clone();
+        let method = req
+            .uri()
+            .path()
+            .strip_prefix(&format!("/{}/", SERVICE_NAME))
+            .unwrap_or_default()
+            .to_string();
+
+        Box::pin(async move {
+            let response = match method.as_str() {
+                "GetLatestBlock" => {
+                    let svc = Unary(move |r| mock.latest_block(r), PhantomData);
+                    Grpc::new(ProstCodec::default()).unary(svc, req).await
+                }
+                "GetBlock" => {
+                    let svc = Unary(move |r| mock.get_block(r), PhantomData);
+                    Grpc::new(ProstCodec::default()).unary(svc, req).await
+                }
+                "GetBlockRange" => {
+                    let svc = Streaming(move |r| mock.block_range(r), PhantomData);
+                    Grpc::new(ProstCodec::default()).server_streaming(svc, req).await
+                }
+                "GetTransaction" => {
+                    let svc = Unary(move |r| mock.transaction(r), PhantomData);
+                    Grpc::new(ProstCodec::default()).unary(svc, req).await
+                }
+                "GetLightdInfo" => {
+                    let svc = Unary(move |r| mock.lightd_info(r), PhantomData);
+                    Grpc::new(ProstCodec::default()).unary(svc, req).await
+                }
+                _ => Status::unimplemented(format!("{} is not mocked", method)).to_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use zcash_client_backend::proto::service::compact_tx_streamer_client::CompactTxStreamerClient;
+
+    #[tokio::test]
+    async fn test_serves_scripted_chain_through_reorg() {
+        let mock = MockLightwalletd::new(100);
+        for _ in 0..5 {
+            mock.push_block(vec![]);
+        }
+        let url = mock.serve().await.unwrap();
+        let mut client = CompactTxStreamerClient::connect(url).await.unwrap();
+
+        let tip = client.get_latest_block(ChainSpec {}).await.unwrap().into_inner();
+        assert_eq!(tip.height, 104);
+
+        // Replace the last two blocks with a longer competing chain
+        let old_hash = mock.block_hash(103).unwrap();
+        mock.reorg(103);
+        for _ in 0..3 {
+            mock.push_block(vec![]);
+        }
+        assert_ne!(mock.block_hash(103).unwrap(), old_hash);
+
+        let range = BlockRange {
+            start: Some(BlockId { height: 102, hash: vec![] }),
+            end: Some(BlockId { height: 105, hash: vec![] }),
+        };
+        let blocks: Vec<CompactBlock> = client
+            .get_block_range(range)
+            .await
+            .unwrap()
+            .into_inner()
+            .map(|b| b.unwrap())
+            .collect()
+            .await;
+        assert_eq!(blocks.len(), 4);
+        assert!(blocks.windows(2).all(|w| w[1].prev_hash == w[0].hash));
+
+        mock.fail_next(1);
+        assert!(client.get_latest_block(ChainSpec {}).await.is_err());
+        assert!(client.get_latest_block(ChainSpec {}).await.is_ok());
+    }
+}
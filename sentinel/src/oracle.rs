@@ -0,0 +1,262 @@
+//! Price oracle for quote-denominated limits and fees
+//!
+//! Deposit bounds and the flat bridge fee can be configured in the oracle's
+//! quote currency (USD, ETH, ...) instead of zatoshi. At attestation time the
+//! current ZEC price is read from a Chainlink aggregator on L1 or from an HTTP
+//! price endpoint and used to convert them. Prices older than the configured
+//! maximum age are refused rather than used.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use ethers::abi::{decode, ParamType};
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes};
+use ethers::utils::keccak256;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Fixed-point decimals used for prices and quote amounts
+pub const PRICE_DECIMALS: u32 = 8;
+
+/// Default maximum age of a price before it is considered stale (1 hour)
+pub const DEFAULT_PRICE_MAX_AGE_SECS: u64 = 60 * 60;
+
+/// Zatoshi per ZEC
+const ZATOSHI_PER_ZEC: u128 = 100_000_000;
+
+/// ZEC price in quote units, scaled by [`PRICE_DECIMALS`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Price {
+    /// Quote units per ZEC, fixed-point
+    pub per_zec: u128,
+
+    /// Unix timestamp the price was last updated at the source
+    pub updated_at: u64,
+}
+
+impl Price {
+    /// Convert a fixed-point quote amount into zatoshi
+    pub fn to_zatoshi(&self, quote: u128) -> Option<u64> {
+        if self.per_zec == 0 {
+            return None;
+        }
+        let zatoshi = quote.checked_mul(ZATOSHI_PER_ZEC)? / self.per_zec;
+        u64::try_from(zatoshi).ok()
+    }
+}
+
+/// Where prices come from
+enum PriceSource {
+    /// Chainlink aggregator on L1
+    Chainlink {
+        provider: Arc<Provider<Http>>,
+        feed: Address,
+    },
+    /// HTTP endpoint returning `{"price": "<decimal>", "timestamp": <unix secs>}`
+    Http { client: reqwest::Client, url: String },
+}
+
+/// HTTP oracle response
+#[derive(Debug, Deserialize)]
+struct HttpPrice {
+    price: String,
+    timestamp: u64,
+}
+
+/// ZEC price oracle with staleness checks
+pub struct PriceOracle {
+    /// Price source
+    source: PriceSource,
+
+    /// Maximum accepted price age
+    max_age: Duration,
+}
+
+impl PriceOracle {
+    /// Create an oracle from a `PRICE_ORACLE` spec
+    ///
+    /// `chainlink:<aggregator address>` reads the feed on L1; an `http(s)://`
+    /// URL is polled directly.
+    pub fn new(
+        spec: &str,
+        provider: Arc<Provider<Http>>,
+        max_age: Duration,
+    ) -> Result<Self, SentinelError> {
+        let source = if let Some(feed) = spec.strip_prefix("chainlink:") {
+            PriceSource::Chainlink {
+                provider,
+                feed: feed
+                    .parse()
+                    .map_err(|_| SentinelError::Config(format!("Invalid Chainlink feed {}", feed)))?,
+            }
+        } else if spec.starts_with("http://") || spec.starts_with("https://") {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .map_err(SentinelError::network)?;
+            PriceSource::Http {
+                client,
+                url: spec.to_string(),
+            }
+        } else {
+            return Err(SentinelError::Config(format!(
+                "Unsupported price oracle {}",
+                spec
+            )));
+        };
+
+        Ok(Self { source, max_age })
+    }
+
+    /// Current ZEC price, refusing stale data
+    pub async fn price(&self) -> Result<Price, SentinelError> {
+        let price = match &self.source {
+            PriceSource::Chainlink { provider, feed } => chainlink_price(provider, *feed).await?,
+            PriceSource::Http { client, url } => http_price(client, url).await?,
+        };
+
+        let now = now_secs();
+        check_fresh(&price, now, self.max_age)?;
+
+        debug!("ZEC price: {} (updated {})", price.per_zec, price.updated_at);
+        Ok(price)
+    }
+}
+
+/// Refuse prices older than `max_age` (or from the future)
+fn check_fresh(price: &Price, now: u64, max_age: Duration) -> Result<(), SentinelError> {
+    if price.updated_at > now || now - price.updated_at > max_age.as_secs() {
+        return Err(SentinelError::Oracle(format!(
+            "price updated at {} is stale (now {}, max age {}s)",
+            price.updated_at,
+            now,
+            max_age.as_secs()
+        )));
+    }
+    if price.per_zec == 0 {
+        return Err(SentinelError::Oracle("price is zero".to_string()));
+    }
+    Ok(())
+}
+
+/// Read `latestRoundData()` from a Chainlink aggregator
+async fn chainlink_price(provider: &Provider<Http>, feed: Address) -> Result<Price, SentinelError> {
+    let call = |selector: &[u8]| {
+        TransactionRequest::new()
+            .to(feed)
+            .data(Bytes::from(keccak256(selector)[0..4].to_vec()))
+    };
+
+    let decimals = provider.call(&call(b"decimals()").into(), None).await?;
+    let decimals = decode(&[ParamType::Uint(8)], &decimals)
+        .map_err(|e| SentinelError::Oracle(e.to_string()))?[0]
+        .clone()
+        .into_uint()
+        .unwrap_or_default()
+        .as_u32();
+
+    // (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    let round = provider.call(&call(b"latestRoundData()").into(), None).await?;
+    let tokens = decode(
+        &[
+            ParamType::Uint(80),
+            ParamType::Int(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(80),
+        ],
+        &round,
+    )
+    .map_err(|e| SentinelError::Oracle(e.to_string()))?;
+
+    let answer = I256::from_raw(tokens[1].clone().into_int().unwrap_or_default());
+    if answer <= I256::zero() {
+        return Err(SentinelError::Oracle(format!("non-positive answer {}", answer)));
+    }
+    let answer = answer.into_raw().as_u128();
+    let per_zec = if decimals >= PRICE_DECIMALS {
+        answer / 10u128.pow(decimals - PRICE_DECIMALS)
+    } else {
+        answer * 10u128.pow(PRICE_DECIMALS - decimals)
+    };
+
+    Ok(Price {
+        per_zec,
+        updated_at: tokens[3].clone().into_uint().unwrap_or_default().as_u64(),
+    })
+}
+
+/// Fetch the price from an HTTP oracle
+async fn http_price(client: &reqwest::Client, url: &str) -> Result<Price, SentinelError> {
+    let response: HttpPrice = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(SentinelError::network)?
+        .json()
+        .await
+        .map_err(SentinelError::network)?;
+
+    Ok(Price {
+        per_zec: parse_decimal(&response.price)
+            .ok_or_else(|| SentinelError::Oracle(format!("invalid price {}", response.price)))?,
+        updated_at: response.timestamp,
+    })
+}
+
+/// Parse a decimal string into a [`PRICE_DECIMALS`] fixed-point value
+pub fn parse_decimal(s: &str) -> Option<u128> {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && frac.is_empty() || frac.len() > PRICE_DECIMALS as usize {
+        return None;
+    }
+    if !whole.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let frac_digits = format!("{:0<width$}", frac, width = PRICE_DECIMALS as usize);
+    let frac: u128 = frac_digits.parse().ok()?;
+
+    whole.checked_mul(10u128.pow(PRICE_DECIMALS))?.checked_add(frac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("1"), Some(100_000_000));
+        assert_eq!(parse_decimal("12.5"), Some(1_250_000_000));
+        assert_eq!(parse_decimal("0.00000001"), Some(1));
+        assert_eq!(parse_decimal("0.000000001"), None);
+        assert_eq!(parse_decimal("-1"), None);
+        assert_eq!(parse_decimal("."), None);
+    }
+
+    #[test]
+    fn test_to_zatoshi() {
+        // ZEC at $40: $10 is 0.25 ZEC
+        let price = Price {
+            per_zec: parse_decimal("40").unwrap(),
+            updated_at: 0,
+        };
+        assert_eq!(price.to_zatoshi(parse_decimal("10").unwrap()), Some(25_000_000));
+    }
+
+    #[test]
+    fn test_staleness() {
+        let price = Price {
+            per_zec: 1,
+            updated_at: 1_000,
+        };
+        let max_age = Duration::from_secs(60);
+        assert!(check_fresh(&price, 1_060, max_age).is_ok());
+        assert!(check_fresh(&price, 1_061, max_age).is_err());
+        assert!(check_fresh(&price, 999, max_age).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+//! Payout policy
+//!
+//! Every release instruction is checked against a declarative policy before
+//! it reaches the release queue, i.e. before anything authorizes the vault
+//! to pay out. The policy is a JSON file (`PAYOUT_POLICY_PATH`):
+//!
+//! ```json
+//! {
+//!   "max_per_withdrawal": 100000000000,
+//!   "daily_limit": 500000000000,
+//!   "approval_threshold": 10000000000,
+//!   "required_approvals": 2,
+//!   "approvers": { "alice": "<sha256 of alice's token, hex>" }
+//! }
+//! ```
+//!
+//! Releases above `max_per_withdrawal` are refused. Releases above
+//! `approval_threshold`, or that would take the rolling day past
+//! `daily_limit`, are held until `required_approvals` distinct approvers
+//! approve them through the admin API. Every decision and approval is
+//! written to the key audit trail.
+//!
+//! The rolling day is rebuilt from the queue's timestamps and held releases
+//! are kept next to the queue, so a restart neither resets the daily limit
+//! nor drops approvals in progress.
+
+use crate::error::SentinelError;
+use crate::keyaudit::KeyAuditLog;
+use crate::withdrawal::{append_release, ReleaseInstruction};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Held releases file next to `queue_path`
+fn holds_path(queue_path: &Path) -> PathBuf {
+    let mut name = queue_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".holds.json");
+    queue_path.with_file_name(name)
+}
+
+/// Declarative limits on releases
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PayoutPolicy {
+    /// Largest single release, in zatoshi; larger ones are refused
+    #[serde(default)]
+    pub max_per_withdrawal: Option<u64>,
+
+    /// Value released per rolling day without co-approval, in zatoshi
+    #[serde(default)]
+    pub daily_limit: Option<u64>,
+
+    /// Releases above this many zatoshi need co-approval
+    #[serde(default)]
+    pub approval_threshold: Option<u64>,
+
+    /// Distinct approvals a held release needs
+    #[serde(default = "default_required_approvals")]
+    pub required_approvals: usize,
+
+    /// Approver names and the SHA-256 (hex) of their approval tokens
+    #[serde(default)]
+    pub approvers: BTreeMap<String, String>,
+}
+
+fn default_required_approvals() -> usize {
+    1
+}
+
+/// Outcome of checking a release against the policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayoutDecision {
+    /// Queue the release now
+    Release,
+    /// Hold it for co-approval, for the given reason
+    Hold(String),
+    /// Refuse it, for the given reason
+    Deny(String),
+}
+
+impl PayoutPolicy {
+    /// Load and check a policy file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read payout policy {}", path.display()))?;
+        let policy: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid payout policy {}", path.display()))?;
+
+        let can_hold = policy.approval_threshold.is_some() || policy.daily_limit.is_some();
+        if can_hold && policy.required_approvals == 0 {
+            bail!("Payout policy required_approvals must be at least 1");
+        }
+        if can_hold && policy.required_approvals > policy.approvers.len() {
+            bail!(
+                "Payout policy requires {} approvals but lists {} approvers",
+                policy.required_approvals,
+                policy.approvers.len()
+            );
+        }
+        Ok(policy)
+    }
+
+    /// Decide on a release of `amount` with `released_today` already paid out
+    pub fn evaluate(&self, amount: u64, released_today: u64) -> PayoutDecision {
+        if let Some(max) = self.max_per_withdrawal {
+            if amount > max {
+                return PayoutDecision::Deny(format!("above the {} zatoshi maximum", max));
+            }
+        }
+        if let Some(threshold) = self.approval_threshold {
+            if amount > threshold {
+                return PayoutDecision::Hold(format!(
+                    "above the {} zatoshi approval threshold",
+                    threshold
+                ));
+            }
+        }
+        if let Some(limit) = self.daily_limit {
+            if released_today.saturating_add(amount) > limit {
+                return PayoutDecision::Hold(format!(
+                    "would exceed the {} zatoshi daily limit ({} released)",
+                    limit, released_today
+                ));
+            }
+        }
+        PayoutDecision::Release
+    }
+}
+
+/// A release waiting for co-approval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPayout {
+    /// The held instruction
+    pub instruction: ReleaseInstruction,
+
+    /// Why it was held
+    pub reason: String,
+
+    /// Approvers so far
+    pub approvals: BTreeSet<String>,
+
+    /// Approvals needed
+    pub required: usize,
+}
+
+/// Mutable engine state
+#[derive(Default)]
+struct PolicyState {
+    /// Message hashes already in the release queue
+    released: HashSet<String>,
+
+    /// Releases within the last day as (unix secs, amount)
+    window: VecDeque<(u64, u64)>,
+
+    /// Held releases by message hash
+    pending: HashMap<String, PendingPayout>,
+}
+
+/// Applies the payout policy in front of the release queue
+pub struct PayoutPolicyEngine {
+    /// Policy to enforce
+    policy: PayoutPolicy,
+
+    /// Release queue file
+    queue_path: PathBuf,
+
+    /// Where held releases are persisted
+    holds_path: PathBuf,
+
+    /// Trail that decisions are recorded in
+    audit: Option<Arc<KeyAuditLog>>,
+
+    /// Released, recent and held releases
+    state: Mutex<PolicyState>,
+}
+
+impl PayoutPolicyEngine {
+    /// Create an engine in front of the queue at `queue_path`
+    ///
+    /// Releases already in the queue are remembered so a rescan of L1 does
+    /// not queue (or count) them twice, and those it queued count towards the
+    /// rolling day again. Releases held by an earlier run are held again.
+    pub fn new(
+        policy: PayoutPolicy,
+        queue_path: PathBuf,
+        audit: Option<Arc<KeyAuditLog>>,
+    ) -> Result<Self, SentinelError> {
+        let mut state = PolicyState::default();
+        match std::fs::read_to_string(&queue_path) {
+            Ok(contents) => {
+                let queued = contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<ReleaseInstruction>(line).ok());
+                for instruction in queued {
+                    if let Some(ts) = instruction.queued_at {
+                        state.window.push_back((ts, instruction.amount));
+                    }
+                    state.released.insert(instruction.message_hash);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        }
+
+        let holds_path = holds_path(&queue_path);
+        match std::fs::read(&holds_path) {
+            Ok(contents) => {
+                state.pending = serde_json::from_slice(&contents).map_err(|e| {
+                    SentinelError::Storage(format!("{}: {}", holds_path.display(), e))
+                })?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        }
+
+        Ok(Self {
+            policy,
+            queue_path,
+            holds_path,
+            audit,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Name of the approver holding `token`, if any
+    pub fn approver_for(&self, token: &str) -> Option<String> {
+        let hash = hex::encode(Sha256::digest(token.as_bytes()));
+        self.policy
+            .approvers
+            .iter()
+            .find(|(_, expected)| expected.eq_ignore_ascii_case(&hash))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Releases waiting for co-approval
+    pub fn pending(&self) -> Vec<PendingPayout> {
+        self.state
+            .lock()
+            .unwrap()
+            .pending
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Check a release against the policy and queue it if allowed
+    ///
+    /// Returns whether it was queued.
+    pub async fn submit(
+        &self,
+        mut instruction: ReleaseInstruction,
+        now: u64,
+    ) -> Result<bool, SentinelError> {
+        let decision = {
+            let mut state = self.state.lock().unwrap();
+            let hash = &instruction.message_hash;
+            if state.released.contains(hash) || state.pending.contains_key(hash) {
+                return Ok(false);
+            }
+
+            while let Some((ts, _)) = state.window.front() {
+                if now.saturating_sub(*ts) < DAY {
+                    break;
+                }
+                state.window.pop_front();
+            }
+            let released_today = state.window.iter().map(|(_, a)| a).sum();
+            let decision = self.policy.evaluate(instruction.amount, released_today);
+
+            let (purpose, reason) = match &decision {
+                PayoutDecision::Release => ("payout_release", "within policy"),
+                PayoutDecision::Hold(reason) => ("payout_hold", reason.as_str()),
+                PayoutDecision::Deny(reason) => ("payout_deny", reason.as_str()),
+            };
+            self.record(purpose, &instruction, reason.to_string())?;
+
+            match &decision {
+                PayoutDecision::Release => {
+                    state.released.insert(hash.clone());
+                    state.window.push_back((now, instruction.amount));
+                }
+                PayoutDecision::Hold(reason) => {
+                    state.pending.insert(
+                        hash.clone(),
+                        PendingPayout {
+                            instruction: instruction.clone(),
+                            reason: reason.clone(),
+                            approvals: BTreeSet::new(),
+                            required: self.policy.required_approvals,
+                        },
+                    );
+                    self.save_holds(&state.pending)?;
+                }
+                PayoutDecision::Deny(_) => {}
+            }
+            decision
+        };
+
+        match decision {
+            PayoutDecision::Release => {
+                instruction.queued_at = Some(now);
+                append_release(&self.queue_path, &instruction).await?;
+                Ok(true)
+            }
+            PayoutDecision::Hold(reason) => {
+                warn!(
+                    "Release for withdrawal {} held for approval: {}",
+                    instruction.message_hash, reason
+                );
+                Ok(false)
+            }
+            PayoutDecision::Deny(reason) => {
+                warn!(
+                    "Release for withdrawal {} refused by payout policy: {}",
+                    instruction.message_hash, reason
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Record `approver`'s approval of a held release, queueing it once
+    /// enough distinct approvers agree
+    ///
+    /// Returns `None` if no release with that message hash is held.
+    pub async fn approve(
+        &self,
+        message_hash: &str,
+        approver: &str,
+        now: u64,
+    ) -> Result<Option<PendingPayout>, SentinelError> {
+        let (pending, release) = {
+            let mut state = self.state.lock().unwrap();
+            let Some(pending) = state.pending.get_mut(message_hash) else {
+                return Ok(None);
+            };
+            if !pending.approvals.insert(approver.to_string()) {
+                return Ok(Some(pending.clone()));
+            }
+            let mut pending = pending.clone();
+            self.record(
+                "payout_approval",
+                &pending.instruction,
+                format!(
+                    "approved by {} ({} of {})",
+                    approver,
+                    pending.approvals.len(),
+                    pending.required
+                ),
+            )?;
+
+            let release = pending.approvals.len() >= pending.required;
+            if release {
+                let approvers: Vec<&str> = pending.approvals.iter().map(String::as_str).collect();
+                self.record(
+                    "payout_release",
+                    &pending.instruction,
+                    format!("co-approved by {}", approvers.join(", ")),
+                )?;
+                state.pending.remove(message_hash);
+                state.released.insert(message_hash.to_string());
+                state.window.push_back((now, pending.instruction.amount));
+                pending.instruction.queued_at = Some(now);
+            }
+            self.save_holds(&state.pending)?;
+            (pending, release)
+        };
+
+        if release {
+            append_release(&self.queue_path, &pending.instruction).await?;
+            info!(
+                "Release for withdrawal {} co-approved and queued",
+                message_hash
+            );
+        }
+        Ok(Some(pending))
+    }
+
+    /// Replace the held releases file in one rename
+    fn save_holds(&self, pending: &HashMap<String, PendingPayout>) -> Result<(), SentinelError> {
+        let tmp = self.holds_path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(pending)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.holds_path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// Write a decision to the audit trail; a decision that cannot be
+    /// recorded is not acted on
+    fn record(
+        &self,
+        purpose: &str,
+        instruction: &ReleaseInstruction,
+        reason: String,
+    ) -> Result<(), SentinelError> {
+        let Some(audit) = &self.audit else {
+            return Ok(());
+        };
+        let digest: [u8; 32] = hex::decode(
+            instruction
+                .message_hash
+                .strip_prefix("0x")
+                .unwrap_or(&instruction.message_hash),
+        )
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_default();
+        audit.record_decision(
+            purpose,
+            &digest,
+            format!("{} zatoshi: {}", instruction.amount, reason),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(id: u8, amount: u64) -> ReleaseInstruction {
+        ReleaseInstruction {
+            message_hash: format!("0x{}", hex::encode([id; 32])),
+            amount,
+            zcash_address: format!("0x{}", hex::encode([7; 32])),
+            l1_block: 1,
+            l1_tx_hash: String::new(),
+            vault_shard: None,
+            queued_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_policy_holds_until_co_approved() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join("releases.jsonl");
+        let audit_path = dir.path().join("key_audit.jsonl");
+        let audit = Arc::new(KeyAuditLog::open(&audit_path).unwrap());
+
+        let policy = PayoutPolicy {
+            max_per_withdrawal: Some(1_000),
+            daily_limit: Some(600),
+            approval_threshold: Some(500),
+            required_approvals: 2,
+            approvers: BTreeMap::from([
+                ("alice".to_string(), hex::encode(Sha256::digest(b"a-token"))),
+                ("bob".to_string(), hex::encode(Sha256::digest(b"b-token"))),
+            ]),
+        };
+        assert_eq!(policy.evaluate(100, 0), PayoutDecision::Release);
+        assert!(matches!(policy.evaluate(100, 550), PayoutDecision::Hold(_)));
+        assert!(matches!(policy.evaluate(2_000, 0), PayoutDecision::Deny(_)));
+
+        let engine = PayoutPolicyEngine::new(policy, queue.clone(), Some(audit)).unwrap();
+        assert_eq!(engine.approver_for("a-token").as_deref(), Some("alice"));
+        assert!(engine.approver_for("nope").is_none());
+
+        assert!(engine.submit(instruction(1, 400), 0).await.unwrap());
+        assert!(!engine.submit(instruction(1, 400), 0).await.unwrap());
+        assert!(!engine.submit(instruction(2, 800), 0).await.unwrap());
+        assert!(!engine.submit(instruction(3, 5_000), 0).await.unwrap());
+        assert_eq!(engine.pending().len(), 1);
+
+        let hash = instruction(2, 800).message_hash;
+        let held = engine.approve(&hash, "alice", 1).await.unwrap().unwrap();
+        assert_eq!(held.approvals.len(), 1);
+        engine.approve(&hash, "alice", 1).await.unwrap();
+        engine.approve(&hash, "bob", 2).await.unwrap();
+        assert!(engine.pending().is_empty());
+        assert!(engine.approve(&hash, "bob", 2).await.unwrap().is_none());
+
+        let queued = std::fs::read_to_string(&queue).unwrap();
+        assert_eq!(queued.lines().count(), 2);
+
+        // Decisions are in the audit trail, and a restart does not requeue
+        let trail = crate::keyaudit::verify(&audit_path).unwrap();
+        assert!(trail.iter().any(|e| e.purpose == "payout_deny"));
+        assert_eq!(
+            trail
+                .iter()
+                .filter(|e| e.purpose == "payout_release")
+                .count(),
+            2
+        );
+        let engine = PayoutPolicyEngine::new(PayoutPolicy::default(), queue, None).unwrap();
+        assert!(!engine.submit(instruction(1, 400), 0).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_daily_window_and_holds_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join("releases.jsonl");
+        let policy = PayoutPolicy {
+            daily_limit: Some(600),
+            required_approvals: 1,
+            approvers: BTreeMap::from([(
+                "alice".to_string(),
+                hex::encode(Sha256::digest(b"a-token")),
+            )]),
+            ..PayoutPolicy::default()
+        };
+
+        let engine = PayoutPolicyEngine::new(policy.clone(), queue.clone(), None).unwrap();
+        assert!(engine.submit(instruction(1, 400), 0).await.unwrap());
+        assert!(!engine.submit(instruction(2, 300), 10).await.unwrap());
+
+        let engine = PayoutPolicyEngine::new(policy.clone(), queue.clone(), None).unwrap();
+        assert_eq!(engine.pending().len(), 1);
+        assert!(!engine.submit(instruction(3, 300), 20).await.unwrap());
+        assert_eq!(engine.pending().len(), 2);
+
+        let hash = instruction(2, 300).message_hash;
+        engine.approve(&hash, "alice", 30).await.unwrap();
+        let engine = PayoutPolicyEngine::new(policy, queue, None).unwrap();
+        assert_eq!(engine.pending().len(), 1);
+        // Both queued releases still count towards the day
+        assert!(!engine.submit(instruction(4, 1), 40).await.unwrap());
+        // The day has rolled past the first release but not the approved one
+        assert!(engine.submit(instruction(5, 300), DAY + 1).await.unwrap());
+    }
+}
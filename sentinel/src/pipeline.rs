@@ -0,0 +1,96 @@
+//! Staged deposit pipeline
+//!
+//! Deposits flow through six stages, each its own task:
+//!
+//! ```text
+//! fetch → decrypt → parse → persist → sign → submit
+//! ```
+//!
+//! Neighbouring stages are joined by bounded channels. A stage that falls
+//! behind fills its inbound queue and the stage before it waits on `send`,
+//! all the way back to the gRPC block stream, so a backfill holds at most the
+//! configured number of items in memory however far behind the tip it starts.
+//! The first three stages live in the scanner, the last three in `run`.
+//!
+//! Blocks between fetch and decrypt are also bounded by size: past the memory
+//! budget they spill to disk (see `spool`), so spam-heavy ranges cannot
+//! blow the budget either.
+
+use crate::config::SentinelConfig;
+
+/// Default block ranges fetched concurrently
+pub const DEFAULT_FETCH_RANGES: usize = 4;
+
+/// Default blocks per fetched range
+pub const DEFAULT_RANGE_BLOCKS: u32 = 100;
+
+/// Default full transactions fetched concurrently for vault outputs
+pub const DEFAULT_TX_BATCH: usize = 8;
+
+/// Default compact blocks queued for trial decryption
+pub const DEFAULT_BLOCK_QUEUE: usize = 64;
+
+/// Default decrypted blocks queued for full-transaction parsing
+pub const DEFAULT_OUTPUT_QUEUE: usize = 64;
+
+/// Default parsed deposits queued for persistence and screening
+pub const DEFAULT_DEPOSIT_QUEUE: usize = 100;
+
+/// Default admitted deposits queued for signing
+pub const DEFAULT_SIGN_QUEUE: usize = 16;
+
+/// Default signed attestations queued for submission
+pub const DEFAULT_SUBMIT_QUEUE: usize = 16;
+
+/// Capacity of each inter-stage channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageCapacities {
+    /// fetch: `GetBlockRange` requests in flight, reassembled in height order
+    pub fetch_ranges: usize,
+
+    /// fetch: blocks per `GetBlockRange` request
+    pub range_blocks: u32,
+
+    /// fetch → decrypt: compact blocks held in memory
+    pub blocks: usize,
+
+    /// fetch → decrypt: bytes of compact blocks held in memory
+    pub block_memory_bytes: usize,
+
+    /// fetch → decrypt: bytes of compact blocks spilled to disk
+    pub block_spill_bytes: u64,
+
+    /// decrypt → parse: blocks with their vault-addressed outputs
+    pub outputs: usize,
+
+    /// parse: `GetTransaction` requests in flight
+    pub transactions: usize,
+
+    /// parse → persist: extracted deposits
+    pub deposits: usize,
+
+    /// persist → sign: deposits that passed screening
+    pub signing: usize,
+
+    /// sign → submit: signed attestations
+    pub submission: usize,
+}
+
+impl StageCapacities {
+    /// Capacities from the configuration
+    pub fn from_config(config: &SentinelConfig) -> Self {
+        Self {
+            fetch_ranges: config.fetch_window_ranges,
+            range_blocks: config.fetch_range_blocks,
+            blocks: config.pipeline_block_queue,
+            block_memory_bytes: config.scan_memory_budget_mb.saturating_mul(1 << 20),
+            block_spill_bytes: config.scan_spill_budget_mb.saturating_mul(1 << 20),
+            outputs: config.pipeline_output_queue,
+            transactions: config.fetch_tx_batch,
+            deposits: config.pipeline_deposit_queue,
+            signing: config.pipeline_sign_queue,
+            submission: config.pipeline_submit_queue,
+        }
+    }
+}
+
@@ -0,0 +1,236 @@
+//! Aztec portal message tracking
+//!
+//! `verifyAndDispatch` pushes an L1→L2 message into the Aztec inbox for every
+//! attested deposit. This closes the loop on the mint side: once a deposit is
+//! linked to its message hash, the message stored in the inbox is checked
+//! against the deposit (recipient, content hash, secret hash), and the Aztec
+//! node is polled until the message shows up in the L2 message tree and can be
+//! consumed. Consumption itself is tracked by the claim monitor, from the
+//! inbox's `MessageConsumed` events.
+
+use crate::aztec::AztecClient;
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::store::{DepositRecord, DepositStatus, DepositStore};
+use crate::BridgePayload;
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Content hash the ServiceManager puts in the inbox message for `payload`
+pub fn message_content(payload: &BridgePayload) -> [u8; 32] {
+    keccak256(encode(&[
+        Token::Uint(U256::from(payload.net_amount())),
+        Token::FixedBytes(payload.secret_hash.to_vec()),
+        Token::FixedBytes(payload.aztec_address.to_vec()),
+        Token::FixedBytes(payload.tx_hash.to_vec()),
+    ]))
+}
+
+/// Check an ABI-encoded `getMessage` result against the expected message
+fn check_message(data: &[u8], recipient: &[u8; 32], payload: &BridgePayload) -> Result<(), String> {
+    // (sender, recipient, content, secretHash, fee, deadline), one word each
+    if data.len() < 6 * 32 {
+        return Err("message not found in inbox".to_string());
+    }
+    let word = |i: usize| &data[i * 32..(i + 1) * 32];
+    if word(0).iter().all(|b| *b == 0) {
+        return Err("message not found in inbox".to_string());
+    }
+
+    if word(1) != recipient {
+        return Err(format!(
+            "recipient 0x{} is not the L2 bridge",
+            hex::encode(word(1))
+        ));
+    }
+    if word(2) != message_content(payload) {
+        return Err("content hash does not match the deposit".to_string());
+    }
+    if word(3) != payload.secret_hash {
+        return Err("secret hash does not match the deposit".to_string());
+    }
+    Ok(())
+}
+
+/// Verifies inbox messages of submitted deposits and their arrival on L2
+pub struct PortalTracker {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Inbox address and L2 bridge address (resolved from the contracts)
+    inbox: Option<(Address, [u8; 32])>,
+
+    /// Aztec node, to confirm messages are synced to L2
+    aztec: Option<AztecClient>,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+}
+
+impl PortalTracker {
+    /// Create a new tracker
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        aztec: Option<AztecClient>,
+        store: Arc<DepositStore>,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            inbox: None,
+            aztec,
+            store,
+        }
+    }
+
+    /// Poll forever
+    pub async fn run(mut self, poll_interval: Duration) {
+        loop {
+            if let Err(e) = self.poll().await {
+                error!("Portal tracker error: {}", e);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Check every submitted deposit whose message is not yet confirmed
+    pub async fn poll(&mut self) -> Result<(), SentinelError> {
+        let (inbox, l2_bridge) = self.inbox().await?;
+
+        for record in self.store.with_status(DepositStatus::Submitted) {
+            let Some(message_hash) = record.message_hash.clone() else {
+                continue;
+            };
+            if record.portal_error.is_some() {
+                continue;
+            }
+
+            if record.portal_verified_at.is_none() {
+                self.verify(inbox, &l2_bridge, &record, &message_hash)
+                    .await?;
+                continue;
+            }
+
+            if record.l2_synced_at.is_none() {
+                if let Some(aztec) = &self.aztec {
+                    let hash: [u8; 32] = hex::decode(&message_hash)?.try_into().map_err(|_| {
+                        SentinelError::InvalidPayload("expected 32 bytes".to_string())
+                    })?;
+                    if aztec.is_message_synced(&hash).await? {
+                        info!("L1→L2 message for deposit {} synced to L2", record.tx_hash);
+                        self.store
+                            .update(&record.key(), |r| r.l2_synced_at = Some(now_secs()))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare the inbox copy of a deposit's message with the deposit
+    async fn verify(
+        &self,
+        inbox: Address,
+        l2_bridge: &[u8; 32],
+        record: &DepositRecord,
+        message_hash: &str,
+    ) -> Result<(), SentinelError> {
+        let mut data = keccak256(b"getMessage(bytes32)")[0..4].to_vec();
+        data.extend(hex::decode(message_hash)?);
+        let result = self
+            .call(inbox, data)
+            .await
+            .map_err(|e| e.context(format!("reading inbox message 0x{}", message_hash)))?;
+
+        match check_message(&result, l2_bridge, &record.to_payload()?) {
+            Ok(()) => {
+                info!("Inbox message for deposit {} verified", record.tx_hash);
+                self.store
+                    .update(&record.key(), |r| r.portal_verified_at = Some(now_secs()))
+            }
+            Err(reason) => {
+                error!(
+                    "ALERT: inbox message 0x{} for deposit {}: {}",
+                    message_hash, record.tx_hash, reason
+                );
+                self.store
+                    .update(&record.key(), |r| r.portal_error = Some(reason))
+            }
+        }
+    }
+
+    /// Resolve the inbox and L2 bridge addresses once
+    async fn inbox(&mut self) -> Result<(Address, [u8; 32]), SentinelError> {
+        if let Some(inbox) = self.inbox {
+            return Ok(inbox);
+        }
+
+        let result = self
+            .call(
+                self.service_manager_address,
+                keccak256(b"inbox()")[0..4].to_vec(),
+            )
+            .await?;
+        if result.len() < 32 {
+            return Err(SentinelError::L1("Malformed inbox() response".to_string()));
+        }
+        let inbox = Address::from_slice(&result[12..32]);
+
+        let result = self
+            .call(inbox, keccak256(b"l2BridgeAddress()")[0..4].to_vec())
+            .await?;
+        let l2_bridge: [u8; 32] = result
+            .get(0..32)
+            .and_then(|word| word.try_into().ok())
+            .ok_or_else(|| SentinelError::L1("Malformed l2BridgeAddress() response".to_string()))?;
+
+        self.inbox = Some((inbox, l2_bridge));
+        Ok((inbox, l2_bridge))
+    }
+
+    /// Read-only contract call
+    async fn call(&self, to: Address, data: Vec<u8>) -> Result<Bytes, SentinelError> {
+        let call = TransactionRequest::new().to(to).data(Bytes::from(data));
+        Ok(self.provider.call(&call.into(), None).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inbox_message_checked_against_deposit() {
+        let payload = BridgePayload {
+            fee: 1_000,
+            ..BridgePayload::sample()
+        };
+        let bridge = [9u8; 32];
+        let message = |recipient: [u8; 32], content: [u8; 32], secret_hash: [u8; 32]| {
+            encode(&[
+                Token::Address(Address::repeat_byte(4)),
+                Token::FixedBytes(recipient.to_vec()),
+                Token::FixedBytes(content.to_vec()),
+                Token::FixedBytes(secret_hash.to_vec()),
+                Token::Uint(U256::from(10u64).pow(15.into())),
+                Token::Uint(U256::from(1_000u64)),
+            ])
+        };
+
+        let content = message_content(&payload);
+        assert!(check_message(&message(bridge, content, [2; 32]), &bridge, &payload).is_ok());
+        assert!(check_message(&message([8; 32], content, [2; 32]), &bridge, &payload).is_err());
+        assert!(check_message(&message(bridge, [0; 32], [2; 32]), &bridge, &payload).is_err());
+        assert!(check_message(&message(bridge, content, [5; 32]), &bridge, &payload).is_err());
+        assert!(check_message(&[0; 6 * 32], &bridge, &payload).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Production profiling
+//!
+//! Built only with the `profiling` feature. Adds admin routes, behind the
+//! same bearer token as the admin API:
+//!
+//! - `GET /debug/pprof/profile?seconds=N` — CPU profile in pprof protobuf
+//!   format, for `go tool pprof` or any pprof viewer
+//! - `GET /debug/pprof/flamegraph?seconds=N` — the same profile as an SVG
+//! - `GET /debug/stages` — CPU time spent in each pipeline stage's hot path
+//!
+//! Stage accounting reads the thread CPU clock around the synchronous
+//! decryption, parsing and encoding sections, so it is unaffected by time
+//! spent waiting on the network or on other stages.
+
+use crate::admin::authorize;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// Sampling frequency of CPU profiles, in Hz
+const PROFILE_FREQUENCY: i32 = 99;
+
+/// Default and maximum profile length, in seconds
+const DEFAULT_PROFILE_SECS: u64 = 30;
+const MAX_PROFILE_SECS: u64 = 300;
+
+/// Pipeline stages with CPU accounting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Compact trial decryption
+    Decrypt,
+    /// Full-transaction decryption and memo parsing
+    Parse,
+    /// Payload ABI encoding and hashing
+    Encode,
+}
+
+impl Stage {
+    /// Every stage, in pipeline order
+    const ALL: [Stage; 3] = [Stage::Decrypt, Stage::Parse, Stage::Encode];
+
+    /// Name in `/debug/stages`
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Decrypt => "decrypt",
+            Stage::Parse => "parse",
+            Stage::Encode => "encode",
+        }
+    }
+}
+
+/// Accumulated CPU time of one stage
+struct StageCounter {
+    /// Thread CPU time, in nanoseconds
+    cpu_nanos: AtomicU64,
+
+    /// Sections measured
+    calls: AtomicU64,
+}
+
+impl StageCounter {
+    const fn new() -> Self {
+        Self {
+            cpu_nanos: AtomicU64::new(0),
+            calls: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Counters, indexed by `Stage as usize`
+static STAGES: [StageCounter; 3] = [StageCounter::new(), StageCounter::new(), StageCounter::new()];
+
+/// CPU time consumed by the calling thread
+fn thread_cpu_time() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid, writable timespec
+    unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// Charges the thread CPU time until it is dropped to a stage
+///
+/// Only wrap synchronous code: an `.await` in scope may resume the task on
+/// another thread and the reading would be meaningless.
+pub struct StageTimer {
+    stage: Stage,
+    start: Duration,
+}
+
+impl StageTimer {
+    /// Start timing a section of `stage`
+    pub fn start(stage: Stage) -> Self {
+        Self {
+            stage,
+            start: thread_cpu_time(),
+        }
+    }
+}
+
+impl Drop for StageTimer {
+    fn drop(&mut self) {
+        let elapsed = thread_cpu_time().saturating_sub(self.start);
+        let counter = &STAGES[self.stage as usize];
+        counter
+            .cpu_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        counter.calls.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// One stage in `/debug/stages`
+#[derive(Debug, Serialize)]
+pub struct StageStats {
+    /// CPU time since startup, in milliseconds
+    pub cpu_ms: u64,
+
+    /// Sections measured
+    pub calls: u64,
+
+    /// Mean CPU time per section, in microseconds
+    pub mean_us: u64,
+}
+
+/// CPU accounting of every stage
+pub fn stage_stats() -> BTreeMap<&'static str, StageStats> {
+    Stage::ALL
+        .iter()
+        .map(|&stage| {
+            let counter = &STAGES[stage as usize];
+            let nanos = counter.cpu_nanos.load(Ordering::Relaxed);
+            let calls = counter.calls.load(Ordering::Relaxed);
+            let stats = StageStats {
+                cpu_ms: nanos / 1_000_000,
+                calls,
+                mean_us: nanos.checked_div(calls).unwrap_or(0) / 1_000,
+            };
+            (stage.name(), stats)
+        })
+        .collect()
+}
+
+/// Query of the profile routes
+#[derive(Debug, Deserialize)]
+struct ProfileQuery {
+    /// Profile length in seconds
+    seconds: Option<u64>,
+}
+
+/// Build the profiling router, guarded by the admin `token`
+pub fn router(token: String) -> Router {
+    Router::new()
+        .route("/debug/pprof/profile", get(profile))
+        .route("/debug/pprof/flamegraph", get(flamegraph))
+        .route("/debug/stages", get(stages))
+        .with_state(token)
+}
+
+/// Output format of a capture
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Pprof,
+    Flamegraph,
+}
+
+/// Sample the whole process for `seconds` and render the report
+///
+/// Runs on a blocking thread: the profiler guard must stay on one thread
+/// and the capture would otherwise hold a runtime worker for its length.
+async fn capture(seconds: Option<u64>, format: Format) -> Result<Vec<u8>, StatusCode> {
+    let seconds = seconds.unwrap_or(DEFAULT_PROFILE_SECS).clamp(1, MAX_PROFILE_SECS);
+    info!("Capturing {}s CPU profile", seconds);
+
+    tokio::task::spawn_blocking(move || {
+        // Fails while another capture is running
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(PROFILE_FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(|_| StatusCode::CONFLICT)?;
+        std::thread::sleep(Duration::from_secs(seconds));
+
+        let report = guard
+            .report()
+            .build()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let body = match format {
+            Format::Pprof => {
+                let profile = report.pprof().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                prost::Message::encode_to_vec(&profile)
+            }
+            Format::Flamegraph => {
+                let mut svg = Vec::new();
+                report
+                    .flamegraph(&mut svg)
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                svg
+            }
+        };
+        Ok(body)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+}
+
+/// `GET /debug/pprof/profile`
+async fn profile(
+    State(token): State<String>,
+    headers: HeaderMap,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Response, StatusCode> {
+    authorize(&headers, &token)?;
+    let body = capture(query.seconds, Format::Pprof).await?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], body).into_response())
+}
+
+/// `GET /debug/pprof/flamegraph`
+async fn flamegraph(
+    State(token): State<String>,
+    headers: HeaderMap,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Response, StatusCode> {
+    authorize(&headers, &token)?;
+    let body = capture(query.seconds, Format::Flamegraph).await?;
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], body).into_response())
+}
+
+/// `GET /debug/stages`
+async fn stages(
+    State(token): State<String>,
+    headers: HeaderMap,
+) -> Result<Json<BTreeMap<&'static str, StageStats>>, StatusCode> {
+    authorize(&headers, &token)?;
+    Ok(Json(stage_stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_timer_charges_its_stage() {
+        let before = stage_stats()["encode"].calls;
+        {
+            let _cpu = StageTimer::start(Stage::Encode);
+            let mut x = 0u64;
+            for i in 0..1_000_000u64 {
+                x = x.wrapping_mul(31).wrapping_add(i);
+            }
+            std::hint::black_box(x);
+        }
+        let stats = stage_stats();
+        assert_eq!(stats["encode"].calls, before + 1);
+        assert!(stats.contains_key("decrypt") && stats.contains_key("parse"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Stake-weighted quorum computation
+//!
+//! Reads operator stakes from the ServiceManager registry and decides whether
+//! a set of signers controls enough of the total active stake to dispatch an
+//! attestation. A simple signer count is not enough: a handful of small
+//! operators must not be able to outvote the operators carrying most of the
+//! economic security.
+
+use crate::error::SentinelError;
+use ethers::abi::{decode, encode, ParamType, Token};
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Basis points denominator (matches `ServiceManager.BASIS_POINTS`)
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Default stake threshold: two thirds of the total active stake
+pub const DEFAULT_QUORUM_THRESHOLD_BPS: u64 = 6_667;
+
+/// Stake information for a single registered operator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorStake {
+    /// Operator address
+    pub address: Address,
+
+    /// Amount currently staked
+    pub stake: U256,
+
+    /// Whether the operator is active in the registry
+    pub is_active: bool,
+}
+
+/// Read-only view of the operator registry on the ServiceManager contract
+pub struct StakeRegistry {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+}
+
+impl StakeRegistry {
+    /// Create a new registry reader
+    pub fn new(provider: Arc<Provider<Http>>, service_manager_address: Address) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+        }
+    }
+
+    /// Fetch every registered operator together with its stake
+    pub async fn operators(&self) -> Result<Vec<OperatorStake>, SentinelError> {
+        // getOperators() returns address[]
+        let result = self.call(&keccak256(b"getOperators()")[0..4], &[]).await?;
+        let addresses = match decode(&[ParamType::Array(Box::new(ParamType::Address))], &result)
+            .map_err(SentinelError::l1)?
+            .pop()
+        {
+            Some(Token::Array(tokens)) => tokens
+                .into_iter()
+                .filter_map(|t| t.into_address())
+                .collect::<Vec<_>>(),
+            _ => return Err(SentinelError::L1("Malformed getOperators response".to_string())),
+        };
+
+        let mut operators = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            operators.push(self.operator(address).await?);
+        }
+
+        debug!("Loaded {} operators from registry", operators.len());
+        Ok(operators)
+    }
+
+    /// Fetch a single operator's registry entry
+    pub async fn operator(&self, address: Address) -> Result<OperatorStake, SentinelError> {
+        // getOperator(address) returns (address addr, uint256 stake, bool isActive, uint256 registeredAt)
+        let result = self
+            .call(
+                &keccak256(b"getOperator(address)")[0..4],
+                &[Token::Address(address)],
+            )
+            .await?;
+
+        let tokens = decode(
+            &[
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Bool,
+                ParamType::Uint(256),
+            ],
+            &result,
+        )
+        .map_err(SentinelError::l1)?;
+
+        let stake = tokens[1].clone().into_uint().unwrap_or_default();
+        let is_active = tokens[2].clone().into_bool().unwrap_or(false);
+
+        Ok(OperatorStake {
+            address,
+            stake,
+            is_active,
+        })
+    }
+
+    /// Perform an `eth_call` against the ServiceManager
+    async fn call(&self, selector: &[u8], args: &[Token]) -> Result<Bytes, SentinelError> {
+        let mut calldata = Vec::new();
+        calldata.extend_from_slice(selector);
+        calldata.extend_from_slice(&encode(args));
+
+        let call = TransactionRequest::new()
+            .to(self.service_manager_address)
+            .data(Bytes::from(calldata));
+
+        self.provider
+            .call(&call.into(), None)
+            .await
+            .map_err(SentinelError::l1)
+    }
+}
+
+/// Outcome of a quorum evaluation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumStatus {
+    /// Stake controlled by the active signers
+    pub signed_stake: U256,
+
+    /// Total stake of all active operators
+    pub total_stake: U256,
+
+    /// Stake required to reach quorum
+    pub required_stake: U256,
+}
+
+impl QuorumStatus {
+    /// Whether the signers reach the weighted threshold
+    pub fn is_met(&self) -> bool {
+        !self.total_stake.is_zero() && self.signed_stake >= self.required_stake
+    }
+}
+
+/// Stake-weighted quorum calculator
+pub struct QuorumCalculator {
+    /// Required share of total stake in basis points
+    threshold_bps: u64,
+}
+
+impl QuorumCalculator {
+    /// Create a new calculator with the given threshold in basis points
+    pub fn new(threshold_bps: u64) -> Self {
+        Self { threshold_bps }
+    }
+
+    /// Evaluate whether `signers` control enough stake among `operators`
+    ///
+    /// Inactive operators, unknown signers and duplicate signers contribute
+    /// no weight.
+    pub fn evaluate(&self, operators: &[OperatorStake], signers: &[Address]) -> QuorumStatus {
+        let active = operators.iter().filter(|op| op.is_active);
+
+        let total_stake = active
+            .clone()
+            .fold(U256::zero(), |acc, op| acc.saturating_add(op.stake));
+
+        let signed_stake = active
+            .filter(|op| signers.contains(&op.address))
+            .fold(U256::zero(), |acc, op| acc.saturating_add(op.stake));
+
+        // Round up so that e.g. 6667 bps of 3 equal stakes needs all of 2/3
+        let required_stake = (total_stake.saturating_mul(U256::from(self.threshold_bps))
+            + U256::from(BASIS_POINTS - 1))
+            / U256::from(BASIS_POINTS);
+
+        QuorumStatus {
+            signed_stake,
+            total_stake,
+            required_stake,
+        }
+    }
+
+    /// Evaluate and return an error if the threshold is not met
+    pub fn ensure(
+        &self,
+        operators: &[OperatorStake],
+        signers: &[Address],
+    ) -> Result<QuorumStatus, SentinelError> {
+        let status = self.evaluate(operators, signers);
+        if !status.is_met() {
+            return Err(SentinelError::QuorumNotMet(format!(
+                "signed stake {} below required {} of total {}",
+                status.signed_stake, status.required_stake, status.total_stake
+            )));
+        }
+        Ok(status)
+    }
+}
+
+impl Default for QuorumCalculator {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUORUM_THRESHOLD_BPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(byte: u8, stake: u64, is_active: bool) -> OperatorStake {
+        OperatorStake {
+            address: Address::repeat_byte(byte),
+            stake: U256::from(stake),
+            is_active,
+        }
+    }
+
+    #[test]
+    fn test_weighted_quorum_met() {
+        let operators = vec![op(1, 70, true), op(2, 20, true), op(3, 10, true)];
+        let calculator = QuorumCalculator::default();
+
+        // A single operator holding 70% of stake is enough
+        let status = calculator.evaluate(&operators, &[Address::repeat_byte(1)]);
+        assert!(status.is_met());
+
+        // Two small operators holding 30% are not
+        let status = calculator.evaluate(
+            &operators,
+            &[Address::repeat_byte(2), Address::repeat_byte(3)],
+        );
+        assert!(!status.is_met());
+    }
+
+    #[test]
+    fn test_inactive_operators_ignored() {
+        let operators = vec![op(1, 50, true), op(2, 1000, false)];
+        let calculator = QuorumCalculator::default();
+
+        let status = calculator.evaluate(&operators, &[Address::repeat_byte(1)]);
+        assert_eq!(status.total_stake, U256::from(50));
+        assert!(status.is_met());
+
+        assert!(calculator
+            .ensure(&operators, &[Address::repeat_byte(2)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_empty_registry_never_met() {
+        let calculator = QuorumCalculator::default();
+        let status = calculator.evaluate(&[], &[Address::repeat_byte(1)]);
+        assert!(!status.is_met());
+    }
+}
@@ -0,0 +1,191 @@
+//! Hard caps on signing
+//!
+//! Bounds the damage a scanner bug or a forged memo can do before a human
+//! looks: at most so many attestations per minute and per hour, and at most
+//! so much attested value per day. Crossing any cap trips the halt switch,
+//! so signing stays paused until an operator re-arms it. The window is kept on
+//! disk so a crash loop cannot reset the caps.
+
+use crate::error::SentinelError;
+use crate::halt::HaltSwitch;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Halt source name
+const SOURCE: &str = "signing_rate_limit";
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Configured caps; `None` leaves a dimension unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SigningCaps {
+    /// Attestations per rolling minute
+    pub per_minute: Option<u64>,
+
+    /// Attestations per rolling hour
+    pub per_hour: Option<u64>,
+
+    /// Attested value per rolling day, in zatoshi
+    pub daily_value: Option<u64>,
+}
+
+/// Signing rate limiter that halts attestation when a cap is crossed
+pub struct SigningRateLimiter {
+    /// Caps to enforce
+    caps: SigningCaps,
+
+    /// Signatures within the last day as (unix secs, attested amount)
+    window: VecDeque<(u64, u64)>,
+
+    /// Switch tripped when a cap is crossed
+    halt: Arc<HaltSwitch>,
+
+    /// Where `window` is persisted, if anywhere
+    path: Option<PathBuf>,
+}
+
+impl SigningRateLimiter {
+    /// Create a limiter enforcing `caps`
+    pub fn new(caps: SigningCaps, halt: Arc<HaltSwitch>) -> Self {
+        Self {
+            caps,
+            window: VecDeque::new(),
+            halt,
+            path: None,
+        }
+    }
+
+    /// Keep the window at `path`, resuming the one left there by an earlier run
+    pub fn persisted(mut self, path: impl Into<PathBuf>) -> Result<Self, SentinelError> {
+        let path = path.into();
+        match std::fs::read(&path) {
+            Ok(contents) => {
+                self.window = serde_json::from_slice(&contents)
+                    .map_err(|e| SentinelError::Storage(format!("{}: {}", path.display(), e)))?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// Count a signature of `amount` about to be made
+    ///
+    /// If it would cross a cap, nothing is counted, attestation is halted and
+    /// the reason is returned. A signature that cannot be counted on disk is
+    /// refused.
+    pub fn admit(&mut self, amount: u64, now: u64) -> Result<(), String> {
+        while let Some((ts, _)) = self.window.front() {
+            if now.saturating_sub(*ts) < DAY {
+                break;
+            }
+            self.window.pop_front();
+        }
+
+        if let Some(reason) = self.exceeded(amount, now) {
+            self.halt.trip(SOURCE, reason.clone());
+            return Err(reason);
+        }
+        self.window.push_back((now, amount));
+        if let Err(e) = self.save() {
+            self.window.pop_back();
+            return Err(format!("failed to persist the signing window: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Replace the window file in one rename
+    fn save(&self) -> Result<(), SentinelError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.window)?)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// The cap one more signature of `amount` would cross, if any
+    fn exceeded(&self, amount: u64, now: u64) -> Option<String> {
+        let within = |secs: u64| {
+            self.window
+                .iter()
+                .filter(|(ts, _)| now.saturating_sub(*ts) < secs)
+                .count() as u64
+        };
+
+        if let Some(cap) = self.caps.per_minute {
+            if within(MINUTE) >= cap {
+                return Some(format!("more than {} attestations in a minute", cap));
+            }
+        }
+        if let Some(cap) = self.caps.per_hour {
+            if within(HOUR) >= cap {
+                return Some(format!("more than {} attestations in an hour", cap));
+            }
+        }
+        if let Some(cap) = self.caps.daily_value {
+            let used: u64 = self.window.iter().map(|(_, a)| a).sum();
+            if used.saturating_add(amount) > cap {
+                return Some(format!(
+                    "attested value would exceed {} zatoshi in a day ({} used)",
+                    cap, used
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap_halts_until_rearmed() {
+        let halt = Arc::new(HaltSwitch::new());
+        let caps = SigningCaps {
+            per_minute: Some(2),
+            per_hour: None,
+            daily_value: Some(1_000),
+        };
+        let mut limiter = SigningRateLimiter::new(caps, halt.clone());
+
+        assert!(limiter.admit(100, 0).is_ok());
+        assert!(limiter.admit(100, 10).is_ok());
+        assert!(limiter.admit(100, 20).is_err());
+        assert!(halt.is_halted());
+
+        // A minute later the count has rolled, but the value cap still binds
+        halt.rearm();
+        assert!(limiter.admit(700, 80).is_ok());
+        assert!(limiter.admit(200, 90).is_err());
+        assert!(limiter.admit(100, DAY + 1).is_ok());
+    }
+
+    #[test]
+    fn test_window_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signing_window.json");
+        let caps = SigningCaps {
+            per_hour: Some(2),
+            ..SigningCaps::default()
+        };
+        let open = || {
+            SigningRateLimiter::new(caps, Arc::new(HaltSwitch::new()))
+                .persisted(&path)
+                .unwrap()
+        };
+
+        let mut limiter = open();
+        assert!(limiter.admit(100, 0).is_ok());
+        assert!(limiter.admit(100, 10).is_ok());
+
+        let mut limiter = open();
+        assert!(limiter.admit(100, 20).is_err());
+    }
+}
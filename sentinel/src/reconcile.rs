@@ -0,0 +1,245 @@
+//! Continuous vault reconciliation
+//!
+//! Periodically recomputes vault inflows (deposits seen), outflows (releases
+//! and refunds) and the amount minted on L1 from `DepositVerified` events, and
+//! halts attestation if the books stop adding up. Only dispatches of notes in
+//! the deposit store are counted: one the scanner never observed is the
+//! consistency watchdog's to flag. The invariants are:
+//!
+//! - minted on L1 ≤ deposits we attested, net of bridge fees
+//! - minted on L1 ≤ deposits received, leaving out orphaned, rejected and
+//!   refunded ones
+//! - released for withdrawals ≤ minted on L1
+
+use crate::dispatches::{self, DepositVerified};
+use crate::error::SentinelError;
+use crate::halt::HaltSwitch;
+use crate::reserves::read_releases;
+use crate::store::{DepositStatus, DepositStore};
+use ethers::prelude::*;
+use ethers::types::Address;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// Snapshot of the vault's books
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Books {
+    /// Sum of deposits received, leaving out orphaned, rejected and refunded ones
+    pub deposited: u64,
+
+    /// Sum of deposits we attested to L1, net of bridge fees
+    pub attested: u64,
+
+    /// Sum of deposits refunded or queued for refund
+    pub refunded: u64,
+
+    /// Sum of ZEC released for withdrawals
+    pub released: u64,
+
+    /// Sum minted on L1 according to `DepositVerified` events
+    pub minted: u64,
+}
+
+impl Books {
+    /// First invariant violated by more than `tolerance`, if any
+    pub fn violation(&self, tolerance: u64) -> Option<String> {
+        if self.minted > self.attested.saturating_add(tolerance) {
+            return Some(format!(
+                "minted {} exceeds attested {}",
+                self.minted, self.attested
+            ));
+        }
+
+        if self.minted > self.deposited.saturating_add(tolerance) {
+            return Some(format!(
+                "minted {} exceeds vault inflows net of refunds {}",
+                self.minted, self.deposited
+            ));
+        }
+
+        if self.released > self.minted.saturating_add(tolerance) {
+            return Some(format!(
+                "released {} exceeds minted {}",
+                self.released, self.minted
+            ));
+        }
+
+        None
+    }
+}
+
+/// Periodic reconciliation job
+pub struct Reconciler {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Release queue file, if withdrawals are enabled
+    release_queue_path: Option<PathBuf>,
+
+    /// Allowed divergence in zatoshi
+    tolerance: u64,
+
+    /// Switch to trip on divergence
+    halt: Arc<HaltSwitch>,
+
+    /// Dispatches seen on L1, by deposit key
+    dispatched: HashMap<String, DepositVerified>,
+
+    /// Next L1 block to query
+    from_block: u64,
+}
+
+impl Reconciler {
+    /// Create a new reconciler; `start_block` is the ServiceManager's
+    /// deployment block
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        start_block: u64,
+        store: Arc<DepositStore>,
+        release_queue_path: Option<PathBuf>,
+        tolerance: u64,
+        halt: Arc<HaltSwitch>,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            store,
+            release_queue_path,
+            tolerance,
+            halt,
+            dispatched: HashMap::new(),
+            from_block: start_block,
+        }
+    }
+
+    /// Reconcile on a schedule forever
+    pub async fn run(mut self, interval: Duration) {
+        loop {
+            match self.reconcile().await {
+                Ok(books) => {
+                    if let Some(violation) = books.violation(self.tolerance) {
+                        self.halt.trip("reconciliation", violation);
+                    }
+                }
+                Err(e) => error!("Reconciliation failed: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Recompute the vault's books
+    pub async fn reconcile(&mut self) -> Result<Books, SentinelError> {
+        self.update_dispatched().await?;
+
+        let mut books = Books::default();
+        for record in self.store.all() {
+            let mut status = record.status;
+            if let Some(event) = self.dispatched.get(&record.key()) {
+                books.minted = books.minted.saturating_add(minted(event.amount));
+
+                // A follower's record stays Detected when a peer dispatched
+                if status == DepositStatus::Detected && event.mark_submitted(&self.store)? {
+                    status = DepositStatus::Submitted;
+                }
+            }
+
+            // A reorged-out deposit never reached the vault, and a rejected
+            // one goes back to the depositor instead of backing a mint
+            match status {
+                DepositStatus::Orphaned | DepositStatus::Rejected => continue,
+                DepositStatus::RefundQueued | DepositStatus::Refunded => {
+                    books.refunded += record.amount;
+                    continue;
+                }
+                DepositStatus::Submitted | DepositStatus::Claimed | DepositStatus::ClaimExpired => {
+                    books.attested += record.amount.saturating_sub(record.fee)
+                }
+                _ => {}
+            }
+            books.deposited += record.amount;
+        }
+
+        if let Some(path) = &self.release_queue_path {
+            books.released = read_releases(path)
+                .map_err(|e| SentinelError::Storage(e.to_string()))?
+                .iter()
+                .map(|r| r.amount)
+                .sum();
+        }
+
+        debug!("Reconciled books: {:?}", books);
+        Ok(books)
+    }
+
+    /// Collect `DepositVerified` events since the last poll
+    async fn update_dispatched(&mut self) -> Result<(), SentinelError> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        if head < self.from_block {
+            return Ok(());
+        }
+
+        let filter = dispatches::filter(self.service_manager_address)
+            .from_block(self.from_block)
+            .to_block(head);
+
+        for log in self.provider.get_logs(&filter).await? {
+            let event = DepositVerified::decode(&log)?;
+            self.dispatched.entry(event.key()).or_insert(event);
+        }
+
+        self.from_block = head + 1;
+        Ok(())
+    }
+}
+
+/// Minted amount in zatoshi; one beyond `u64` saturates, so it can only
+/// break the invariants rather than wrap around them
+fn minted(amount: U256) -> u64 {
+    u64::try_from(amount).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balanced_books_pass() {
+        let books = Books {
+            deposited: 1_000,
+            attested: 800,
+            refunded: 100,
+            released: 300,
+            minted: 800,
+        };
+        assert_eq!(books.violation(0), None);
+    }
+
+    #[test]
+    fn test_over_minting_detected() {
+        let books = Books {
+            deposited: 1_000,
+            attested: 800,
+            refunded: 0,
+            released: 0,
+            minted: 900,
+        };
+        assert!(books.violation(0).is_some());
+        assert_eq!(books.violation(100), None);
+    }
+
+    #[test]
+    fn test_oversized_mint_saturates() {
+        assert_eq!(minted(U256::from(500)), 500);
+        assert_eq!(minted(U256::from(u64::MAX) + 1), u64::MAX);
+    }
+}
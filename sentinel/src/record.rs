@@ -0,0 +1,187 @@
+//! Fixture recording
+//!
+//! Captures upstream responses during real runs so incidents can be replayed:
+//!
+//! - `RECORD_FIXTURES_DIR` routes L1 JSON-RPC traffic through a local proxy
+//!   that writes every request/response pair to `<dir>/l1/`
+//! - `sentinel record --from <h> --to <h>` pulls compact blocks from
+//!   lightwalletd, plus the full transactions of vault-addressed outputs, into
+//!   the layout `sentinel replay` reads (`blocks/`, `tx/`)
+
+use crate::config::SentinelConfig;
+use crate::lightwalletd::ClientTls;
+use crate::scanner::Scanner;
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use prost::Message;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+use zcash_client_backend::proto::service::{BlockId, BlockRange, TxFilter};
+
+/// Writes fixture files under one directory
+pub struct FixtureRecorder {
+    /// Fixture root
+    dir: PathBuf,
+
+    /// Sequence number for recorded L1 calls
+    seq: AtomicU64,
+}
+
+impl FixtureRecorder {
+    /// Create the fixture layout under `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        for sub in ["blocks", "tx", "l1"] {
+            std::fs::create_dir_all(dir.join(sub))?;
+        }
+        Ok(Self {
+            dir,
+            seq: AtomicU64::new(0),
+        })
+    }
+
+    /// Record a compact block
+    pub fn record_block(&self, block: &CompactBlock) -> Result<()> {
+        let path = self.dir.join("blocks").join(format!("{}.bin", block.height));
+        std::fs::write(path, block.encode_to_vec())?;
+        Ok(())
+    }
+
+    /// Record a raw transaction (txid in internal byte order)
+    pub fn record_tx(&self, txid: &[u8; 32], raw: &[u8]) -> Result<()> {
+        let mut display = *txid;
+        display.reverse();
+        let path = self.dir.join("tx").join(format!("{}.bin", hex::encode(display)));
+        std::fs::write(path, raw)?;
+        Ok(())
+    }
+
+    /// Record one L1 JSON-RPC exchange
+    pub fn record_l1(&self, request: &Value, status: u16, response: &Value) -> Result<()> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let method = request["method"].as_str().unwrap_or("batch");
+        let path = self.dir.join("l1").join(format!("{:06}-{}.json", seq, method));
+        let entry = json!({"request": request, "status": status, "response": response});
+        std::fs::write(path, serde_json::to_vec_pretty(&entry)?)?;
+        Ok(())
+    }
+}
+
+/// Route L1 traffic through a recording proxy if `RECORD_FIXTURES_DIR` is set
+pub async fn install(mut config: SentinelConfig) -> Result<SentinelConfig> {
+    let Some(dir) = config.record_fixtures_dir.clone() else {
+        return Ok(config);
+    };
+    let recorder = Arc::new(FixtureRecorder::new(&dir)?);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let app = Router::new().route("/", post(proxy)).with_state(ProxyState {
+        upstream: config.l1_rpc_url.clone(),
+        recorder,
+        client: reqwest::Client::new(),
+    });
+    let server = axum::Server::from_tcp(listener)?.serve(app.into_make_service());
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("Recording proxy stopped: {}", e);
+        }
+    });
+
+    info!("Recording L1 traffic to {}", dir);
+    config.l1_rpc_url = url;
+    Ok(config)
+}
+
+/// Proxy state
+#[derive(Clone)]
+struct ProxyState {
+    /// Upstream L1 RPC URL
+    upstream: String,
+
+    /// Fixture writer
+    recorder: Arc<FixtureRecorder>,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+/// Forward one JSON-RPC request and record the exchange
+async fn proxy(State(state): State<ProxyState>, body: Bytes) -> Response {
+    let upstream = match state
+        .client
+        .post(&state.upstream)
+        .header("content-type", "application/json")
+        .body(body.clone())
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
+    let status = upstream.status().as_u16();
+    let bytes = upstream.bytes().await.unwrap_or_default();
+
+    let request: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let response: Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    if let Err(e) = state.recorder.record_l1(&request, status, &response) {
+        warn!("Failed to record L1 exchange: {}", e);
+    }
+
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+    (status, bytes.to_vec()).into_response()
+}
+
+/// Entry point for `sentinel record`
+pub async fn record_command(config: &SentinelConfig, dir: &Path, from: u64, to: u64) -> Result<()> {
+    let recorder = FixtureRecorder::new(dir)?;
+    let (sender, _receiver) = mpsc::channel(1);
+    let scanner = Scanner::new(
+        config.lightwalletd_url.clone(),
+        &config.shards(),
+        config.confirmation_depth,
+        sender,
+    )?;
+
+    let mut client = crate::lightwalletd::connect(&config.lightwalletd_url, &ClientTls::from_config(config))
+        .await
+        .context("Cannot reach lightwalletd")?;
+    let mut stream = client
+        .get_block_range(BlockRange {
+            start: Some(BlockId { height: from, hash: vec![] }),
+            end: Some(BlockId { height: to, hash: vec![] }),
+        })
+        .await?
+        .into_inner();
+
+    let (mut blocks, mut txs) = (0, 0);
+    while let Some(block) = stream.message().await? {
+        recorder.record_block(&block)?;
+        blocks += 1;
+
+        for output in scanner.find_vault_outputs(&block)? {
+            let raw = client
+                .get_transaction(TxFilter {
+                    hash: output.txid.to_vec(),
+                    ..TxFilter::default()
+                })
+                .await?
+                .into_inner();
+            recorder.record_tx(&output.txid, &raw.data)?;
+            txs += 1;
+        }
+    }
+
+    println!("Recorded {} blocks and {} vault transactions to {}", blocks, txs, dir.display());
+    Ok(())
+}
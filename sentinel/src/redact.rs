@@ -0,0 +1,210 @@
+//! Credential redaction for logs and errors
+//!
+//! Secret types keep key material out of `Debug` output, but a key can still
+//! end up in a log line through an error message, a URL or a hand-written
+//! format string. Everything the log layer writes, and every message built
+//! with the `SentinelError` helpers, passes through `redact` first. It
+//! replaces:
+//!
+//! - secrets registered at startup (operator key, viewing keys, tokens)
+//! - Zcash viewing and spending key encodings, recognised by their prefix
+//! - values labelled like a secret: `private_key=…`, `"passphrase": "…"`,
+//!   `ADMIN_TOKEN: …`
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::RwLock;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+use zeroize::Zeroizing;
+
+/// Replacement for redacted text
+const REDACTED: &str = "[REDACTED]";
+
+/// Encodings of Zcash viewing and spending keys
+const KEY_PREFIXES: &[&str] = &[
+    "zxviews",
+    "zxviewtestsapling",
+    "zxviewregtestsapling",
+    "uview",
+    "secret-extended-key-",
+];
+
+/// Label endings whose value is a secret
+const SECRET_LABELS: &[&str] = &[
+    "private_key",
+    "privatekey",
+    "secret_key",
+    "viewing_key",
+    "passphrase",
+    "password",
+    "mnemonic",
+    "token",
+];
+
+/// Shortest secret worth registering; anything shorter would mangle logs
+const MIN_SECRET_LEN: usize = 8;
+
+/// Secrets registered at startup
+static SECRETS: RwLock<Vec<Zeroizing<String>>> = RwLock::new(Vec::new());
+
+/// Redact every later occurrence of `secret` (and of it without a `0x` prefix)
+pub fn register_secret(secret: &str) {
+    let mut secrets = SECRETS.write().unwrap();
+    for secret in [secret, secret.strip_prefix("0x").unwrap_or(secret)] {
+        if secret.len() >= MIN_SECRET_LEN && !secrets.iter().any(|s| s.as_str() == secret) {
+            secrets.push(Zeroizing::new(secret.to_string()));
+        }
+    }
+    // Longest first, so a secret is never partly replaced by a shorter one
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+}
+
+/// Whether `b` can be part of a key encoding or label
+fn is_word(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'-'
+}
+
+/// Span of the value after a label ending at `at`, as in `label=value` or `"label": "value"`
+fn labelled_value(bytes: &[u8], mut at: usize) -> Option<(usize, usize)> {
+    let skip = |at: &mut usize| {
+        while *at < bytes.len() && matches!(bytes[*at], b'"' | b'\'' | b' ') {
+            *at += 1;
+        }
+    };
+    skip(&mut at);
+    if !matches!(bytes.get(at), Some(b'=' | b':')) {
+        return None;
+    }
+    at += 1;
+    skip(&mut at);
+
+    let start = at;
+    while at < bytes.len()
+        && !matches!(bytes[at], b'"' | b'\'' | b' ' | b',' | b'}' | b')' | b'&' | b'\n' | b'\r' | b'\t')
+    {
+        at += 1;
+    }
+    (at > start).then_some((start, at))
+}
+
+/// Replace anything that looks like key material in `text`
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut text = Cow::Borrowed(text);
+    for secret in SECRETS.read().unwrap().iter() {
+        if text.contains(secret.as_str()) {
+            text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+        }
+    }
+
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        if !is_word(bytes[at]) {
+            at += 1;
+            continue;
+        }
+        let start = at;
+        while at < bytes.len() && is_word(bytes[at]) {
+            at += 1;
+        }
+
+        let word = text[start..at].to_ascii_lowercase();
+        if KEY_PREFIXES.iter().any(|prefix| word.starts_with(prefix)) {
+            spans.push((start, at));
+        } else if SECRET_LABELS.iter().any(|label| word.ends_with(label)) {
+            // Values already hidden by a secret type are left alone
+            if let Some((value_start, value_end)) = labelled_value(bytes, at) {
+                if !text[value_start..value_end].contains("REDACTED") {
+                    spans.push((value_start, value_end));
+                }
+                at = value_end;
+            }
+        }
+    }
+    if spans.is_empty() {
+        return text;
+    }
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        redacted.push_str(&text[last..start]);
+        redacted.push_str(REDACTED);
+        last = end;
+    }
+    redacted.push_str(&text[last..]);
+    Cow::Owned(redacted)
+}
+
+/// Log output layer that redacts every formatted event before it is written
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer().with_writer(RedactingStdout)
+}
+
+/// Standard output, redacted
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RedactingStdout;
+
+impl<'a> MakeWriter<'a> for RedactingStdout {
+    type Writer = RedactingWriter<io::Stdout>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(io::stdout())
+    }
+}
+
+/// Writer that redacts each write; the fmt layer writes one event per call
+pub struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_keys_labels_and_registered_secrets() {
+        // Viewing keys by encoding, wherever they appear
+        let line = redact("bad key zxviews1qwerty0123 for shard b");
+        assert_eq!(line, "bad key [REDACTED] for shard b");
+
+        // Labelled values in env, Debug and JSON styles
+        assert_eq!(
+            redact("OPERATOR_PRIVATE_KEY=0xabc123 next"),
+            "OPERATOR_PRIVATE_KEY=[REDACTED] next"
+        );
+        assert_eq!(
+            redact(r#"{"passphrase": "hunter22", "id": 1}"#),
+            r#"{"passphrase": "[REDACTED]", "id": 1}"#
+        );
+        assert_eq!(
+            redact("https://rpc.example/v1?token=s3cr3t&x=1"),
+            "https://rpc.example/v1?token=[REDACTED]&x=1"
+        );
+
+        // Public fields that merely look similar are kept
+        let public = "tx_hash: ab12, secret_hash: cd34";
+        assert_eq!(redact(public), public);
+
+        // Registered secrets, with or without 0x
+        register_secret("0xdeadbeefcafef00d");
+        assert_eq!(redact("signing with deadbeefcafef00d"), "signing with [REDACTED]");
+    }
+}
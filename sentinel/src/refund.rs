@@ -0,0 +1,209 @@
+//! Automated refunds for rejected deposits
+//!
+//! Deposits that will never be attested (bad memo, out of bounds, expired,
+//! screened) are refunded to the depositor when they supplied a refund
+//! address. The sentinel only holds a viewing key, so refunds are handed to the
+//! vault's payout queue minus a fee, and their progress is tracked in the
+//! deposit store so user funds are never silently stranded. A deposit is
+//! queued at most once, however often its transition fails, and is marked
+//! refunded when the payout side confirms it through the admin API.
+
+use crate::error::SentinelError;
+use crate::store::{deposit_key, DepositRecord, DepositStatus, DepositStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, error, info};
+
+/// Default fee deducted from refunds (ZIP-317 conventional fee for a small tx)
+pub const DEFAULT_REFUND_FEE_ZATOSHI: u64 = 10_000;
+
+/// Payout instruction for a refund
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundInstruction {
+    /// Zcash transaction hash of the rejected deposit (hex)
+    pub deposit_tx_hash: String,
+
+    /// Index of the rejected vault note in the transaction
+    #[serde(default)]
+    pub deposit_output_index: u32,
+
+    /// Zcash address to pay the refund to
+    pub refund_address: String,
+
+    /// Amount to refund in zatoshi (deposit minus fee)
+    pub amount: u64,
+
+    /// Fee retained in zatoshi
+    pub fee: u64,
+
+    /// Why the deposit was rejected
+    pub reason: Option<String>,
+}
+
+/// Moves rejected deposits into the refund payout queue
+pub struct RefundProcessor {
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Append-only refund payout queue
+    queue_path: PathBuf,
+
+    /// Fee deducted from each refund
+    fee_zatoshi: u64,
+}
+
+impl RefundProcessor {
+    /// Create a new processor
+    pub fn new(store: Arc<DepositStore>, queue_path: PathBuf, fee_zatoshi: u64) -> Self {
+        Self {
+            store,
+            queue_path,
+            fee_zatoshi,
+        }
+    }
+
+    /// Queue refunds for every refundable rejected deposit
+    pub async fn process(&self) -> Result<usize, SentinelError> {
+        let mut queued = 0;
+        let mut in_queue = self.queued_keys().await?;
+
+        for record in self.store.with_status(DepositStatus::Rejected) {
+            let Some(instruction) = self.instruction_for(&record) else {
+                continue;
+            };
+
+            // Queued on an earlier pass whose transition failed
+            if in_queue.insert(record.key()) {
+                self.enqueue(&instruction).await?;
+            }
+            self.store
+                .transition(&record.key(), DepositStatus::RefundQueued, None)?;
+            queued += 1;
+
+            info!(
+                "Refund queued: {} zatoshi to {} for deposit {}",
+                instruction.amount, instruction.refund_address, record.tx_hash
+            );
+        }
+
+        Ok(queued)
+    }
+
+    /// Record that the payout side has paid the queued refund of the deposit
+    /// stored under `key`
+    pub fn mark_refunded(&self, key: &str) -> Result<(), SentinelError> {
+        self.store
+            .transition(key, DepositStatus::Refunded, None)
+            .map(|_| ())
+    }
+
+    /// Process rejected deposits on a schedule forever
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        loop {
+            if let Err(e) = self.process().await {
+                error!("Refund processing failed: {}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Build the refund instruction for a rejected deposit, if refundable
+    fn instruction_for(&self, record: &DepositRecord) -> Option<RefundInstruction> {
+        let Some(refund_address) = &record.refund_address else {
+            debug!(
+                "Rejected deposit {} has no refund address, manual handling required",
+                record.tx_hash
+            );
+            return None;
+        };
+
+        if record.amount <= self.fee_zatoshi {
+            debug!(
+                "Rejected deposit {} of {} zatoshi does not cover the refund fee",
+                record.tx_hash, record.amount
+            );
+            return None;
+        }
+
+        Some(RefundInstruction {
+            deposit_tx_hash: record.tx_hash.clone(),
+            deposit_output_index: record.output_index,
+            refund_address: refund_address.clone(),
+            amount: record.amount - self.fee_zatoshi,
+            fee: self.fee_zatoshi,
+            reason: record.reason.clone(),
+        })
+    }
+
+    /// Store keys of the deposits already in the refund queue
+    async fn queued_keys(&self) -> Result<HashSet<String>, SentinelError> {
+        let contents = match tokio::fs::read_to_string(&self.queue_path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let instruction: RefundInstruction = serde_json::from_str(line)?;
+                Ok(deposit_key(
+                    &instruction.deposit_tx_hash,
+                    instruction.deposit_output_index,
+                ))
+            })
+            .collect()
+    }
+
+    /// Append an instruction to the refund queue
+    async fn enqueue(&self, instruction: &RefundInstruction) -> Result<(), SentinelError> {
+        let mut line = serde_json::to_string(instruction)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.queue_path)
+            .await
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::payload_key;
+    use crate::BridgePayload;
+
+    #[tokio::test]
+    async fn test_refund_queued_once_and_marked_refunded() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let queue_path = dir.path().join("refunds.jsonl");
+        let refunds = RefundProcessor::new(store.clone(), queue_path.clone(), 1_000);
+
+        let payload = BridgePayload::sample();
+        let key = payload_key(&payload);
+        store.insert_detected(&payload).unwrap();
+        store.update(&key, |r| r.refund_address = Some("zs1refund".to_string())).unwrap();
+        store.reject(&key, "out of bounds").unwrap();
+
+        // An instruction left by a pass whose transition failed is not repeated
+        let instruction = refunds.instruction_for(&store.get(&key).unwrap()).unwrap();
+        refunds.enqueue(&instruction).await.unwrap();
+        assert_eq!(refunds.process().await.unwrap(), 1);
+        let queue = std::fs::read_to_string(&queue_path).unwrap();
+        assert_eq!(queue.lines().count(), 1);
+        assert_eq!(store.get(&key).unwrap().status, DepositStatus::RefundQueued);
+
+        refunds.mark_refunded(&key).unwrap();
+        assert_eq!(store.get(&key).unwrap().status, DepositStatus::Refunded);
+    }
+}
@@ -0,0 +1,221 @@
+//! Zcash reorg detection
+//!
+//! The scanner only acts on blocks `CONFIRMATION_DEPTH` below the tip, but a
+//! deeper reorg can still replace blocks it has scanned. The fetch stage
+//! keeps the hashes of the last `REORG_WINDOW` blocks it handed on and checks
+//! that each new block builds on them. When one does not, it walks back to
+//! the fork point, rescans from there and records the blocks it left behind
+//! here.
+//!
+//! Deposits from a reorged-out block move to `Orphaned` and are never signed
+//! or submitted, even if they are already queued. A deposit that was already
+//! attested cannot be taken back; its record is flagged and the operator
+//! alerted through the logs. A reorged-out deposit that is mined again is
+//! detected afresh from its new block. Across a restart only the hash of the
+//! checkpointed block is known (see `checkpoint.rs`), so a reorg below it is
+//! reported as too deep rather than rolled back.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::store::{DepositStatus, DepositStore};
+use crate::BridgePayload;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::{error, warn};
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+
+/// Blocks whose hashes are kept, and so the deepest reorg handled
+pub const REORG_WINDOW: u32 = 100;
+
+/// Hashes of the most recently scanned blocks
+#[derive(Debug, Default)]
+pub struct BlockHashes {
+    hashes: BTreeMap<u32, [u8; 32]>,
+}
+
+impl BlockHashes {
+    /// Whether `block` builds on the scanned block below it, if that is known
+    pub fn extends(&self, block: &CompactBlock) -> bool {
+        let Some(parent) = (block.height as u32).checked_sub(1) else {
+            return true;
+        };
+        self.hashes
+            .get(&parent)
+            .map_or(true, |hash| hash.as_slice() == block.prev_hash.as_slice())
+    }
+
+    /// Record a scanned block, forgetting the oldest past the window
+    pub fn push(&mut self, block: &CompactBlock) -> Result<(), SentinelError> {
+        let height = block.height as u32;
+        let hash = block.hash.as_slice().try_into().map_err(|_| {
+            SentinelError::Scanner(format!("malformed block hash at height {}", height))
+        })?;
+        self.insert(height, hash);
+        Ok(())
+    }
+
+    /// Record the hash of the block at `height`
+    pub fn insert(&mut self, height: u32, hash: [u8; 32]) {
+        self.hashes.insert(height, hash);
+        while self.hashes.len() > REORG_WINDOW as usize {
+            self.hashes.pop_first();
+        }
+    }
+
+    /// Recorded blocks as (height, hash), newest first
+    pub fn newest_first(&self) -> Vec<(u32, [u8; 32])> {
+        self.hashes
+            .iter()
+            .rev()
+            .map(|(h, hash)| (*h, *hash))
+            .collect()
+    }
+
+    /// Forget the blocks above `fork_height` and return them
+    pub fn rewind(&mut self, fork_height: u32) -> Vec<(u32, [u8; 32])> {
+        let orphaned = self.hashes.split_off(&(fork_height + 1));
+        orphaned.into_iter().collect()
+    }
+}
+
+/// Blocks reorged out of the chain, shared by the scanner and the pipeline
+#[derive(Default)]
+pub struct ReorgLog {
+    /// Orphaned blocks as (height, hash)
+    orphaned: Mutex<HashSet<(u32, [u8; 32])>>,
+
+    /// Store whose deposits are orphaned with their blocks
+    store: OnceLock<Arc<DepositStore>>,
+}
+
+impl ReorgLog {
+    /// Log orphaning the deposits of `store`
+    pub fn new(store: Arc<DepositStore>) -> Self {
+        Self {
+            orphaned: Mutex::new(HashSet::new()),
+            store: OnceLock::from(store),
+        }
+    }
+
+    /// Start orphaning the deposits of `store`, including those of blocks
+    /// already recorded, for a log that scanned without one
+    pub fn attach(&self, store: Arc<DepositStore>) {
+        if self.store.set(store).is_ok() {
+            let blocks: Vec<_> = self.orphaned.lock().unwrap().iter().copied().collect();
+            self.orphan(&blocks);
+        }
+    }
+
+    /// Record blocks reorged out above `fork_height` and orphan their deposits
+    pub fn record(&self, fork_height: u32, blocks: &[(u32, [u8; 32])]) {
+        warn!(
+            "Zcash reorg: {} scanned blocks above {} replaced",
+            blocks.len(),
+            fork_height
+        );
+        self.orphaned.lock().unwrap().extend(blocks.iter().copied());
+        self.orphan(blocks);
+    }
+
+    /// Orphan the stored deposits of `blocks`
+    fn orphan(&self, blocks: &[(u32, [u8; 32])]) {
+        let Some(store) = self.store.get() else {
+            return;
+        };
+        let orphaned: HashSet<(u32, String)> = blocks
+            .iter()
+            .map(|(height, hash)| (*height, hex::encode(hash)))
+            .collect();
+        let records = store
+            .all()
+            .into_iter()
+            .filter(|r| orphaned.contains(&(r.block_height, r.block_hash.clone())));
+        for record in records {
+            let reason = format!("block {} reorged out", record.block_height);
+            if record.status.can_transition_to(DepositStatus::Orphaned) {
+                warn!("Deposit {} orphaned: {}", record.tx_hash, reason);
+                if let Err(e) =
+                    store.transition(&record.key(), DepositStatus::Orphaned, Some(reason))
+                {
+                    error!("Failed to orphan deposit {}: {}", record.tx_hash, e);
+                }
+            } else if record.status != DepositStatus::Orphaned {
+                error!(
+                    "ALERT: deposit {} was {:?} before its {}",
+                    record.tx_hash, record.status, reason
+                );
+                if let Err(e) = store.update(&record.key(), |r| r.reorged_at = Some(now_secs())) {
+                    error!("Failed to flag deposit {}: {}", record.tx_hash, e);
+                }
+            }
+        }
+    }
+
+    /// Whether `payload` comes from a block reorged out of the chain
+    pub fn is_orphaned(&self, payload: &BridgePayload) -> bool {
+        self.orphaned
+            .lock()
+            .unwrap()
+            .contains(&(payload.block_height, payload.block_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::payload_key;
+
+    fn block(height: u64, hash: u8, prev_hash: u8) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: vec![hash; 32],
+            prev_hash: vec![prev_hash; 32],
+            ..CompactBlock::default()
+        }
+    }
+
+    #[test]
+    fn test_reorg_orphans_deposits() {
+        let mut hashes = BlockHashes::default();
+        for height in 100..=103u64 {
+            hashes
+                .push(&block(height, height as u8, height as u8 - 1))
+                .unwrap();
+        }
+        assert!(hashes.extends(&block(104, 104, 103)));
+        assert!(!hashes.extends(&block(104, 204, 203)));
+        assert_eq!(hashes.newest_first()[0], (103, [103; 32]));
+        assert_eq!(hashes.rewind(101), [(102, [102; 32]), (103, [103; 32])]);
+        assert_eq!(hashes.newest_first().len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let payload = BridgePayload {
+            amount: 50_000,
+            block_height: 102,
+            block_hash: [102; 32],
+            ..BridgePayload::sample()
+        };
+        store.insert_detected(&payload).unwrap();
+
+        let log = ReorgLog::new(store.clone());
+        log.record(101, &[(102, [102; 32]), (103, [103; 32])]);
+        assert!(log.is_orphaned(&payload));
+        let record = store.get(&payload_key(&payload)).unwrap();
+        assert_eq!(record.status, DepositStatus::Orphaned);
+
+        // Mined again in the new chain, the deposit is detected afresh
+        let remined = BridgePayload {
+            block_height: 103,
+            block_hash: [203; 32],
+            ..payload
+        };
+        assert!(!log.is_orphaned(&remined));
+        let record = store.insert_detected(&remined).unwrap();
+        assert_eq!(record.status, DepositStatus::Detected);
+        assert_eq!(
+            (record.block_height, record.block_hash),
+            (103, hex::encode([203u8; 32]))
+        );
+    }
+}
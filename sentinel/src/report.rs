@@ -0,0 +1,282 @@
+//! Historical deposit reporting
+//!
+//! `sentinel report --period monthly` aggregates the deposit store into one
+//! row per period: deposit count and volume, fees collected, average
+//! detection-to-submission latency and failure rate. Output is JSON or CSV,
+//! for operator reporting and bridge governance updates. Deposits are
+//! bucketed by detection time, in UTC.
+
+use crate::clock::civil_date;
+use crate::config::SentinelConfig;
+use crate::store::{DepositRecord, DepositStatus, DepositStore};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Seconds per day
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Reporting period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            "monthly" => Ok(Self::Monthly),
+            other => Err(format!(
+                "unknown period {} (expected daily, weekly or monthly)",
+                other
+            )),
+        }
+    }
+}
+
+impl Period {
+    /// Label of the period containing Unix time `at`
+    ///
+    /// Days are `YYYY-MM-DD`, weeks are labelled by their Monday and months
+    /// are `YYYY-MM`, so labels sort chronologically.
+    fn label(self, at: u64) -> String {
+        let days = at / DAY_SECS;
+        match self {
+            Self::Daily => date(days),
+            // 1970-01-01 was a Thursday
+            Self::Weekly => date(days.saturating_sub((days + 3) % 7)),
+            Self::Monthly => date(days)[..7].to_string(),
+        }
+    }
+}
+
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            other => Err(format!("unknown format {} (expected json or csv)", other)),
+        }
+    }
+}
+
+/// `YYYY-MM-DD` of the day `days` after the Unix epoch
+fn date(days: u64) -> String {
+    let (year, month, day) = civil_date(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Statistics of one period
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeriodStats {
+    /// Period label
+    pub period: String,
+
+    /// Deposits detected
+    pub deposits: u64,
+
+    /// Zatoshi deposited
+    pub volume_zatoshi: u64,
+
+    /// Deposits attested on L1 (submitted, claimed or claim expired)
+    pub attested: u64,
+
+    /// Bridge fees on attested deposits, in zatoshi
+    pub fees_zatoshi: u64,
+
+    /// Mean seconds from detection to submission of attested deposits
+    pub avg_latency_secs: Option<u64>,
+
+    /// Deposits rejected (refunded or not) or stale
+    pub failed: u64,
+
+    /// `failed / deposits`
+    pub failure_rate: f64,
+}
+
+/// Time the deposit was first submitted to L1
+fn submitted_at(record: &DepositRecord) -> Option<u64> {
+    record
+        .history
+        .iter()
+        .find(|change| change.status == DepositStatus::Submitted)
+        .map(|change| change.at)
+}
+
+/// Aggregate `records` per `period`, oldest period first
+pub fn build_report(records: &[DepositRecord], period: Period) -> Vec<PeriodStats> {
+    let mut periods: BTreeMap<String, (PeriodStats, u64)> = BTreeMap::new();
+
+    for record in records {
+        let label = period.label(record.detected_at);
+        let (stats, latency_total) = periods.entry(label.clone()).or_insert_with(|| {
+            (
+                PeriodStats {
+                    period: label,
+                    ..Default::default()
+                },
+                0,
+            )
+        });
+
+        stats.deposits += 1;
+        stats.volume_zatoshi += record.amount;
+        match record.status {
+            DepositStatus::Rejected
+            | DepositStatus::RefundQueued
+            | DepositStatus::Refunded
+            | DepositStatus::Stale => stats.failed += 1,
+            _ => {}
+        }
+        if let Some(at) = submitted_at(record) {
+            stats.attested += 1;
+            stats.fees_zatoshi += record.fee;
+            *latency_total += at.saturating_sub(record.detected_at);
+        }
+    }
+
+    periods
+        .into_values()
+        .map(|(mut stats, latency_total)| {
+            stats.avg_latency_secs = (stats.attested > 0).then(|| latency_total / stats.attested);
+            stats.failure_rate = stats.failed as f64 / stats.deposits as f64;
+            stats
+        })
+        .collect()
+}
+
+/// Render rows as CSV with a header line
+fn to_csv(rows: &[PeriodStats]) -> String {
+    let mut out = String::from(
+        "period,deposits,volume_zatoshi,attested,fees_zatoshi,avg_latency_secs,failed,failure_rate\n",
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{:.4}\n",
+            row.period,
+            row.deposits,
+            row.volume_zatoshi,
+            row.attested,
+            row.fees_zatoshi,
+            row.avg_latency_secs
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            row.failed,
+            row.failure_rate
+        ));
+    }
+    out
+}
+
+/// Entry point for `sentinel report`
+pub fn report_command(
+    config: &SentinelConfig,
+    period: Period,
+    format: ReportFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let store = DepositStore::open(config.data_path("deposits.json"))?;
+    let rows = build_report(&store.all(), period);
+
+    let rendered = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)?,
+        ReportFormat::Csv => to_csv(&rows),
+    };
+    match output {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => println!("{}", rendered.trim_end()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(
+        tx: u8,
+        amount: u64,
+        detected_at: u64,
+        history: &[(DepositStatus, u64)],
+    ) -> DepositRecord {
+        let status = history.last().map_or(DepositStatus::Detected, |(s, _)| *s);
+        let history: Vec<_> = history
+            .iter()
+            .map(|(status, at)| json!({ "status": status, "at": at, "reason": null }))
+            .collect();
+        serde_json::from_value(json!({
+            "tx_hash": hex::encode([tx; 32]),
+            "amount": amount,
+            "secret_hash": hex::encode([2u8; 32]),
+            "aztec_address": hex::encode([3u8; 32]),
+            "block_height": 100,
+            "refund_address": null,
+            "fee": 1_000,
+            "status": status,
+            "reason": null,
+            "history": history,
+            "detected_at": detected_at,
+            "updated_at": detected_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_monthly_report() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(19_782), "2024-02-29");
+        // 2024-03-06 is a Wednesday
+        assert_eq!(Period::Weekly.label(19_788 * DAY_SECS), "2024-03-04");
+
+        let jan = 1_704_067_200; // 2024-01-01T00:00:00Z
+        let feb = 1_706_745_600; // 2024-02-01T00:00:00Z
+        let records = vec![
+            record(1, 100_000, jan, &[(DepositStatus::Submitted, jan + 60)]),
+            record(
+                2,
+                300_000,
+                jan + 10,
+                &[(DepositStatus::Submitted, jan + 130)],
+            ),
+            record(3, 50_000, jan + 20, &[(DepositStatus::Rejected, jan + 25)]),
+            record(4, 70_000, feb, &[]),
+        ];
+
+        let rows = build_report(&records, Period::Monthly);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].period, "2024-01");
+        assert_eq!(rows[0].deposits, 3);
+        assert_eq!(rows[0].volume_zatoshi, 450_000);
+        assert_eq!(rows[0].attested, 2);
+        assert_eq!(rows[0].fees_zatoshi, 2_000);
+        assert_eq!(rows[0].avg_latency_secs, Some(90));
+        assert_eq!(rows[0].failed, 1);
+        assert_eq!(rows[1].period, "2024-02");
+        assert_eq!(rows[1].avg_latency_secs, None);
+
+        let csv = to_csv(&rows);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("2024-01,3,450000,2,2000,90,1,0.3333")
+        );
+        assert_eq!(csv.lines().nth(2), Some("2024-02,1,70000,0,0,,0,0.0000"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Peer operator performance and reputation tracking
+//!
+//! Keeps per-operator statistics (attestation latency, missed attestations,
+//! invalid signatures) so the operator community can monitor AVS health.
+//! Statistics are fed from on-chain `DepositVerified` dispatches, which carry
+//! the signer set in their calldata, and from locally observed signature
+//! failures.
+
+use crate::clock::now_secs;
+use crate::dispatches::{self, DepositVerified};
+use crate::error::SentinelError;
+use crate::withdrawal::MAX_LOG_RANGE;
+use ethers::abi::{decode, ParamType, Token};
+use ethers::prelude::*;
+use ethers::types::{Address, H256};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use sentinel_core::payload::payload_param_types;
+use tracing::{debug, warn};
+
+/// Dispatch transactions and blocks fetched at once
+const FETCH_CONCURRENCY: usize = 16;
+
+/// Per-operator statistics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OperatorStats {
+    /// Attestations the operator took part in
+    pub attestations: u64,
+
+    /// Quorum-complete attestations the operator did not sign
+    pub missed_attestations: u64,
+
+    /// Signatures from the operator that failed verification
+    pub invalid_signatures: u64,
+
+    /// Sum of observed latencies in seconds (for averaging)
+    total_latency_secs: u64,
+
+    /// Average latency from local deposit detection to dispatch, in seconds
+    pub avg_latency_secs: Option<u64>,
+
+    /// Unix timestamp of the last attestation the operator signed
+    pub last_seen: Option<u64>,
+}
+
+impl OperatorStats {
+    /// Share of attestations the operator participated in, in basis points
+    pub fn participation_bps(&self) -> u64 {
+        let total = self.attestations + self.missed_attestations;
+        if total == 0 {
+            return 10_000;
+        }
+        self.attestations * 10_000 / total
+    }
+}
+
+/// Shared reputation tracker
+#[derive(Default)]
+pub struct ReputationTracker {
+    /// Statistics keyed by operator address
+    stats: RwLock<HashMap<Address, OperatorStats>>,
+
+    /// Local detection time of deposits, keyed by store key
+    detected_at: RwLock<HashMap<String, u64>>,
+}
+
+impl ReputationTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember when this sentinel first saw a deposit
+    pub fn note_deposit_detected(&self, key: String) {
+        self.detected_at
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(now_secs);
+    }
+
+    /// Record a dispatched attestation and its signer set
+    ///
+    /// Active operators missing from `signers` are counted as having missed it.
+    pub fn record_dispatch(
+        &self,
+        key: &str,
+        signers: &[Address],
+        active_operators: &[Address],
+        dispatched_at: u64,
+    ) {
+        let latency = self
+            .detected_at
+            .write()
+            .unwrap()
+            .remove(key)
+            .map(|seen| dispatched_at.saturating_sub(seen));
+
+        let mut stats = self.stats.write().unwrap();
+        for signer in signers {
+            let entry = stats.entry(*signer).or_default();
+            entry.attestations += 1;
+            entry.last_seen = Some(dispatched_at);
+            if let Some(latency) = latency {
+                entry.total_latency_secs += latency;
+                entry.avg_latency_secs = Some(entry.total_latency_secs / entry.attestations);
+            }
+        }
+
+        for operator in active_operators.iter().filter(|op| !signers.contains(op)) {
+            stats.entry(*operator).or_default().missed_attestations += 1;
+        }
+    }
+
+    /// Record a signature from `operator` that failed verification
+    pub fn record_invalid_signature(&self, operator: Address) {
+        self.stats
+            .write()
+            .unwrap()
+            .entry(operator)
+            .or_default()
+            .invalid_signatures += 1;
+    }
+
+    /// Snapshot of all statistics
+    pub fn snapshot(&self) -> HashMap<Address, OperatorStats> {
+        self.stats.read().unwrap().clone()
+    }
+}
+
+/// Watches ServiceManager dispatches and feeds them into a [`ReputationTracker`]
+pub struct DispatchObserver {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Tracker to update
+    tracker: Arc<ReputationTracker>,
+
+    /// Next L1 block to query
+    from_block: u64,
+}
+
+impl DispatchObserver {
+    /// Create a new observer starting at `from_block`
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        tracker: Arc<ReputationTracker>,
+        from_block: u64,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            tracker,
+            from_block,
+        }
+    }
+
+    /// Process `DepositVerified` events up to the current L1 head, in ranges
+    /// of at most [`MAX_LOG_RANGE`] blocks
+    pub async fn poll(&mut self, active_operators: &[Address]) -> Result<usize, SentinelError> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        let mut observed = 0;
+        while self.from_block <= head {
+            let to_block = head.min(self.from_block + MAX_LOG_RANGE - 1);
+            observed += self
+                .process_range(self.from_block, to_block, active_operators)
+                .await?;
+            self.from_block = to_block + 1;
+        }
+        Ok(observed)
+    }
+
+    /// Record the dispatches in L1 blocks `from_block..=to_block`
+    async fn process_range(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        active_operators: &[Address],
+    ) -> Result<usize, SentinelError> {
+        let filter = dispatches::filter(self.service_manager_address)
+            .from_block(from_block)
+            .to_block(to_block);
+        let events = self
+            .provider
+            .get_logs(&filter)
+            .await?
+            .iter()
+            .map(DepositVerified::decode)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let txs = events.iter().filter_map(|e| e.l1_tx_hash).collect();
+        let blocks = events.iter().filter_map(|e| e.block_number).collect();
+        let (signers, timestamps) =
+            futures::join!(self.dispatch_signer_sets(txs), self.block_timestamps(blocks));
+
+        for event in &events {
+            let Some(signers) = event.l1_tx_hash.and_then(|tx| signers.get(&tx)) else {
+                continue;
+            };
+            let dispatched_at = event
+                .block_number
+                .and_then(|number| timestamps.get(&number).copied())
+                .unwrap_or_else(now_secs);
+            self.tracker
+                .record_dispatch(&event.key(), signers, active_operators, dispatched_at);
+        }
+
+        debug!(
+            "Observed {} dispatches in L1 blocks {}..={}",
+            events.len(),
+            from_block,
+            to_block
+        );
+        Ok(events.len())
+    }
+
+    /// Signer sets of dispatch transactions, fetched concurrently; those that
+    /// cannot be decoded are left out
+    async fn dispatch_signer_sets(&self, txs: HashSet<H256>) -> HashMap<H256, Vec<Address>> {
+        futures::stream::iter(txs)
+            .map(|tx| async move {
+                let signers = self.dispatch_signers(tx).await.map_err(|e| {
+                    warn!("Could not decode signers of dispatch {:?}: {}", tx, e);
+                });
+                (tx, signers)
+            })
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .filter_map(|(tx, signers)| async move { Some((tx, signers.ok()?)) })
+            .collect()
+            .await
+    }
+
+    /// Timestamps of L1 blocks, fetched concurrently; blocks that cannot be
+    /// read are left out
+    async fn block_timestamps(&self, blocks: HashSet<u64>) -> HashMap<u64, u64> {
+        futures::stream::iter(blocks)
+            .map(|number| async move {
+                let block = self.provider.get_block(number).await.unwrap_or_else(|e| {
+                    warn!("Could not read L1 block {}: {}", number, e);
+                    None
+                });
+                (number, block.map(|b| b.timestamp.as_u64()))
+            })
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .filter_map(|(number, timestamp)| async move { Some((number, timestamp?)) })
+            .collect()
+            .await
+    }
+
+    /// Decode the signer set from a `verifyAndDispatch` transaction's calldata
+    async fn dispatch_signers(&self, tx: H256) -> Result<Vec<Address>, SentinelError> {
+        let tx = self
+            .provider
+            .get_transaction(tx)
+            .await?
+            .ok_or_else(|| SentinelError::L1("Dispatch transaction not found".to_string()))?;
+
+        match decode_dispatch(&tx.input)?.into_iter().nth(2) {
+            Some(Token::Array(signers)) => {
+                Ok(signers.into_iter().filter_map(|t| t.into_address()).collect())
+            }
+            _ => Err(SentinelError::L1("Malformed signer array".to_string())),
+        }
+    }
+}
+
+/// Decode `verifyAndDispatch` calldata into (payload tuple, signature, signers)
+pub fn decode_dispatch(input: &[u8]) -> Result<Vec<Token>, SentinelError> {
+    if input.len() < 4 {
+        return Err(SentinelError::L1("Dispatch calldata too short".to_string()));
+    }
+
+    decode(
+        &[
+            ParamType::Tuple(payload_param_types()),
+            ParamType::Bytes,
+            ParamType::Array(Box::new(ParamType::Address)),
+        ],
+        &input[4..],
+    )
+    .map_err(SentinelError::l1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_dispatch_counts_misses() {
+        let tracker = ReputationTracker::new();
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+
+        tracker.record_dispatch("00:0", &[a], &[a, b], 100);
+        tracker.record_dispatch("01:0", &[a, b], &[a, b], 200);
+
+        let stats = tracker.snapshot();
+        assert_eq!(stats[&a].attestations, 2);
+        assert_eq!(stats[&a].missed_attestations, 0);
+        assert_eq!(stats[&b].attestations, 1);
+        assert_eq!(stats[&b].missed_attestations, 1);
+        assert_eq!(stats[&b].participation_bps(), 5_000);
+    }
+
+    #[test]
+    fn test_latency_from_detection() {
+        let tracker = ReputationTracker::new();
+        let a = Address::repeat_byte(1);
+
+        tracker
+            .detected_at
+            .write()
+            .unwrap()
+            .insert("07:0".to_string(), 1_000);
+        tracker.record_dispatch("07:0", &[a], &[a], 1_030);
+
+        assert_eq!(tracker.snapshot()[&a].avg_latency_secs, Some(30));
+    }
+
+    #[test]
+    fn test_decode_own_dispatch() {
+        let payload = crate::BridgePayload::sample();
+        let signers = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        let calldata = crate::signer::dispatch_calldata(&payload, 7, vec![0xab; 130], &signers);
+
+        let tokens = decode_dispatch(&calldata).unwrap();
+        let Some(Token::Tuple(fields)) = tokens.first() else {
+            panic!("payload is not a tuple");
+        };
+        assert_eq!(fields.len(), payload_param_types().len());
+        assert_eq!(tokens[1], Token::Bytes(vec![0xab; 130]));
+        assert_eq!(
+            tokens[2],
+            Token::Array(signers.iter().copied().map(Token::Address).collect())
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Proof-of-reserves reporting
+//!
+//! `sentinel reserves report` summarises the vault's position at a given
+//! height from the deposit store and the release queue: every deposit note
+//! received, the total unspent balance after releases and refunds, and the
+//! liabilities we have attested to on L1. The report is signed with the
+//! operator key so third parties can verify who published it.
+
+use crate::clock::now_secs;
+use crate::config::SentinelConfig;
+use crate::signer::AttestationSigner;
+use crate::store::{DepositStatus, DepositStore};
+use crate::withdrawal::ReleaseInstruction;
+use anyhow::{Context, Result};
+use ethers::utils::keccak256;
+use serde::Serialize;
+use std::path::Path;
+
+/// Report format version
+pub const RESERVES_REPORT_VERSION: u8 = 1;
+
+/// A deposit note held by the vault
+#[derive(Debug, Clone, Serialize)]
+pub struct ReserveNote {
+    /// Zcash transaction hash (hex)
+    pub tx_hash: String,
+
+    /// Note value in zatoshi
+    pub amount: u64,
+
+    /// Bridge fee retained by the vault, in zatoshi
+    pub fee: u64,
+
+    /// Block height the note was received at
+    pub block_height: u32,
+
+    /// Deposit lifecycle state
+    pub status: DepositStatus,
+}
+
+/// Unsigned report body; the signature covers its canonical JSON encoding
+#[derive(Debug, Clone, Serialize)]
+pub struct ReservesBody {
+    /// Report format version
+    pub version: u8,
+
+    /// Zcash network
+    pub network: String,
+
+    /// Vault shielded address
+    pub vault_address: String,
+
+    /// Zcash height the report is computed at
+    pub height: u32,
+
+    /// Unix timestamp of report generation
+    pub generated_at: u64,
+
+    /// Deposit notes received up to `height`
+    pub notes: Vec<ReserveNote>,
+
+    /// Sum of all deposit notes
+    pub total_deposited: u64,
+
+    /// ZEC released for L1 withdrawals
+    pub total_released: u64,
+
+    /// ZEC refunded (or queued for refund) to depositors
+    pub total_refunded: u64,
+
+    /// Deposits minus releases and refunds
+    pub unspent_balance: u64,
+
+    /// Deposits attested to L1 and not refunded, net of bridge fees
+    pub attested_liabilities: u64,
+}
+
+/// Signed proof-of-reserves report
+#[derive(Debug, Clone, Serialize)]
+pub struct ReservesReport {
+    /// Report contents
+    #[serde(flatten)]
+    pub body: ReservesBody,
+
+    /// Operator address that signed the report
+    pub operator: String,
+
+    /// keccak256 of the canonical JSON encoding of `body`
+    pub digest: String,
+
+    /// EIP-191 signature over `digest`
+    pub signature: String,
+}
+
+/// Build the unsigned report body at `height`
+pub fn build_report(
+    config: &SentinelConfig,
+    store: &DepositStore,
+    releases: &[ReleaseInstruction],
+    height: u32,
+) -> ReservesBody {
+    let mut notes: Vec<ReserveNote> = store
+        .all()
+        .into_iter()
+        .filter(|r| r.block_height <= height)
+        .map(|r| ReserveNote {
+            tx_hash: r.tx_hash,
+            amount: r.amount,
+            fee: r.fee,
+            block_height: r.block_height,
+            status: r.status,
+        })
+        .collect();
+    notes.sort_by(|a, b| (a.block_height, &a.tx_hash).cmp(&(b.block_height, &b.tx_hash)));
+
+    let sum = |filter: &dyn Fn(&ReserveNote) -> bool| -> u64 {
+        notes.iter().filter(|n| filter(n)).map(|n| n.amount).sum()
+    };
+
+    let total_deposited = sum(&|_| true);
+    let total_refunded = sum(&|n| {
+        matches!(n.status, DepositStatus::RefundQueued | DepositStatus::Refunded)
+    });
+    let attested_liabilities = notes
+        .iter()
+        .filter(|n| {
+            matches!(
+                n.status,
+                DepositStatus::Submitted | DepositStatus::Claimed | DepositStatus::ClaimExpired
+            )
+        })
+        .map(|n| n.amount.saturating_sub(n.fee))
+        .sum();
+    let total_released: u64 = releases.iter().map(|r| r.amount).sum();
+
+    ReservesBody {
+        version: RESERVES_REPORT_VERSION,
+        network: config.network.clone(),
+        vault_address: config.vault_address.clone(),
+        height,
+        generated_at: now_secs(),
+        notes,
+        total_deposited,
+        total_released,
+        total_refunded,
+        unspent_balance: total_deposited
+            .saturating_sub(total_released)
+            .saturating_sub(total_refunded),
+        attested_liabilities,
+    }
+}
+
+/// Read release instructions from a JSON-lines queue file
+pub fn read_releases(path: &Path) -> Result<Vec<ReleaseInstruction>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).context("Malformed release queue entry"))
+        .collect()
+}
+
+/// Entry point for `sentinel reserves report`
+pub async fn report_command(
+    config: &SentinelConfig,
+    height: Option<u32>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let store = DepositStore::open(config.data_path("deposits.json"))?;
+    let releases = match &config.release_queue_path {
+        Some(path) => read_releases(Path::new(path))?,
+        None => Vec::new(),
+    };
+
+    let height = height.unwrap_or(u32::MAX);
+    let body = build_report(config, &store, &releases, height);
+
+    let signer = AttestationSigner::from_config(config).await?;
+    let digest = keccak256(serde_json::to_vec(&body)?);
+    let signature = signer.sign_hash(digest, "reserves_report").await?;
+
+    let report = ReservesReport {
+        body,
+        operator: format!("{:?}", signer.address()),
+        digest: format!("0x{}", hex::encode(digest)),
+        signature: format!("0x{}", hex::encode(signature)),
+    };
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match output {
+        Some(path) => std::fs::write(path, json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Shared retry policy
+//!
+//! Calls to lightwalletd, the L1 node and webhook receivers go through
+//! [`RetryPolicy::run`]: errors the error type classifies as retryable are
+//! retried up to `MAX_RETRIES` times with exponential backoff starting at
+//! `RETRY_DELAY_MS`, with full jitter so sentinels restarted together do not
+//! retry in lockstep. Other errors are returned at once.
+//!
+//! Retries also draw on a budget shared by every user of a policy, refilled
+//! by successful calls. When an endpoint is down for good, the budget runs
+//! dry and calls fail fast instead of multiplying the load on it.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Longest wait between two attempts
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Retries the budget holds when full
+const BUDGET_CAPACITY: f64 = 100.0;
+
+/// Budget refilled by each successful call
+const BUDGET_REFILL: f64 = 0.1;
+
+/// Retries left to spend, shared by the clones of a policy
+#[derive(Debug)]
+struct RetryBudget {
+    tokens: Mutex<f64>,
+}
+
+impl RetryBudget {
+    /// Take one retry from the budget, if any is left
+    fn withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap();
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+
+    /// Credit a successful call
+    fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap();
+        *tokens = (*tokens + BUDGET_REFILL).min(BUDGET_CAPACITY);
+    }
+}
+
+/// Retry settings with a shared budget
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    max_retries: u32,
+
+    /// Upper bound of the first backoff, doubled on each further retry
+    base_delay: Duration,
+
+    /// Shared retry budget
+    budget: Arc<RetryBudget>,
+}
+
+impl RetryPolicy {
+    /// Policy retrying up to `max_retries` times, backing off from `base_delay`
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            budget: Arc::new(RetryBudget {
+                tokens: Mutex::new(BUDGET_CAPACITY),
+            }),
+        }
+    }
+
+    /// Policy from `MAX_RETRIES` and `RETRY_DELAY_MS`
+    pub fn from_config(config: &SentinelConfig) -> Self {
+        Self::new(
+            config.max_retries,
+            Duration::from_millis(config.retry_delay_ms),
+        )
+    }
+
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Run `op` until it succeeds, fails for good or retries run out
+    pub async fn run<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T, SentinelError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SentinelError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Ok(value) => {
+                    self.budget.deposit();
+                    return Ok(value);
+                }
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    if !self.budget.withdraw() {
+                        warn!("Retry budget exhausted, not retrying {}: {}", what, e);
+                        return Err(e);
+                    }
+                    let delay = self.backoff(attempt);
+                    warn!(
+                        "{} failed ({}), retry {}/{} in {:?}",
+                        what,
+                        e,
+                        attempt + 1,
+                        self.max_retries,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait before retry `attempt` (from 0): uniform in [0, base * 2^attempt]
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(MAX_DELAY);
+        ceiling.mul_f64(jitter())
+    }
+}
+
+/// Pseudo-random factor in [0, 1)
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    // Scramble the low bits, which carry most of the entropy
+    let mixed = nanos.wrapping_mul(0x9E37_79B9).rotate_left(13);
+    f64::from(mixed) / (f64::from(u32::MAX) + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_only() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+
+        let calls = AtomicU32::new(0);
+        let result = policy
+            .run("flaky call", || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(SentinelError::Network("reset".to_string())),
+                    _ => Ok(7),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = policy
+            .run("bad request", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(SentinelError::InvalidPayload("no".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = policy
+            .run("dead endpoint", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(SentinelError::Network("refused".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // An empty budget stops retries
+        *policy.budget.tokens.lock().unwrap() = 0.0;
+        let calls = AtomicU32::new(0);
+        let _: Result<(), _> = policy
+            .run("dead endpoint", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(SentinelError::Network("refused".to_string()))
+            })
+            .await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        for attempt in 0..20 {
+            assert!(policy.backoff(attempt) <= MAX_DELAY);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Automated AVS reward claiming
+//!
+//! Checks the operator's claimable balance on the rewards coordinator and
+//! submits a claim once it crosses a threshold, either on a schedule in the
+//! background or on demand via `sentinel rewards claim`.
+
+use crate::config::SentinelConfig;
+use crate::error::SentinelError;
+use crate::signer::AttestationSigner;
+use anyhow::Result;
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Default interval between claim checks (6 hours)
+pub const DEFAULT_REWARDS_CLAIM_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// Claims operator rewards from the rewards coordinator contract
+pub struct RewardsClaimer {
+    /// Signer holding the operator key
+    signer: Arc<AttestationSigner>,
+
+    /// Rewards coordinator contract address
+    coordinator: Address,
+
+    /// Minimum claimable amount (wei) before a claim is submitted
+    threshold: U256,
+
+    /// Interval between checks
+    interval: Duration,
+}
+
+impl RewardsClaimer {
+    /// Build a claimer from config, or `None` if no coordinator is configured
+    pub fn from_config(
+        config: &SentinelConfig,
+        signer: Arc<AttestationSigner>,
+    ) -> Result<Option<Self>> {
+        let Some(coordinator) = &config.rewards_coordinator_address else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            signer,
+            coordinator: coordinator.parse()?,
+            threshold: U256::from_dec_str(&config.rewards_claim_threshold_wei)?,
+            interval: Duration::from_secs(config.rewards_claim_interval_secs),
+        }))
+    }
+
+    /// Amount currently claimable by the operator
+    pub async fn claimable(&self) -> Result<U256, SentinelError> {
+        // claimableRewards(address) returns uint256
+        let mut calldata = keccak256(b"claimableRewards(address)")[0..4].to_vec();
+        calldata.extend_from_slice(&encode(&[Token::Address(self.signer.address())]));
+
+        let call = TransactionRequest::new()
+            .to(self.coordinator)
+            .data(Bytes::from(calldata));
+
+        let result = self
+            .signer
+            .provider()
+            .call(&call.into(), None)
+            .await
+            .map_err(SentinelError::l1)?;
+
+        if result.len() < 32 {
+            return Err(SentinelError::L1("Malformed claimableRewards response".to_string()));
+        }
+        Ok(U256::from_big_endian(&result[..32]))
+    }
+
+    /// Claim if the claimable amount is at or above the threshold (or `force`)
+    ///
+    /// Returns the claim transaction hash if a claim was submitted.
+    pub async fn claim_if_due(&self, force: bool) -> Result<Option<String>, SentinelError> {
+        let claimable = self.claimable().await?;
+
+        if claimable.is_zero() || (!force && claimable < self.threshold) {
+            info!(
+                "Claimable rewards {} wei below threshold {} wei, not claiming",
+                claimable, self.threshold
+            );
+            return Ok(None);
+        }
+
+        // claimRewards(address recipient)
+        let mut calldata = keccak256(b"claimRewards(address)")[0..4].to_vec();
+        calldata.extend_from_slice(&encode(&[Token::Address(self.signer.address())]));
+
+        let tx_hash = self.signer.send_call(self.coordinator, calldata).await?;
+        info!("Claimed {} wei of rewards in tx {}", claimable, tx_hash);
+        Ok(Some(tx_hash))
+    }
+
+    /// Check and claim on a schedule forever
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.claim_if_due(false).await {
+                error!("Reward claim failed: {}", e);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+/// Entry point for `sentinel rewards claim`
+pub async fn claim_command(config: &SentinelConfig, force: bool) -> Result<()> {
+    let signer = Arc::new(AttestationSigner::from_config(config).await?);
+
+    let claimer = RewardsClaimer::from_config(config, signer)?
+        .ok_or_else(|| anyhow::anyhow!("REWARDS_COORDINATOR_ADDRESS is not set"))?;
+
+    match claimer.claim_if_due(force).await? {
+        Some(tx_hash) => println!("Rewards claimed: {}", tx_hash),
+        None => println!("Nothing to claim"),
+    }
+    Ok(())
+}
@@ -0,0 +1,433 @@
+//! Scripted chain-history scenarios
+//!
+//! Drives the mock lightwalletd through multi-step histories (deposit, reorg
+//! it out, re-include with a different amount, ...) and applies the
+//! sentinel's confirmation and attestation rules after every step, so tests
+//! can assert the store and the attest/reject decisions at each point.
+//! Available to tests and, with the `mock-lightwalletd` feature, as
+//! `sentinel scenario <file.json>`:
+//!
+//! ```json
+//! {"confirmation_depth": 3, "steps": [
+//!   {"deposit": {"name": "a", "amount": 5000}},
+//!   {"mine": {"blocks": 4}},
+//!   {"expect": {"name": "a", "status": "submitted", "decision": "attest"}}
+//! ]}
+//! ```
+
+#![allow(dead_code)]
+
+use crate::mock_lightwalletd::MockLightwalletd;
+use crate::signer::check_amounts;
+use crate::store::{payload_key, DepositStatus, DepositStore};
+use crate::BridgePayload;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{info, warn};
+use zcash_client_backend::proto::compact_formats::CompactTx;
+
+/// Height of the first scripted block
+const BASE_HEIGHT: u64 = 1_000;
+
+/// A scenario file
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Confirmations before a deposit is acted on
+    pub confirmation_depth: u32,
+
+    /// Steps, in order
+    pub steps: Vec<Step>,
+}
+
+/// One scripted step
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Broadcast a deposit; it is mined into the next block
+    Deposit {
+        /// Name later steps refer to the deposit by
+        name: String,
+
+        /// Note value in zatoshi
+        amount: u64,
+
+        /// Amount claimed by the memo, if any
+        #[serde(default)]
+        memo_amount: Option<u64>,
+    },
+
+    /// Mine blocks on the current chain
+    Mine {
+        /// Number of blocks
+        blocks: u32,
+    },
+
+    /// Drop the top blocks so the next mined blocks form a competing chain
+    Reorg {
+        /// Number of blocks dropped
+        depth: u32,
+    },
+
+    /// Assert the state of the named deposit's latest inclusion
+    Expect {
+        /// Deposit name
+        name: String,
+
+        /// Expected store status
+        #[serde(default)]
+        status: Option<DepositStatus>,
+
+        /// Expected attestation decision
+        #[serde(default)]
+        decision: Option<Decision>,
+
+        /// Expect the deposit to be unknown to the store
+        #[serde(default)]
+        absent: bool,
+    },
+}
+
+/// What the sentinel decided for a confirmed deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Passed every check and would be attested
+    Attest,
+
+    /// Refused by a payload check
+    Reject,
+
+    /// Acted on, then reorged out below the confirmation depth
+    Orphaned,
+}
+
+/// Replays a scenario against a mock chain and a real deposit store
+pub struct ScenarioDriver {
+    /// Scripted chain
+    chain: MockLightwalletd,
+
+    /// Deposit store the decisions are recorded in
+    store: DepositStore,
+
+    /// Confirmations before a deposit is acted on
+    confirmation_depth: u32,
+
+    /// Transactions waiting for the next block
+    mempool: Vec<CompactTx>,
+
+    /// Memo contents of every scripted deposit, by txid
+    payloads: HashMap<[u8; 32], BridgePayload>,
+
+    /// Latest txid of each named deposit
+    names: HashMap<String, [u8; 32]>,
+
+    /// Blocks acted on: height to (hash, deposit txids)
+    processed: BTreeMap<u64, (Vec<u8>, Vec<[u8; 32]>)>,
+
+    /// Latest decision per txid
+    decisions: HashMap<[u8; 32], Decision>,
+
+    /// Deposits broadcast so far, to give re-broadcasts fresh txids
+    broadcasts: u64,
+}
+
+impl ScenarioDriver {
+    /// Create a driver with an empty chain and a store at `store_path`
+    pub fn new(store_path: &Path, confirmation_depth: u32) -> Result<Self> {
+        Ok(Self {
+            chain: MockLightwalletd::new(BASE_HEIGHT),
+            store: DepositStore::open(store_path)?,
+            confirmation_depth,
+            mempool: Vec::new(),
+            payloads: HashMap::new(),
+            names: HashMap::new(),
+            processed: BTreeMap::new(),
+            decisions: HashMap::new(),
+            broadcasts: 0,
+        })
+    }
+
+    /// The scripted chain, e.g. to serve it to a real scanner
+    pub fn chain(&self) -> &MockLightwalletd {
+        &self.chain
+    }
+
+    /// The deposit store
+    pub fn store(&self) -> &DepositStore {
+        &self.store
+    }
+
+    /// Run every step of `scenario`, failing on the first unmet expectation
+    pub fn run(&mut self, scenario: &Scenario) -> Result<()> {
+        for (index, step) in scenario.steps.iter().enumerate() {
+            self.step(step)
+                .with_context(|| format!("Step {} ({:?})", index + 1, step))?;
+        }
+        Ok(())
+    }
+
+    /// Apply one step
+    pub fn step(&mut self, step: &Step) -> Result<()> {
+        match step {
+            Step::Deposit {
+                name,
+                amount,
+                memo_amount,
+            } => self.deposit(name, *amount, *memo_amount),
+            Step::Mine { blocks } => {
+                for _ in 0..*blocks {
+                    let vtx = std::mem::take(&mut self.mempool);
+                    self.chain.push_block(vtx);
+                }
+                self.sync()?;
+            }
+            Step::Reorg { depth } => {
+                if let Some(tip) = self.chain.tip() {
+                    self.chain.reorg((tip + 1).saturating_sub(*depth as u64));
+                }
+            }
+            Step::Expect {
+                name,
+                status,
+                decision,
+                absent,
+            } => self.expect(name, *status, *decision, *absent)?,
+        }
+        Ok(())
+    }
+
+    /// Latest decision for the named deposit
+    pub fn decision(&self, name: &str) -> Option<Decision> {
+        self.decisions.get(self.names.get(name)?).copied()
+    }
+
+    /// Put a deposit in the mempool
+    fn deposit(&mut self, name: &str, amount: u64, memo_amount: Option<u64>) {
+        self.broadcasts += 1;
+        let mut hasher = Sha256::new();
+        hasher.update(name.as_bytes());
+        hasher.update(amount.to_le_bytes());
+        hasher.update(self.broadcasts.to_le_bytes());
+        let txid: [u8; 32] = hasher.finalize().into();
+
+        // Re-broadcasts of a name keep its claim secret
+        let secret_hash: [u8; 32] = Sha256::digest(format!("secret:{}", name)).into();
+        self.payloads.insert(
+            txid,
+            BridgePayload {
+                tx_hash: txid,
+                output_index: 0,
+                amount,
+                secret_hash,
+                aztec_address: [0x42; 32],
+                block_height: 0,
+                block_hash: [0; 32],
+                refund_address: None,
+                fee: 0,
+                memo_amount,
+                vault_shard: crate::shards::PRIMARY_SHARD.to_string(),
+                target: None,
+            },
+        );
+        self.names.insert(name.to_string(), txid);
+        self.mempool.push(CompactTx {
+            index: self.mempool.len() as u64 + 1,
+            hash: txid.to_vec(),
+            ..CompactTx::default()
+        });
+    }
+
+    /// Act on newly confirmed blocks, after checking for deep reorgs
+    fn sync(&mut self) -> Result<()> {
+        let Some(tip) = self.chain.tip() else {
+            return Ok(());
+        };
+
+        // Blocks already acted on that are no longer on the chain
+        let orphaned: Vec<u64> = self
+            .processed
+            .iter()
+            .filter(|(height, (hash, _))| self.chain.block_hash(**height).as_ref() != Some(hash))
+            .map(|(height, _)| *height)
+            .collect();
+        for height in orphaned {
+            let (_, txids) = self.processed.remove(&height).unwrap_or_default();
+            warn!("Reorg removed confirmed block {}", height);
+            for txid in txids {
+                self.decisions.insert(txid, Decision::Orphaned);
+            }
+        }
+
+        let safe_height = tip.saturating_sub(self.confirmation_depth as u64);
+        let next = self.processed.keys().last().map_or(BASE_HEIGHT, |h| h + 1);
+        for height in next..=safe_height {
+            let block = self
+                .chain
+                .block(height)
+                .with_context(|| format!("Block {} missing below the tip", height))?;
+            let mut deposits = Vec::new();
+            for tx in &block.vtx {
+                let Ok(txid) = <[u8; 32]>::try_from(tx.hash.as_slice()) else {
+                    continue;
+                };
+                if let Some(payload) = self.payloads.get(&txid).cloned() {
+                    self.decide(BridgePayload {
+                        block_height: height as u32,
+                        block_hash: block.hash.as_slice().try_into().unwrap_or_default(),
+                        ..payload
+                    })?;
+                    deposits.push(txid);
+                }
+            }
+            self.processed.insert(height, (block.hash, deposits));
+        }
+        Ok(())
+    }
+
+    /// Apply the attestation checks to a confirmed deposit
+    fn decide(&mut self, payload: BridgePayload) -> Result<()> {
+        let key = payload_key(&payload);
+        let record = self.store.insert_detected(&payload)?;
+        if record.status != DepositStatus::Detected {
+            return Ok(());
+        }
+
+        let reason = crate::rejection_reason(&payload)
+            .map(str::to_string)
+            .or_else(|| check_amounts(&payload).err().map(|e| e.to_string()));
+        let decision = match reason {
+            Some(reason) => {
+                info!("Scenario deposit {} rejected: {}", key, reason);
+                self.store.reject(&key, reason)?;
+                Decision::Reject
+            }
+            None => {
+                self.store.transition(&key, DepositStatus::Submitted, None)?;
+                Decision::Attest
+            }
+        };
+        self.decisions.insert(payload.tx_hash, decision);
+        Ok(())
+    }
+
+    /// Check the named deposit against expectations
+    fn expect(
+        &self,
+        name: &str,
+        status: Option<DepositStatus>,
+        decision: Option<Decision>,
+        absent: bool,
+    ) -> Result<()> {
+        let txid = self
+            .names
+            .get(name)
+            .with_context(|| format!("No deposit named '{}'", name))?;
+        let record = self.store.resolve(&hex::encode(txid));
+
+        if absent {
+            if let Some(record) = record {
+                bail!("Deposit '{}' is in the store as {:?}", name, record.status);
+            }
+            return Ok(());
+        }
+        if let Some(expected) = status {
+            let actual = record.as_ref().map(|r| r.status);
+            if actual != Some(expected) {
+                bail!("Deposit '{}': expected status {:?}, got {:?}", name, expected, actual);
+            }
+        }
+        if let Some(expected) = decision {
+            let actual = self.decisions.get(txid).copied();
+            if actual != Some(expected) {
+                bail!("Deposit '{}': expected decision {:?}, got {:?}", name, expected, actual);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Entry point for `sentinel scenario`
+pub fn scenario_command(file: &Path) -> Result<()> {
+    let scenario: Scenario = serde_json::from_slice(
+        &std::fs::read(file).with_context(|| format!("Cannot read {}", file.display()))?,
+    )
+    .context("Malformed scenario")?;
+
+    let dir = std::env::temp_dir().join(format!("sentinel-scenario-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let mut driver = ScenarioDriver::new(&dir.join("deposits.json"), scenario.confirmation_depth)?;
+    let result = driver.run(&scenario);
+    let _ = std::fs::remove_dir_all(&dir);
+    result?;
+
+    println!("Scenario passed: {} steps", scenario.steps.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn driver(dir: &tempfile::TempDir) -> ScenarioDriver {
+        ScenarioDriver::new(&dir.path().join("deposits.json"), 3).unwrap()
+    }
+
+    #[test]
+    fn test_reorged_out_deposit_reincluded_with_different_amount() {
+        let dir = tempfile::tempdir().unwrap();
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"confirmation_depth": 3, "steps": [
+                {"deposit": {"name": "a", "amount": 5000, "memo_amount": 5000}},
+                {"mine": {"blocks": 2}},
+                {"expect": {"name": "a", "absent": true}},
+                {"reorg": {"depth": 2}},
+                {"deposit": {"name": "a", "amount": 4000, "memo_amount": 5000}},
+                {"deposit": {"name": "b", "amount": 7000}},
+                {"mine": {"blocks": 4}},
+                {"expect": {"name": "a", "status": "rejected", "decision": "reject"}},
+                {"expect": {"name": "b", "status": "submitted", "decision": "attest"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        driver(&dir).run(&scenario).unwrap();
+    }
+
+    #[test]
+    fn test_deep_reorg_orphans_attested_deposit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut driver = driver(&dir);
+
+        driver
+            .step(&Step::Deposit {
+                name: "c".to_string(),
+                amount: 9000,
+                memo_amount: None,
+            })
+            .unwrap();
+        driver.step(&Step::Mine { blocks: 5 }).unwrap();
+        assert_eq!(driver.decision("c"), Some(Decision::Attest));
+
+        driver.step(&Step::Reorg { depth: 5 }).unwrap();
+        driver.step(&Step::Mine { blocks: 6 }).unwrap();
+        assert_eq!(driver.decision("c"), Some(Decision::Orphaned));
+
+        // The attestation already went out
+        let status = driver.store().resolve(&hex::encode(driver.names["c"])).unwrap().status;
+        assert_eq!(status, DepositStatus::Submitted);
+
+        // A failed expectation reports what was observed
+        let err = driver
+            .step(&Step::Expect {
+                name: "c".to_string(),
+                status: None,
+                decision: Some(Decision::Attest),
+                absent: false,
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("Orphaned"));
+    }
+}
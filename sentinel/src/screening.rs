@@ -0,0 +1,280 @@
+//! Compliance screening of deposit recipients
+//!
+//! Before a deposit is attested, its Aztec recipient (and refund address, if
+//! any) is looked up with a [`ScreeningProvider`]. Operators that need
+//! address screening plug in their provider here instead of forking the
+//! pipeline; without one every address passes.
+//!
+//! The built-in HTTP provider posts `{"chain": "aztec", "address": "0x.."}`
+//! to `SCREENING_URL` and expects `{"allowed": bool, "reason": "..."}`.
+//! Verdicts are cached, and a lookup that fails or times out leaves the
+//! deposit pending rather than letting it through.
+
+use crate::error::SentinelError;
+use crate::BridgePayload;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Default seconds a screening verdict is cached
+pub const DEFAULT_SCREENING_CACHE_SECS: u64 = 60 * 60;
+
+/// Default seconds to wait for a screening lookup
+pub const DEFAULT_SCREENING_TIMEOUT_SECS: u64 = 10;
+
+/// Outcome of screening an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskVerdict {
+    /// The address may receive funds
+    Allow,
+    /// The address is refused, for the given reason
+    Deny(String),
+}
+
+/// Address risk lookup
+#[async_trait]
+pub trait ScreeningProvider: Send + Sync {
+    /// Provider name for logs
+    fn name(&self) -> &str;
+
+    /// Screen `address` on `chain` (`aztec` or `zcash`)
+    async fn screen(&self, chain: &str, address: &str) -> Result<RiskVerdict, SentinelError>;
+}
+
+/// Provider that allows every address
+pub struct NoScreening;
+
+#[async_trait]
+impl ScreeningProvider for NoScreening {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    async fn screen(&self, _chain: &str, _address: &str) -> Result<RiskVerdict, SentinelError> {
+        Ok(RiskVerdict::Allow)
+    }
+}
+
+/// Lookup response of the HTTP provider
+#[derive(Debug, Deserialize)]
+struct ScreeningResponse {
+    allowed: bool,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Provider backed by an HTTP screening service
+pub struct HttpScreening {
+    /// Lookup URL
+    url: String,
+
+    /// Bearer token, if the service needs one
+    api_key: Option<String>,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl HttpScreening {
+    /// Create a provider posting lookups to `url`
+    pub fn new(url: String, api_key: Option<String>) -> Result<Self, SentinelError> {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(SentinelError::network)?;
+        Ok(Self {
+            url,
+            api_key,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl ScreeningProvider for HttpScreening {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn screen(&self, chain: &str, address: &str) -> Result<RiskVerdict, SentinelError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "chain": chain, "address": address }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response: ScreeningResponse = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(SentinelError::network)?
+            .json()
+            .await
+            .map_err(SentinelError::network)?;
+
+        Ok(if response.allowed {
+            RiskVerdict::Allow
+        } else {
+            RiskVerdict::Deny(
+                response
+                    .reason
+                    .unwrap_or_else(|| "flagged by screening provider".to_string()),
+            )
+        })
+    }
+}
+
+/// Screens deposits through a provider, with caching and a timeout
+pub struct Screener {
+    /// Provider doing the lookups
+    provider: Arc<dyn ScreeningProvider>,
+
+    /// How long a verdict is reused
+    cache_ttl: Duration,
+
+    /// Longest a lookup may take
+    timeout: Duration,
+
+    /// Verdicts by (chain, address)
+    cache: Mutex<HashMap<(String, String), (RiskVerdict, Instant)>>,
+}
+
+impl Screener {
+    /// Screen through `provider`
+    pub fn new(
+        provider: Arc<dyn ScreeningProvider>,
+        cache_ttl: Duration,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            provider,
+            cache_ttl,
+            timeout,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Screener that allows everything
+    pub fn disabled() -> Self {
+        Self::new(
+            Arc::new(NoScreening),
+            Duration::ZERO,
+            Duration::from_secs(1),
+        )
+    }
+
+    /// Provider name for logs
+    pub fn provider_name(&self) -> &str {
+        self.provider.name()
+    }
+
+    /// Screen the deposit's recipient and refund address
+    ///
+    /// An error means no verdict could be reached.
+    pub async fn check(&self, payload: &BridgePayload) -> Result<RiskVerdict, SentinelError> {
+        let recipient = format!("0x{}", hex::encode(payload.aztec_address));
+        let mut addresses = vec![("aztec", recipient.as_str())];
+        if let Some(refund) = &payload.refund_address {
+            addresses.push(("zcash", refund.as_str()));
+        }
+
+        for (chain, address) in addresses {
+            if let RiskVerdict::Deny(reason) = self.screen(chain, address).await? {
+                return Ok(RiskVerdict::Deny(format!("{} address {}", chain, reason)));
+            }
+        }
+        Ok(RiskVerdict::Allow)
+    }
+
+    /// Screen one address, from the cache when possible
+    async fn screen(&self, chain: &str, address: &str) -> Result<RiskVerdict, SentinelError> {
+        let key = (chain.to_string(), address.to_string());
+        if let Some((verdict, at)) = self.cache.lock().unwrap().get(&key) {
+            if at.elapsed() < self.cache_ttl {
+                return Ok(verdict.clone());
+            }
+        }
+
+        let verdict = tokio::time::timeout(self.timeout, self.provider.screen(chain, address))
+            .await
+            .map_err(|_| {
+                SentinelError::Network(format!(
+                    "{} screening timed out after {:?}",
+                    self.provider.name(),
+                    self.timeout
+                ))
+            })??;
+        debug!("Screened {} address {}: {:?}", chain, address, verdict);
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key, (verdict.clone(), Instant::now()));
+        Ok(verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Denies one address, stalls on another, counts lookups
+    struct FakeProvider {
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ScreeningProvider for FakeProvider {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        async fn screen(&self, _chain: &str, address: &str) -> Result<RiskVerdict, SentinelError> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            match address {
+                "zs1flagged" => Ok(RiskVerdict::Deny("sanctioned".to_string())),
+                "zs1slow" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(RiskVerdict::Allow)
+                }
+                _ => Ok(RiskVerdict::Allow),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_screening_caches_and_times_out() {
+        let provider = Arc::new(FakeProvider {
+            lookups: AtomicUsize::new(0),
+        });
+        let screener = Screener::new(
+            provider.clone(),
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+        );
+        let mut payload = BridgePayload::sample();
+
+        assert_eq!(screener.check(&payload).await.unwrap(), RiskVerdict::Allow);
+        assert_eq!(screener.check(&payload).await.unwrap(), RiskVerdict::Allow);
+        assert_eq!(provider.lookups.load(Ordering::SeqCst), 1);
+
+        payload.refund_address = Some("zs1flagged".to_string());
+        assert!(matches!(
+            screener.check(&payload).await.unwrap(),
+            RiskVerdict::Deny(_)
+        ));
+
+        payload.refund_address = Some("zs1slow".to_string());
+        assert!(screener.check(&payload).await.is_err());
+
+        let disabled = Screener::disabled();
+        payload.refund_address = Some("zs1flagged".to_string());
+        assert_eq!(disabled.check(&payload).await.unwrap(), RiskVerdict::Allow);
+    }
+}
@@ -0,0 +1,297 @@
+//! Multi-vault sharding
+//!
+//! A bridge can spread deposits over several vault addresses to cap the value
+//! held by any single address. Every shard has its own viewing key, so the
+//! scanner attributes each note to the shard that decrypted it. Balances are
+//! tracked per shard from the deposit store and the release queue, and the
+//! status API exposes a selection hint for deposit-creation tooling.
+
+use crate::reserves::read_releases;
+use crate::store::{DepositStatus, DepositStore};
+use anyhow::{bail, Result};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Shard id of the vault configured through `VAULT_ADDRESS`
+pub const PRIMARY_SHARD: &str = "primary";
+
+/// Serde default for records written before sharding
+pub fn primary_shard() -> String {
+    PRIMARY_SHARD.to_string()
+}
+
+/// A single vault address operated as part of the bridge
+#[derive(Debug, Clone, Deserialize)]
+pub struct VaultShard {
+    /// Short identifier, unique within the bridge
+    pub id: String,
+
+    /// Shielded vault address
+    pub address: String,
+
+    /// Viewing key for the vault
+    pub viewing_key: SecretString,
+
+    /// Maximum balance this shard should hold, in zatoshi
+    pub cap_zatoshi: Option<u64>,
+}
+
+/// What kind of spending key material `key` is, if it is not view-only
+///
+/// The watcher only ever needs a viewing key; a spending key here would let
+/// anyone who compromises the sentinel move the vault's funds.
+pub fn spending_key_kind(key: &str) -> Option<&'static str> {
+    let key = key.trim();
+    if key.starts_with("secret-extended-key-") {
+        return Some("Sapling spending key");
+    }
+    let words: Vec<&str> = key.split_whitespace().collect();
+    if (12..=24).contains(&words.len()) && words.iter().all(|w| w.chars().all(char::is_alphabetic)) {
+        return Some("seed phrase");
+    }
+    None
+}
+
+/// Parse `VAULT_SHARDS` entries of the form `id:address:viewing_key[:cap]`
+pub fn parse_shards(entries: &[String]) -> Result<Vec<VaultShard>> {
+    let mut shards = Vec::new();
+    for entry in entries {
+        let parts: Vec<&str> = entry.split(':').collect();
+        let (id, address, viewing_key, cap) = match parts.as_slice() {
+            [id, address, key] => (*id, *address, *key, None),
+            [id, address, key, cap] => (*id, *address, *key, Some(cap.parse()?)),
+            // Never echo the entry: it holds a viewing key
+            _ => bail!(
+                "Invalid vault shard '{}': expected id:address:viewing_key[:cap]",
+                parts[0]
+            ),
+        };
+        if id.is_empty() || id == PRIMARY_SHARD || shards.iter().any(|s: &VaultShard| s.id == id) {
+            bail!("Invalid or duplicate vault shard id '{}'", id);
+        }
+
+        shards.push(VaultShard {
+            id: id.to_string(),
+            address: address.to_string(),
+            viewing_key: SecretString::new(viewing_key.to_string()),
+            cap_zatoshi: cap,
+        });
+    }
+    Ok(shards)
+}
+
+/// Balance of one shard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShardBalance {
+    /// Shard id
+    pub id: String,
+
+    /// Shielded vault address
+    pub address: String,
+
+    /// Deposits received, in zatoshi; orphaned, rejected and refunded ones
+    /// are left out
+    pub deposited: u64,
+
+    /// Withdrawal releases paid from the shard, in zatoshi
+    pub withdrawn: u64,
+
+    /// Current balance, in zatoshi
+    pub balance: u64,
+
+    /// Balance cap, if any
+    pub cap_zatoshi: Option<u64>,
+}
+
+impl ShardBalance {
+    /// Room left under the cap
+    pub fn headroom(&self) -> u64 {
+        self.cap_zatoshi
+            .map_or(u64::MAX, |cap| cap.saturating_sub(self.balance))
+    }
+}
+
+/// The bridge's vault shards and their accounting
+pub struct ShardSet {
+    /// Configured shards, primary first
+    shards: Vec<VaultShard>,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Release queue file, if withdrawals are enabled
+    release_queue_path: Option<PathBuf>,
+}
+
+impl ShardSet {
+    /// Create a shard set
+    pub fn new(
+        shards: Vec<VaultShard>,
+        store: Arc<DepositStore>,
+        release_queue_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            shards,
+            store,
+            release_queue_path,
+        }
+    }
+
+    /// Current balance of every shard
+    pub fn balances(&self) -> Result<Vec<ShardBalance>> {
+        let mut totals: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+
+        for record in self.store.all() {
+            let Some(shard) = self.shards.iter().find(|s| s.id == record.vault_shard) else {
+                continue;
+            };
+            // Reorged-out deposits never reached the shard, and rejected ones
+            // are on their way back to the depositor
+            if matches!(
+                record.status,
+                DepositStatus::Orphaned
+                    | DepositStatus::Rejected
+                    | DepositStatus::RefundQueued
+                    | DepositStatus::Refunded
+            ) {
+                continue;
+            }
+            totals.entry(&shard.id).or_default().0 += record.amount;
+        }
+
+        if let Some(path) = &self.release_queue_path {
+            for release in read_releases(path)? {
+                let id = release.vault_shard.as_deref().unwrap_or(PRIMARY_SHARD);
+                if let Some(shard) = self.shards.iter().find(|s| s.id == id) {
+                    totals.entry(&shard.id).or_default().1 += release.amount;
+                }
+            }
+        }
+
+        Ok(self
+            .shards
+            .iter()
+            .map(|shard| {
+                let (deposited, withdrawn) = totals.get(shard.id.as_str()).copied().unwrap_or_default();
+                ShardBalance {
+                    id: shard.id.clone(),
+                    address: shard.address.clone(),
+                    deposited,
+                    withdrawn,
+                    balance: deposited.saturating_sub(withdrawn),
+                    cap_zatoshi: shard.cap_zatoshi,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Shard a new deposit of `amount` should go to: the emptiest with room for it
+pub fn select_for_deposit(balances: &[ShardBalance], amount: u64) -> Option<&ShardBalance> {
+    balances
+        .iter()
+        .filter(|b| b.headroom() >= amount)
+        .min_by_key(|b| b.balance)
+}
+
+/// Shard a release of `amount` should be paid from: the fullest that covers it
+pub fn select_for_release(balances: &[ShardBalance], amount: u64) -> Option<&ShardBalance> {
+    balances
+        .iter()
+        .filter(|b| b.balance >= amount)
+        .max_by_key(|b| b.balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BridgePayload;
+
+    fn balance(id: &str, balance: u64, cap: Option<u64>) -> ShardBalance {
+        ShardBalance {
+            id: id.to_string(),
+            address: format!("zs1{}", id),
+            deposited: balance,
+            withdrawn: 0,
+            balance,
+            cap_zatoshi: cap,
+        }
+    }
+
+    #[test]
+    fn test_parse_shards() {
+        let shards = parse_shards(&["b:zs1b:zxviews1b:5000".to_string()]).unwrap();
+        assert_eq!(shards[0].id, "b");
+        assert_eq!(shards[0].cap_zatoshi, Some(5_000));
+
+        assert!(parse_shards(&["primary:zs1a:zxviews1a".to_string()]).is_err());
+        assert!(parse_shards(&["b:zs1b".to_string()]).is_err());
+
+        // Viewing keys never reach Debug output or error messages
+        assert!(!format!("{:?}", shards[0]).contains("zxviews1b"));
+        let error = parse_shards(&["c:zs1c:zxviews1c:1:2".to_string()]).unwrap_err();
+        assert!(!error.to_string().contains("zxviews1c"));
+    }
+
+    #[test]
+    fn test_spending_keys_recognised() {
+        assert_eq!(spending_key_kind("zxviews1qqq"), None);
+        assert_eq!(
+            spending_key_kind("secret-extended-key-main1qqq"),
+            Some("Sapling spending key")
+        );
+        let phrase = ["abandon"; 23].join(" ") + " art";
+        assert_eq!(spending_key_kind(&phrase), Some("seed phrase"));
+    }
+
+    #[test]
+    fn test_selection() {
+        let balances = vec![
+            balance("primary", 900, Some(1_000)),
+            balance("b", 500, Some(1_000)),
+            balance("c", 700, None),
+        ];
+
+        // Emptiest shard with room
+        assert_eq!(select_for_deposit(&balances, 100).unwrap().id, "b");
+        // Too big for any capped shard
+        assert_eq!(select_for_deposit(&balances, 600).unwrap().id, "c");
+
+        assert_eq!(select_for_release(&balances, 100).unwrap().id, "primary");
+        assert!(select_for_release(&balances, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_balances_leave_out_orphaned_and_rejected_deposits() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        for (tx, status) in [
+            (1, None),
+            (2, Some(DepositStatus::Orphaned)),
+            (3, Some(DepositStatus::Rejected)),
+        ] {
+            let record = store
+                .insert_detected(&BridgePayload {
+                    tx_hash: [tx; 32],
+                    ..BridgePayload::sample()
+                })
+                .unwrap();
+            if let Some(status) = status {
+                store.transition(&record.key(), status, None).unwrap();
+            }
+        }
+
+        let primary = VaultShard {
+            id: PRIMARY_SHARD.to_string(),
+            address: "zs1primary".to_string(),
+            viewing_key: SecretString::new("zxviews1primary".to_string()),
+            cap_zatoshi: None,
+        };
+        let balances = ShardSet::new(vec![primary], store, None).balances().unwrap();
+        assert_eq!(balances[0].deposited, BridgePayload::sample().amount);
+        assert_eq!(balances[0].balance, BridgePayload::sample().amount);
+    }
+}
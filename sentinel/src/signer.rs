@@ -3,20 +3,37 @@
 //! Signs deposit attestations using ECDSA and submits them to the
 //! ServiceManager contract on L1.
 
+use crate::aggregate::{aggregate, verify_aggregate};
+use crate::config::SentinelConfig;
 use crate::error::SentinelError;
+use crate::keyaudit::KeyAuditLog;
+use crate::keystore::{self, LockedWallet, OperatorSigner};
+use crate::leader::note_seed;
+use crate::lease::LeaseFence;
+use crate::retry::RetryPolicy;
+use crate::signlog::{SignedNote, SigningLog};
+use crate::targets::{GasPolicy, TargetSpec};
 use crate::{Attestation, BridgePayload};
 use anyhow::Result;
 use ethers::prelude::*;
 use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::{Address, Bytes, U256};
 use ethers::utils::keccak256;
-use std::sync::Arc;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tracing::{debug, info};
 
+pub use sentinel_core::payload::{
+    batch_digest, deposit_hash, depositor_hash, domain_separator, eip712_digest, payload_tokens,
+    PAYLOAD_VERSION, VERIFY_AND_DISPATCH, VERIFY_BATCH,
+};
+
 /// Attestation signer for bridge deposits
 pub struct AttestationSigner {
-    /// Ethereum wallet for signing
-    wallet: LocalWallet,
+    /// Operator key for signing, in locked memory or an enclave
+    wallet: OperatorSigner,
 
     /// Provider for L1 interaction
     provider: Arc<Provider<Http>>,
@@ -26,19 +43,73 @@ pub struct AttestationSigner {
 
     /// Chain ID for signing
     chain_id: u64,
+
+    /// Persistent record of signed attestations, refusing repeats
+    signing_log: Option<Mutex<SigningLog>>,
+
+    /// Audit trail of every use of the operator key
+    audit: Option<Arc<KeyAuditLog>>,
+
+    /// Limits on the transactions this signer sends
+    gas: GasPolicy,
+
+    /// Retries of read-only L1 calls
+    retry: RetryPolicy,
+
+    /// Transaction nonces of the operator's account, shared by every signer
+    /// sending from it
+    nonces: Arc<TxNonces>,
+
+    /// Leader lease the key is only used under, in an HA pair
+    fence: Option<Arc<LeaseFence>>,
+}
+
+/// Next transaction nonce of each account on each chain
+///
+/// Deployments served from one process send from the same account; sends go
+/// through here one at a time so they never race for a nonce.
+#[derive(Default)]
+pub struct TxNonces {
+    /// Next nonce by (chain ID, account), once known
+    next: tokio::sync::Mutex<HashMap<(u64, Address), U256>>,
 }
 
 impl AttestationSigner {
     /// Create a new attestation signer
     pub fn new(
-        private_key: String,
+        private_key: &SecretString,
         l1_rpc_url: String,
         service_manager_address: String,
     ) -> Result<Self> {
-        // Parse private key
-        let key = private_key.strip_prefix("0x").unwrap_or(&private_key);
-        let wallet: LocalWallet = key.parse()?;
+        // Parse private key; the signing key inside the wallet zeroizes on drop
+        let key = private_key.expose_secret();
+        let wallet: LocalWallet = key.strip_prefix("0x").unwrap_or(key).parse()?;
+        Self::with_wallet(
+            OperatorSigner::Local(LockedWallet::new(wallet)),
+            l1_rpc_url,
+            service_manager_address,
+        )
+    }
+
+    /// Create the operator's signer from its keystore, key or enclave
+    pub async fn from_config(config: &SentinelConfig) -> Result<Self> {
+        let audit = KeyAuditLog::open(config.data_path("key_audit.jsonl"))?;
+        let mut signer = Self::with_wallet(
+            keystore::operator_signer(config).await?,
+            config.l1_rpc_url.clone(),
+            config.service_manager_address.clone(),
+        )?
+        .with_audit_log(Arc::new(audit));
+        // Signatures are bound to the chain the contract lives on
+        signer.chain_id = signer.provider.get_chainid().await?.as_u64();
+        Ok(signer)
+    }
 
+    fn with_wallet(
+        wallet: OperatorSigner,
+        l1_rpc_url: String,
+        service_manager_address: String,
+    ) -> Result<Self> {
         // Create provider
         let provider = Provider::<Http>::try_from(l1_rpc_url)?;
 
@@ -50,26 +121,149 @@ impl AttestationSigner {
             provider: Arc::new(provider),
             service_manager_address: address,
             chain_id: 31337, // Anvil default
+            signing_log: None,
+            audit: None,
+            gas: GasPolicy::default(),
+            retry: RetryPolicy::none(),
+            nonces: Arc::new(TxNonces::default()),
+            fence: None,
+        })
+    }
+
+    /// Signer with the same key for another deployment, with its own nonces
+    ///
+    /// The chain ID is read from the target's RPC endpoint.
+    pub async fn for_target(&self, target: &TargetSpec, signing_log: SigningLog) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(target.rpc_url.as_str())?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        Ok(Self {
+            wallet: self.wallet.clone(),
+            provider: Arc::new(provider),
+            service_manager_address: target.service_manager.parse()?,
+            chain_id,
+            signing_log: Some(Mutex::new(signing_log)),
+            audit: self.audit.clone(),
+            gas: target.gas,
+            retry: self.retry.clone(),
+            nonces: self.nonces.clone(),
+            fence: self.fence.clone(),
         })
     }
 
+    /// Record every attestation in `log` and refuse to sign a repeat
+    pub fn with_signing_log(mut self, log: SigningLog) -> Self {
+        self.signing_log = Some(Mutex::new(log));
+        self
+    }
+
+    /// Record every use of the key in `audit` before making it
+    pub fn with_audit_log(mut self, audit: Arc<KeyAuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Limit the transactions sent with `gas`
+    pub fn with_gas_policy(mut self, gas: GasPolicy) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    /// Retry read-only L1 calls under `retry`
+    ///
+    /// Transactions are never resent here: a send whose response was lost may
+    /// still be mined, and the pipeline re-checks the nonce before retrying.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Take transaction nonces from `nonces`, shared with the other
+    /// deployments sending from the same account
+    pub fn with_nonces(mut self, nonces: Arc<TxNonces>) -> Self {
+        self.nonces = nonces;
+        self
+    }
+
+    /// Only sign and send while `fence` holds the leader lease
+    pub fn with_fence(mut self, fence: Arc<LeaseFence>) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+
+    /// Key audit trail, shared with the payout policy
+    pub fn audit_log(&self) -> Option<Arc<KeyAuditLog>> {
+        self.audit.clone()
+    }
+
+    /// Append to the audit trail; the key must not be used if this fails
+    fn audit(
+        &self,
+        purpose: &str,
+        digest: &[u8; 32],
+        nonce: Option<u64>,
+        deposit: Option<&[u8; 32]>,
+    ) -> Result<(), SentinelError> {
+        // A deposed leader must not use the key its successor now uses
+        if let Some(fence) = &self.fence {
+            fence.check().map_err(SentinelError::Signing)?;
+        }
+        match &self.audit {
+            Some(audit) => audit.record(purpose, digest, nonce, deposit),
+            None => Ok(()),
+        }
+    }
+
+    /// What this operator signed for a vault note, if anything
+    pub fn signed_deposit(&self, tx_hash: &[u8; 32], output_index: u32) -> Option<SignedNote> {
+        self.signing_log
+            .as_ref()
+            .and_then(|log| log.lock().unwrap().signed_deposit(tx_hash, output_index))
+    }
+
     /// Sign an attestation for a deposit
     pub async fn sign_attestation(
         &self,
         payload: &BridgePayload,
         nonce: u64,
     ) -> Result<Attestation, SentinelError> {
-        // Compute the message hash (matching Solidity encoding)
-        let message_hash = self.compute_payload_hash(payload, nonce);
+        // Never sign an amount other than what was actually deposited
+        check_amounts(payload)?;
+
+        // Deposits recorded before payload v2 have no block hash to attest
+        if payload.block_hash == [0u8; 32] {
+            return Err(SentinelError::InvalidPayload(format!(
+                "deposit {} has no block hash; rescan its block to attest it",
+                hex::encode(payload.tx_hash)
+            )));
+        }
+
+        // The EIP-712 digest ServiceManager verifies, bound to this deployment
+        let message_hash = self.compute_digest(payload, nonce);
 
         debug!("Signing message hash: {}", hex::encode(message_hash));
 
+        // Durably record the signature first; a conflicting one is refused
+        // here. Signing the identical digest again, after a restart or a
+        // rescan, yields the identical signature and needs no new entry.
+        if let Some(log) = &self.signing_log {
+            let mut log = log.lock().unwrap();
+            if !log.is_signed(&message_hash) {
+                log.reserve(message_hash, nonce, payload)?;
+            }
+        }
+        self.audit(
+            "attestation",
+            &message_hash,
+            Some(nonce),
+            Some(&payload.tx_hash),
+        )?;
+
         // Sign the message with EIP-191 prefix
         let signature = self
             .wallet
             .sign_message(message_hash)
             .await
-            .map_err(|e| SentinelError::Signing(e.to_string()))?;
+            .map_err(SentinelError::signing)?;
 
         // Convert signature to bytes (r, s, v format)
         let sig_bytes = signature.to_vec();
@@ -81,72 +275,125 @@ impl AttestationSigner {
         })
     }
 
+    /// Send a transaction with raw calldata and wait for its receipt
+    pub async fn send_call(&self, to: Address, calldata: Vec<u8>) -> Result<String, SentinelError> {
+        let client = SignerMiddleware::new(
+            self.provider.clone(),
+            self.wallet.clone().with_chain_id(self.chain_id),
+        );
+
+        let tx = self
+            .apply_gas_policy(TransactionRequest::new().to(to).data(Bytes::from(calldata.clone())))
+            .await?;
+        self.audit("l1_transaction", &keccak256(&calldata), None, None)?;
+
+        let receipt = self
+            .send(&client, tx)
+            .await?
+            .await
+            .map_err(SentinelError::l1)?
+            .ok_or_else(|| SentinelError::L1("Transaction receipt not found".to_string()))?;
+
+        Ok(format!("{:?}", receipt.transaction_hash))
+    }
+
+    /// Send `tx` under the account's next nonce
+    ///
+    /// The nonce is held until the node accepts the transaction; a failed
+    /// send makes the next one read the nonce from the chain again.
+    async fn send<'a>(
+        &self,
+        client: &'a SignerMiddleware<Arc<Provider<Http>>, OperatorSigner>,
+        tx: TransactionRequest,
+    ) -> Result<PendingTransaction<'a, Http>, SentinelError> {
+        let account = (self.chain_id, self.address());
+        let mut next = self.nonces.next.lock().await;
+        let nonce = match next.get(&account) {
+            Some(nonce) => *nonce,
+            None => self
+                .provider
+                .get_transaction_count(account.1, Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(SentinelError::l1)?,
+        };
+
+        match client.send_transaction(tx.nonce(nonce), None).await {
+            Ok(pending) => {
+                next.insert(account, nonce + 1);
+                Ok(pending)
+            }
+            Err(e) => {
+                next.remove(&account);
+                Err(SentinelError::l1(e))
+            }
+        }
+    }
+
+    /// Sign an arbitrary 32-byte hash with the EIP-191 prefix
+    ///
+    /// `purpose` names what the signature is for in the key audit trail.
+    pub async fn sign_hash(
+        &self,
+        hash: [u8; 32],
+        purpose: &str,
+    ) -> Result<Vec<u8>, SentinelError> {
+        self.audit(purpose, &hash, None, None)?;
+        let signature = self
+            .wallet
+            .sign_message(hash)
+            .await
+            .map_err(SentinelError::signing)?;
+
+        Ok(signature.to_vec())
+    }
+
     /// Submit an attestation to the ServiceManager contract
-    /// 
-    /// This uses raw ABI encoding to call verifyAndDispatch
+    ///
+    /// This uses raw ABI encoding to call verifyAndDispatch. `cosigners` are
+    /// peers' (signer, signature) pairs over the same digest, appended after
+    /// our own.
     pub async fn submit_attestation(
         &self,
         attestation: &Attestation,
+        cosigners: &[(Address, Vec<u8>)],
     ) -> Result<String, SentinelError> {
         let client = SignerMiddleware::new(
             self.provider.clone(),
             self.wallet.clone().with_chain_id(self.chain_id),
         );
 
-        // Encode the function call manually
-        // verifyAndDispatch(DepositPayload payload, bytes aggregatedSig, address[] signers)
-        // Function selector: keccak256("verifyAndDispatch((bytes32,uint256,bytes32,bytes32,uint64,uint32),bytes,address[])")
-        
-        let function_selector = &keccak256(
-            b"verifyAndDispatch((bytes32,uint256,bytes32,bytes32,uint64,uint32),bytes,address[])"
-        )[0..4];
-
-        // Encode payload struct
+        // Our partial first, then each cosigner's, checked before spending gas
         let payload = &attestation.payload;
-        let encoded_payload = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(payload.tx_hash.to_vec()),
-            ethers::abi::Token::Uint(U256::from(payload.amount)),
-            ethers::abi::Token::FixedBytes(payload.secret_hash.to_vec()),
-            ethers::abi::Token::FixedBytes(payload.aztec_address.to_vec()),
-            ethers::abi::Token::Uint(U256::from(attestation.nonce)),
-            ethers::abi::Token::Uint(U256::from(payload.block_height)),
-        ]);
-
-        // Encode signature bytes
-        let encoded_sig = ethers::abi::encode(&[ethers::abi::Token::Bytes(
-            attestation.signature.clone(),
-        )]);
-
-        // Encode signers array
-        let signers = vec![self.wallet.address()];
-        let encoded_signers = ethers::abi::encode(&[ethers::abi::Token::Array(
-            signers
-                .iter()
-                .map(|a| ethers::abi::Token::Address(*a))
-                .collect(),
-        )]);
-
-        // Combine all encoded data
-        let mut calldata = Vec::new();
-        calldata.extend_from_slice(function_selector);
-        calldata.extend_from_slice(&encoded_payload);
-        calldata.extend_from_slice(&encoded_sig);
-        calldata.extend_from_slice(&encoded_signers);
+        let (signers, aggregated) = self
+            .aggregate_with(
+                self.compute_digest(payload, attestation.nonce),
+                &attestation.signature,
+                cosigners,
+            )
+            .await?;
+        let calldata = dispatch_calldata(payload, attestation.nonce, aggregated, &signers);
 
         // Create transaction
-        let tx = TransactionRequest::new()
-            .to(self.service_manager_address)
-            .data(Bytes::from(calldata));
+        let tx = self
+            .apply_gas_policy(
+                TransactionRequest::new()
+                    .to(self.service_manager_address)
+                    .data(Bytes::from(calldata.clone())),
+            )
+            .await?;
+        self.audit(
+            "l1_transaction",
+            &keccak256(&calldata),
+            Some(attestation.nonce),
+            Some(&payload.tx_hash),
+        )?;
 
         // Send transaction
-        let pending_tx = client
-            .send_transaction(tx, None)
-            .await
-            .map_err(|e| SentinelError::L1(e.to_string()))?;
+        let pending_tx = self.send(&client, tx).await?;
 
         let receipt = pending_tx
             .await
-            .map_err(|e| SentinelError::L1(e.to_string()))?
+            .map_err(SentinelError::l1)?
             .ok_or_else(|| SentinelError::L1("Transaction receipt not found".to_string()))?;
 
         info!(
@@ -157,21 +404,127 @@ impl AttestationSigner {
         Ok(format!("{:?}", receipt.transaction_hash))
     }
 
-    /// Compute the hash of a payload (matching Solidity encoding)
-    fn compute_payload_hash(&self, payload: &BridgePayload, nonce: u64) -> [u8; 32] {
-        use ethers::abi::{encode, Token};
+    /// Aggregate our signature over `digest` with the cosigners', ours first,
+    /// and verify the aggregate locally before any gas is spent on it
+    async fn aggregate_with(
+        &self,
+        digest: [u8; 32],
+        signature: &[u8],
+        cosigners: &[(Address, Vec<u8>)],
+    ) -> Result<(Vec<Address>, Vec<u8>), SentinelError> {
+        let mut signers = vec![self.wallet.address()];
+        let mut partials = vec![signature.to_vec()];
+        for (cosigner, signature) in cosigners {
+            signers.push(*cosigner);
+            partials.push(signature.clone());
+        }
+        let aggregated = aggregate(&partials);
+
+        let registered = self.registered_signers(&signers).await?;
+        verify_aggregate(digest, &aggregated, &signers, &registered)?;
+        Ok((signers, aggregated))
+    }
+
+    /// Sign the root of a Merkle batch over `payloads`
+    ///
+    /// Each batched note is first recorded in the signing log under its note
+    /// nonce, keyed by its leaf, so a note is never batched with other fields
+    /// nor attested singly as well.
+    pub async fn sign_batch(
+        &self,
+        payloads: &[BridgePayload],
+        root: [u8; 32],
+        nonce: u64,
+    ) -> Result<Vec<u8>, SentinelError> {
+        for payload in payloads {
+            check_amounts(payload)?;
+            if payload.block_hash == [0u8; 32] {
+                return Err(SentinelError::InvalidPayload(format!(
+                    "deposit {} has no block hash; rescan its block to attest it",
+                    hex::encode(payload.tx_hash)
+                )));
+            }
+        }
+
+        if let Some(log) = &self.signing_log {
+            let mut log = log.lock().unwrap();
+            for payload in payloads {
+                let leaf = deposit_hash(payload);
+                if !log.is_signed(&leaf) {
+                    log.reserve(leaf, note_nonce(payload), payload)?;
+                }
+            }
+        }
+
+        let count = u32::try_from(payloads.len())
+            .map_err(|_| SentinelError::InvalidPayload("batch too large".to_string()))?;
+        self.sign_hash(self.compute_batch_digest(root, nonce, count), "batch_root")
+            .await
+    }
+
+    /// Submit a signed batch root with `verifyBatch`
+    pub async fn submit_batch(
+        &self,
+        root: [u8; 32],
+        nonce: u64,
+        count: u32,
+        signature: &[u8],
+        cosigners: &[(Address, Vec<u8>)],
+    ) -> Result<String, SentinelError> {
+        let (signers, aggregated) = self
+            .aggregate_with(self.compute_batch_digest(root, nonce, count), signature, cosigners)
+            .await?;
+
+        let mut calldata = keccak256(VERIFY_BATCH)[0..4].to_vec();
+        calldata.extend(ethers::abi::encode(&[
+            ethers::abi::Token::FixedBytes(root.to_vec()),
+            ethers::abi::Token::Uint(U256::from(nonce)),
+            ethers::abi::Token::Uint(U256::from(count)),
+            ethers::abi::Token::Bytes(aggregated),
+            ethers::abi::Token::Array(
+                signers.into_iter().map(ethers::abi::Token::Address).collect(),
+            ),
+        ]));
+        self.send_call(self.service_manager_address, calldata).await
+    }
 
-        let tokens = vec![
-            Token::FixedBytes(payload.tx_hash.to_vec()),
-            Token::Uint(U256::from(payload.amount)),
-            Token::FixedBytes(payload.secret_hash.to_vec()),
-            Token::FixedBytes(payload.aztec_address.to_vec()),
-            Token::Uint(U256::from(nonce)),
-            Token::Uint(U256::from(payload.block_height)),
-        ];
+    /// Apply the gas limit, and refuse to send while gas is above the cap
+    async fn apply_gas_policy(
+        &self,
+        mut tx: TransactionRequest,
+    ) -> Result<TransactionRequest, SentinelError> {
+        if let Some(limit) = self.gas.gas_limit {
+            tx = tx.gas(limit);
+        }
+        if let Some(cap_gwei) = self.gas.max_gas_price_gwei {
+            let price = self
+                .retry
+                .run("eth_gasPrice", || async {
+                    Ok(self.provider.get_gas_price().await?)
+                })
+                .await?;
+            if price > U256::from(cap_gwei) * U256::exp10(9) {
+                return Err(SentinelError::L1(format!(
+                    "gas price {} gwei above the {} gwei cap, not submitting",
+                    price / U256::exp10(9),
+                    cap_gwei
+                )));
+            }
+            tx = tx.gas_price(price);
+        }
+        Ok(tx)
+    }
 
-        let encoded = encode(&tokens);
-        keccak256(&encoded)
+    /// EIP-712 digest of a payload, as `ServiceManager._computePayloadHash`
+    /// computes it for this deployment
+    pub fn compute_digest(&self, payload: &BridgePayload, nonce: u64) -> [u8; 32] {
+        eip712_digest(payload, nonce, self.chain_id, self.service_manager_address)
+    }
+
+    /// EIP-712 digest of a batch root, as `ServiceManager.verifyBatch`
+    /// computes it for this deployment
+    pub fn compute_batch_digest(&self, root: [u8; 32], nonce: u64, count: u32) -> [u8; 32] {
+        batch_digest(root, nonce, count, self.chain_id, self.service_manager_address)
     }
 
     /// Get the operator's address
@@ -179,6 +532,67 @@ impl AttestationSigner {
         self.wallet.address()
     }
 
+    /// Get the L1 provider
+    pub fn provider(&self) -> Arc<Provider<Http>> {
+        self.provider.clone()
+    }
+
+    /// Get the ServiceManager contract address
+    pub fn service_manager_address(&self) -> Address {
+        self.service_manager_address
+    }
+
+    /// Filter `signers` down to those with a key registered on the verifier
+    pub async fn registered_signers(
+        &self,
+        signers: &[Address],
+    ) -> Result<Vec<Address>, SentinelError> {
+        // blsVerifier() returns the verifier contract address
+        let result = self
+            .call(
+                self.service_manager_address,
+                keccak256(b"blsVerifier()")[0..4].to_vec(),
+            )
+            .await?;
+        if result.len() < 32 {
+            return Err(SentinelError::L1("Malformed blsVerifier response".to_string()));
+        }
+        let verifier = Address::from_slice(&result[12..32]);
+
+        let selector = &keccak256(b"hasRegisteredKey(address)")[0..4];
+        let mut registered = Vec::new();
+        for signer in signers {
+            let mut calldata = selector.to_vec();
+            calldata.extend_from_slice(&ethers::abi::encode(&[ethers::abi::Token::Address(
+                *signer,
+            )]));
+
+            let result = self.call(verifier, calldata).await?;
+            if !result.is_empty() && result[result.len() - 1] != 0 {
+                registered.push(*signer);
+            }
+        }
+
+        Ok(registered)
+    }
+
+    /// Perform an `eth_call` against `to`
+    async fn call(&self, to: Address, calldata: Vec<u8>) -> Result<Bytes, SentinelError> {
+        let call: TypedTransaction = TransactionRequest::new()
+            .to(to)
+            .data(Bytes::from(calldata))
+            .into();
+
+        self.retry
+            .run("eth_call", || async {
+                self.provider
+                    .call(&call, None)
+                    .await
+                    .map_err(SentinelError::l1)
+            })
+            .await
+    }
+
     /// Check if a nonce has been used
     pub async fn is_nonce_used(&self, nonce: u64) -> Result<bool, SentinelError> {
         // Encode function call for isNonceUsed(uint64)
@@ -189,50 +603,176 @@ impl AttestationSigner {
         calldata.extend_from_slice(function_selector);
         calldata.extend_from_slice(&encoded_nonce);
 
-        let call = TransactionRequest::new()
-            .to(self.service_manager_address)
-            .data(Bytes::from(calldata));
-
-        let result = self
-            .provider
-            .call(&call.into(), None)
-            .await
-            .map_err(|e| SentinelError::L1(e.to_string()))?;
+        let result = self.call(self.service_manager_address, calldata).await?;
 
         // Decode bool result
         let used = !result.is_empty() && result[result.len() - 1] != 0;
         Ok(used)
     }
+
+    /// Root verified on L1 under a batch nonce; zero while the nonce is unused
+    pub async fn batch_root(&self, nonce: u64) -> Result<[u8; 32], SentinelError> {
+        let mut calldata = keccak256(b"batchRoot(uint64)")[0..4].to_vec();
+        calldata.extend(ethers::abi::encode(&[ethers::abi::Token::Uint(U256::from(nonce))]));
+
+        let result = self.call(self.service_manager_address, calldata).await?;
+        result
+            .get(..32)
+            .and_then(|root| root.try_into().ok())
+            .ok_or_else(|| SentinelError::L1("Malformed batchRoot response".to_string()))
+    }
+
+    /// Check if the vault note at `output_index` of `tx_hash` has been
+    /// dispatched, by any operator
+    pub async fn is_deposit_processed(
+        &self,
+        tx_hash: &[u8; 32],
+        output_index: u32,
+    ) -> Result<bool, SentinelError> {
+        // Encode function call for isDepositProcessed(bytes32,uint32)
+        let function_selector = &keccak256(b"isDepositProcessed(bytes32,uint32)")[0..4];
+        let encoded_note = ethers::abi::encode(&[
+            ethers::abi::Token::FixedBytes(tx_hash.to_vec()),
+            ethers::abi::Token::Uint(U256::from(output_index)),
+        ]);
+
+        let mut calldata = Vec::new();
+        calldata.extend_from_slice(function_selector);
+        calldata.extend_from_slice(&encoded_note);
+
+        let result = self.call(self.service_manager_address, calldata).await?;
+
+        // Decode bool result
+        let processed = !result.is_empty() && result[result.len() - 1] != 0;
+        Ok(processed)
+    }
+}
+
+/// Attestation nonce of a deposit's vault note
+///
+/// Derived from the note rather than assigned in signing order, so every
+/// operator signs the same digest and their signatures aggregate.
+pub fn note_nonce(payload: &BridgePayload) -> u64 {
+    let seed = note_seed(&payload.tx_hash, payload.output_index);
+    u64::from_be_bytes(seed[..8].try_into().unwrap())
+}
+
+/// `verifyAndDispatch(DepositPayload payload, bytes aggregatedSig, address[] signers)`
+/// calldata
+pub fn dispatch_calldata(
+    payload: &BridgePayload,
+    nonce: u64,
+    aggregated: Vec<u8>,
+    signers: &[Address],
+) -> Vec<u8> {
+    let mut calldata = keccak256(VERIFY_AND_DISPATCH)[0..4].to_vec();
+    calldata.extend(ethers::abi::encode(&[
+        ethers::abi::Token::Tuple(payload_tokens(payload, nonce)),
+        ethers::abi::Token::Bytes(aggregated),
+        ethers::abi::Token::Array(
+            signers.iter().copied().map(ethers::abi::Token::Address).collect(),
+        ),
+    ]));
+    calldata
+}
+
+/// Hash of a payload and nonce as signed by the operator (matching Solidity encoding)
+pub fn payload_hash(payload: &BridgePayload, nonce: u64) -> [u8; 32] {
+    #[cfg(feature = "profiling")]
+    let _cpu = crate::profiling::StageTimer::start(crate::profiling::Stage::Encode);
+
+    sentinel_core::payload::payload_hash(payload, nonce)
+}
+
+/// Cross-check the note value against the memo claim and the attested split
+pub fn check_amounts(payload: &BridgePayload) -> Result<(), SentinelError> {
+    if let Some(expected) = payload.memo_amount {
+        if expected != payload.amount {
+            return Err(SentinelError::InvalidPayload(format!(
+                "note value {} does not match memo amount {}",
+                payload.amount, expected
+            )));
+        }
+    }
+
+    if payload.fee >= payload.amount {
+        return Err(SentinelError::InvalidPayload(format!(
+            "fee {} leaves nothing to attest from note value {}",
+            payload.fee, payload.amount
+        )));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Signer with the Anvil dev key for the zero contract address
+    fn test_signer() -> AttestationSigner {
+        AttestationSigner::new(
+            &SecretString::new(
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".to_string(),
+            ),
+            "http://localhost:8545".to_string(),
+            format!("{:?}", Address::zero()),
+        )
+        .unwrap()
+    }
+
     #[test]
-    fn test_payload_hash() {
-        // This test verifies that our Rust hash computation matches Solidity
-        let signer = AttestationSigner {
-            wallet: "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
-                .parse()
-                .unwrap(),
-            provider: Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap()),
-            service_manager_address: Address::zero(),
-            chain_id: 31337,
-        };
+    fn test_payload_digest() {
+        // This test verifies that our Rust digest computation matches Solidity
+        let signer = test_signer();
 
         let payload = BridgePayload {
             tx_hash: [0xab; 32],
             amount: 1000000000, // 10 ZEC
             secret_hash: [0xcd; 32],
             aztec_address: [0xef; 32],
-            block_height: 100,
+            block_hash: [0x12; 32],
+            ..BridgePayload::sample()
         };
 
-        let hash = signer.compute_payload_hash(&payload, 1);
+        let hash = signer.compute_digest(&payload, 1);
 
         // Hash should be deterministic
         assert_eq!(hash.len(), 32);
         assert_ne!(hash, [0u8; 32]);
+
+        // The fee is covered by the signature
+        let with_fee = BridgePayload { fee: 1_000, ..payload.clone() };
+        assert_ne!(signer.compute_digest(&with_fee, 1), hash);
+
+        // So are the chain and the contract
+        assert_eq!(hash, eip712_digest(&payload, 1, 31337, Address::zero()));
+        assert_ne!(hash, eip712_digest(&payload, 1, 1, Address::zero()));
+    }
+
+    #[test]
+    fn test_check_amounts() {
+        let payload = BridgePayload {
+            tx_hash: [0xab; 32],
+            amount: 1_000,
+            secret_hash: [0xcd; 32],
+            aztec_address: [0xef; 32],
+            block_hash: [0x12; 32],
+            fee: 10,
+            memo_amount: Some(1_000),
+            ..BridgePayload::sample()
+        };
+        assert!(check_amounts(&payload).is_ok());
+
+        // Memo claims a different value than the note carries
+        let mismatch = BridgePayload {
+            memo_amount: Some(2_000),
+            ..payload.clone()
+        };
+        assert!(check_amounts(&mismatch).is_err());
+
+        // Fee eats the whole deposit
+        let all_fee = BridgePayload { fee: 1_000, ..payload };
+        assert!(check_amounts(&all_fee).is_err());
     }
 }
@@ -0,0 +1,372 @@
+//! Persistent record of signed attestations
+//!
+//! Every attestation the operator signs is first appended to
+//! `signed_attestations.jsonl` and synced to disk, and the signer refuses a
+//! payload hash or nonce already in the log. The contract rejects a used
+//! nonce too, but only once a signature reaches it; this makes sure a
+//! restart, a replayed queue or a bug in nonce assignment can never make the
+//! operator put its name to two attestations for one nonce.
+//!
+//! Each entry also carries the deposit hash (the attested fields without the
+//! nonce) and the depositor hash (the fields the depositor controls), so a
+//! second attestation for the same vault note (Zcash transaction and output
+//! index) with a different amount or recipient is refused as equivocation.
+//! A fee or block hash derived differently is not: the note's nonce is fixed,
+//! so only one of the two attestations can ever be dispatched. Entries are
+//! only compared within one payload version: an attestation signed in an
+//! older format no longer verifies on L1, so re-attesting its deposit in the
+//! current one is not equivocation.
+//!
+//! The entry is written before the signature is produced, so a crash in
+//! between costs a nonce, never a duplicate.
+
+use crate::error::SentinelError;
+use crate::signer::{deposit_hash, depositor_hash, PAYLOAD_VERSION};
+use crate::BridgePayload;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// One signed attestation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEntry {
+    /// Signed message hash (payload and nonce), hex
+    pub payload_hash: String,
+
+    /// Attestation nonce
+    pub nonce: u64,
+
+    /// Zcash transaction hash of the deposit, hex
+    pub tx_hash: String,
+
+    /// Index of the vault note in the transaction; 0 for entries written
+    /// before deposits were per note
+    #[serde(default)]
+    pub output_index: u32,
+
+    /// Deposit hash (attested fields without the nonce), hex
+    #[serde(default)]
+    pub deposit_hash: Option<String>,
+
+    /// Depositor hash (note, amount and recipient), hex; absent from entries
+    /// written before it was logged
+    #[serde(default)]
+    pub depositor_hash: Option<String>,
+
+    /// Payload format version signed; entries without one are version 1
+    #[serde(default)]
+    pub version: Option<u8>,
+
+    /// Unix timestamp of signing
+    pub at: u64,
+}
+
+/// Append-only log of signed payload hashes and nonces
+pub struct SigningLog {
+    /// Log file
+    path: PathBuf,
+
+    /// Payload hashes already signed
+    hashes: HashSet<[u8; 32]>,
+
+    /// Vault note each nonce was used for, by tx hash and output index
+    nonces: HashMap<u64, ([u8; 32], u32)>,
+
+    /// What was signed for each vault note, by tx hash and output index
+    deposits: HashMap<([u8; 32], u32), SignedNote>,
+}
+
+/// What the operator signed for one vault note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedNote {
+    /// Deposit hash signed
+    pub deposit: [u8; 32],
+
+    /// Depositor hash signed, if logged
+    pub depositor: Option<[u8; 32]>,
+}
+
+impl SignedNote {
+    /// What attesting `payload` signs
+    pub fn of(payload: &BridgePayload) -> Self {
+        Self {
+            deposit: deposit_hash(payload),
+            depositor: Some(depositor_hash(payload)),
+        }
+    }
+
+    /// Whether attesting `payload` would contradict this signature
+    ///
+    /// Only the fields the depositor controls count; entries without a
+    /// depositor hash fall back to the full deposit hash.
+    pub fn conflicts(&self, payload: &BridgePayload) -> bool {
+        match self.depositor {
+            Some(depositor) => depositor != depositor_hash(payload),
+            None => self.deposit != deposit_hash(payload),
+        }
+    }
+}
+
+impl SigningLog {
+    /// Open the log at `path`, creating it if missing
+    ///
+    /// A torn last line, left by a crash mid-append, is dropped; damage
+    /// anywhere else is an error rather than a silently forgotten signature.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SentinelError> {
+        let path = path.as_ref().to_path_buf();
+        let mut log = Self {
+            path,
+            hashes: HashSet::new(),
+            nonces: HashMap::new(),
+            deposits: HashMap::new(),
+        };
+
+        let contents = match std::fs::read_to_string(&log.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(log),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+
+        let mut offset = 0;
+        for (i, line) in contents.split_inclusive('\n').enumerate() {
+            let entry = match serde_json::from_str::<SignedEntry>(line) {
+                Ok(entry) if line.ends_with('\n') => entry,
+                Err(e) if line.ends_with('\n') => {
+                    return Err(SentinelError::Storage(format!(
+                        "{} line {}: {}",
+                        log.path.display(),
+                        i + 1,
+                        e
+                    )))
+                }
+                _ => {
+                    // Only the last line can lack its newline. Cut it off so
+                    // the next append starts a fresh line.
+                    warn!("Dropping torn last entry of {}", log.path.display());
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&log.path)
+                        .and_then(|file| file.set_len(offset as u64))
+                        .map_err(|e| SentinelError::Storage(e.to_string()))?;
+                    break;
+                }
+            };
+            let bad = |field: &str| {
+                SentinelError::Storage(format!(
+                    "{} line {}: bad {}",
+                    log.path.display(),
+                    i + 1,
+                    field
+                ))
+            };
+            let hash = parse_hash(&entry.payload_hash).ok_or_else(|| bad("payload hash"))?;
+            let tx_hash = parse_hash(&entry.tx_hash).ok_or_else(|| bad("tx hash"))?;
+            let deposit = match &entry.deposit_hash {
+                Some(deposit) if entry.version == Some(PAYLOAD_VERSION) => Some(SignedNote {
+                    deposit: parse_hash(deposit).ok_or_else(|| bad("deposit hash"))?,
+                    depositor: match &entry.depositor_hash {
+                        Some(depositor) => {
+                            Some(parse_hash(depositor).ok_or_else(|| bad("depositor hash"))?)
+                        }
+                        None => None,
+                    },
+                }),
+                _ => None,
+            };
+            log.remember(hash, entry.nonce, (tx_hash, entry.output_index), deposit);
+            offset += line.len();
+        }
+        Ok(log)
+    }
+
+    /// Number of attestations signed
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether this exact payload hash was signed already
+    pub fn is_signed(&self, payload_hash: &[u8; 32]) -> bool {
+        self.hashes.contains(payload_hash)
+    }
+
+    /// What was signed for the vault note at `output_index` in a Zcash
+    /// transaction, if anything
+    pub fn signed_deposit(&self, tx_hash: &[u8; 32], output_index: u32) -> Option<SignedNote> {
+        self.deposits.get(&(*tx_hash, output_index)).copied()
+    }
+
+    /// Durably record a signature of `payload` about to be made, refusing a
+    /// repeat or a conflict
+    ///
+    /// A nonce is only refused when another note used it; the nonce of a
+    /// note is fixed, so signing its deposit again can never be dispatched
+    /// twice.
+    pub fn reserve(
+        &mut self,
+        payload_hash: [u8; 32],
+        nonce: u64,
+        payload: &BridgePayload,
+    ) -> Result<(), SentinelError> {
+        let note = (payload.tx_hash, payload.output_index);
+        if self.hashes.contains(&payload_hash) {
+            return Err(SentinelError::Signing(format!(
+                "payload hash {} was already signed",
+                hex::encode(payload_hash)
+            )));
+        }
+        if self.nonces.get(&nonce).is_some_and(|used| *used != note) {
+            return Err(SentinelError::Signing(format!(
+                "nonce {} was already used",
+                nonce
+            )));
+        }
+        if self
+            .signed_deposit(&payload.tx_hash, payload.output_index)
+            .is_some_and(|signed| signed.conflicts(payload))
+        {
+            return Err(SentinelError::Equivocation(format!(
+                "note {} of transaction {} was attested with different fields",
+                payload.output_index,
+                hex::encode(payload.tx_hash)
+            )));
+        }
+
+        let signed = SignedNote::of(payload);
+        let entry = SignedEntry {
+            payload_hash: hex::encode(payload_hash),
+            nonce,
+            tx_hash: hex::encode(payload.tx_hash),
+            output_index: payload.output_index,
+            deposit_hash: Some(hex::encode(signed.deposit)),
+            depositor_hash: signed.depositor.map(hex::encode),
+            version: Some(PAYLOAD_VERSION),
+            at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+        let mut line =
+            serde_json::to_vec(&entry).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+        file.write_all(&line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+
+        self.remember(payload_hash, nonce, note, Some(signed));
+        Ok(())
+    }
+
+    fn remember(
+        &mut self,
+        payload_hash: [u8; 32],
+        nonce: u64,
+        note: ([u8; 32], u32),
+        signed: Option<SignedNote>,
+    ) {
+        self.hashes.insert(payload_hash);
+        self.nonces.insert(nonce, note);
+        if let Some(signed) = signed {
+            self.deposits.insert(note, signed);
+        }
+    }
+}
+
+/// 32-byte hash from hex
+fn parse_hash(s: &str) -> Option<[u8; 32]> {
+    hex::decode(s).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_refuses_repeats_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signed_attestations.jsonl");
+        let deposit = BridgePayload {
+            tx_hash: [9; 32],
+            ..BridgePayload::sample()
+        };
+        let other = BridgePayload {
+            tx_hash: [8; 32],
+            ..BridgePayload::sample()
+        };
+
+        let mut log = SigningLog::open(&path).unwrap();
+        assert!(!log.is_signed(&[1; 32]));
+        log.reserve([1; 32], 0, &deposit).unwrap();
+        log.reserve([2; 32], 4, &deposit).unwrap();
+        drop(log);
+
+        // A crash mid-append leaves a torn line behind
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"payload_hash":"03"#).unwrap();
+
+        let mut log = SigningLog::open(&path).unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log.is_signed(&[1; 32]));
+        assert!(log.reserve([1; 32], 7, &deposit).is_err());
+        // Another note may not reuse a nonce
+        assert!(log.reserve([3; 32], 4, &other).is_err());
+        let inflated = BridgePayload {
+            amount: deposit.amount + 1,
+            ..deposit.clone()
+        };
+        assert!(matches!(
+            log.reserve([3; 32], 5, &inflated),
+            Err(SentinelError::Equivocation(_))
+        ));
+        // A fee or block hash derived differently is not equivocation
+        let rederived = BridgePayload {
+            fee: 10,
+            block_hash: [6; 32],
+            ..deposit.clone()
+        };
+        log.reserve([3; 32], 5, &rederived).unwrap();
+        assert_eq!(log.signed_deposit(&[9; 32], 0), Some(SignedNote::of(&rederived)));
+
+        // Another note in the same transaction is a separate deposit
+        let second = BridgePayload {
+            output_index: 1,
+            amount: 1,
+            ..deposit.clone()
+        };
+        log.reserve([6; 32], 8, &second).unwrap();
+        assert_eq!(log.signed_deposit(&[9; 32], 1), Some(SignedNote::of(&second)));
+        drop(log);
+
+        // Deposits last signed in the version 1 format may be attested anew
+        let v1 = json!({
+            "payload_hash": hex::encode([4; 32]),
+            "nonce": 6,
+            "tx_hash": hex::encode([8; 32]),
+            "deposit_hash": hex::encode([7; 32]),
+            "at": 0,
+        });
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{}", v1).unwrap();
+        let log = SigningLog::open(&path).unwrap();
+        assert!(log.is_signed(&[4; 32]));
+        assert_eq!(log.signed_deposit(&[8; 32], 0), None);
+        assert_eq!(log.signed_deposit(&[9; 32], 0), Some(SignedNote::of(&rederived)));
+
+        // Entries without a depositor hash compare the full deposit hash
+        let legacy = SignedNote {
+            depositor: None,
+            ..SignedNote::of(&deposit)
+        };
+        assert!(!legacy.conflicts(&deposit));
+        assert!(legacy.conflicts(&rederived));
+    }
+}
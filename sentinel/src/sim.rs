@@ -0,0 +1,184 @@
+//! Deterministic simulation runtime
+//!
+//! Runs async code on a current-thread tokio runtime with paused time. The
+//! runtime jumps to the next timer whenever every task is idle and
+//! [`crate::clock`] follows it, so block arrival, confirmations, expiry and
+//! retry schedules spanning hours complete in milliseconds, identically on
+//! every run.
+
+use crate::clock;
+use crate::scanner::ScanProgress;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Unix time every simulation starts at
+pub const SIM_START: u64 = 1_700_000_000;
+
+/// Zcash target block spacing
+pub const BLOCK_SPACING: Duration = Duration::from_secs(75);
+
+/// Run `future` to completion on virtual time starting at [`SIM_START`]
+pub fn run<F: Future>(future: F) -> F::Output {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .expect("simulation runtime");
+
+    let output = runtime.block_on(async {
+        clock::set_virtual(SIM_START);
+        future.await
+    });
+    clock::clear_virtual();
+    output
+}
+
+/// Simulated chain tip feeding scanner progress
+pub struct SimChain {
+    /// Progress as the scanner would report it
+    progress: Arc<ScanProgress>,
+
+    /// Current tip height
+    tip: u32,
+
+    /// Confirmations before a block counts as scanned
+    confirmation_depth: u32,
+}
+
+impl SimChain {
+    /// Start a chain at `tip`
+    pub fn new(tip: u32, confirmation_depth: u32) -> Self {
+        let chain = Self {
+            progress: Arc::new(ScanProgress::default()),
+            tip,
+            confirmation_depth,
+        };
+        chain.report();
+        chain
+    }
+
+    /// Scanner progress driven by this chain
+    pub fn progress(&self) -> Arc<ScanProgress> {
+        self.progress.clone()
+    }
+
+    /// Current tip height
+    pub fn tip(&self) -> u32 {
+        self.tip
+    }
+
+    /// Confirmations of a block at `height` (0 if not mined yet)
+    pub fn confirmations(&self, height: u32) -> u32 {
+        if height > self.tip {
+            return 0;
+        }
+        self.tip - height + 1
+    }
+
+    /// Mine `count` blocks at the target spacing
+    pub async fn mine(&mut self, count: u32) {
+        for _ in 0..count {
+            tokio::time::sleep(BLOCK_SPACING).await;
+            self.tip += 1;
+            self.report();
+        }
+    }
+
+    /// Report a scan pass up to the confirmed height
+    fn report(&self) {
+        self.progress
+            .record_success(self.tip.saturating_sub(self.confirmation_depth));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stale::StaleDepositMonitor;
+    use crate::store::{deposit_key, DepositStatus, DepositStore};
+    use crate::BridgePayload;
+
+    fn payload(block_height: u32) -> BridgePayload {
+        BridgePayload {
+            tx_hash: [0x11; 32],
+            amount: 5_000,
+            secret_hash: [0x22; 32],
+            aztec_address: [0x33; 32],
+            block_height,
+            ..BridgePayload::sample()
+        }
+    }
+
+    #[test]
+    fn test_stale_after_wall_clock_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let key = deposit_key(&hex::encode([0x11; 32]), 0);
+
+        let stale_at = run(async {
+            store.insert_detected(&payload(100)).unwrap();
+            let monitor = StaleDepositMonitor::new(
+                store.clone(),
+                Arc::new(ScanProgress::default()),
+                Duration::from_secs(3600),
+                None,
+            );
+            tokio::spawn(monitor.run(Duration::from_secs(60)));
+
+            tokio::time::sleep(Duration::from_secs(59 * 60)).await;
+            assert_eq!(store.get(&key).unwrap().status, DepositStatus::Detected);
+
+            tokio::time::sleep(Duration::from_secs(150)).await;
+            let record = store.get(&key).unwrap();
+            assert_eq!(record.status, DepositStatus::Stale);
+            record.history.last().unwrap().at
+        });
+
+        // First check past the one-hour budget, on every run
+        assert_eq!(stale_at, SIM_START + 3660);
+    }
+
+    #[test]
+    fn test_stale_after_block_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let key = deposit_key(&hex::encode([0x11; 32]), 0);
+
+        run(async {
+            let mut chain = SimChain::new(1_000, 10);
+            store.insert_detected(&payload(chain.tip())).unwrap();
+            let monitor = StaleDepositMonitor::new(
+                store.clone(),
+                chain.progress(),
+                Duration::from_secs(24 * 3600),
+                Some(20),
+            );
+            tokio::spawn(monitor.run(Duration::from_secs(30)));
+
+            // 30 blocks: only 20 of them confirmed past the deposit
+            chain.mine(30).await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            assert_eq!(store.get(&key).unwrap().status, DepositStatus::Detected);
+
+            chain.mine(2).await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            assert_eq!(store.get(&key).unwrap().status, DepositStatus::Stale);
+            assert_eq!(chain.confirmations(1_000), 33);
+        });
+    }
+
+    #[test]
+    fn test_scanner_health_expires_without_blocks() {
+        run(async {
+            let mut chain = SimChain::new(1_000, 10);
+            chain.mine(4).await;
+            let progress = chain.progress();
+            assert!(progress.is_healthy(Duration::from_secs(90)));
+            assert_eq!(progress.synced_height(), 994);
+
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            assert!(!progress.is_healthy(Duration::from_secs(90)));
+        });
+    }
+}
@@ -0,0 +1,204 @@
+//! N-of-M source confirmation
+//!
+//! High-assurance mode for networks where a single lying lightwalletd must
+//! not be enough to mint. Each deposit is re-checked against every source in
+//! `CONFIRMATION_SOURCES`, and is only attestable once `required` of them
+//! independently report its transaction in the claimed block, buried at the
+//! confirmation depth:
+//!
+//! - `lightwalletd=<url>`: the transaction is fetched and must hash to the
+//!   txid at the claimed height
+//! - `zebrad=<url>`: the full-node check (Merkle branch against the header)
+//!
+//! A source that contradicts the deposit is logged; it simply does not count.
+
+use crate::error::SentinelError;
+use crate::fullnode::FullNodeVerifier;
+use crate::lightwalletd::ClientTls;
+use crate::zcash_rpc::ZcashRpcClient;
+use anyhow::{bail, Result};
+use std::time::Duration;
+use tracing::{debug, warn};
+use zcash_client_backend::proto::service::{ChainSpec, TxFilter};
+use zcash_primitives::consensus::{BlockHeight, BranchId, MAIN_NETWORK};
+use zcash_primitives::transaction::Transaction;
+
+/// Default number of sources that must confirm a deposit on `network`
+///
+/// Mainnet needs a strict majority of the configured sources; test networks
+/// settle for one.
+pub fn default_required(network: &str, sources: usize) -> usize {
+    match (network, sources) {
+        (_, 0) => 0,
+        ("mainnet", n) => n / 2 + 1,
+        _ => 1,
+    }
+}
+
+/// An independent chain data source
+enum Source {
+    /// A lightwalletd server
+    Lightwalletd(String),
+    /// A zebrad (or zcashd) JSON-RPC node
+    Zebrad(FullNodeVerifier),
+}
+
+/// Requires deposits to be confirmed by several independent sources
+pub struct SourceConfirmer {
+    /// Sources with their display names
+    sources: Vec<(String, Source)>,
+
+    /// Sources that must confirm
+    required: usize,
+
+    /// Blocks required on top of the deposit block
+    confirmation_depth: u32,
+}
+
+impl SourceConfirmer {
+    /// Build from `kind=url` source entries
+    pub fn new(entries: &[String], required: usize, confirmation_depth: u32) -> Result<Self> {
+        let mut sources = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let Some((kind, url)) = entry.split_once('=') else {
+                bail!(
+                    "Confirmation source {} must be lightwalletd=<url> or zebrad=<url>",
+                    entry
+                );
+            };
+            let source = match kind {
+                "lightwalletd" => Source::Lightwalletd(url.to_string()),
+                "zebrad" => {
+                    let rpc = ZcashRpcClient::new(url.to_string(), Duration::from_secs(30))?;
+                    Source::Zebrad(FullNodeVerifier::new(rpc, confirmation_depth))
+                }
+                _ => bail!("Unknown confirmation source kind {}", kind),
+            };
+            sources.push((entry.clone(), source));
+        }
+        if required > sources.len() {
+            bail!(
+                "{} confirmations required but only {} sources configured",
+                required,
+                sources.len()
+            );
+        }
+        Ok(Self {
+            sources,
+            required,
+            confirmation_depth,
+        })
+    }
+
+    /// Check the deposit against every source concurrently
+    ///
+    /// Fails, leaving the deposit pending, until `required` sources agree.
+    pub async fn confirm(
+        &self,
+        tx_hash: &[u8; 32],
+        block_height: u32,
+    ) -> Result<(), SentinelError> {
+        let checks = self.sources.iter().map(|(name, source)| async move {
+            let result = match source {
+                Source::Lightwalletd(url) => {
+                    self.check_lightwalletd(url, tx_hash, block_height).await
+                }
+                Source::Zebrad(node) => node.verify(tx_hash, block_height).await,
+            };
+            match &result {
+                Ok(()) => debug!("Source {} confirms deposit", name),
+                Err(SentinelError::InvalidPayload(e)) => {
+                    warn!(
+                        "Source {} contradicts deposit {}: {}",
+                        name,
+                        hex::encode(tx_hash),
+                        e
+                    )
+                }
+                Err(e) => debug!("Source {} cannot confirm deposit yet: {}", name, e),
+            }
+            result.is_ok()
+        });
+        let confirmed = futures::future::join_all(checks)
+            .await
+            .into_iter()
+            .filter(|&ok| ok)
+            .count();
+
+        if confirmed < self.required {
+            return Err(SentinelError::Scanner(format!(
+                "confirmed by {} of {} sources, {} required",
+                confirmed,
+                self.sources.len(),
+                self.required
+            )));
+        }
+        Ok(())
+    }
+
+    /// Locate the transaction through one lightwalletd
+    async fn check_lightwalletd(
+        &self,
+        url: &str,
+        tx_hash: &[u8; 32],
+        block_height: u32,
+    ) -> Result<(), SentinelError> {
+        let mut client = crate::lightwalletd::connect(url, &ClientTls::default()).await?;
+
+        let tip = client
+            .get_latest_block(ChainSpec {})
+            .await?
+            .into_inner()
+            .height;
+        let raw = client
+            .get_transaction(TxFilter {
+                hash: tx_hash.to_vec(),
+                ..TxFilter::default()
+            })
+            .await?
+            .into_inner();
+
+        if raw.height != u64::from(block_height) {
+            return Err(SentinelError::InvalidPayload(format!(
+                "transaction is at height {}, not {}",
+                raw.height, block_height
+            )));
+        }
+        let height = BlockHeight::from_u32(block_height);
+        let tx = Transaction::read(&raw.data[..], BranchId::for_height(&MAIN_NETWORK, height))
+            .map_err(|e| SentinelError::InvalidPayload(e.to_string()))?;
+        if tx.txid().as_ref() != tx_hash {
+            return Err(SentinelError::InvalidPayload(
+                "returned transaction does not match the txid".to_string(),
+            ));
+        }
+        if tip < raw.height + u64::from(self.confirmation_depth) {
+            return Err(SentinelError::Scanner(format!(
+                "block {} not yet {} deep (tip {})",
+                block_height, self.confirmation_depth, tip
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_parsed_and_counted() {
+        assert_eq!(default_required("mainnet", 3), 2);
+        assert_eq!(default_required("mainnet", 4), 3);
+        assert_eq!(default_required("testnet", 3), 1);
+        assert_eq!(default_required("mainnet", 0), 0);
+
+        let entries = [
+            "lightwalletd=https://a.example:443".to_string(),
+            "zebrad=http://127.0.0.1:8232".to_string(),
+        ];
+        assert!(SourceConfirmer::new(&entries, 2, 24).is_ok());
+        assert!(SourceConfirmer::new(&entries, 3, 24).is_err());
+        assert!(SourceConfirmer::new(&["https://a.example".to_string()], 1, 24).is_err());
+    }
+}
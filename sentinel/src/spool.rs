@@ -0,0 +1,304 @@
+//! Block spool with a memory budget
+//!
+//! Replaces the plain channel between the fetch and decrypt stages. Blocks
+//! are held in memory until the in-flight bytes reach the memory budget;
+//! after that they are written to the spill directory and read back in
+//! order when the decrypt stage gets to them. The fetch stage only waits
+//! once the spill budget is exhausted too, so a small host can keep the
+//! network busy through a long backfill without holding it all in RAM.
+
+use crate::error::SentinelError;
+use prost::Message;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+
+/// Default bytes of compact blocks held in memory between fetch and decrypt
+pub const DEFAULT_MEMORY_BUDGET_MB: usize = 256;
+
+/// Default bytes of compact blocks spilled to disk before fetching waits
+pub const DEFAULT_SPILL_BUDGET_MB: u64 = 8192;
+
+/// Limits for one spool
+#[derive(Debug, Clone)]
+pub struct SpoolLimits {
+    /// Blocks held in memory
+    pub max_blocks: usize,
+
+    /// Bytes held in memory
+    pub memory_bytes: usize,
+
+    /// Bytes spilled to disk
+    pub spill_bytes: u64,
+
+    /// Directory spilled blocks are written to
+    pub dir: PathBuf,
+}
+
+/// A queued block, resident or spilled
+enum Slot {
+    /// Held in memory, with its encoded size
+    Memory(CompactBlock, usize),
+
+    /// Written to the spill directory
+    Disk { height: u64, len: u64 },
+}
+
+/// Queue and accounting shared by both ends
+struct State {
+    /// Blocks in arrival order
+    queue: VecDeque<Slot>,
+
+    /// Blocks held in memory
+    memory_blocks: usize,
+
+    /// Encoded bytes held in memory
+    memory_bytes: usize,
+
+    /// Encoded bytes on disk
+    spill_bytes: u64,
+
+    /// One end has been dropped
+    closed: bool,
+}
+
+/// State plus wakeups for both ends
+struct Shared {
+    /// Queue state
+    state: Mutex<State>,
+
+    /// Signalled when a block is queued or the sender goes away
+    pushed: Notify,
+
+    /// Signalled when a block is taken or the receiver goes away
+    popped: Notify,
+
+    /// Budgets
+    limits: SpoolLimits,
+}
+
+impl Shared {
+    /// Path of a spilled block
+    fn path(&self, height: u64) -> PathBuf {
+        self.limits.dir.join(format!("{}.bin", height))
+    }
+
+    /// Mark the spool closed and wake the other end
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.pushed.notify_one();
+        self.popped.notify_one();
+    }
+}
+
+/// Fetch-stage end of a spool
+pub struct SpoolSender {
+    shared: Arc<Shared>,
+}
+
+/// Decrypt-stage end of a spool
+pub struct SpoolReceiver {
+    shared: Arc<Shared>,
+}
+
+/// Create a spool, clearing blocks left in the spill directory by an earlier run
+pub fn spool(limits: SpoolLimits) -> Result<(SpoolSender, SpoolReceiver), SentinelError> {
+    let storage = |e: std::io::Error| SentinelError::Storage(format!("spill directory: {}", e));
+    if limits.dir.exists() {
+        std::fs::remove_dir_all(&limits.dir).map_err(storage)?;
+    }
+    std::fs::create_dir_all(&limits.dir).map_err(storage)?;
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            memory_blocks: 0,
+            memory_bytes: 0,
+            spill_bytes: 0,
+            closed: false,
+        }),
+        pushed: Notify::new(),
+        popped: Notify::new(),
+        limits,
+    });
+    Ok((
+        SpoolSender {
+            shared: shared.clone(),
+        },
+        SpoolReceiver { shared },
+    ))
+}
+
+impl SpoolSender {
+    /// Queue a block, spilling it if memory is full and waiting if disk is too
+    pub async fn send(&self, block: CompactBlock) -> Result<(), SentinelError> {
+        let len = block.encoded_len();
+        let shared = &self.shared;
+        loop {
+            let popped = shared.popped.notified();
+            {
+                let mut state = shared.state.lock().unwrap();
+                if state.closed {
+                    return Err(SentinelError::Scanner("decrypt stage stopped".to_string()));
+                }
+
+                // An empty queue always takes the block, however large
+                let fits_memory = state.memory_blocks < shared.limits.max_blocks
+                    && state.memory_bytes + len <= shared.limits.memory_bytes;
+                if fits_memory || state.queue.is_empty() {
+                    state.memory_blocks += 1;
+                    state.memory_bytes += len;
+                    state.queue.push_back(Slot::Memory(block, len));
+                    shared.pushed.notify_one();
+                    return Ok(());
+                }
+                if state.spill_bytes + len as u64 <= shared.limits.spill_bytes {
+                    std::fs::write(shared.path(block.height), block.encode_to_vec())
+                        .map_err(|e| SentinelError::Storage(format!("spill block {}: {}", block.height, e)))?;
+                    state.spill_bytes += len as u64;
+                    state.queue.push_back(Slot::Disk {
+                        height: block.height,
+                        len: len as u64,
+                    });
+                    shared.pushed.notify_one();
+                    return Ok(());
+                }
+            }
+            popped.await;
+        }
+    }
+
+    /// Whether the decrypt stage has gone away
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().closed
+    }
+
+    /// Bytes currently held in memory and on disk
+    pub fn usage(&self) -> (usize, u64) {
+        let state = self.shared.state.lock().unwrap();
+        (state.memory_bytes, state.spill_bytes)
+    }
+}
+
+impl SpoolReceiver {
+    /// Take the next block in order; `None` once the sender is gone and the spool is drained
+    pub async fn recv(&mut self) -> Result<Option<CompactBlock>, SentinelError> {
+        let shared = &self.shared;
+        loop {
+            let pushed = shared.pushed.notified();
+            let slot = {
+                let mut state = shared.state.lock().unwrap();
+                match state.queue.pop_front() {
+                    Some(slot) => {
+                        match &slot {
+                            Slot::Memory(_, len) => {
+                                state.memory_blocks -= 1;
+                                state.memory_bytes -= len;
+                            }
+                            Slot::Disk { len, .. } => state.spill_bytes -= len,
+                        }
+                        Some(slot)
+                    }
+                    None if state.closed => return Ok(None),
+                    None => None,
+                }
+            };
+
+            match slot {
+                Some(Slot::Memory(block, _)) => {
+                    shared.popped.notify_one();
+                    return Ok(Some(block));
+                }
+                Some(Slot::Disk { height, .. }) => {
+                    shared.popped.notify_one();
+                    let path = shared.path(height);
+                    let bytes = std::fs::read(&path)
+                        .map_err(|e| SentinelError::Storage(format!("read spilled block {}: {}", height, e)))?;
+                    let _ = std::fs::remove_file(&path);
+                    let block = CompactBlock::decode(bytes.as_slice())
+                        .map_err(|e| SentinelError::Storage(format!("spilled block {}: {}", height, e)))?;
+                    return Ok(Some(block));
+                }
+                None => pushed.await,
+            }
+        }
+    }
+}
+
+impl Drop for SpoolSender {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+impl Drop for SpoolReceiver {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn block(height: u64, size: usize) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: vec![0xab; size],
+            ..CompactBlock::default()
+        }
+    }
+
+    fn limits(dir: &tempfile::TempDir, memory_bytes: usize, spill_bytes: u64) -> SpoolLimits {
+        SpoolLimits {
+            max_blocks: 100,
+            memory_bytes,
+            spill_bytes,
+            dir: dir.path().join("spill"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spills_over_budget_and_keeps_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sender, mut receiver) = spool(limits(&dir, 2_500, 1 << 20)).unwrap();
+
+        for height in 1..=5 {
+            sender.send(block(height, 1_000)).await.unwrap();
+        }
+        let (memory, spilled) = sender.usage();
+        assert!(memory <= 2_500);
+        assert!(spilled > 0);
+        assert!(dir.path().join("spill").join("5.bin").exists());
+
+        drop(sender);
+        let mut heights = Vec::new();
+        while let Some(block) = receiver.recv().await.unwrap() {
+            assert_eq!(block.hash.len(), 1_000);
+            heights.push(block.height);
+        }
+        assert_eq!(heights, vec![1, 2, 3, 4, 5]);
+        assert!(!dir.path().join("spill").join("5.bin").exists());
+    }
+
+    #[tokio::test]
+    async fn test_waits_when_both_budgets_are_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sender, mut receiver) = spool(limits(&dir, 1_500, 1_500)).unwrap();
+
+        sender.send(block(1, 1_000)).await.unwrap();
+        sender.send(block(2, 1_000)).await.unwrap();
+        let blocked = tokio::time::timeout(Duration::from_millis(20), sender.send(block(3, 1_000))).await;
+        assert!(blocked.is_err());
+
+        assert_eq!(receiver.recv().await.unwrap().unwrap().height, 1);
+        sender.send(block(3, 1_000)).await.unwrap();
+
+        drop(receiver);
+        assert!(sender.is_closed());
+        assert!(sender.send(block(4, 1_000)).await.is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Deposit timeout and stale-deposit handling
+//!
+//! A detected deposit that cannot be attested within its budget (L1 down,
+//! quorum unreachable, attestation halted) is moved to `Stale` instead of
+//! being attested arbitrarily late. Stale deposits stay put until an operator
+//! resumes them through the admin API.
+
+use crate::clock::now_secs;
+use crate::scanner::ScanProgress;
+use crate::store::{DepositRecord, DepositStatus, DepositStore};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// Default wall-clock budget for attesting a deposit (1 hour)
+pub const DEFAULT_DEPOSIT_TIMEOUT_SECS: u64 = 60 * 60;
+
+/// Moves deposits that blew their attestation budget to `Stale`
+pub struct StaleDepositMonitor {
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Scanner progress, for the block-height budget
+    progress: Arc<ScanProgress>,
+
+    /// Wall-clock budget since detection
+    timeout: Duration,
+
+    /// Block budget past the deposit height, if configured
+    timeout_blocks: Option<u32>,
+}
+
+impl StaleDepositMonitor {
+    /// Create a new monitor
+    pub fn new(
+        store: Arc<DepositStore>,
+        progress: Arc<ScanProgress>,
+        timeout: Duration,
+        timeout_blocks: Option<u32>,
+    ) -> Self {
+        Self {
+            store,
+            progress,
+            timeout,
+            timeout_blocks,
+        }
+    }
+
+    /// Check for stale deposits on a schedule forever
+    pub async fn run(self, interval: Duration) {
+        loop {
+            self.check();
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Mark every over-budget deposit as stale
+    pub fn check(&self) -> usize {
+        let now = now_secs();
+        let height = self.progress.synced_height();
+        let mut marked = 0;
+
+        for record in self.store.with_status(DepositStatus::Detected) {
+            let Some(reason) = self.over_budget(&record, now, height) else {
+                continue;
+            };
+
+            warn!("ALERT: deposit {} is stale: {}", record.tx_hash, reason);
+            match self
+                .store
+                .transition(&record.key(), DepositStatus::Stale, Some(reason))
+            {
+                Ok(_) => marked += 1,
+                Err(e) => error!("Failed to mark deposit {} stale: {}", record.tx_hash, e),
+            }
+        }
+
+        marked
+    }
+
+    /// Why a deposit exceeded its budget, if it did
+    fn over_budget(&self, record: &DepositRecord, now: u64, height: u32) -> Option<String> {
+        let age = now.saturating_sub(record.detected_at);
+        if age > self.timeout.as_secs() {
+            return Some(format!("not attested within {}s", self.timeout.as_secs()));
+        }
+
+        if let Some(blocks) = self.timeout_blocks {
+            if height > record.block_height.saturating_add(blocks) {
+                return Some(format!("not attested within {} blocks", blocks));
+            }
+        }
+
+        None
+    }
+}
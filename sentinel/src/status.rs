@@ -0,0 +1,312 @@
+//! HTTP status API
+//!
+//! Exposes read-only JSON views of the sentinel's state so operators and the
+//! wider operator community can monitor AVS health.
+
+use crate::batch::{Batch, BatchAttester, InclusionProof, Proposal};
+use crate::clock::now_secs;
+use crate::consistency::{ObservedDeposit, PeerSignature, SignedDigests};
+use crate::evidence::{DepositEvidence, EvidenceCollector};
+use crate::halt::{HaltState, HaltSwitch};
+use crate::heartbeat::Heartbeat;
+use crate::maintenance::{MaintenanceSchedule, MaintenanceStatus, ScheduledWindow};
+use crate::reputation::{OperatorStats, ReputationTracker};
+use crate::shards::{self, ShardBalance, ShardSet};
+use crate::store::{DepositRecord, DepositStore};
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// Shared state behind the status API
+#[derive(Clone)]
+pub struct StatusState {
+    /// Sentinel version
+    pub version: &'static str,
+
+    /// Zcash network name
+    pub network: String,
+
+    /// Bridge deployment served, for tenants
+    pub tenant: Option<String>,
+
+    /// Peer operator statistics
+    pub reputation: Arc<ReputationTracker>,
+
+    /// Attestation signatures this sentinel made, for the dispatching peer
+    pub signed: Arc<SignedDigests>,
+
+    /// Most recent signed heartbeat
+    pub heartbeat: Arc<RwLock<Option<Heartbeat>>>,
+
+    /// Attestation halt switch
+    pub halt: Arc<HaltSwitch>,
+
+    /// Vault shards and their balances
+    pub shards: Arc<ShardSet>,
+
+    /// Merkle batches, in batch attestation mode
+    pub batches: Option<Arc<BatchAttester>>,
+
+    /// Header-chain evidence, if collected
+    pub evidence: Option<Arc<EvidenceCollector>>,
+
+    /// Deposit store, for peer cross-checks and the GraphQL API
+    pub store: Arc<DepositStore>,
+
+    /// Scheduled maintenance windows
+    pub maintenance: Arc<MaintenanceSchedule>,
+}
+
+/// Top-level status response
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    version: &'static str,
+    network: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    operators: usize,
+    halted: Option<HaltState>,
+    maintenance: Option<ScheduledWindow>,
+}
+
+/// Build the status API router
+pub fn router(state: StatusState) -> Router {
+    #[cfg(feature = "graphql")]
+    let graphql = crate::graphql::router(state.store.clone(), state.reputation.clone());
+
+    let router = Router::new()
+        .route("/status", get(status))
+        .route("/operators", get(operators))
+        .route("/deposits/:key", get(deposit))
+        .route("/deposits/:key/proof", get(deposit_proof))
+        .route("/deposits/:key/evidence", get(deposit_evidence))
+        .route("/signatures/:digest", get(signature))
+        .route("/batches/proposal", get(batch_proposal))
+        .route("/batches/:root", get(batch))
+        .route("/heartbeat", get(heartbeat))
+        .route("/shards", get(shard_balances))
+        .route("/shards/select", get(select_shard))
+        .route("/maintenance", get(maintenance))
+        .route("/metrics", get(metrics))
+        .with_state(state);
+    #[cfg(feature = "graphql")]
+    let router = router.merge(graphql);
+    router
+}
+
+/// Serve the status API (and admin routes, if any) until the process exits
+pub async fn serve(addr: SocketAddr, state: StatusState, admin: Option<Router>) -> Result<()> {
+    let mut app = router(state);
+    if let Some(admin) = admin {
+        app = app.merge(admin);
+    }
+
+    info!("Status API listening on {}", addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// `GET /status`
+async fn status(State(state): State<StatusState>) -> Json<StatusResponse> {
+    Json(StatusResponse {
+        version: state.version,
+        network: state.network.clone(),
+        tenant: state.tenant.clone(),
+        operators: state.reputation.snapshot().len(),
+        halted: state.halt.state(),
+        maintenance: state.maintenance.active(now_secs()),
+    })
+}
+
+/// `GET /metrics` — deposit counts, fees and the halt state in the
+/// Prometheus text format, labelled with the deployment served
+async fn metrics(State(state): State<StatusState>) -> String {
+    let deployment = state.tenant.as_deref().unwrap_or("primary");
+    let mut deposits = BTreeMap::new();
+    for record in state.store.all() {
+        let status = serde_json::to_value(record.status).unwrap_or_default();
+        *deposits
+            .entry(status.as_str().unwrap_or_default().to_string())
+            .or_insert(0u64) += 1;
+    }
+
+    let mut out = String::from("# TYPE sentinel_deposits gauge\n");
+    for (status, count) in deposits {
+        out.push_str(&format!(
+            "sentinel_deposits{{deployment=\"{}\",status=\"{}\"}} {}\n",
+            deployment, status, count
+        ));
+    }
+    out.push_str(&format!(
+        "# TYPE sentinel_fees_collected_zatoshi counter\n\
+         sentinel_fees_collected_zatoshi{{deployment=\"{}\"}} {}\n",
+        deployment,
+        state.store.fees_collected()
+    ));
+    out.push_str(&format!(
+        "# TYPE sentinel_halted gauge\nsentinel_halted{{deployment=\"{}\"}} {}\n",
+        deployment,
+        u8::from(state.halt.is_halted())
+    ));
+    out
+}
+
+/// `GET /maintenance` — configured windows, the open one and the next
+async fn maintenance(State(state): State<StatusState>) -> Json<MaintenanceStatus> {
+    Json(state.maintenance.status(now_secs()))
+}
+
+/// `GET /operators` — per-operator performance statistics
+async fn operators(State(state): State<StatusState>) -> Json<BTreeMap<String, OperatorStats>> {
+    Json(
+        state
+            .reputation
+            .snapshot()
+            .into_iter()
+            .map(|(address, stats)| (format!("{:?}", address), stats))
+            .collect(),
+    )
+}
+
+/// `GET /deposits/:key` — this sentinel's view of a deposit, by
+/// `<txid>:<output_index>`, once its fee is fixed
+async fn deposit(
+    State(state): State<StatusState>,
+    Path(key): Path<String>,
+) -> Result<Json<ObservedDeposit>, StatusCode> {
+    let record = state
+        .store
+        .get(&key)
+        .filter(DepositRecord::fee_fixed)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let payload = record
+        .to_payload()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ObservedDeposit::from(&payload)))
+}
+
+/// `GET /signatures/:digest` — this sentinel's signature over an attestation
+/// digest, hex
+async fn signature(
+    State(state): State<StatusState>,
+    Path(digest): Path<String>,
+) -> Result<Json<PeerSignature>, StatusCode> {
+    let digest = hex::decode(digest.strip_prefix("0x").unwrap_or(&digest))
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    state
+        .signed
+        .get(&digest)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `GET /heartbeat` — the latest signed operator heartbeat
+async fn heartbeat(State(state): State<StatusState>) -> Result<Json<Heartbeat>, StatusCode> {
+    state
+        .heartbeat
+        .read()
+        .unwrap()
+        .clone()
+        .map(Json)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// `GET /shards` — balance of every vault shard
+async fn shard_balances(
+    State(state): State<StatusState>,
+) -> Result<Json<Vec<ShardBalance>>, StatusCode> {
+    state
+        .shards
+        .balances()
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Query for `GET /shards/select`
+#[derive(Debug, Deserialize)]
+struct SelectQuery {
+    /// Intended deposit amount in zatoshi
+    amount: u64,
+}
+
+/// `GET /shards/select?amount=N` — which vault shard a new deposit should pay
+async fn select_shard(
+    State(state): State<StatusState>,
+    Query(query): Query<SelectQuery>,
+) -> Result<Json<ShardBalance>, StatusCode> {
+    let balances = state
+        .shards
+        .balances()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    shards::select_for_deposit(&balances, query.amount)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::CONFLICT)
+}
+
+/// `GET /deposits/:key/proof` — Merkle inclusion proof of a batched deposit
+async fn deposit_proof(
+    State(state): State<StatusState>,
+    Path(key): Path<String>,
+) -> Result<Json<InclusionProof>, StatusCode> {
+    state
+        .batches
+        .as_ref()
+        .and_then(|batches| batches.proof(&key))
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `GET /batches/:root` — a submitted Merkle batch
+async fn batch(
+    State(state): State<StatusState>,
+    Path(root): Path<String>,
+) -> Result<Json<Batch>, StatusCode> {
+    state
+        .batches
+        .as_ref()
+        .and_then(|batches| batches.batch(&root))
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `GET /batches/proposal` — the batch this operator is collecting
+/// signatures for, which peers check and co-sign
+async fn batch_proposal(State(state): State<StatusState>) -> Result<Json<Proposal>, StatusCode> {
+    state
+        .batches
+        .as_ref()
+        .and_then(|batches| batches.proposal())
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `GET /deposits/:key/evidence` — header-chain evidence for a deposit's
+/// transaction
+async fn deposit_evidence(
+    State(state): State<StatusState>,
+    Path(key): Path<String>,
+) -> Result<Json<DepositEvidence>, StatusCode> {
+    // Evidence covers the whole transaction, whichever note is asked about
+    let tx_hash = key.split(':').next().unwrap_or_default();
+    state
+        .evidence
+        .as_ref()
+        .and_then(|evidence| evidence.get(tx_hash))
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
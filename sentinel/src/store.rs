@@ -0,0 +1,613 @@
+//! Persistent deposit store
+//!
+//! Tracks every detected deposit through its lifecycle in a small JSON file
+//! under the data directory. A deposit is one vault note, keyed by its
+//! transaction hash and output index (see [`deposit_key`]). Writes go to a
+//! temporary file that is renamed over the previous state, so a crash never
+//! leaves a half-written store.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::lease::LeaseFence;
+use crate::BridgePayload;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Lifecycle state of a deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositStatus {
+    /// Seen on the Zcash chain, not yet attested
+    Detected,
+    /// Attestation submitted to L1
+    Submitted,
+    /// Will not be attested (see `reason`)
+    Rejected,
+    /// Refund handed to the payout queue
+    RefundQueued,
+    /// Refund paid out from the vault
+    Refunded,
+    /// Claim secret revealed and the L1→L2 message consumed
+    Claimed,
+    /// Not claimed within the claim timeout
+    ClaimExpired,
+    /// Could not be attested within the timeout budget; needs operator action
+    Stale,
+    /// Its block was reorged out of the Zcash chain before attestation
+    Orphaned,
+}
+
+impl DepositStatus {
+    /// Whether moving from `self` to `next` is a valid transition
+    pub fn can_transition_to(self, next: DepositStatus) -> bool {
+        use DepositStatus::*;
+        matches!(
+            (self, next),
+            (Detected, Submitted)
+                | (Detected, Rejected)
+                | (Rejected, RefundQueued)
+                | (RefundQueued, Refunded)
+                | (Submitted, Claimed)
+                | (Submitted, ClaimExpired)
+                | (ClaimExpired, Claimed)
+                | (Detected, Stale)
+                | (Stale, Detected)
+                | (Stale, Rejected)
+                | (Detected, Orphaned)
+                | (Stale, Orphaned)
+                | (Orphaned, Detected)
+        )
+    }
+}
+
+/// Stored deposit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositRecord {
+    /// Zcash transaction hash (hex)
+    pub tx_hash: String,
+
+    /// Index of the vault note in the transaction; 0 for deposits recorded
+    /// before payload v3
+    #[serde(default)]
+    pub output_index: u32,
+
+    /// Amount in zatoshi
+    pub amount: u64,
+
+    /// Hash of the claim secret (hex)
+    pub secret_hash: String,
+
+    /// Recipient's Aztec address (hex)
+    pub aztec_address: String,
+
+    /// Block height of the deposit
+    pub block_height: u32,
+
+    /// Hash of the block at `block_height` (hex); empty for deposits
+    /// recorded before payload v2
+    #[serde(default)]
+    pub block_hash: String,
+
+    /// Zcash address to refund to, if the depositor supplied one
+    pub refund_address: Option<String>,
+
+    /// Bridge fee in zatoshi, deducted from `amount` at attestation
+    #[serde(default)]
+    pub fee: u64,
+
+    /// Unix timestamp the fee was fixed, at first admission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_fixed_at: Option<u64>,
+
+    /// Amount claimed by the deposit memo, if any
+    #[serde(default)]
+    pub memo_amount: Option<u64>,
+
+    /// Vault shard that received the deposit
+    #[serde(default = "crate::shards::primary_shard")]
+    pub vault_shard: String,
+
+    /// L1 target requested by the deposit memo, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// Current lifecycle state
+    pub status: DepositStatus,
+
+    /// Why the deposit was rejected
+    pub reason: Option<String>,
+
+    /// L1→L2 message hash returned by `verifyAndDispatch` (hex)
+    #[serde(default)]
+    pub message_hash: Option<String>,
+
+    /// L1 transaction that dispatched the attestation
+    #[serde(default)]
+    pub l1_tx_hash: Option<String>,
+
+    /// L1 transaction in which the claim consumed the L1→L2 message
+    #[serde(default)]
+    pub claim_tx_hash: Option<String>,
+
+    /// Unix timestamp the inbox message was checked against the deposit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portal_verified_at: Option<u64>,
+
+    /// Why the inbox message does not match the deposit, if it does not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portal_error: Option<String>,
+
+    /// Unix timestamp the Aztec node first reported the message synced to L2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l2_synced_at: Option<u64>,
+
+    /// Unix timestamp the deposit's block was reorged out after attestation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorged_at: Option<u64>,
+
+    /// Every status change, oldest first
+    #[serde(default)]
+    pub history: Vec<StatusChange>,
+
+    /// Unix timestamp of first detection
+    pub detected_at: u64,
+
+    /// Unix timestamp of the last status change
+    pub updated_at: u64,
+}
+
+/// A single lifecycle transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusChange {
+    /// Status entered
+    pub status: DepositStatus,
+
+    /// Unix timestamp of the change
+    pub at: u64,
+
+    /// Reason, if one was given
+    pub reason: Option<String>,
+}
+
+/// Store key of the vault note at `output_index` in `tx_hash` (hex)
+pub fn deposit_key(tx_hash: &str, output_index: u32) -> String {
+    format!("{}:{}", tx_hash.strip_prefix("0x").unwrap_or(tx_hash), output_index)
+}
+
+/// Store key of the deposit a payload attests
+pub fn payload_key(payload: &BridgePayload) -> String {
+    deposit_key(&hex::encode(payload.tx_hash), payload.output_index)
+}
+
+impl DepositRecord {
+    /// Store key of this deposit
+    pub fn key(&self) -> String {
+        deposit_key(&self.tx_hash, self.output_index)
+    }
+
+    /// Whether the fee is fixed; records from before fees were fixed count
+    /// as fixed once they carry a fee
+    pub fn fee_fixed(&self) -> bool {
+        self.fee_fixed_at.is_some() || self.fee > 0
+    }
+
+    /// Rebuild the bridge payload for re-processing
+    pub fn to_payload(&self) -> Result<BridgePayload, SentinelError> {
+        fn bytes32(hex_str: &str) -> Result<[u8; 32], SentinelError> {
+            hex::decode(hex_str)?
+                .try_into()
+                .map_err(|_| SentinelError::InvalidPayload("expected 32 bytes".to_string()))
+        }
+
+        Ok(BridgePayload {
+            tx_hash: bytes32(&self.tx_hash)?,
+            output_index: self.output_index,
+            amount: self.amount,
+            secret_hash: bytes32(&self.secret_hash)?,
+            aztec_address: bytes32(&self.aztec_address)?,
+            block_height: self.block_height,
+            block_hash: if self.block_hash.is_empty() {
+                [0u8; 32]
+            } else {
+                bytes32(&self.block_hash)?
+            },
+            refund_address: self.refund_address.clone(),
+            fee: self.fee,
+            memo_amount: self.memo_amount,
+            vault_shard: self.vault_shard.clone(),
+            target: self.target.clone(),
+        })
+    }
+}
+
+/// On-disk store contents
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreState {
+    /// Deposits keyed by [`deposit_key`]
+    deposits: BTreeMap<String, DepositRecord>,
+
+    /// Total bridge fees on submitted deposits, in zatoshi
+    #[serde(default)]
+    fees_collected: u64,
+}
+
+/// JSON-file backed deposit store
+pub struct DepositStore {
+    /// Path of the store file
+    path: PathBuf,
+
+    /// In-memory copy of the store
+    state: Mutex<StoreState>,
+
+    /// Leader lease the store may only be written under, in an HA pair
+    fence: Option<Arc<LeaseFence>>,
+}
+
+impl DepositStore {
+    /// Open (or create) the store at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SentinelError> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut state: StoreState = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| SentinelError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StoreState::default(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+
+        // Stores written before deposits were per note are keyed by tx hash
+        state.deposits = std::mem::take(&mut state.deposits)
+            .into_values()
+            .map(|record| (record.key(), record))
+            .collect();
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+            fence: None,
+        })
+    }
+
+    /// Only write the store while `fence` holds the leader lease
+    pub fn with_fence(mut self, fence: Arc<LeaseFence>) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+
+    /// Record a newly detected deposit; existing records are left untouched,
+    /// except that an orphaned deposit mined again is detected afresh
+    pub fn insert_detected(&self, payload: &BridgePayload) -> Result<DepositRecord, SentinelError> {
+        let key = payload_key(payload);
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(existing) = state.deposits.get_mut(&key) {
+            let block_hash = hex::encode(payload.block_hash);
+            if existing.status != DepositStatus::Orphaned || existing.block_hash == block_hash {
+                return Ok(existing.clone());
+            }
+            let now = now_secs();
+            existing.block_height = payload.block_height;
+            existing.block_hash = block_hash;
+            existing.status = DepositStatus::Detected;
+            existing.history.push(StatusChange {
+                status: DepositStatus::Detected,
+                at: now,
+                reason: Some(format!("mined again in block {}", payload.block_height)),
+            });
+            existing.updated_at = now;
+            let record = existing.clone();
+            self.persist(&state)?;
+            return Ok(record);
+        }
+
+        let now = now_secs();
+        let record = DepositRecord {
+            tx_hash: hex::encode(payload.tx_hash),
+            output_index: payload.output_index,
+            amount: payload.amount,
+            secret_hash: hex::encode(payload.secret_hash),
+            aztec_address: hex::encode(payload.aztec_address),
+            block_height: payload.block_height,
+            block_hash: hex::encode(payload.block_hash),
+            refund_address: payload.refund_address.clone(),
+            fee: payload.fee,
+            fee_fixed_at: None,
+            memo_amount: payload.memo_amount,
+            vault_shard: payload.vault_shard.clone(),
+            target: payload.target.clone(),
+            status: DepositStatus::Detected,
+            reason: None,
+            message_hash: None,
+            l1_tx_hash: None,
+            claim_tx_hash: None,
+            portal_verified_at: None,
+            portal_error: None,
+            l2_synced_at: None,
+            reorged_at: None,
+            history: vec![StatusChange {
+                status: DepositStatus::Detected,
+                at: now,
+                reason: None,
+            }],
+            detected_at: now,
+            updated_at: now,
+        };
+        state.deposits.insert(key, record.clone());
+        self.persist(&state)?;
+
+        Ok(record)
+    }
+
+    /// Move the deposit stored under `key` to a new status
+    pub fn transition(
+        &self,
+        key: &str,
+        next: DepositStatus,
+        reason: Option<String>,
+    ) -> Result<DepositRecord, SentinelError> {
+        let mut state = self.state.lock().unwrap();
+        let record = state
+            .deposits
+            .get_mut(key)
+            .ok_or_else(|| SentinelError::Storage(format!("Unknown deposit {}", key)))?;
+
+        if !record.status.can_transition_to(next) {
+            return Err(SentinelError::Storage(format!(
+                "Invalid transition {:?} -> {:?} for deposit {}",
+                record.status, next, key
+            )));
+        }
+
+        debug!("Deposit {}: {:?} -> {:?}", key, record.status, next);
+        let now = now_secs();
+        record.status = next;
+        record.history.push(StatusChange {
+            status: next,
+            at: now,
+            reason: reason.clone(),
+        });
+        if reason.is_some() {
+            record.reason = reason;
+        }
+        record.updated_at = now;
+        let record = record.clone();
+
+        if next == DepositStatus::Submitted {
+            state.fees_collected = state.fees_collected.saturating_add(record.fee);
+        }
+
+        self.persist(&state)?;
+        Ok(record)
+    }
+
+    /// Update non-status fields of a deposit (cross-chain identifiers)
+    pub fn update(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut DepositRecord),
+    ) -> Result<(), SentinelError> {
+        let mut state = self.state.lock().unwrap();
+        let record = state
+            .deposits
+            .get_mut(key)
+            .ok_or_else(|| SentinelError::Storage(format!("Unknown deposit {}", key)))?;
+
+        f(record);
+        self.persist(&state)
+    }
+
+    /// Fix the bridge fee of a deposit to `fee` and return the fee it carries
+    ///
+    /// A fee fixed earlier is kept, so a re-driven deposit signs the same
+    /// fields whatever the price or fee config is now.
+    pub fn fix_fee(&self, key: &str, fee: u64) -> Result<u64, SentinelError> {
+        let mut state = self.state.lock().unwrap();
+        let record = state
+            .deposits
+            .get_mut(key)
+            .ok_or_else(|| SentinelError::Storage(format!("Unknown deposit {}", key)))?;
+        if record.fee_fixed() {
+            return Ok(record.fee);
+        }
+
+        record.fee = fee;
+        record.fee_fixed_at = Some(now_secs());
+        self.persist(&state)?;
+        Ok(fee)
+    }
+
+    /// Attach the dispatched L1→L2 message hash to a deposit
+    pub fn set_message_hash(&self, key: &str, message_hash: &str) -> Result<(), SentinelError> {
+        self.update(key, |record| {
+            record.message_hash = Some(message_hash.to_string())
+        })
+    }
+
+    /// Find a deposit by its L1→L2 message hash
+    pub fn find_by_message_hash(&self, message_hash: &str) -> Option<DepositRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .deposits
+            .values()
+            .find(|r| r.message_hash.as_deref() == Some(message_hash))
+            .cloned()
+    }
+
+    /// Mark a deposit as rejected with a reason
+    pub fn reject(
+        &self,
+        key: &str,
+        reason: impl Into<String>,
+    ) -> Result<DepositRecord, SentinelError> {
+        self.transition(key, DepositStatus::Rejected, Some(reason.into()))
+    }
+
+    /// Look up a deposit by [`deposit_key`]
+    pub fn get(&self, key: &str) -> Option<DepositRecord> {
+        let key = key.strip_prefix("0x").unwrap_or(key);
+        self.state.lock().unwrap().deposits.get(key).cloned()
+    }
+
+    /// Look up a deposit given by an operator: a [`deposit_key`], or a bare
+    /// txid whose transaction holds exactly one vault note
+    pub fn resolve(&self, id: &str) -> Option<DepositRecord> {
+        if id.contains(':') {
+            return self.get(id);
+        }
+        match self.for_tx(id).as_slice() {
+            [record] => Some(record.clone()),
+            _ => None,
+        }
+    }
+
+    /// Every deposit in the transaction with hex hash `tx_hash`, by output index
+    pub fn for_tx(&self, tx_hash: &str) -> Vec<DepositRecord> {
+        let prefix = deposit_key(tx_hash, 0);
+        let prefix = &prefix[..prefix.len() - 1];
+        self.state
+            .lock()
+            .unwrap()
+            .deposits
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    /// All stored deposits
+    pub fn all(&self) -> Vec<DepositRecord> {
+        self.state.lock().unwrap().deposits.values().cloned().collect()
+    }
+
+    /// All deposits currently in `status`
+    pub fn with_status(&self, status: DepositStatus) -> Vec<DepositRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .deposits
+            .values()
+            .filter(|r| r.status == status)
+            .cloned()
+            .collect()
+    }
+
+    /// Total bridge fees on submitted deposits, in zatoshi
+    pub fn fees_collected(&self) -> u64 {
+        self.state.lock().unwrap().fees_collected
+    }
+
+    /// Write the state atomically to disk
+    fn persist(&self, state: &StoreState) -> Result<(), SentinelError> {
+        if let Some(fence) = &self.fence {
+            fence.check().map_err(SentinelError::Storage)?;
+        }
+        let bytes = serde_json::to_vec_pretty(state)?;
+        let tmp = self.path.with_extension("json.tmp");
+
+        std::fs::write(&tmp, bytes).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> BridgePayload {
+        BridgePayload {
+            tx_hash: [0xab; 32],
+            amount: 5_000,
+            secret_hash: [0xcd; 32],
+            aztec_address: [0xef; 32],
+            block_height: 10,
+            ..BridgePayload::sample()
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deposits.json");
+        let tx_hash = deposit_key(&hex::encode([0xab; 32]), 0);
+
+        let store = DepositStore::open(&path).unwrap();
+        store.insert_detected(&payload()).unwrap();
+        store.reject(&tx_hash, "bad memo").unwrap();
+
+        // Invalid transitions are refused
+        assert!(store
+            .transition(&tx_hash, DepositStatus::Submitted, None)
+            .is_err());
+
+        let reopened = DepositStore::open(&path).unwrap();
+        let record = reopened.get(&tx_hash).unwrap();
+        assert_eq!(record.status, DepositStatus::Rejected);
+        assert_eq!(record.reason.as_deref(), Some("bad memo"));
+
+        // The full history survives a reopen
+        let statuses: Vec<_> = record.history.iter().map(|c| c.status).collect();
+        assert_eq!(statuses, vec![DepositStatus::Detected, DepositStatus::Rejected]);
+    }
+
+    #[test]
+    fn test_fee_fixed_at_first_admission() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deposits.json");
+        let key = payload_key(&payload());
+
+        let store = DepositStore::open(&path).unwrap();
+        store.insert_detected(&payload()).unwrap();
+        assert_eq!(store.fix_fee(&key, 0).unwrap(), 0);
+
+        // A resume at another price or fee config keeps the first fee
+        let reopened = DepositStore::open(&path).unwrap();
+        assert_eq!(reopened.fix_fee(&key, 50).unwrap(), 0);
+        let record = reopened.get(&key).unwrap();
+        assert_eq!(record.to_payload().unwrap().fee, 0);
+        assert!(record.fee_fixed_at.is_some());
+    }
+
+    #[test]
+    fn test_dispatched_deposits_are_never_rejected() {
+        for status in [DepositStatus::Submitted, DepositStatus::ClaimExpired] {
+            assert!(!status.can_transition_to(DepositStatus::Rejected));
+        }
+    }
+
+    #[test]
+    fn test_notes_in_one_transaction_are_separate_deposits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deposits.json");
+        let store = DepositStore::open(&path).unwrap();
+
+        let first = store.insert_detected(&payload()).unwrap();
+        let second = store
+            .insert_detected(&BridgePayload {
+                output_index: 1,
+                ..payload()
+            })
+            .unwrap();
+        assert_ne!(first.key(), second.key());
+
+        store.reject(&first.key(), "bad memo").unwrap();
+        assert_eq!(store.get(&second.key()).unwrap().status, DepositStatus::Detected);
+        assert_eq!(store.for_tx(&first.tx_hash).len(), 2);
+        // A bare txid is ambiguous once the transaction has two notes
+        assert!(store.resolve(&first.tx_hash).is_none());
+        assert_eq!(store.resolve(&second.key()).unwrap().output_index, 1);
+
+        // A store keyed by bare tx hash is rekeyed on open
+        let mut legacy: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let record = legacy["deposits"][first.key()].take();
+        legacy["deposits"] = serde_json::json!({ first.tx_hash.clone(): record });
+        std::fs::write(&path, serde_json::to_vec(&legacy).unwrap()).unwrap();
+        let reopened = DepositStore::open(&path).unwrap();
+        assert_eq!(reopened.get(&first.key()).unwrap().status, DepositStatus::Rejected);
+    }
+}
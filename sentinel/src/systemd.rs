@@ -0,0 +1,195 @@
+//! systemd readiness and watchdog notifications
+//!
+//! Under `Type=notify` units, systemd passes `NOTIFY_SOCKET`. The sentinel
+//! then reports `READY=1` only once the scanner has reached lightwalletd and
+//! is within a configured number of blocks of the confirmed tip, keeps a
+//! human-readable `STATUS=` line current, and, with `WatchdogSec=` set, sends
+//! `WATCHDOG=1` only while scanning makes progress. A sentinel whose pipeline
+//! is wedged stops pinging and is restarted by systemd.
+
+use crate::halt::HaltSwitch;
+use crate::scanner::ScanProgress;
+use std::os::unix::net::UnixDatagram;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Default distance from the confirmed tip at which the sentinel is ready
+pub const DEFAULT_READY_LAG_BLOCKS: u32 = 10;
+
+/// Status refresh interval without a watchdog
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Connection to the service manager's notification socket
+pub struct Notifier {
+    /// Unbound datagram socket
+    socket: UnixDatagram,
+
+    /// `NOTIFY_SOCKET` value
+    path: String,
+}
+
+impl Notifier {
+    /// Notifier for `NOTIFY_SOCKET`, if the process runs under systemd
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var("NOTIFY_SOCKET")
+            .ok()
+            .filter(|p| !p.is_empty())?;
+        match UnixDatagram::unbound() {
+            Ok(socket) => Some(Self { socket, path }),
+            Err(e) => {
+                warn!("Cannot open systemd notification socket: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Send one notification, e.g. `READY=1`
+    pub fn notify(&self, state: &str) -> std::io::Result<()> {
+        // A leading '@' names a socket in the abstract namespace
+        if let Some(name) = self.path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                return self
+                    .socket
+                    .send_to_addr(state.as_bytes(), &addr)
+                    .map(|_| ());
+            }
+            #[cfg(not(target_os = "linux"))]
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("abstract socket @{} needs Linux", name),
+            ));
+        }
+        self.socket
+            .send_to(state.as_bytes(), &self.path)
+            .map(|_| ())
+    }
+}
+
+/// Watchdog interval requested by systemd for this process, if any
+fn watchdog_interval() -> Option<Duration> {
+    // WATCHDOG_PID, when set, names the process expected to ping
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    std::env::var("WATCHDOG_USEC")
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|usec| *usec > 0)
+        .map(Duration::from_micros)
+}
+
+/// Whether the scanner is connected and close enough to the tip
+fn is_ready(tip: u32, synced: u32, ready_lag: u32) -> bool {
+    tip > 0 && tip.saturating_sub(synced) <= ready_lag
+}
+
+/// `STATUS=` text
+fn status_line(tip: u32, synced: u32, ready: bool, halted_by: Option<&str>) -> String {
+    let mut status = if tip == 0 {
+        "Connecting to lightwalletd".to_string()
+    } else if ready {
+        format!("Scanning at {} (confirmed tip {})", synced, tip)
+    } else {
+        format!(
+            "Catching up: {} of {} ({} blocks behind)",
+            synced,
+            tip,
+            tip.saturating_sub(synced)
+        )
+    };
+    if let Some(source) = halted_by {
+        status.push_str(&format!("; attestation halted by {}", source));
+    }
+    status
+}
+
+/// Report readiness, status and liveness to systemd forever
+pub async fn run(
+    notifier: Notifier,
+    progress: Arc<ScanProgress>,
+    halt: Arc<HaltSwitch>,
+    ready_lag: u32,
+) {
+    let watchdog = watchdog_interval();
+    let interval = watchdog.map_or(STATUS_INTERVAL, |w| (w / 2).min(STATUS_INTERVAL));
+    if let Some(watchdog) = watchdog {
+        info!("systemd watchdog enabled ({:?})", watchdog);
+    }
+
+    let mut ready = false;
+    let mut last_status = String::new();
+    loop {
+        let tip = progress.confirmed_tip();
+        let synced = progress.synced_height();
+
+        let mut message = Vec::new();
+        if !ready && is_ready(tip, synced, ready_lag) {
+            ready = true;
+            info!(
+                "Scanner within {} blocks of tip, notifying systemd",
+                ready_lag
+            );
+            message.push("READY=1".to_string());
+        }
+        let status = status_line(
+            tip,
+            synced,
+            ready,
+            halt.state().as_ref().map(|h| h.source.as_str()),
+        );
+        if status != last_status {
+            message.push(format!("STATUS={}", status));
+            last_status = status;
+        }
+        // Only a scanner that completed work within the watchdog period counts as alive
+        match watchdog {
+            Some(watchdog) if progress.is_healthy(watchdog) => {
+                message.push("WATCHDOG=1".to_string())
+            }
+            Some(_) => debug!("No scan progress, withholding systemd watchdog ping"),
+            None => {}
+        }
+
+        if !message.is_empty() {
+            if let Err(e) = notifier.notify(&message.join("\n")) {
+                warn!("systemd notification failed: {}", e);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_and_notifications() {
+        assert!(!is_ready(0, 0, 10));
+        assert!(!is_ready(2_500_100, 2_500_000, 10));
+        assert!(is_ready(2_500_100, 2_500_090, 10));
+        assert_eq!(
+            status_line(2_500_100, 2_500_000, false, Some("reconciliation")),
+            "Catching up: 2500000 of 2500100 (100 blocks behind); attestation halted by reconciliation"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier {
+            socket: UnixDatagram::unbound().unwrap(),
+            path: path.to_string_lossy().into_owned(),
+        };
+        notifier.notify("READY=1\nSTATUS=ok").unwrap();
+        let mut buf = [0u8; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1\nSTATUS=ok");
+    }
+}
@@ -0,0 +1,337 @@
+//! Transparent vault address monitoring
+//!
+//! Deposits must go to the shielded vault address, since only a shielded
+//! memo can name the Aztec recipient. Funds sent to the vault's transparent
+//! address (`VAULT_TRANSPARENT_ADDRESS`) by mistake would otherwise sit
+//! there unnoticed. This watcher lists the address's transactions with
+//! `GetTaddressTxids` once they are `CONFIRMATION_DEPTH` deep and records
+//! each as a rejected deposit. The refund goes to the address that funded
+//! the transaction's first input, through the usual refund flow; deposits
+//! without a transparent input are left for manual handling. The watcher
+//! starts over from `VAULT_BIRTHDAY_HEIGHT` on every start; payments already
+//! in the deposit store are not recorded twice.
+
+use crate::error::SentinelError;
+use crate::lightwalletd::{ClientTls, LightwalletdClient};
+use crate::retry::RetryPolicy;
+use crate::store::{DepositStatus, DepositStore};
+use crate::BridgePayload;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use zcash_client_backend::address::RecipientAddress;
+use zcash_client_backend::proto::service::{
+    BlockId, BlockRange, ChainSpec, TransparentAddressBlockFilter, TxFilter,
+};
+use zcash_primitives::consensus::{BlockHeight, BranchId, Network, MAIN_NETWORK};
+use zcash_primitives::legacy::TransparentAddress;
+use zcash_primitives::transaction::Transaction;
+
+/// Why transparent deposits are rejected
+pub const TRANSPARENT_REASON: &str = "sent to the transparent vault address";
+
+/// A confirmed payment to the transparent vault address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransparentDeposit {
+    /// Transaction id (internal byte order)
+    pub txid: [u8; 32],
+
+    /// Zatoshi paid to the vault address
+    pub amount: u64,
+
+    /// Block height
+    pub block_height: u32,
+
+    /// Block hash (internal byte order)
+    pub block_hash: [u8; 32],
+
+    /// Address funding the first transparent input, refunded to
+    pub sender: Option<String>,
+}
+
+/// Record `deposit` as rejected; false if it was already recorded
+pub fn flag_deposit(
+    store: &DepositStore,
+    deposit: &TransparentDeposit,
+) -> Result<bool, SentinelError> {
+    let payload = BridgePayload {
+        tx_hash: deposit.txid,
+        output_index: 0,
+        amount: deposit.amount,
+        secret_hash: [0; 32],
+        aztec_address: [0; 32],
+        block_height: deposit.block_height,
+        block_hash: deposit.block_hash,
+        refund_address: deposit.sender.clone(),
+        fee: 0,
+        memo_amount: None,
+        vault_shard: crate::shards::PRIMARY_SHARD.to_string(),
+        target: None,
+    };
+    let record = store.insert_detected(&payload)?;
+    if record.status != DepositStatus::Detected {
+        return Ok(false);
+    }
+    store.reject(&record.key(), TRANSPARENT_REASON)?;
+    Ok(true)
+}
+
+/// Parse a raw transaction mined at `height`
+fn read_transaction(data: &[u8], height: u32) -> Result<Transaction, SentinelError> {
+    let branch = BranchId::for_height(&MAIN_NETWORK, BlockHeight::from_u32(height));
+    Transaction::read(data, branch).map_err(|e| SentinelError::Scanner(e.to_string()))
+}
+
+/// Polls lightwalletd for transactions paying the transparent vault address
+pub struct TransparentWatcher {
+    /// Lightwalletd URL
+    url: String,
+
+    /// TLS settings for lightwalletd
+    tls: ClientTls,
+
+    /// Vault transparent address, as configured
+    address: String,
+
+    /// Vault transparent address, decoded
+    vault: TransparentAddress,
+
+    /// Network the vault address belongs to
+    network: Network,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Confirmations before a payment is recorded
+    confirmation_depth: u32,
+
+    /// Next height to list
+    next_height: u32,
+
+    /// Retries of lightwalletd calls
+    retry: RetryPolicy,
+}
+
+impl TransparentWatcher {
+    /// Watch `address`, listing from `start_height`
+    pub fn new(
+        url: String,
+        tls: ClientTls,
+        address: &str,
+        store: Arc<DepositStore>,
+        confirmation_depth: u32,
+        start_height: u32,
+    ) -> Result<Self, SentinelError> {
+        let decoded = [Network::MainNetwork, Network::TestNetwork]
+            .into_iter()
+            .find_map(|network| Some((RecipientAddress::decode(&network, address)?, network)));
+        let Some((RecipientAddress::Transparent(vault), network)) = decoded else {
+            return Err(SentinelError::Config(format!(
+                "{} is not a transparent address",
+                address
+            )));
+        };
+        Ok(Self {
+            url,
+            tls,
+            address: address.to_string(),
+            vault,
+            network,
+            store,
+            confirmation_depth,
+            next_height: start_height.max(1),
+            retry: RetryPolicy::none(),
+        })
+    }
+
+    /// Retry failed lightwalletd calls under `retry`
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Record transparent deposits up to the confirmed tip
+    pub async fn poll(&mut self) -> Result<usize, SentinelError> {
+        let mut client = crate::lightwalletd::connect(&self.url, &self.tls).await?;
+        let tip = client
+            .get_latest_block(ChainSpec {})
+            .await?
+            .into_inner()
+            .height as u32;
+        let safe_height = tip.saturating_sub(self.confirmation_depth);
+        if safe_height < self.next_height {
+            return Ok(0);
+        }
+
+        let filter = TransparentAddressBlockFilter {
+            address: self.address.clone(),
+            range: Some(BlockRange {
+                start: Some(BlockId {
+                    height: u64::from(self.next_height),
+                    hash: vec![],
+                }),
+                end: Some(BlockId {
+                    height: u64::from(safe_height),
+                    hash: vec![],
+                }),
+            }),
+        };
+        let mut stream = client.get_taddress_txids(filter).await?.into_inner();
+        let mut raw_txs = Vec::new();
+        while let Some(raw) = stream.message().await? {
+            raw_txs.push(raw);
+        }
+
+        let mut flagged = 0;
+        for raw in raw_txs {
+            let height = raw.height as u32;
+            let tx = read_transaction(&raw.data, height)?;
+            let Some(deposit) = self.deposit(&client, &tx, height).await? else {
+                continue;
+            };
+            if flag_deposit(&self.store, &deposit)? {
+                let mut display = deposit.txid;
+                display.reverse();
+                warn!(
+                    "Transparent deposit of {} zatoshi in {}, flagged for refund to {}",
+                    deposit.amount,
+                    hex::encode(display),
+                    deposit.sender.as_deref().unwrap_or("(manual handling)")
+                );
+                flagged += 1;
+            }
+        }
+        self.next_height = safe_height + 1;
+        Ok(flagged)
+    }
+
+    /// The payment `tx` makes to the vault, if any
+    async fn deposit(
+        &self,
+        client: &LightwalletdClient,
+        tx: &Transaction,
+        height: u32,
+    ) -> Result<Option<TransparentDeposit>, SentinelError> {
+        let Some(bundle) = tx.transparent_bundle() else {
+            return Ok(None);
+        };
+        let amount: u64 = bundle
+            .vout
+            .iter()
+            .filter(|out| out.recipient_address() == Some(self.vault))
+            .map(|out| i64::from(out.value) as u64)
+            .sum();
+        if amount == 0 {
+            return Ok(None);
+        }
+
+        let sender = match bundle.vin.first() {
+            Some(input) => {
+                self.funding_address(client, input.prevout.hash(), input.prevout.n())
+                    .await?
+            }
+            None => None,
+        };
+        // The vault moving its own transparent funds is not a deposit
+        if sender == Some(self.vault) {
+            return Ok(None);
+        }
+        let block = self.block_id(client, height).await?;
+        Ok(Some(TransparentDeposit {
+            txid: *tx.txid().as_ref(),
+            amount,
+            block_height: height,
+            block_hash: block,
+            sender: sender
+                .map(|address| RecipientAddress::Transparent(address).encode(&self.network)),
+        }))
+    }
+
+    /// Address paid by output `n` of transaction `txid`
+    async fn funding_address(
+        &self,
+        client: &LightwalletdClient,
+        txid: &[u8; 32],
+        n: u32,
+    ) -> Result<Option<TransparentAddress>, SentinelError> {
+        let raw = self
+            .retry
+            .run("GetTransaction", || {
+                let mut client = client.clone();
+                async move {
+                    let filter = TxFilter {
+                        hash: txid.to_vec(),
+                        ..TxFilter::default()
+                    };
+                    Ok(client.get_transaction(filter).await?.into_inner())
+                }
+            })
+            .await?;
+        let tx = read_transaction(&raw.data, raw.height as u32)?;
+        Ok(tx
+            .transparent_bundle()
+            .and_then(|b| b.vout.get(n as usize))
+            .and_then(|out| out.recipient_address()))
+    }
+
+    /// Hash of the block at `height`
+    async fn block_id(
+        &self,
+        client: &LightwalletdClient,
+        height: u32,
+    ) -> Result<[u8; 32], SentinelError> {
+        let block = self
+            .retry
+            .run("GetBlock", || {
+                let mut client = client.clone();
+                async move {
+                    let id = BlockId {
+                        height: u64::from(height),
+                        hash: vec![],
+                    };
+                    Ok(client.get_block(id).await?.into_inner())
+                }
+            })
+            .await?;
+        block.hash.as_slice().try_into().map_err(|_| {
+            SentinelError::Scanner(format!("malformed block hash at height {}", height))
+        })
+    }
+
+    /// Poll every `interval` forever
+    pub async fn run(mut self, interval: Duration) {
+        info!("Watching transparent vault address {}", self.address);
+        loop {
+            if let Err(e) = self.poll().await {
+                error!("Transparent address poll failed: {}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::deposit_key;
+
+    #[test]
+    fn test_transparent_deposit_flagged_for_refund() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DepositStore::open(dir.path().join("deposits.json")).unwrap();
+        let deposit = TransparentDeposit {
+            txid: [9; 32],
+            amount: 120_000,
+            block_height: 2_500_000,
+            block_hash: [4; 32],
+            sender: Some("t1Vc5Eo2xrcpsTVHyVkLq3ZhZyyyuyX6ePe".to_string()),
+        };
+        assert!(flag_deposit(&store, &deposit).unwrap());
+        assert!(!flag_deposit(&store, &deposit).unwrap());
+
+        let record = store.get(&deposit_key(&hex::encode([9u8; 32]), 0)).unwrap();
+        assert_eq!(record.status, DepositStatus::Rejected);
+        assert_eq!(record.reason.as_deref(), Some(TRANSPARENT_REASON));
+        assert_eq!(record.refund_address, deposit.sender);
+    }
+}
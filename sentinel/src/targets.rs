@@ -0,0 +1,589 @@
+//! Attestation targets
+//!
+//! Besides the primary ServiceManager (`L1_RPC_URL`, `SERVICE_MANAGER_ADDRESS`),
+//! deposits can be attested on further deployments, e.g. an L2 rollup, listed
+//! in the JSON file at `L1_TARGETS_PATH`:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "base",
+//!     "rpc_url": "https://base.example.org",
+//!     "service_manager": "0x...",
+//!     "start_block": 12000000,
+//!     "shards": ["vault-b"],
+//!     "gas": { "max_gas_price_gwei": 2, "gas_limit": 600000 }
+//!   }
+//! ]
+//! ```
+//!
+//! A deposit goes to the target its memo names, else to the target serving
+//! its vault shard, else to the primary one. Each target has its own signer
+//! connection, signing log, gas policy and sign and submit stages, so a
+//! congested chain never holds up the others. The contract's EIP-712 domain
+//! binds each signature to one deployment.
+
+use crate::consistency::{PeerChecker, SignedDigests};
+use crate::dispatches;
+use crate::equivocation::EquivocationCheck;
+use crate::events::{BridgeEvent, EventEmitter, EventKind};
+use crate::halt::HaltSwitch;
+use crate::leader::{note_seed, LeaderSchedule};
+use crate::maintenance::MaintenanceSchedule;
+use crate::quorum::{QuorumCalculator, StakeRegistry};
+use crate::ratelimit::SigningRateLimiter;
+use crate::reorg::ReorgLog;
+use crate::signer::{note_nonce, AttestationSigner};
+use crate::store::{payload_key, DepositStatus, DepositStore};
+use crate::{Attestation, BridgePayload};
+use anyhow::{bail, Context, Result};
+use ethers::types::Address;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// How long to wait before retrying a dispatch short of quorum or that failed
+const QUORUM_RETRY: Duration = Duration::from_secs(15);
+
+/// Name of the target configured by `L1_RPC_URL` and `SERVICE_MANAGER_ADDRESS`
+pub const PRIMARY_TARGET: &str = "primary";
+
+/// Limits on the transactions sent to a target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GasPolicy {
+    /// Refuse to submit while the network gas price is above this
+    #[serde(default)]
+    pub max_gas_price_gwei: Option<u64>,
+
+    /// Gas limit instead of an estimate
+    #[serde(default)]
+    pub gas_limit: Option<u64>,
+}
+
+/// An additional ServiceManager deployment
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetSpec {
+    /// Name used in memos, logs and file names
+    pub name: String,
+
+    /// JSON-RPC URL of the target chain
+    pub rpc_url: String,
+
+    /// ServiceManager contract address
+    pub service_manager: String,
+
+    /// Block the ServiceManager was deployed in
+    #[serde(default)]
+    pub start_block: u64,
+
+    /// Vault shards whose deposits go here unless their memo says otherwise
+    #[serde(default)]
+    pub shards: Vec<String>,
+
+    /// Gas policy
+    #[serde(default)]
+    pub gas: GasPolicy,
+}
+
+/// Read and check the target list at `path`
+pub fn load_targets(path: &str) -> Result<Vec<TargetSpec>> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path))?;
+    let targets: Vec<TargetSpec> =
+        serde_json::from_str(&contents).with_context(|| format!("Invalid targets in {}", path))?;
+
+    let mut names = HashSet::from([PRIMARY_TARGET.to_string()]);
+    let mut shards = HashSet::new();
+    for target in &targets {
+        if target.name.is_empty() || !names.insert(target.name.clone()) {
+            bail!(
+                "L1 target names must be unique and not {:?}",
+                PRIMARY_TARGET
+            );
+        }
+        target
+            .service_manager
+            .parse::<Address>()
+            .with_context(|| format!("Invalid service_manager of target {}", target.name))?;
+        if !target.rpc_url.starts_with("http://") && !target.rpc_url.starts_with("https://") {
+            bail!("rpc_url of target {} must be http(s)", target.name);
+        }
+        for shard in &target.shards {
+            if !shards.insert(shard.clone()) {
+                bail!("Vault shard {} is routed to more than one target", shard);
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// Picks the target of each deposit
+#[derive(Debug, Clone)]
+pub struct TargetRouter {
+    /// Every target name, the primary included
+    names: HashSet<String>,
+
+    /// Target serving each routed vault shard
+    by_shard: HashMap<String, String>,
+}
+
+impl TargetRouter {
+    /// Router over the primary target and `targets`
+    pub fn new(targets: &[TargetSpec]) -> Self {
+        let mut names = HashSet::from([PRIMARY_TARGET.to_string()]);
+        let mut by_shard = HashMap::new();
+        for target in targets {
+            names.insert(target.name.clone());
+            for shard in &target.shards {
+                by_shard.insert(shard.clone(), target.name.clone());
+            }
+        }
+        Self { names, by_shard }
+    }
+
+    /// Target of `payload`, or why it has none
+    pub fn route<'a>(&'a self, payload: &'a BridgePayload) -> Result<&'a str, String> {
+        if let Some(target) = &payload.target {
+            return match self.names.get(target) {
+                Some(name) => Ok(name),
+                None => Err(format!("unknown L1 target {}", target)),
+            };
+        }
+        Ok(self
+            .by_shard
+            .get(&payload.vault_shard)
+            .map_or(PRIMARY_TARGET, String::as_str))
+    }
+}
+
+/// What a target's sign and submit stages share with the rest of the pipeline
+pub struct StageContext {
+    /// Switch halting all signing
+    pub halt: Arc<HaltSwitch>,
+
+    /// Signing caps, across all targets
+    pub rate_limiter: Arc<Mutex<SigningRateLimiter>>,
+
+    /// Deposit store
+    pub store: Arc<DepositStore>,
+
+    /// Lifecycle events
+    pub events: EventEmitter,
+
+    /// Stake-weighted quorum threshold
+    pub quorum_threshold_bps: u64,
+
+    /// How long the leader has to dispatch before others take over
+    pub leader_timeout: Duration,
+
+    /// Queue capacity between the sign and submit stages
+    pub submission_capacity: usize,
+
+    /// Windows in which signing and submission pause
+    pub maintenance: Arc<MaintenanceSchedule>,
+
+    /// Zcash blocks reorged out, whose deposits are never attested
+    pub reorgs: Arc<ReorgLog>,
+
+    /// Peer sentinels, asked for their signatures before dispatching
+    pub peers: Arc<PeerChecker>,
+
+    /// Our signatures, served to peers
+    pub signed: Arc<SignedDigests>,
+}
+
+/// Start the sign and submit stages of one target, fed from `admitted_rx`;
+/// `start_block` is the target ServiceManager's deployment block
+pub fn spawn_stages(
+    name: String,
+    signer: Arc<AttestationSigner>,
+    start_block: u64,
+    mut admitted_rx: mpsc::Receiver<BridgePayload>,
+    context: &StageContext,
+) -> (JoinHandle<()>, JoinHandle<()>) {
+    let (signed_tx, mut signed_rx) = mpsc::channel::<Attestation>(context.submission_capacity);
+
+    // Never sign anything contradicting an earlier attestation of the same note
+    let equivocation = EquivocationCheck::new(
+        signer.provider(),
+        signer.service_manager_address(),
+        start_block,
+        context.store.clone(),
+        context.halt.clone(),
+    );
+
+    // Sign stage: every operator signs a note under the nonce derived from
+    // it, so their signatures over the same deposit aggregate. Signatures
+    // are published for whichever operator ends up dispatching.
+    let signer_clone = signer.clone();
+    let sign_halt = context.halt.clone();
+    let rate_limiter = context.rate_limiter.clone();
+    let sign_events = context.events.clone();
+    let sign_target = name.clone();
+    let sign_maintenance = context.maintenance.clone();
+    let sign_reorgs = context.reorgs.clone();
+    let sign_store = context.store.clone();
+    let signed = context.signed.clone();
+    let sign_handle = tokio::spawn(async move {
+        while let Some(payload) = admitted_rx.recv().await {
+            // Hold the deposit, and the queue behind it, through maintenance
+            // and halts
+            sign_maintenance.wait_out("signing").await;
+            sign_halt.wait_out("signing").await;
+            if sign_reorgs.is_orphaned(&payload) {
+                warn!(
+                    "Deposit {} reorged out, not signed",
+                    hex::encode(payload.tx_hash)
+                );
+                continue;
+            }
+
+            let signed_deposit =
+                signer_clone.signed_deposit(&payload.tx_hash, payload.output_index);
+            match equivocation.check(&payload, signed_deposit).await {
+                Ok(None) => {}
+                // Dispatched already, by us before a restart or by a peer
+                Ok(Some(dispatch)) => {
+                    info!(
+                        "Deposit {} already dispatched on L1, not signed",
+                        dispatch.key()
+                    );
+                    if let Err(e) = dispatch.mark_submitted(&sign_store) {
+                        error!("Failed to record dispatch of {}: {}", dispatch.key(), e);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    error!(
+                        "Refusing to sign deposit {}: {}",
+                        hex::encode(payload.tx_hash),
+                        e
+                    );
+                    continue;
+                }
+            }
+            let admitted = rate_limiter
+                .lock()
+                .unwrap()
+                .admit(payload.net_amount(), crate::clock::now_secs());
+            if let Err(reason) = admitted {
+                error!(
+                    "Not signing deposit {}: {}",
+                    hex::encode(payload.tx_hash),
+                    reason
+                );
+                continue;
+            }
+
+            let nonce = note_nonce(&payload);
+            match signer_clone.sign_attestation(&payload, nonce).await {
+                Ok(attestation) => {
+                    info!("Attestation signed successfully for {}", sign_target);
+                    signed.insert(
+                        signer_clone.compute_digest(&payload, nonce),
+                        signer_clone.address(),
+                        &attestation.signature,
+                    );
+                    sign_events.emit(
+                        BridgeEvent::new(EventKind::AttestationSigned, &payload).with_nonce(nonce),
+                    );
+                    if signed_tx.send(attestation).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to sign attestation: {}", e);
+                }
+            }
+        }
+    });
+
+    // Submit stage: wait for our dispatch turn and for peers' signatures to
+    // reach quorum, then submit to L1. Attestations not ready yet are set
+    // aside, so one deposit's wait never holds up the queue behind it.
+    let submitter = Submitter {
+        target: name,
+        stake_registry: StakeRegistry::new(signer.provider(), signer.service_manager_address()),
+        signer,
+        start_block,
+        quorum: QuorumCalculator::new(context.quorum_threshold_bps),
+        leader_timeout: context.leader_timeout,
+        store: context.store.clone(),
+        events: context.events.clone(),
+        maintenance: context.maintenance.clone(),
+        reorgs: context.reorgs.clone(),
+        peers: context.peers.clone(),
+    };
+    let submit_handle = tokio::spawn(async move {
+        // (next attempt, first received, attestation)
+        let mut waiting: Vec<(Instant, Instant, Attestation)> = Vec::new();
+        let mut open = true;
+        while open || !waiting.is_empty() {
+            let next_turn = waiting.iter().map(|(turn, _, _)| *turn).min();
+            let turn_due = async move {
+                match next_turn {
+                    Some(turn) => tokio::time::sleep_until(turn).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                received = signed_rx.recv(), if open => match received {
+                    Some(attestation) => {
+                        let now = Instant::now();
+                        waiting.push((now, now, attestation));
+                    }
+                    None => open = false,
+                },
+                _ = turn_due => {
+                    let position = waiting
+                        .iter()
+                        .position(|(turn, _, _)| Some(*turn) == next_turn)
+                        .unwrap_or_default();
+                    let (_, received, attestation) = waiting.swap_remove(position);
+                    if let Some(retry) = submitter.attempt(received, &attestation).await {
+                        waiting.push((retry, received, attestation));
+                    }
+                }
+            }
+        }
+    });
+
+    (sign_handle, submit_handle)
+}
+
+/// A target's submit stage
+struct Submitter {
+    /// Target name
+    target: String,
+
+    /// Signer connected to the target
+    signer: Arc<AttestationSigner>,
+
+    /// Block the target ServiceManager was deployed in
+    start_block: u64,
+
+    /// Operator stakes on the target
+    stake_registry: StakeRegistry,
+
+    /// Stake-weighted quorum check
+    quorum: QuorumCalculator,
+
+    /// How long the leader has to dispatch before others take over
+    leader_timeout: Duration,
+
+    /// Deposit store
+    store: Arc<DepositStore>,
+
+    /// Lifecycle events
+    events: EventEmitter,
+
+    /// Windows in which submission pauses
+    maintenance: Arc<MaintenanceSchedule>,
+
+    /// Zcash blocks reorged out
+    reorgs: Arc<ReorgLog>,
+
+    /// Peer sentinels serving their signatures
+    peers: Arc<PeerChecker>,
+}
+
+impl Submitter {
+    /// Try to dispatch `attestation`, first received at `received`
+    ///
+    /// Returns when to try again if it is not our turn yet, quorum is not
+    /// met yet or the submission failed, and `None` once the deposit is
+    /// dispatched or given up on.
+    async fn attempt(&self, received: Instant, attestation: &Attestation) -> Option<Instant> {
+        let payload = &attestation.payload;
+        let key = payload_key(payload);
+        let retry = Some(Instant::now() + QUORUM_RETRY);
+
+        self.maintenance.wait_out("submission").await;
+        if self.reorgs.is_orphaned(payload) {
+            warn!("Deposit {} reorged out, not submitted", key);
+            return None;
+        }
+
+        match self
+            .signer
+            .is_deposit_processed(&payload.tx_hash, payload.output_index)
+            .await
+        {
+            Ok(true) => {
+                info!("Deposit {} already dispatched by another operator", key);
+                self.events.emit(
+                    BridgeEvent::new(EventKind::AttestationFinalized, payload)
+                        .with_nonce(attestation.nonce),
+                );
+                self.record_dispatch(payload).await;
+                return None;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                error!("Failed to check whether deposit {} was dispatched: {}", key, e);
+                return retry;
+            }
+        }
+
+        let operators = match self.stake_registry.operators().await {
+            Ok(operators) => operators,
+            Err(e) => {
+                error!("Failed to read operator registry: {}", e);
+                return retry;
+            }
+        };
+
+        // Wait for our turn in the dispatch rotation; every operator
+        // derives the same rotation from the note
+        let active: Vec<_> = operators
+            .iter()
+            .filter(|op| op.is_active)
+            .map(|op| op.address)
+            .collect();
+        let schedule = LeaderSchedule::new(
+            note_seed(&payload.tx_hash, payload.output_index),
+            &active,
+            self.leader_timeout,
+        );
+        let Some(turn) = schedule.wait_for(self.signer.address()) else {
+            warn!("Operator is not in the active set, not dispatching");
+            return None;
+        };
+        let wait = turn.saturating_sub(received.elapsed());
+        if !wait.is_zero() {
+            info!("Not leader for deposit {}, dispatch turn in {:?}", key, wait);
+            return Some(Instant::now() + wait);
+        }
+
+        // Refuse to dispatch until the signers carry enough stake
+        let digest = self.signer.compute_digest(payload, attestation.nonce);
+        let cosigners = self
+            .peers
+            .signatures(&digest, self.signer.address(), &active)
+            .await;
+        let mut signers = vec![self.signer.address()];
+        signers.extend(cosigners.iter().map(|(cosigner, _)| *cosigner));
+        if let Err(e) = self.quorum.ensure(&operators, &signers) {
+            warn!("Not dispatching deposit {} yet: {}", key, e);
+            return retry;
+        }
+
+        if self.dispatch(attestation, &cosigners).await {
+            None
+        } else {
+            retry
+        }
+    }
+
+    /// Submit `attestation` with `cosigners`, recording the outcome in the
+    /// store; returns whether it was submitted
+    async fn dispatch(&self, attestation: &Attestation, cosigners: &[(Address, Vec<u8>)]) -> bool {
+        let payload = &attestation.payload;
+        let key = payload_key(payload);
+        match self.signer.submit_attestation(attestation, cosigners).await {
+            Ok(l1_tx_hash) => {
+                info!("Attestation submitted to {}: {}", self.target, l1_tx_hash);
+                self.events.emit(
+                    BridgeEvent::new(EventKind::AttestationFinalized, payload)
+                        .with_nonce(attestation.nonce)
+                        .with_l1_tx(l1_tx_hash.clone()),
+                );
+                if let Err(e) = self
+                    .store
+                    .transition(&key, DepositStatus::Submitted, None)
+                    .and_then(|_| {
+                        self.store
+                            .update(&key, |r| r.l1_tx_hash = Some(l1_tx_hash.clone()))
+                    })
+                {
+                    error!("Failed to record submission: {}", e);
+                }
+                true
+            }
+            Err(e) => {
+                error!("Failed to submit attestation {}, will retry: {}", key, e);
+                false
+            }
+        }
+    }
+
+    /// Mark a note another operator dispatched as submitted, with the L1
+    /// transaction and message hash of its `DepositVerified` when found
+    async fn record_dispatch(&self, payload: &BridgePayload) {
+        let key = payload_key(payload);
+        let event = match dispatches::for_note(
+            &self.signer.provider(),
+            self.signer.service_manager_address(),
+            self.start_block,
+            &payload.tx_hash,
+            payload.output_index,
+        )
+        .await
+        {
+            Ok(events) => events.into_iter().next(),
+            Err(e) => {
+                warn!("DepositVerified of {} unavailable: {}", key, e);
+                None
+            }
+        };
+
+        let recorded = match event {
+            Some(event) => event.mark_submitted(&self.store).map(|_| ()),
+            None => match self.store.get(&key) {
+                Some(record) if record.status == DepositStatus::Detected => self
+                    .store
+                    .transition(&key, DepositStatus::Submitted, None)
+                    .map(|_| ()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = recorded {
+            error!("Failed to record dispatch of {}: {}", key, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets_load_and_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("targets.json");
+        std::fs::write(
+            &path,
+            r#"[{"name": "base", "rpc_url": "https://base.example.org",
+                 "service_manager": "0x0000000000000000000000000000000000000001",
+                 "shards": ["vault-b"], "gas": {"max_gas_price_gwei": 2}}]"#,
+        )
+        .unwrap();
+        let targets = load_targets(path.to_str().unwrap()).unwrap();
+        assert_eq!(targets[0].gas.max_gas_price_gwei, Some(2));
+
+        std::fs::write(
+            &path,
+            r#"[{"name": "primary", "rpc_url": "https://x.example.org",
+                 "service_manager": "0x0000000000000000000000000000000000000001"}]"#,
+        )
+        .unwrap();
+        assert!(load_targets(path.to_str().unwrap()).is_err());
+
+        let router = TargetRouter::new(&targets);
+        let mut payload = BridgePayload::sample();
+        assert_eq!(router.route(&payload), Ok(PRIMARY_TARGET));
+        payload.vault_shard = "vault-b".to_string();
+        assert_eq!(router.route(&payload), Ok("base"));
+        payload.target = Some("primary".to_string());
+        assert_eq!(router.route(&payload), Ok(PRIMARY_TARGET));
+        payload.target = Some("arbitrum".to_string());
+        assert!(router.route(&payload).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Several bridge deployments in one process
+//!
+//! An operator serving more than one bridge can list the extra deployments
+//! in the JSON file at `TENANTS_PATH` instead of running a sentinel each:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "bridge-b",
+//!     "viewing_key_source": "kms:/etc/sentinel/bridge-b-viewing-key.enc",
+//!     "vault_address": "zs1...",
+//!     "vault_birthday_height": 2500000,
+//!     "service_manager": "0x...",
+//!     "l1_start_block": 19000000,
+//!     "memo_domain": "bridge-b",
+//!     "status_addr": "0.0.0.0:8081"
+//!   }
+//! ]
+//! ```
+//!
+//! Each tenant is a full pipeline of its own, with its own scanner, stores
+//! and signing log under `<DATA_DIR>/tenants/<name>`, halt switch and status
+//! API. Settings tied to a deployment (transparent vault address, release
+//! queue, payout policy, rewards coordinator) are off unless the tenant sets
+//! them. Everything else (lightwalletd, L1 RPC unless overridden, operator
+//! key, limits) is shared with the primary configuration, and transactions
+//! from the shared operator account take their nonces in turn. Deposits name
+//! their deployment with the memo's `domain` field, so a memo is only ever
+//! acted on by the tenant it was meant for. Viewing keys come from a key
+//! source (see `viewkey.rs`), never from the file itself.
+
+use crate::config::SentinelConfig;
+use crate::viewkey::ViewingKeySource;
+use anyhow::{bail, Context, Result};
+use ethers::types::Address;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+/// One additional bridge deployment
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantSpec {
+    /// Name, used for the data directory and in logs
+    pub name: String,
+
+    /// Where the tenant's vault viewing key is kept
+    pub viewing_key_source: String,
+
+    /// Tenant's vault shielded address
+    pub vault_address: String,
+
+    /// Height the tenant's vault was created at
+    pub vault_birthday_height: u32,
+
+    /// Tenant's vault transparent address, if watched
+    #[serde(default)]
+    pub vault_transparent_address: Option<String>,
+
+    /// Tenant's ServiceManager contract address
+    pub service_manager: String,
+
+    /// L1 block the tenant's ServiceManager was deployed in
+    pub l1_start_block: u64,
+
+    /// Domain the tenant's deposit memos name
+    pub memo_domain: String,
+
+    /// Listen address of the tenant's status API
+    pub status_addr: String,
+
+    /// L1 RPC URL, if not the primary one
+    #[serde(default)]
+    pub l1_rpc_url: Option<String>,
+
+    /// File the tenant's release instructions are appended to, if it
+    /// processes withdrawals
+    #[serde(default)]
+    pub release_queue_path: Option<String>,
+
+    /// Payout policy of the tenant's releases
+    #[serde(default)]
+    pub payout_policy_path: Option<String>,
+
+    /// Tenant's rewards coordinator, if it differs from the primary's
+    #[serde(default)]
+    pub rewards_coordinator_address: Option<String>,
+}
+
+/// Load and check the tenants listed at `path`
+///
+/// `primary_domain` and `primary_addr` are the primary deployment's memo
+/// domain and status address, which no tenant may reuse.
+pub fn load_tenants(
+    path: &str,
+    primary_domain: Option<&str>,
+    primary_addr: &str,
+) -> Result<Vec<TenantSpec>> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path))?;
+    let tenants: Vec<TenantSpec> =
+        serde_json::from_str(&contents).with_context(|| format!("Invalid tenants in {}", path))?;
+
+    let mut names = HashSet::new();
+    let mut queues = HashSet::new();
+    let mut domains = HashSet::from([primary_domain.map(str::to_string)]);
+    let mut addrs = HashSet::from([primary_addr.to_string()]);
+    for tenant in &tenants {
+        let valid_name = !tenant.name.is_empty()
+            && tenant
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_name || !names.insert(tenant.name.clone()) {
+            bail!(
+                "Tenant names must be unique and use only a-z, 0-9 and '-' ({:?})",
+                tenant.name
+            );
+        }
+        if tenant.memo_domain.is_empty() || !domains.insert(Some(tenant.memo_domain.clone())) {
+            bail!(
+                "Memo domain of tenant {} must be set and differ from every other deployment's",
+                tenant.name
+            );
+        }
+        if !addrs.insert(tenant.status_addr.clone()) {
+            bail!("Status address of tenant {} is already in use", tenant.name);
+        }
+        tenant
+            .status_addr
+            .parse::<SocketAddr>()
+            .with_context(|| format!("Invalid status_addr of tenant {}", tenant.name))?;
+        tenant
+            .service_manager
+            .parse::<Address>()
+            .with_context(|| format!("Invalid service_manager of tenant {}", tenant.name))?;
+        tenant
+            .viewing_key_source
+            .parse::<ViewingKeySource>()
+            .with_context(|| format!("Invalid viewing_key_source of tenant {}", tenant.name))?;
+        if tenant.vault_address.is_empty() {
+            bail!("vault_address of tenant {} is empty", tenant.name);
+        }
+        if let Some(queue) = &tenant.release_queue_path {
+            if !queues.insert(queue.clone()) {
+                bail!("Release queue of tenant {} is already in use", tenant.name);
+            }
+        }
+    }
+    Ok(tenants)
+}
+
+/// Configuration of `tenant`, derived from the primary `config`
+///
+/// Loads the tenant's viewing key from its source.
+pub fn tenant_config(config: &SentinelConfig, tenant: &TenantSpec) -> Result<SentinelConfig> {
+    if tenant.release_queue_path.is_some()
+        && tenant.release_queue_path == config.release_queue_path
+    {
+        bail!("Release queue of tenant {} is the primary's", tenant.name);
+    }
+    if tenant.rewards_coordinator_address.is_some()
+        && tenant.rewards_coordinator_address == config.rewards_coordinator_address
+    {
+        bail!("Tenant {} claims from the primary's rewards coordinator", tenant.name);
+    }
+
+    let viewing_key = tenant
+        .viewing_key_source
+        .parse::<ViewingKeySource>()?
+        .load()
+        .with_context(|| format!("Cannot load the viewing key of tenant {}", tenant.name))?;
+    crate::redact::register_secret(viewing_key.expose_secret());
+
+    let mut tenant_config = config.clone();
+    tenant_config.tenant = Some(tenant.name.clone());
+    tenant_config.viewing_key = viewing_key;
+    tenant_config.viewing_key_source = Some(tenant.viewing_key_source.clone());
+    tenant_config.vault_address = tenant.vault_address.clone();
+    tenant_config.vault_birthday_height = tenant.vault_birthday_height;
+    tenant_config.vault_transparent_address = tenant.vault_transparent_address.clone();
+    tenant_config.service_manager_address = tenant.service_manager.clone();
+    tenant_config.l1_start_block = tenant.l1_start_block;
+    tenant_config.release_queue_path = tenant.release_queue_path.clone();
+    tenant_config.payout_policy_path = tenant.payout_policy_path.clone();
+    tenant_config.rewards_coordinator_address = tenant.rewards_coordinator_address.clone();
+    tenant_config.memo_domain = Some(tenant.memo_domain.clone());
+    tenant_config.status_addr = tenant.status_addr.clone();
+    if let Some(url) = &tenant.l1_rpc_url {
+        tenant_config.l1_rpc_url = url.clone();
+    }
+    tenant_config.data_dir = config
+        .data_path("tenants")
+        .join(&tenant.name)
+        .to_string_lossy()
+        .into_owned();
+
+    // Shards, extra L1 targets and fixture recording belong to the primary deployment
+    tenant_config.vault_shards = Vec::new();
+    tenant_config.l1_targets_path = None;
+    tenant_config.record_fixtures_dir = None;
+    tenant_config.tenants_path = None;
+    tenant_config
+        .validate()
+        .with_context(|| format!("Invalid configuration of tenant {}", tenant.name))?;
+    Ok(tenant_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenants_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tenants.json");
+        let path = path.to_str().unwrap();
+        let tenant = r#"{"name": "bridge-b", "viewing_key_source": "kms:/etc/sentinel/b.enc",
+                         "vault_address": "zs1b", "vault_birthday_height": 100,
+                         "service_manager": "0x0000000000000000000000000000000000000002",
+                         "l1_start_block": 5, "memo_domain": "bridge-b",
+                         "status_addr": "0.0.0.0:8081", "release_queue_path": "b.jsonl"}"#;
+        std::fs::write(path, format!("[{}]", tenant)).unwrap();
+        let tenants = load_tenants(path, None, "0.0.0.0:8080").unwrap();
+        assert_eq!(tenants[0].memo_domain, "bridge-b");
+        assert_eq!(tenants[0].l1_rpc_url, None);
+        assert_eq!(tenants[0].vault_transparent_address, None);
+
+        // Domains and status addresses are never shared
+        assert!(load_tenants(path, Some("bridge-b"), "0.0.0.0:8080").is_err());
+        assert!(load_tenants(path, None, "0.0.0.0:8081").is_err());
+        let other = tenant.replace("\"bridge-b\"", "\"bridge-c\"");
+        std::fs::write(path, format!("[{}, {}]", tenant, other)).unwrap();
+        assert!(load_tenants(path, None, "0.0.0.0:8080").is_err());
+
+        // Nor are release queues
+        let other = tenant
+            .replace("bridge-b", "bridge-c")
+            .replace("0.0.0.0:8081", "0.0.0.0:8082");
+        std::fs::write(path, format!("[{}, {}]", tenant, other)).unwrap();
+        assert!(load_tenants(path, None, "0.0.0.0:8080").is_err());
+        let other = other.replace("b.jsonl", "c.jsonl");
+        std::fs::write(path, format!("[{}, {}]", tenant, other)).unwrap();
+        assert_eq!(load_tenants(path, None, "0.0.0.0:8080").unwrap().len(), 2);
+
+        std::fs::write(
+            path,
+            format!("[{}]", tenant.replace("bridge-b", "Bridge B")),
+        )
+        .unwrap();
+        assert!(load_tenants(path, None, "0.0.0.0:8080").is_err());
+    }
+}
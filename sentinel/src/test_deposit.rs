@@ -0,0 +1,176 @@
+//! Test-network deposit generator
+//!
+//! `sentinel send-test-deposit` shields transparent testnet funds into the
+//! vault with a valid bridge memo, proves the transaction locally and
+//! broadcasts it through lightwalletd. QA and integrators get realistic
+//! deposits from a single funded t-address instead of a full wallet setup.
+
+use crate::config::SentinelConfig;
+use crate::lightwalletd::ClientTls;
+use anyhow::{bail, Context, Result};
+use sentinel_core::memo::MemoPayload;
+use std::path::PathBuf;
+use zcash_client_backend::address::RecipientAddress;
+use zcash_client_backend::proto::service::{ChainSpec, GetAddressUtxosArg, RawTransaction};
+use zcash_primitives::consensus::{BlockHeight, TEST_NETWORK};
+use zcash_primitives::legacy::Script;
+use zcash_primitives::memo::MemoBytes;
+use zcash_primitives::transaction::builder::Builder;
+use zcash_primitives::transaction::components::{Amount, OutPoint, TxOut};
+use zcash_primitives::transaction::fees::fixed::FeeRule;
+use zcash_proofs::prover::LocalTxProver;
+
+/// ZIP 317 marginal fee per logical action, in zatoshi
+const MARGINAL_FEE: u64 = 5_000;
+
+/// ZIP 317 grace actions
+const GRACE_ACTIONS: u64 = 2;
+
+/// Flags for `sentinel send-test-deposit`
+pub struct TestDepositArgs {
+    /// Amount to deposit, in zatoshi
+    pub amount: u64,
+
+    /// Recipient Aztec address (hex)
+    pub aztec_address: String,
+
+    /// Claim secret hash (hex)
+    pub secret_hash: String,
+
+    /// Funded transparent address
+    pub from: String,
+
+    /// Hex secp256k1 key for `from`
+    pub transparent_key: String,
+
+    /// Directory holding the Sapling proving parameters
+    pub params_dir: Option<PathBuf>,
+}
+
+/// Entry point for `sentinel send-test-deposit`
+pub async fn send_test_deposit(config: &SentinelConfig, args: TestDepositArgs) -> Result<()> {
+    if config.network != "testnet" {
+        bail!("send-test-deposit only runs against testnet (ZCASH_NETWORK=testnet)");
+    }
+
+    let Some(RecipientAddress::Transparent(from)) = RecipientAddress::decode(&TEST_NETWORK, &args.from)
+    else {
+        bail!("--from must be a testnet transparent address");
+    };
+    // Test deposits are built for Sapling, also when the vault has a unified address
+    let Some(vault) = RecipientAddress::decode(&TEST_NETWORK, &config.vault_address).and_then(|address| match address {
+        RecipientAddress::Shielded(vault) => Some(vault),
+        RecipientAddress::Unified(ua) => ua.sapling().cloned(),
+        RecipientAddress::Transparent(_) => None,
+    }) else {
+        bail!("VAULT_ADDRESS must be a testnet Sapling address or a unified address with a Sapling receiver");
+    };
+    let key_bytes = hex::decode(args.transparent_key.trim_start_matches("0x"))?;
+    let key = secp256k1::SecretKey::from_slice(&key_bytes).context("Invalid transparent key")?;
+
+    let memo = serde_json::to_string(&MemoPayload {
+        msg_type: "bridge_deposit".to_string(),
+        aztec_address: format!("0x{}", hex32(&args.aztec_address)?),
+        secret_hash: format!("0x{}", hex32(&args.secret_hash)?),
+        version: 1,
+        refund_address: None,
+        amount: Some(args.amount),
+        target: None,
+        domain: config.memo_domain.clone(),
+    })?;
+    let memo = MemoBytes::from_bytes(memo.as_bytes()).map_err(|_| anyhow::anyhow!("Memo too large"))?;
+
+    let mut client = crate::lightwalletd::connect(&config.lightwalletd_url, &ClientTls::from_config(config)).await?;
+    let tip = client.get_latest_block(ChainSpec {}).await?.into_inner().height;
+    let target_height = BlockHeight::from_u32(tip as u32 + 1);
+
+    // Spend every UTXO of the funding address; change goes back to it
+    let utxos = client
+        .get_address_utxos(GetAddressUtxosArg {
+            addresses: vec![args.from.clone()],
+            start_height: 0,
+            max_entries: 0,
+        })
+        .await?
+        .into_inner()
+        .address_utxos;
+    if utxos.is_empty() {
+        bail!("{} has no UTXOs", args.from);
+    }
+
+    // Transparent inputs plus the Sapling bundle, which the builder pads to two outputs
+    let logical_actions = utxos.len() as u64 + 2;
+    let fee = MARGINAL_FEE * logical_actions.max(GRACE_ACTIONS);
+    let available: u64 = utxos.iter().map(|u| u.value_zat as u64).sum();
+    let Some(change) = available.checked_sub(args.amount + fee) else {
+        bail!(
+            "{} holds {} zatoshi, need {} plus a {} zatoshi fee",
+            args.from, available, args.amount, fee
+        );
+    };
+
+    let mut builder = Builder::new(TEST_NETWORK, target_height);
+    for utxo in &utxos {
+        let txid: [u8; 32] = utxo
+            .txid
+            .clone()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Malformed UTXO txid"))?;
+        let coin = TxOut {
+            value: amount(utxo.value_zat as u64)?,
+            script_pubkey: Script(utxo.script.clone()),
+        };
+        builder
+            .add_transparent_input(key, OutPoint::new(txid, utxo.index as u32), coin)
+            .map_err(|e| anyhow::anyhow!("Cannot spend UTXO: {:?}", e))?;
+    }
+    builder
+        .add_sapling_output(None, vault, amount(args.amount)?, memo)
+        .map_err(|e| anyhow::anyhow!("Cannot add vault output: {:?}", e))?;
+    if change > 0 {
+        builder
+            .add_transparent_output(&from, amount(change)?)
+            .map_err(|e| anyhow::anyhow!("Cannot add change output: {:?}", e))?;
+    }
+
+    let prover = match &args.params_dir {
+        Some(dir) => LocalTxProver::new(&dir.join("sapling-spend.params"), &dir.join("sapling-output.params")),
+        None => LocalTxProver::with_default_location()
+            .context("Sapling parameters not found; pass --params-dir")?,
+    };
+    let (tx, _) = builder
+        .build(&prover, &FeeRule::non_standard(amount(fee)?))
+        .map_err(|e| anyhow::anyhow!("Failed to build transaction: {:?}", e))?;
+
+    let mut data = Vec::new();
+    tx.write(&mut data)?;
+    let response = client
+        .send_transaction(RawTransaction { data, height: 0 })
+        .await?
+        .into_inner();
+    if response.error_code != 0 {
+        bail!("Broadcast rejected ({}): {}", response.error_code, response.error_message);
+    }
+
+    println!(
+        "Sent {} zatoshi to the vault in {} (fee {} zatoshi)",
+        args.amount,
+        tx.txid(),
+        fee
+    );
+    Ok(())
+}
+
+/// Zatoshi value as a consensus amount
+fn amount(zatoshi: u64) -> Result<Amount> {
+    Amount::from_u64(zatoshi).map_err(|_| anyhow::anyhow!("Amount out of range: {}", zatoshi))
+}
+
+/// Validate a 32-byte hex value and normalise it without a prefix
+fn hex32(value: &str) -> Result<String> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))?;
+    if bytes.len() != 32 {
+        bail!("Expected 32 bytes of hex, got {}", bytes.len());
+    }
+    Ok(hex::encode(bytes))
+}
@@ -0,0 +1,74 @@
+//! End-to-end deposit tracing
+//!
+//! `sentinel trace <zcash-txid>` prints everything the sentinel knows about a
+//! deposit across both chains: the Zcash note, the L1 dispatch transaction and
+//! L1→L2 message hash, the Aztec claim, and every status change in between.
+
+use crate::config::SentinelConfig;
+use crate::store::{DepositRecord, DepositStore};
+use anyhow::{bail, Result};
+
+/// Entry point for `sentinel trace`
+pub fn trace_command(config: &SentinelConfig, tx_hash: &str, json: bool) -> Result<()> {
+    let store = DepositStore::open(config.data_path("deposits.json"))?;
+    let tx_hash = tx_hash.strip_prefix("0x").unwrap_or(tx_hash);
+
+    let Some(record) = store.resolve(tx_hash) else {
+        let notes = store.for_tx(tx_hash);
+        if notes.len() > 1 {
+            let keys: Vec<_> = notes.iter().map(|r| r.key()).collect();
+            bail!(
+                "Zcash txid {} holds several deposits; trace one of {}",
+                tx_hash,
+                keys.join(", ")
+            );
+        }
+        bail!("No deposit with Zcash txid {} in the store", tx_hash);
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&record)?);
+    } else {
+        print!("{}", render(&record));
+    }
+    Ok(())
+}
+
+/// Human-readable journey of a deposit
+fn render(record: &DepositRecord) -> String {
+    let or_pending = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    let mut out = String::new();
+
+    out.push_str(&format!("Deposit {}\n", record.tx_hash));
+    out.push_str(&format!("  status:          {:?}\n", record.status));
+    if let Some(reason) = &record.reason {
+        out.push_str(&format!("  reason:          {}\n", reason));
+    }
+
+    out.push_str("Zcash\n");
+    out.push_str(&format!("  output index:    {}\n", record.output_index));
+    out.push_str(&format!("  amount:          {} zatoshi\n", record.amount));
+    out.push_str(&format!("  block height:    {}\n", record.block_height));
+    out.push_str(&format!("  block hash:      {}\n", record.block_hash));
+    out.push_str(&format!("  refund address:  {}\n", or_pending(&record.refund_address)));
+
+    out.push_str("L1\n");
+    out.push_str(&format!("  dispatch tx:     {}\n", or_pending(&record.l1_tx_hash)));
+    out.push_str(&format!("  message hash:    {}\n", or_pending(&record.message_hash)));
+
+    out.push_str("Aztec\n");
+    out.push_str(&format!("  recipient:       {}\n", record.aztec_address));
+    out.push_str(&format!("  secret hash:     {}\n", record.secret_hash));
+    out.push_str(&format!("  claim tx:        {}\n", or_pending(&record.claim_tx_hash)));
+
+    out.push_str("History\n");
+    for change in &record.history {
+        out.push_str(&format!("  {:>10}  {:?}", change.at, change.status));
+        if let Some(reason) = &change.reason {
+            out.push_str(&format!(" ({})", reason));
+        }
+        out.push('\n');
+    }
+
+    out
+}
@@ -0,0 +1,182 @@
+//! Shared Rust/Solidity payload test vectors
+//!
+//! `sentinel test-vectors` emits fixed payloads with their payload hash, the
+//! EIP-712 digest the ServiceManager computes and the sentinel signs, and a
+//! signature from a well-known test key. The Foundry suite reads the committed copy in
+//! `contracts/l1/test/vectors/payloads.json`, so an encoding change on either
+//! side fails a test instead of an attestation on L1.
+
+use crate::signer::{domain_separator, eip712_digest, payload_hash, PAYLOAD_VERSION};
+use crate::BridgePayload;
+use anyhow::Result;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, H256};
+use ethers::utils::hash_message;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Anvil dev account #0; never holds funds outside local chains
+const TEST_VECTOR_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Default chain id (anvil and Foundry tests)
+pub const DEFAULT_CHAIN_ID: u64 = 31337;
+
+/// Default verifying contract (first contract deployed by anvil account #0)
+pub const DEFAULT_VERIFYING_CONTRACT: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+
+/// The payloads covered, with their nonces
+fn cases() -> Vec<(&'static str, BridgePayload, u64)> {
+    let payload = |tx_hash, output_index, amount, fee, secret_hash, aztec_address, block_height, block_hash| BridgePayload {
+        tx_hash,
+        output_index,
+        amount,
+        secret_hash,
+        aztec_address,
+        block_height,
+        block_hash,
+        refund_address: None,
+        fee,
+        memo_amount: None,
+        vault_shard: crate::shards::PRIMARY_SHARD.to_string(),
+        target: None,
+    };
+    let bytes32 = |hex_str: &str| -> [u8; 32] { hex::decode(hex_str).unwrap().try_into().unwrap() };
+
+    vec![
+        (
+            "basic",
+            payload([0x11; 32], 0, 100_000_000, 0, [0x22; 32], [0x33; 32], 2_000_000, [0x44; 32]),
+            1,
+        ),
+        (
+            "with_fee",
+            payload(
+                bytes32("9a3c5e7f10b2d4f6a8c0e2f4b6d8f0a2c4e6f8a0b2c4d6e8f0a1b3c5d7e9f1a3"),
+                sentinel_core::payload::ORCHARD_OUTPUT_OFFSET | 2,
+                5_000_000,
+                25_000,
+                bytes32("0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0"),
+                bytes32("00a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f"),
+                2_500_123,
+                bytes32("0000000001d4a6c3e5f70819a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5"),
+            ),
+            42,
+        ),
+        (
+            "max_fields",
+            payload([0xff; 32], u32::MAX, u64::MAX, 1, [0xff; 32], [0xff; 32], u32::MAX, [0xff; 32]),
+            u64::MAX,
+        ),
+    ]
+}
+
+/// Build the vector document; integers are decimal strings so no JSON
+/// parser rounds them
+pub fn generate(chain_id: u64, verifying_contract: Address) -> Result<Value> {
+    let wallet: LocalWallet = TEST_VECTOR_KEY.parse()?;
+
+    let mut vectors = Vec::new();
+    for (name, payload, nonce) in cases() {
+        let hash = payload_hash(&payload, nonce);
+        let digest = eip712_digest(&payload, nonce, chain_id, verifying_contract);
+        // Signed exactly as the signer does: EIP-191 over the EIP-712 digest
+        let signature = wallet.sign_hash(hash_message(digest))?;
+        vectors.push(json!({
+            "name": name,
+            "version": PAYLOAD_VERSION.to_string(),
+            "tx_hash": format!("0x{}", hex::encode(payload.tx_hash)),
+            "output_index": payload.output_index.to_string(),
+            "amount": payload.amount.to_string(),
+            "fee": payload.fee.to_string(),
+            "net_amount": payload.net_amount().to_string(),
+            "secret_hash": format!("0x{}", hex::encode(payload.secret_hash)),
+            "aztec_address": format!("0x{}", hex::encode(payload.aztec_address)),
+            "nonce": nonce.to_string(),
+            "block_height": payload.block_height.to_string(),
+            "block_hash": format!("0x{}", hex::encode(payload.block_hash)),
+            "payload_hash": format!("0x{}", hex::encode(hash)),
+            "eip712_digest": format!("0x{}", hex::encode(digest)),
+            "signature": format!("0x{}", hex::encode(signature.to_vec())),
+        }));
+    }
+
+    Ok(json!({
+        "chain_id": chain_id.to_string(),
+        "verifying_contract": format!("{:?}", verifying_contract),
+        "domain_separator": format!("{:?}", H256::from(domain_separator(chain_id, verifying_contract))),
+        "signer": format!("{:?}", wallet.address()),
+        "vectors": vectors,
+    }))
+}
+
+/// Entry point for `sentinel test-vectors`
+pub fn vectors_command(output: Option<&Path>, chain_id: u64, verifying_contract: Address) -> Result<()> {
+    let document = serde_json::to_string_pretty(&generate(chain_id, verifying_contract)?)?;
+    match output {
+        Some(path) => std::fs::write(path, document + "\n")?,
+        None => println!("{}", document),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{RecoveryMessage, Signature};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_committed_vectors_are_current() {
+        let committed: Value =
+            serde_json::from_str(include_str!("../../contracts/l1/test/vectors/payloads.json")).unwrap();
+        let generated = generate(DEFAULT_CHAIN_ID, DEFAULT_VERIFYING_CONTRACT.parse().unwrap()).unwrap();
+
+        for key in ["chain_id", "verifying_contract", "domain_separator", "signer"] {
+            assert_eq!(committed[key], generated[key], "{}", key);
+        }
+
+        let signer: Address = generated["signer"].as_str().unwrap().parse().unwrap();
+        let generated = generated["vectors"].as_array().unwrap();
+        let committed = committed["vectors"].as_array().unwrap();
+        assert_eq!(committed.len(), generated.len());
+
+        for (committed, generated) in committed.iter().zip(generated) {
+            let mut expected = generated.clone();
+            expected["signature"] = committed["signature"].clone();
+            assert_eq!(committed, &expected, "regenerate with `sentinel test-vectors`");
+
+            // Any valid signature of the EIP-712 digest will do
+            let hash: H256 = committed["eip712_digest"].as_str().unwrap().parse().unwrap();
+            let signature = Signature::from_str(committed["signature"].as_str().unwrap()).unwrap();
+            let recovered = signature
+                .recover(RecoveryMessage::Data(hash.as_bytes().to_vec()))
+                .unwrap();
+            assert_eq!(recovered, signer);
+        }
+    }
+
+    #[test]
+    fn test_contract_payload_layout_matches() {
+        use sentinel_core::payload::DEPOSIT_PAYLOAD_TYPE;
+
+        let manager = include_str!("../../contracts/l1/src/ServiceManager.sol");
+        assert!(manager.contains(DEPOSIT_PAYLOAD_TYPE), "DEPOSIT_PAYLOAD_TYPEHASH differs");
+
+        // The struct declares the fields in the order the type string does
+        let interface = include_str!("../../contracts/l1/src/interfaces/IServiceManager.sol");
+        let body = interface.split("struct DepositPayload {").nth(1).unwrap();
+        let body = &body[..body.find('}').unwrap()];
+        let declared: Vec<String> = body
+            .lines()
+            .filter_map(|line| line.split("//").next().unwrap().trim().strip_suffix(';'))
+            .map(|field| field.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        let expected: Vec<&str> = DEPOSIT_PAYLOAD_TYPE
+            .strip_prefix("DepositPayload(")
+            .and_then(|f| f.strip_suffix(')'))
+            .unwrap()
+            .split(',')
+            .collect();
+        assert_eq!(declared, expected);
+    }
+}
@@ -0,0 +1,211 @@
+//! Offline attestation verification
+//!
+//! `sentinel verify-attestation` recomputes the EIP-712 digest exactly as the
+//! signer and the ServiceManager do and checks which address the signature
+//! recovers to. When the contract rejects an attestation this separates a
+//! payload mismatch from a wrong key, a wrong domain or a missing EIP-191
+//! prefix without touching L1.
+
+use crate::shards::PRIMARY_SHARD;
+use crate::signer::{eip712_digest, payload_hash};
+use crate::BridgePayload;
+use anyhow::{bail, Context, Result};
+use ethers::types::{Address, RecoveryMessage, Signature};
+use ethers::utils::hash_message;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Flags for `sentinel verify-attestation`
+pub struct VerifyAttestationArgs {
+    /// JSON payload file (`-` for stdin), e.g. the output of `sentinel trace --json`
+    pub payload: Option<PathBuf>,
+
+    /// Payload fields given as flags, overriding the JSON
+    pub fields: PayloadFields,
+
+    /// Attestation nonce
+    pub nonce: Option<u64>,
+
+    /// Signature (hex, 65 bytes)
+    pub signature: String,
+
+    /// Operator expected to have signed
+    pub operator: Address,
+
+    /// Chain id of the EIP-712 domain
+    pub chain_id: u64,
+
+    /// ServiceManager address of the EIP-712 domain
+    pub verifying_contract: Address,
+}
+
+/// Attested payload fields; hex values may carry a `0x` prefix
+#[derive(Debug, Default, Deserialize)]
+pub struct PayloadFields {
+    /// Zcash transaction hash
+    pub tx_hash: Option<String>,
+
+    /// Index of the vault note in the transaction; 0 if unset
+    pub output_index: Option<u32>,
+
+    /// Note value in zatoshi, before the fee
+    pub amount: Option<u64>,
+
+    /// Bridge fee in zatoshi
+    pub fee: Option<u64>,
+
+    /// Claim secret hash
+    pub secret_hash: Option<String>,
+
+    /// Recipient Aztec address
+    pub aztec_address: Option<String>,
+
+    /// Zcash block height
+    pub block_height: Option<u32>,
+
+    /// Hash of the Zcash block at `block_height`
+    pub block_hash: Option<String>,
+
+    /// Attestation nonce
+    pub nonce: Option<u64>,
+}
+
+impl PayloadFields {
+    /// Fill unset fields from `other`
+    fn or(self, other: PayloadFields) -> PayloadFields {
+        PayloadFields {
+            tx_hash: self.tx_hash.or(other.tx_hash),
+            output_index: self.output_index.or(other.output_index),
+            amount: self.amount.or(other.amount),
+            fee: self.fee.or(other.fee),
+            secret_hash: self.secret_hash.or(other.secret_hash),
+            aztec_address: self.aztec_address.or(other.aztec_address),
+            block_height: self.block_height.or(other.block_height),
+            block_hash: self.block_hash.or(other.block_hash),
+            nonce: self.nonce.or(other.nonce),
+        }
+    }
+
+    /// Build the payload the signer would have seen
+    fn into_payload(self) -> Result<BridgePayload> {
+        fn bytes32(name: &str, value: Option<String>) -> Result<[u8; 32]> {
+            let value = value.with_context(|| format!("Missing {}", name))?;
+            hex::decode(value.trim_start_matches("0x"))
+                .with_context(|| format!("Invalid {}", name))?
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("{} must be 32 bytes", name))
+        }
+
+        Ok(BridgePayload {
+            tx_hash: bytes32("tx_hash", self.tx_hash)?,
+            output_index: self.output_index.unwrap_or_default(),
+            amount: self.amount.context("Missing amount")?,
+            secret_hash: bytes32("secret_hash", self.secret_hash)?,
+            aztec_address: bytes32("aztec_address", self.aztec_address)?,
+            block_height: self.block_height.context("Missing block_height")?,
+            block_hash: bytes32("block_hash", self.block_hash)?,
+            refund_address: None,
+            fee: self.fee.unwrap_or_default(),
+            memo_amount: None,
+            vault_shard: PRIMARY_SHARD.to_string(),
+            target: None,
+        })
+    }
+}
+
+/// Entry point for `sentinel verify-attestation`
+pub fn verify_attestation_command(args: VerifyAttestationArgs) -> Result<()> {
+    let from_json = match &args.payload {
+        Some(path) => {
+            let bytes = if path.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())?.into_bytes()
+            } else {
+                std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?
+            };
+            serde_json::from_slice(&bytes).context("Malformed payload JSON")?
+        }
+        None => PayloadFields::default(),
+    };
+    let fields = args.fields.or(from_json);
+    let nonce = args.nonce.or(fields.nonce).context("Missing nonce")?;
+    let payload = fields.into_payload()?;
+
+    let signature = Signature::from_str(args.signature.trim_start_matches("0x"))
+        .context("Invalid signature")?;
+    let message_hash = eip712_digest(&payload, nonce, args.chain_id, args.verifying_contract);
+
+    println!("Payload hash:      0x{}", hex::encode(payload_hash(&payload, nonce)));
+    println!("EIP-712 digest:    0x{}", hex::encode(message_hash));
+    println!("EIP-191 digest:    {:?}", hash_message(message_hash));
+    println!("  net amount:      {} zatoshi (fee {})", payload.net_amount(), payload.fee);
+    println!("  nonce:           {}", nonce);
+
+    // The signer prefixes the hash as an EIP-191 personal message
+    let recovered = signature
+        .recover(RecoveryMessage::Data(message_hash.to_vec()))
+        .context("Signature does not recover")?;
+    println!("Recovered:         {:?}", recovered);
+    println!("Expected:          {:?}", args.operator);
+
+    if recovered == args.operator {
+        println!("OK: signature matches the operator");
+        return Ok(());
+    }
+
+    // Signed without the prefix, e.g. by a tool calling sign_hash directly
+    if signature.recover(RecoveryMessage::Hash(message_hash.into())).ok() == Some(args.operator) {
+        bail!("Signature is over the raw EIP-712 digest, not its EIP-191 hash");
+    }
+    // Signed by a sentinel that predates the EIP-712 domain
+    let plain = payload_hash(&payload, nonce);
+    if signature.recover(RecoveryMessage::Data(plain.to_vec())).ok() == Some(args.operator) {
+        bail!("Signature is over the plain payload hash, not the EIP-712 digest");
+    }
+    bail!("Signature does not match the operator: wrong key, or the payload differs from what was signed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+
+    #[tokio::test]
+    async fn test_recovers_signer_address() {
+        let wallet: LocalWallet = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+            .parse()
+            .unwrap();
+        let fields = || PayloadFields {
+            tx_hash: Some(format!("0x{}", hex::encode([0xab; 32]))),
+            output_index: Some(1),
+            amount: Some(5_000),
+            fee: Some(100),
+            secret_hash: Some(hex::encode([0xcd; 32])),
+            aztec_address: Some(hex::encode([0xef; 32])),
+            block_height: Some(10),
+            block_hash: Some(hex::encode([0x12; 32])),
+            nonce: None,
+        };
+
+        let payload = fields().into_payload().unwrap();
+        let contract = Address::repeat_byte(0x5f);
+        let signature = wallet
+            .sign_message(eip712_digest(&payload, 7, 31337, contract))
+            .await
+            .unwrap();
+        let args = |nonce, chain_id| VerifyAttestationArgs {
+            payload: None,
+            fields: fields(),
+            nonce: Some(nonce),
+            signature: signature.to_string(),
+            operator: wallet.address(),
+            chain_id,
+            verifying_contract: contract,
+        };
+
+        assert!(verify_attestation_command(args(7, 31337)).is_ok());
+        assert!(verify_attestation_command(args(8, 31337)).is_err());
+        // Replayed against another chain
+        assert!(verify_attestation_command(args(7, 1)).is_err());
+    }
+}
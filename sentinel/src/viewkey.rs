@@ -0,0 +1,183 @@
+//! Viewing key sources
+//!
+//! Whoever holds the vault's viewing key can link every bridge deposit to
+//! its depositor, so it need not sit in the environment in plaintext.
+//! `VAULT_VIEWING_KEY_SOURCE` names where to fetch it at startup instead:
+//!
+//! - `keychain:<service>/<account>`: the OS keychain (`security` on macOS,
+//!   the Secret Service via `secret-tool` elsewhere)
+//! - `tpm:<context file>`: a blob sealed to this machine's TPM, opened with
+//!   `tpm2_unseal`
+//! - `kms:<ciphertext file>`: a file encrypted under an AWS KMS key, opened
+//!   with `aws kms decrypt`
+//! - `cmd:<command>`: the output of any command, for other stores
+//!
+//! The key is read straight into zeroizing memory and never written back.
+
+use anyhow::{bail, Context, Result};
+use secrecy::SecretString;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Where the viewing key is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViewingKeySource {
+    /// OS keychain entry
+    Keychain { service: String, account: String },
+    /// TPM-sealed object context
+    Tpm(String),
+    /// KMS-encrypted file
+    Kms(String),
+    /// Shell command printing the key
+    Command(String),
+}
+
+impl FromStr for ViewingKeySource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((kind, rest)) = s.split_once(':') else {
+            bail!("VAULT_VIEWING_KEY_SOURCE must start with keychain:, tpm:, kms: or cmd:");
+        };
+        if rest.is_empty() {
+            bail!("VAULT_VIEWING_KEY_SOURCE {} is missing its location", kind);
+        }
+        match kind {
+            "keychain" => {
+                let Some((service, account)) = rest.split_once('/') else {
+                    bail!("Keychain source must be keychain:<service>/<account>");
+                };
+                Ok(Self::Keychain {
+                    service: service.to_string(),
+                    account: account.to_string(),
+                })
+            }
+            "tpm" => Ok(Self::Tpm(rest.to_string())),
+            "kms" => Ok(Self::Kms(rest.to_string())),
+            "cmd" => Ok(Self::Command(rest.to_string())),
+            _ => bail!("Unknown viewing key source {}", kind),
+        }
+    }
+}
+
+impl ViewingKeySource {
+    /// Fetch and unwrap the key
+    pub fn load(&self) -> Result<SecretString> {
+        let raw = match self {
+            Self::Keychain { service, account } => {
+                if cfg!(target_os = "macos") {
+                    output(Command::new("security").args([
+                        "find-generic-password",
+                        "-s",
+                        service,
+                        "-a",
+                        account,
+                        "-w",
+                    ]))?
+                } else {
+                    output(
+                        Command::new("secret-tool")
+                            .args(["lookup", "service", service, "account", account]),
+                    )?
+                }
+            }
+            Self::Tpm(context) => output(Command::new("tpm2_unseal").args(["-c", context]))?,
+            Self::Kms(path) => {
+                let encoded = output(Command::new("aws").args([
+                    "kms",
+                    "decrypt",
+                    "--ciphertext-blob",
+                    &format!("fileb://{}", path),
+                    "--query",
+                    "Plaintext",
+                    "--output",
+                    "text",
+                ]))?;
+                piped(Command::new("base64").arg("-d"), &encoded)?
+            }
+            Self::Command(command) => output(Command::new("sh").arg("-c").arg(command))?,
+        };
+
+        let key = std::str::from_utf8(&raw).context("Viewing key is not UTF-8")?;
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Viewing key source returned nothing");
+        }
+        Ok(SecretString::new(key.to_string()))
+    }
+}
+
+/// Standard output of `command`, which must succeed
+fn output(command: &mut Command) -> Result<Zeroizing<Vec<u8>>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Cannot run {}", program))?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        bail!("{} failed ({})", program, output.status);
+    }
+    Ok(stdout)
+}
+
+/// Standard output of `command` fed `input` on standard input
+fn piped(command: &mut Command, input: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("Cannot run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        bail!("{} failed ({})", program, output.status);
+    }
+    Ok(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn test_sources_parse_and_load() {
+        assert_eq!(
+            "keychain:sentinel/vault"
+                .parse::<ViewingKeySource>()
+                .unwrap(),
+            ViewingKeySource::Keychain {
+                service: "sentinel".to_string(),
+                account: "vault".to_string()
+            }
+        );
+        assert_eq!(
+            "tpm:/etc/sentinel/vk.ctx"
+                .parse::<ViewingKeySource>()
+                .unwrap(),
+            ViewingKeySource::Tpm("/etc/sentinel/vk.ctx".to_string())
+        );
+        assert!("keychain:sentinel".parse::<ViewingKeySource>().is_err());
+        assert!("file:/tmp/vk".parse::<ViewingKeySource>().is_err());
+        assert!("kms:".parse::<ViewingKeySource>().is_err());
+
+        let source: ViewingKeySource = "cmd:printf 'zivktestsapling1abc\\n'".parse().unwrap();
+        assert_eq!(
+            source.load().unwrap().expose_secret(),
+            "zivktestsapling1abc"
+        );
+        assert!("cmd:false"
+            .parse::<ViewingKeySource>()
+            .unwrap()
+            .load()
+            .is_err());
+    }
+}
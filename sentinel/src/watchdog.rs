@@ -0,0 +1,375 @@
+//! Scanner/L1 consistency watchdog
+//!
+//! Compares what the scanner believes against what the ServiceManager shows
+//! as processed. Every `DepositVerified` event is matched with the deposit
+//! store; attestation is halted when:
+//!
+//! - a dispatch references a Zcash transaction the scanner never observed,
+//!   although it has already scanned past the deposit's block
+//! - a dispatch disagrees with the observed deposit (amount, recipient or
+//!   block height)
+//! - a deposit this sentinel rejected or refunded was dispatched anyway
+//!
+//! Dispatches for blocks the scanner has not reached yet are re-checked once
+//! it has. Dispatches of deposits below the scanner's coverage (the vault
+//! birthday, else the earliest stored deposit) cannot be judged and pass.
+
+use crate::dispatches::{self, DepositVerified};
+use crate::error::SentinelError;
+use crate::halt::HaltSwitch;
+use crate::reputation::decode_dispatch;
+use crate::scanner::ScanProgress;
+use crate::store::{deposit_key, DepositRecord, DepositStatus, DepositStore};
+use ethers::abi::Token;
+use ethers::prelude::*;
+use ethers::types::{Address, H256};
+use futures::StreamExt;
+use sentinel_core::payload::BLOCK_HEIGHT_FIELD;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+/// Halt source name
+const SOURCE: &str = "consistency_watchdog";
+
+/// Default seconds between watchdog passes
+pub const DEFAULT_WATCHDOG_INTERVAL_SECS: u64 = 60;
+
+/// Dispatch transactions fetched concurrently
+const FETCH_CONCURRENCY: usize = 16;
+
+/// A deposit dispatched on L1
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dispatch {
+    /// Zcash transaction hash
+    tx_hash: [u8; 32],
+
+    /// Index of the vault note in the transaction
+    output_index: u32,
+
+    /// Amount minted, in zatoshi
+    amount: U256,
+
+    /// Claim secret hash
+    secret_hash: Vec<u8>,
+
+    /// Aztec recipient
+    aztec_address: Vec<u8>,
+
+    /// Zcash block height from the dispatch calldata, if it could be decoded
+    block_height: Option<u32>,
+
+    /// Passes on which the scanner had passed the block without a record
+    strikes: u8,
+}
+
+/// How a dispatch compares with the scanner's view
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Verdict {
+    /// Matches an observed deposit
+    Consistent,
+    /// The scanner has not reached the deposit's block yet
+    Pending,
+    /// The scanner passed the deposit's block without observing it
+    Unobserved,
+    /// Contradicts the scanner, for the given reason
+    Inconsistent(String),
+}
+
+/// Compare a dispatch with the store record for its txid; the scanner
+/// covers Zcash blocks `covered_from..=synced_height`
+fn assess(
+    dispatch: &Dispatch,
+    record: Option<&DepositRecord>,
+    covered_from: u32,
+    synced_height: u32,
+) -> Verdict {
+    let Some(record) = record else {
+        return match dispatch.block_height {
+            // Deposited before the scanner's coverage, nothing to judge against
+            Some(height) if height < covered_from => Verdict::Consistent,
+            Some(height) if height <= synced_height => Verdict::Unobserved,
+            Some(_) => Verdict::Pending,
+            // Without a height there is nothing to judge against
+            None => Verdict::Consistent,
+        };
+    };
+
+    if matches!(
+        record.status,
+        DepositStatus::Rejected | DepositStatus::RefundQueued | DepositStatus::Refunded
+    ) {
+        return Verdict::Inconsistent(format!(
+            "dispatched although {:?} here ({})",
+            record.status,
+            record.reason.as_deref().unwrap_or("no reason recorded")
+        ));
+    }
+    let payload = match record.to_payload() {
+        Ok(payload) => payload,
+        Err(e) => return Verdict::Inconsistent(format!("stored deposit unreadable: {}", e)),
+    };
+    if dispatch.amount != U256::from(payload.net_amount()) {
+        return Verdict::Inconsistent(format!(
+            "dispatched for {} but observed {} net of fees",
+            dispatch.amount,
+            payload.net_amount()
+        ));
+    }
+    if dispatch.secret_hash != payload.secret_hash
+        || dispatch.aztec_address != payload.aztec_address
+    {
+        return Verdict::Inconsistent("dispatched to a different recipient".to_string());
+    }
+    if let Some(height) = dispatch.block_height {
+        if height != payload.block_height {
+            return Verdict::Inconsistent(format!(
+                "dispatched at height {} but observed at {}",
+                height, payload.block_height
+            ));
+        }
+    }
+    Verdict::Consistent
+}
+
+/// Watches L1 dispatches against the scanner's deposits
+pub struct ConsistencyWatchdog {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Deposit store, the scanner's view
+    store: Arc<DepositStore>,
+
+    /// Scanner progress
+    progress: Arc<ScanProgress>,
+
+    /// Vault birthday height, where the scanner's coverage starts (0 if unset)
+    birthday: u32,
+
+    /// Switch tripped on an inconsistency
+    halt: Arc<HaltSwitch>,
+
+    /// Dispatches waiting for the scanner to reach their block
+    deferred: Vec<Dispatch>,
+
+    /// Next L1 block to query
+    from_block: u64,
+}
+
+impl ConsistencyWatchdog {
+    /// Create a new watchdog; `start_block` is the ServiceManager's
+    /// deployment block
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        start_block: u64,
+        store: Arc<DepositStore>,
+        progress: Arc<ScanProgress>,
+        birthday: u32,
+        halt: Arc<HaltSwitch>,
+    ) -> Self {
+        Self {
+            provider,
+            service_manager_address,
+            store,
+            progress,
+            birthday,
+            halt,
+            deferred: Vec::new(),
+            from_block: start_block,
+        }
+    }
+
+    /// First Zcash block the scanner covers
+    fn covered_from(&self) -> u32 {
+        if self.birthday > 0 {
+            return self.birthday;
+        }
+        self.store
+            .all()
+            .iter()
+            .map(|record| record.block_height)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Check on a schedule forever
+    pub async fn run(mut self, interval: Duration) {
+        loop {
+            match self.poll().await {
+                Ok(Some(reason)) => self.halt.trip(SOURCE, reason),
+                Ok(None) => {}
+                Err(e) => error!("Consistency watchdog failed: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Check new dispatches and deferred ones, returning the first
+    /// inconsistency found
+    pub async fn poll(&mut self) -> Result<Option<String>, SentinelError> {
+        let new = self.new_dispatches().await?;
+        let mut dispatches = std::mem::take(&mut self.deferred);
+        dispatches.extend(new);
+
+        let synced_height = self.progress.synced_height();
+        let covered_from = self.covered_from();
+        let mut inconsistency = None;
+        for mut dispatch in dispatches {
+            let key = deposit_key(&hex::encode(dispatch.tx_hash), dispatch.output_index);
+            let record = self.store.get(&key);
+            let reason = match assess(&dispatch, record.as_ref(), covered_from, synced_height) {
+                Verdict::Consistent => continue,
+                Verdict::Pending => {
+                    self.deferred.push(dispatch);
+                    continue;
+                }
+                // The scanner hands deposits to the store asynchronously,
+                // so give it one more pass before calling it missed
+                Verdict::Unobserved if dispatch.strikes == 0 => {
+                    dispatch.strikes += 1;
+                    self.deferred.push(dispatch);
+                    continue;
+                }
+                Verdict::Unobserved => format!(
+                    "deposit {} dispatched on L1 was never observed by the scanner (synced to {})",
+                    key, synced_height
+                ),
+                Verdict::Inconsistent(reason) => format!("deposit {} {}", key, reason),
+            };
+            warn!("Scanner/L1 inconsistency: {}", reason);
+            inconsistency.get_or_insert(reason);
+        }
+        Ok(inconsistency)
+    }
+
+    /// `DepositVerified` events since the last poll
+    async fn new_dispatches(&mut self) -> Result<Vec<Dispatch>, SentinelError> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        if head < self.from_block {
+            return Ok(Vec::new());
+        }
+
+        let filter = dispatches::filter(self.service_manager_address)
+            .from_block(self.from_block)
+            .to_block(head);
+
+        let logs = self.provider.get_logs(&filter).await?;
+        let heights = self
+            .dispatch_heights(logs.iter().filter_map(|log| log.transaction_hash).collect())
+            .await;
+
+        let mut dispatches = Vec::new();
+        for log in logs {
+            let event = DepositVerified::decode(&log)?;
+            let block_height = log
+                .transaction_hash
+                .and_then(|tx| heights.get(&tx).copied().flatten());
+
+            dispatches.push(Dispatch {
+                tx_hash: event.tx_hash,
+                output_index: event.output_index,
+                amount: event.amount,
+                secret_hash: event.secret_hash.to_vec(),
+                aztec_address: event.aztec_address.to_vec(),
+                block_height,
+                strikes: 0,
+            });
+        }
+
+        debug!(
+            "Watchdog checked L1 blocks {}..={}: {} dispatches",
+            self.from_block,
+            head,
+            dispatches.len()
+        );
+        self.from_block = head + 1;
+        Ok(dispatches)
+    }
+
+    /// Zcash block heights of dispatch transactions, fetched concurrently
+    async fn dispatch_heights(&self, txs: HashSet<H256>) -> HashMap<H256, Option<u32>> {
+        futures::stream::iter(txs)
+            .map(|tx| async move {
+                let height = self.dispatch_height(tx).await.unwrap_or_else(|e| {
+                    warn!("Could not decode block height of dispatch {:?}: {}", tx, e);
+                    None
+                });
+                (tx, height)
+            })
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Zcash block height from a `verifyAndDispatch` transaction's calldata
+    async fn dispatch_height(&self, tx: H256) -> Result<Option<u32>, SentinelError> {
+        let Some(tx) = self.provider.get_transaction(tx).await? else {
+            return Ok(None);
+        };
+        let height = match decode_dispatch(&tx.input)?.into_iter().next() {
+            Some(Token::Tuple(fields)) => fields
+                .get(BLOCK_HEIGHT_FIELD)
+                .cloned()
+                .and_then(Token::into_uint)
+                .map(|h| h.low_u32()),
+            _ => None,
+        };
+        Ok(height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BridgePayload;
+
+    #[test]
+    fn test_dispatches_checked_against_scanner() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DepositStore::open(dir.path().join("deposits.json")).unwrap();
+        let payload = BridgePayload::sample();
+        let record = store.insert_detected(&payload).unwrap();
+
+        let dispatch = Dispatch {
+            tx_hash: [1; 32],
+            output_index: 0,
+            amount: U256::from(100_000),
+            secret_hash: vec![2; 32],
+            aztec_address: vec![3; 32],
+            block_height: Some(100),
+            strikes: 0,
+        };
+        assert_eq!(assess(&dispatch, Some(&record), 0, 200), Verdict::Consistent);
+        assert_eq!(assess(&dispatch, None, 0, 99), Verdict::Pending);
+        assert_eq!(assess(&dispatch, None, 0, 100), Verdict::Unobserved);
+        assert_eq!(assess(&dispatch, None, 101, 200), Verdict::Consistent);
+
+        let other_height = Dispatch {
+            block_height: Some(101),
+            ..dispatch.clone()
+        };
+        assert!(matches!(
+            assess(&other_height, Some(&record), 0, 200),
+            Verdict::Inconsistent(_)
+        ));
+        let other_amount = Dispatch {
+            amount: U256::from(1),
+            ..dispatch.clone()
+        };
+        assert!(matches!(
+            assess(&other_amount, Some(&record), 0, 200),
+            Verdict::Inconsistent(_)
+        ));
+
+        let rejected = store.reject(&record.key(), "too small").unwrap();
+        assert!(matches!(
+            assess(&dispatch, Some(&rejected), 0, 200),
+            Verdict::Inconsistent(_)
+        ));
+    }
+}
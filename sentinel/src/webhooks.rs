@@ -0,0 +1,220 @@
+//! Outbound webhooks
+//!
+//! Posts each deposit lifecycle event as JSON to the configured URLs so bridge
+//! front-ends can tell users the moment their deposit is attested. Requests
+//! carry:
+//!
+//! - `X-Sentinel-Event`: the event kind
+//! - `X-Sentinel-Timestamp`: Unix seconds at sending
+//! - `X-Sentinel-Signature`: `sha256=<hex>`, the HMAC-SHA256 of
+//!   `<timestamp>.<body>` under `WEBHOOK_SECRET`, if one is set
+//!
+//! Network errors, 429 and 5xx responses are retried under the shared retry
+//! policy, with `WEBHOOK_MAX_RETRIES` retries. Deliveries that still fail, or that the receiver refuses, are
+//! appended to a dead-letter file for inspection and replay.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::events::{BridgeEvent, EventSink};
+use crate::retry::RetryPolicy;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use sha2::Sha256;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Default retries after a failed delivery
+pub const DEFAULT_WEBHOOK_MAX_RETRIES: u32 = 5;
+
+/// Longest a single delivery attempt may take
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `X-Sentinel-Signature` value for `message`
+pub fn signature(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(message);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Webhook endpoint receiving every event
+pub struct WebhookSink {
+    /// Endpoint URL
+    url: String,
+
+    /// HMAC signing secret
+    secret: Option<SecretString>,
+
+    /// Retries of failed deliveries
+    retry: RetryPolicy,
+
+    /// File failed deliveries are appended to
+    dead_letter_path: PathBuf,
+
+    /// HTTP client
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    /// Create a sink posting to `url`
+    pub fn new(
+        url: String,
+        secret: Option<SecretString>,
+        retry: RetryPolicy,
+        dead_letter_path: PathBuf,
+    ) -> Result<Self, SentinelError> {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .map_err(SentinelError::network)?;
+        Ok(Self {
+            url,
+            secret,
+            retry,
+            dead_letter_path,
+            client,
+        })
+    }
+
+    /// One delivery attempt
+    ///
+    /// Refusals by the receiver are [`SentinelError::InvalidPayload`], which
+    /// is not retried; transport errors, 429 and 5xx are retryable.
+    async fn attempt(&self, event: &BridgeEvent, body: &str) -> Result<(), SentinelError> {
+        let timestamp = now_secs().to_string();
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Sentinel-Event", event.kind.as_str())
+            .header("X-Sentinel-Timestamp", &timestamp);
+        if let Some(secret) = &self.secret {
+            let message = format!("{}.{}", timestamp, body);
+            request = request.header(
+                "X-Sentinel-Signature",
+                signature(secret.expose_secret(), message.as_bytes()),
+            );
+        }
+
+        let response = request
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(SentinelError::network)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        if status.is_server_error() || status.as_u16() == 429 {
+            Err(SentinelError::Network(format!("HTTP {}", status)))
+        } else {
+            Err(SentinelError::InvalidPayload(format!("HTTP {}", status)))
+        }
+    }
+
+    /// Record an undeliverable event
+    fn dead_letter(
+        &self,
+        event: &BridgeEvent,
+        attempts: u32,
+        error: &str,
+    ) -> Result<(), SentinelError> {
+        if let Some(dir) = self.dead_letter_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| SentinelError::Storage(e.to_string()))?;
+        }
+        let line = json!({
+            "url": self.url,
+            "event": event,
+            "attempts": attempts,
+            "error": error,
+            "at": now_secs(),
+        });
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dead_letter_path)
+            .map_err(|e| SentinelError::Storage(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn publish(&self, event: &BridgeEvent) -> Result<(), SentinelError> {
+        let body = serde_json::to_string(event)
+            .map_err(|e| SentinelError::InvalidPayload(e.to_string()))?;
+
+        let attempts = AtomicU32::new(0);
+        let what = format!("webhook to {}", self.url);
+        let error = match self
+            .retry
+            .run(&what, || {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                self.attempt(event, &body)
+            })
+            .await
+        {
+            Ok(()) => {
+                debug!("Delivered {} webhook to {}", event.kind.as_str(), self.url);
+                return Ok(());
+            }
+            Err(e) => e.to_string(),
+        };
+
+        let attempts = attempts.into_inner();
+        warn!("Webhook to {} failed for good: {}", self.url, error);
+        self.dead_letter(event, attempts, &error)?;
+        Err(SentinelError::Network(format!(
+            "webhook gave up after {} attempts ({}), dead-lettered",
+            attempts, error
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    #[tokio::test]
+    async fn test_webhook_signs_and_dead_letters() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // Nothing listens on a port just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letters = dir.path().join("webhook_dead_letter.jsonl");
+        let sink = WebhookSink::new(
+            format!("http://{}/hook", address),
+            Some(SecretString::new("secret".to_string())),
+            RetryPolicy::new(2, Duration::from_millis(1)),
+            dead_letters.clone(),
+        )
+        .unwrap();
+
+        let payload = crate::BridgePayload::sample();
+        let event = BridgeEvent::new(EventKind::AttestationFinalized, &payload);
+        assert!(sink.publish(&event).await.is_err());
+
+        let contents = std::fs::read_to_string(&dead_letters).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry["attempts"], 3);
+        assert_eq!(entry["event"]["kind"], "attestation_finalized");
+    }
+}
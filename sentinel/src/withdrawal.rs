@@ -0,0 +1,353 @@
+//! Withdrawal direction: L1 burn events to ZEC release instructions
+//!
+//! Watches the ServiceManager for `WithdrawalProcessed` events (zZEC burned on
+//! Aztec and exited through L1), waits for L1 finality, validates each event
+//! and turns it into a release instruction for the Zcash vault. Instructions
+//! are appended as JSON lines to a release queue consumed by the payout side,
+//! after passing the payout policy when one is configured.
+//!
+//! The next L1 block to query and the withdrawals already queued are kept in
+//! a state file next to the release queue, so a restart neither rescans L1
+//! from the deployment block nor queues a release twice. L1 is queried in
+//! ranges of at most [`MAX_LOG_RANGE`] blocks, which public RPC endpoints
+//! accept.
+
+use crate::clock::now_secs;
+use crate::error::SentinelError;
+use crate::payout::PayoutPolicyEngine;
+use crate::shards::{self, ShardSet};
+use ethers::abi::{decode, ParamType};
+use ethers::prelude::*;
+use ethers::types::{Address, H256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+/// Default number of L1 blocks before a withdrawal event is considered final
+pub const DEFAULT_L1_FINALITY_DEPTH: u64 = 64;
+
+/// Most L1 blocks covered by one `eth_getLogs` query
+pub const MAX_LOG_RANGE: u64 = 2_000;
+
+/// Instruction to release ZEC from the vault
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseInstruction {
+    /// L1→L2 message hash identifying the withdrawal (hex)
+    pub message_hash: String,
+
+    /// Amount to release in zatoshi
+    pub amount: u64,
+
+    /// Destination Zcash shielded address commitment (hex)
+    pub zcash_address: String,
+
+    /// L1 block the withdrawal was processed in
+    pub l1_block: u64,
+
+    /// L1 transaction that processed the withdrawal (hex)
+    pub l1_tx_hash: String,
+
+    /// Vault shard to pay the release from (primary if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_shard: Option<String>,
+
+    /// Unix time the payout policy queued the release, if one is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queued_at: Option<u64>,
+}
+
+/// Watcher progress, persisted next to the release queue
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WatcherState {
+    /// Next L1 block to query
+    from_block: u64,
+
+    /// Message hashes already turned into instructions
+    seen: HashSet<H256>,
+}
+
+/// State file of the watcher feeding `queue_path`
+fn state_path(queue_path: &Path) -> PathBuf {
+    let mut name = queue_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".watcher.json");
+    queue_path.with_file_name(name)
+}
+
+/// Watches L1 for finalized withdrawals
+pub struct WithdrawalWatcher {
+    /// Provider for L1 interaction
+    provider: Arc<Provider<Http>>,
+
+    /// ServiceManager contract address
+    service_manager_address: Address,
+
+    /// Blocks required on top of an event before acting on it
+    finality_depth: u64,
+
+    /// Cursor and queued withdrawals
+    state: WatcherState,
+
+    /// Where `state` is persisted
+    state_path: PathBuf,
+
+    /// Append-only release queue file
+    queue_path: PathBuf,
+
+    /// Vault shards, to pick which one pays each release
+    shards: Arc<ShardSet>,
+
+    /// Policy each release must pass before it is queued
+    policy: Option<Arc<PayoutPolicyEngine>>,
+}
+
+impl WithdrawalWatcher {
+    /// Create a watcher resuming from its state file, or from `start_block`
+    /// (the ServiceManager's deployment block) on a first run
+    pub fn new(
+        provider: Arc<Provider<Http>>,
+        service_manager_address: Address,
+        finality_depth: u64,
+        start_block: u64,
+        queue_path: PathBuf,
+        shards: Arc<ShardSet>,
+    ) -> Result<Self, SentinelError> {
+        let state_path = state_path(&queue_path);
+        let mut state: WatcherState = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                SentinelError::Storage(format!("{}: {}", state_path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WatcherState::default(),
+            Err(e) => return Err(SentinelError::Storage(e.to_string())),
+        };
+        state.from_block = state.from_block.max(start_block);
+
+        Ok(Self {
+            provider,
+            service_manager_address,
+            finality_depth,
+            state,
+            state_path,
+            queue_path,
+            shards,
+            policy: None,
+        })
+    }
+
+    /// Queue releases only once `policy` allows them
+    pub fn with_policy(mut self, policy: Arc<PayoutPolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Poll for finalized withdrawals forever
+    pub async fn run(mut self, poll_interval: Duration) {
+        loop {
+            match self.poll().await {
+                Ok(0) => {}
+                Ok(count) => info!("Queued {} release instructions", count),
+                Err(e) => warn!("Withdrawal watcher error: {}", e),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Process finalized withdrawal events and queue release instructions
+    pub async fn poll(&mut self) -> Result<usize, SentinelError> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        let finalized = head.saturating_sub(self.finality_depth);
+        let mut queued = 0;
+
+        while self.state.from_block <= finalized {
+            let from_block = self.state.from_block;
+            let to_block = finalized.min(from_block + MAX_LOG_RANGE - 1);
+            queued += self.process_range(from_block, to_block).await?;
+
+            debug!("Scanned L1 blocks {}..={} for withdrawals", from_block, to_block);
+            self.state.from_block = to_block + 1;
+            self.persist()?;
+        }
+        Ok(queued)
+    }
+
+    /// Queue the withdrawals processed in L1 blocks `from_block..=to_block`
+    ///
+    /// A withdrawal counts as seen only once its instruction is queued, so
+    /// one that failed is retried with the range on the next poll.
+    async fn process_range(
+        &mut self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<usize, SentinelError> {
+        let topic = H256::from(keccak256(b"WithdrawalProcessed(bytes32,uint256,bytes32)"));
+        let filter = Filter::new()
+            .address(self.service_manager_address)
+            .topic0(topic)
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let logs = self.provider.get_logs(&filter).await?;
+        let mut queued = 0;
+
+        for log in logs {
+            match self.to_instruction(&log) {
+                Ok(Some((message_hash, mut instruction))) => {
+                    instruction.vault_shard = self.release_shard(instruction.amount);
+                    let released = match &self.policy {
+                        Some(policy) => policy.submit(instruction, now_secs()).await?,
+                        None => {
+                            append_release(&self.queue_path, &instruction).await?;
+                            true
+                        }
+                    };
+                    self.state.seen.insert(message_hash);
+                    self.persist()?;
+                    if released {
+                        queued += 1;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Skipping invalid withdrawal event: {}", e),
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Replace the state file in one rename
+    fn persist(&self) -> Result<(), SentinelError> {
+        let tmp = self.state_path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.state)?)
+            .and_then(|_| std::fs::rename(&tmp, &self.state_path))
+            .map_err(|e| SentinelError::Storage(e.to_string()))
+    }
+
+    /// Validate a log and convert it into a release instruction for its
+    /// message hash; `None` if it is removed or already queued
+    fn to_instruction(
+        &self,
+        log: &Log,
+    ) -> Result<Option<(H256, ReleaseInstruction)>, SentinelError> {
+        if log.removed == Some(true) {
+            return Ok(None);
+        }
+
+        let message_hash = *log
+            .topics
+            .get(1)
+            .ok_or_else(|| SentinelError::InvalidPayload("Missing message hash topic".to_string()))?;
+
+        if self.state.seen.contains(&message_hash) {
+            return Ok(None);
+        }
+
+        let tokens = decode(&[ParamType::Uint(256), ParamType::FixedBytes(32)], &log.data)
+            .map_err(|e| SentinelError::InvalidPayload(e.to_string()))?;
+
+        let amount = tokens[0].clone().into_uint().unwrap_or_default();
+        let zcash_address = tokens[1].clone().into_fixed_bytes().unwrap_or_default();
+
+        if amount.is_zero() || amount > U256::from(u64::MAX) {
+            return Err(SentinelError::InvalidPayload(format!(
+                "Withdrawal amount out of range: {}",
+                amount
+            )));
+        }
+        if zcash_address.iter().all(|b| *b == 0) {
+            return Err(SentinelError::InvalidPayload(
+                "Withdrawal has empty Zcash address".to_string(),
+            ));
+        }
+
+        let instruction = ReleaseInstruction {
+            message_hash: format!("0x{}", hex::encode(message_hash)),
+            amount: amount.as_u64(),
+            zcash_address: format!("0x{}", hex::encode(zcash_address)),
+            l1_block: log.block_number.map(|n| n.as_u64()).unwrap_or_default(),
+            l1_tx_hash: log
+                .transaction_hash
+                .map(|h| format!("{:?}", h))
+                .unwrap_or_default(),
+            vault_shard: None,
+            queued_at: None,
+        };
+        Ok(Some((message_hash, instruction)))
+    }
+
+    /// Shard that should pay a release of `amount`
+    fn release_shard(&self, amount: u64) -> Option<String> {
+        match self.shards.balances() {
+            Ok(balances) => {
+                shards::select_for_release(&balances, amount).map(|b| b.id.clone())
+            }
+            Err(e) => {
+                warn!("Shard balances unavailable: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Append an instruction to the release queue at `queue_path`
+pub async fn append_release(
+    queue_path: &Path,
+    instruction: &ReleaseInstruction,
+) -> Result<(), SentinelError> {
+    let mut line = serde_json::to_string(instruction)?;
+    line.push('\n');
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue_path)
+        .await
+        .map_err(|e| SentinelError::Storage(e.to_string()))?;
+    file.write_all(line.as_bytes())
+        .await
+        .map_err(|e| SentinelError::Storage(e.to_string()))?;
+
+    info!(
+        "Release queued: {} zatoshi for withdrawal {}",
+        instruction.amount, instruction.message_hash
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::DepositStore;
+
+    #[test]
+    fn test_watcher_resumes_from_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue_path = dir.path().join("releases.jsonl");
+        let store = Arc::new(DepositStore::open(dir.path().join("deposits.json")).unwrap());
+        let watcher = |start_block| {
+            WithdrawalWatcher::new(
+                Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap()),
+                Address::zero(),
+                DEFAULT_L1_FINALITY_DEPTH,
+                start_block,
+                queue_path.clone(),
+                Arc::new(ShardSet::new(Vec::new(), store.clone(), None)),
+            )
+            .unwrap()
+        };
+
+        // A first run starts at the deployment block
+        let mut first = watcher(1_000);
+        assert_eq!(first.state.from_block, 1_000);
+        first.state.from_block = 5_000;
+        first.state.seen.insert(H256::repeat_byte(7));
+        first.persist().unwrap();
+
+        let resumed = watcher(1_000);
+        assert_eq!(resumed.state.from_block, 5_000);
+        assert!(resumed.state.seen.contains(&H256::repeat_byte(7)));
+        assert!(dir.path().join("releases.jsonl.watcher.json").exists());
+    }
+}
@@ -0,0 +1,239 @@
+//! Zcash full-node JSON-RPC client
+//!
+//! Minimal client for the zcashd-compatible JSON-RPC interface that zebrad
+//! also serves. Used where lightwalletd's compact blocks are not enough, e.g.
+//! raw block headers and full transaction lists. zcashd wants credentials:
+//! `ZCASH_RPC_USER` and `ZCASH_RPC_PASSWORD`, or the cookie file it writes
+//! at startup (`ZCASH_RPC_COOKIE_FILE`), re-read on every call so a restarted
+//! node's new cookie is picked up.
+
+use crate::error::SentinelError;
+use crate::evidence::from_display_hex;
+use crate::config::SentinelConfig;
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use sentinel_core::{ChainSource, CoreError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// JSON-RPC response envelope
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Block as returned by `getblock <hash|height> 1`
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockInfo {
+    /// Block hash (display hex)
+    pub hash: String,
+
+    /// Transaction ids in block order (display hex)
+    pub tx: Vec<String>,
+}
+
+/// Credentials for the node's RPC interface
+#[derive(Debug, Clone)]
+pub enum RpcAuth {
+    /// `rpcuser` and `rpcpassword`
+    Password(String, SecretString),
+    /// Cookie file holding `user:password`
+    Cookie(PathBuf),
+}
+
+impl RpcAuth {
+    /// Credentials from the configuration, if any are set
+    pub fn from_config(config: &SentinelConfig) -> Option<Self> {
+        if let Some(path) = &config.zcash_rpc_cookie_file {
+            return Some(Self::Cookie(PathBuf::from(path)));
+        }
+        let user = config.zcash_rpc_user.clone()?;
+        let password = config.zcash_rpc_password.clone()?;
+        Some(Self::Password(user, password))
+    }
+
+    /// User name and password to send
+    fn credentials(&self) -> Result<(String, Zeroizing<String>), SentinelError> {
+        match self {
+            Self::Password(user, password) => {
+                Ok((user.clone(), Zeroizing::new(password.expose_secret().clone())))
+            }
+            Self::Cookie(path) => {
+                let cookie = Zeroizing::new(std::fs::read_to_string(path).map_err(|e| {
+                    SentinelError::Config(format!("Cannot read {}: {}", path.display(), e))
+                })?);
+                let (user, password) = cookie.trim().split_once(':').ok_or_else(|| {
+                    SentinelError::Config(format!("{} is not a user:password cookie", path.display()))
+                })?;
+                Ok((user.to_string(), Zeroizing::new(password.to_string())))
+            }
+        }
+    }
+}
+
+/// Zcash node JSON-RPC client
+pub struct ZcashRpcClient {
+    /// Node JSON-RPC URL
+    url: String,
+
+    /// HTTP client
+    client: reqwest::Client,
+
+    /// Credentials, if the node wants them
+    auth: Option<RpcAuth>,
+}
+
+impl ZcashRpcClient {
+    /// Create a new client
+    pub fn new(url: String, timeout: Duration) -> Result<Self, SentinelError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(SentinelError::network)?;
+
+        Ok(Self {
+            url,
+            client,
+            auth: None,
+        })
+    }
+
+    /// Authenticate with `auth`, if set
+    pub fn with_auth(mut self, auth: Option<RpcAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Height of the node's best chain tip
+    pub async fn block_count(&self) -> Result<u32, SentinelError> {
+        let result = self.request("getblockcount", json!([])).await?;
+        result
+            .as_u64()
+            .and_then(|h| u32::try_from(h).ok())
+            .ok_or_else(|| SentinelError::Network("Malformed getblockcount response".to_string()))
+    }
+
+    /// Best-chain block at `height` with its transaction ids
+    pub async fn block(&self, height: u32) -> Result<BlockInfo, SentinelError> {
+        let result = self
+            .request("getblock", json!([height.to_string(), 1]))
+            .await?;
+        serde_json::from_value(result).map_err(SentinelError::network)
+    }
+
+    /// Serialized best-chain block at `height`
+    pub async fn raw_block(&self, height: u32) -> Result<Vec<u8>, SentinelError> {
+        let result = self
+            .request("getblock", json!([height.to_string(), 0]))
+            .await?;
+        let hex_str = result
+            .as_str()
+            .ok_or_else(|| SentinelError::Network("Malformed getblock response".to_string()))?;
+        Ok(hex::decode(hex_str)?)
+    }
+
+    /// Serialized transaction by txid (display hex)
+    pub async fn raw_transaction(&self, txid: &str) -> Result<Vec<u8>, SentinelError> {
+        let result = self.request("getrawtransaction", json!([txid, 0])).await?;
+        let hex_str = result.as_str().ok_or_else(|| {
+            SentinelError::Network("Malformed getrawtransaction response".to_string())
+        })?;
+        Ok(hex::decode(hex_str)?)
+    }
+
+    /// Serialized block header (including the Equihash solution) by block hash
+    pub async fn raw_header(&self, hash: &str) -> Result<Vec<u8>, SentinelError> {
+        let result = self.request("getblockheader", json!([hash, false])).await?;
+        let hex_str = result
+            .as_str()
+            .ok_or_else(|| SentinelError::Network("Malformed getblockheader response".to_string()))?;
+        Ok(hex::decode(hex_str)?)
+    }
+
+    /// Perform a JSON-RPC request
+    async fn request(&self, method: &str, params: Value) -> Result<Value, SentinelError> {
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": "sentinel",
+            "method": method,
+            "params": params,
+        });
+
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(auth) = &self.auth {
+            let (user, password) = auth.credentials()?;
+            request = request.basic_auth(user, Some(password.as_str()));
+        }
+        let response = request.send().await.map_err(SentinelError::network)?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(SentinelError::Config(format!(
+                "Zcash RPC {} refused our credentials",
+                method
+            )));
+        }
+        let response: RpcResponse = response.json().await.map_err(SentinelError::network)?;
+
+        if let Some(error) = response.error {
+            return Err(SentinelError::Network(format!(
+                "Zcash RPC {} failed ({}): {}",
+                method, error.code, error.message
+            )));
+        }
+
+        Ok(response.result.unwrap_or(Value::Null))
+    }
+}
+
+#[async_trait]
+impl ChainSource for ZcashRpcClient {
+    fn name(&self) -> &str {
+        "zcash-rpc"
+    }
+
+    async fn tip_height(&self) -> Result<u32, CoreError> {
+        self.block_count()
+            .await
+            .map_err(|e| CoreError::Source(e.to_string()))
+    }
+
+    async fn block_txids(&self, height: u32) -> Result<Vec<[u8; 32]>, CoreError> {
+        let block = self
+            .block(height)
+            .await
+            .map_err(|e| CoreError::Source(e.to_string()))?;
+        block
+            .tx
+            .iter()
+            .map(|t| from_display_hex(t).map_err(|e| CoreError::Source(e.to_string())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".cookie");
+        std::fs::write(&path, "__cookie__:abc123\n").unwrap();
+        let (user, password) = RpcAuth::Cookie(path.clone()).credentials().unwrap();
+        assert_eq!((user.as_str(), password.as_str()), ("__cookie__", "abc123"));
+
+        std::fs::write(&path, "abc123").unwrap();
+        assert!(RpcAuth::Cookie(path).credentials().is_err());
+        assert!(RpcAuth::Cookie(dir.path().join("missing")).credentials().is_err());
+    }
+}